@@ -0,0 +1,296 @@
+//! BLAKE3 outboard encodings for verified streaming, as used by bao and Iroh,
+//! committed to by [`Mode::Outboard`](crate::Mode::Outboard).
+//!
+//! The input is split into blocks of `1024 << block_log` bytes: `block_log` is
+//! 0 for bao and 4 for Iroh's 16 KiB chunk groups. The outboard holds the two
+//! child chaining values of every parent node above the blocks, 64 bytes each,
+//! in post order. The input length is not part of it. With the outboard and
+//! the root, a consumer can check any downloaded range of blocks without
+//! having the rest of the input.
+//!
+//! Hashing may be personalized with a BLAKE3 derive-key context, in which case
+//! the root is `blake3::derive_key(context, input)`.
+//!
+//! A [`Slice`] proof, verified by [`Mode::Slice`](crate::Mode::Slice), shows
+//! that a byte range is part of the input with a given root. It consists of
+//! the blocks covering the range and the chaining values of the subtrees that
+//! do not overlap it, in the order a left-to-right descent of the tree meets
+//! them.
+
+use core::fmt;
+use std::io::Read;
+
+use blake3::{
+    hazmat::{self, ChainingValue, ContextKey, HasherExt},
+    Hasher,
+};
+
+/// Largest accepted `block_log`, for blocks of 64 MiB.
+pub const MAX_BLOCK_LOG: u8 = 16;
+
+/// Computes the root hash of an input while emitting its outboard, in
+/// constant memory apart from one chaining value per tree level.
+pub struct OutboardHasher<F> {
+    block_len: u64,
+    context: Option<ContextKey>,
+    /// Hasher of the block currently being read.
+    block: Hasher,
+    /// Bytes of the current block read so far.
+    filled: u64,
+    /// Number of blocks finished before the current one.
+    blocks: u64,
+    /// Chaining values of the complete subtrees not yet merged, largest first.
+    stack: Vec<ChainingValue>,
+    emit: F,
+}
+
+impl<F: FnMut(&[u8; 64])> OutboardHasher<F> {
+    /// Returns `None` if `block_log` exceeds [`MAX_BLOCK_LOG`]. `emit` is
+    /// called with each outboard entry in order.
+    pub fn new(block_log: u8, context: Option<ContextKey>, emit: F) -> Option<Self> {
+        if block_log > MAX_BLOCK_LOG {
+            return None;
+        }
+        Some(Self {
+            block_len: 1024 << block_log,
+            block: block_hasher(context.as_ref(), 0),
+            context,
+            filled: 0,
+            blocks: 0,
+            stack: Vec::new(),
+            emit,
+        })
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // A full block is only finished once more input follows, since
+            // the last block of a single-block input is the root.
+            if self.filled == self.block_len {
+                let cv = self.block.finalize_non_root();
+                self.blocks += 1;
+                self.push(cv);
+                self.block = block_hasher(self.context.as_ref(), self.blocks * self.block_len);
+                self.filled = 0;
+            }
+            let n = (self.block_len - self.filled).min(data.len() as u64) as usize;
+            self.block.update(&data[..n]);
+            self.filled += n as u64;
+            data = &data[n..];
+        }
+    }
+
+    /// Returns the root hash and the input length.
+    pub fn finalize(mut self) -> ([u8; 32], u64) {
+        let len = self.blocks * self.block_len + self.filled;
+        if self.stack.is_empty() {
+            return (self.block.finalize().into(), len);
+        }
+        let mut right = self.block.finalize_non_root();
+        while let Some(left) = self.stack.pop() {
+            self.emit_pair(&left, &right);
+            if self.stack.is_empty() {
+                return (
+                    hazmat::merge_subtrees_root(&left, &right, self.mode()).into(),
+                    len,
+                );
+            }
+            right = hazmat::merge_subtrees_non_root(&left, &right, self.mode());
+        }
+        unreachable!("the loop returns once the stack is empty")
+    }
+
+    /// Pushes the chaining value of the block just finished, merging the
+    /// subtrees it completes.
+    fn push(&mut self, mut cv: ChainingValue) {
+        let mut blocks = self.blocks;
+        while blocks & 1 == 0 {
+            let left = self
+                .stack
+                .pop()
+                .expect("a complete subtree has a left half");
+            self.emit_pair(&left, &cv);
+            cv = hazmat::merge_subtrees_non_root(&left, &cv, self.mode());
+            blocks >>= 1;
+        }
+        self.stack.push(cv);
+    }
+
+    fn emit_pair(&mut self, left: &ChainingValue, right: &ChainingValue) {
+        let mut pair = [0; 64];
+        pair[..32].copy_from_slice(left);
+        pair[32..].copy_from_slice(right);
+        (self.emit)(&pair);
+    }
+
+    fn mode(&self) -> hazmat::Mode<'_> {
+        hash_mode(self.context.as_ref())
+    }
+}
+
+/// Largest number of chaining values in a slice proof: one per side of each
+/// tree level.
+pub const MAX_SLICE_NODES: usize = 128;
+
+/// A byte range of an input with a known root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slice {
+    pub root: [u8; 32],
+    /// Length of the whole input.
+    pub len: u64,
+    pub offset: u64,
+    pub length: u64,
+    pub block_log: u8,
+    pub context: Option<ContextKey>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceError {
+    /// The range is empty or extends past the end of the input, or the block
+    /// size is too large.
+    Range,
+    /// The proof holds too few or too many chaining values or data bytes.
+    Shape,
+    /// The proof leads to a different root.
+    Root,
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Range => write!(f, "slice is empty or out of range"),
+            Self::Shape => write!(f, "slice proof does not match the tree shape"),
+            Self::Root => write!(f, "slice does not match the root"),
+        }
+    }
+}
+
+impl std::error::Error for SliceError {}
+
+impl Slice {
+    pub fn block_len(&self) -> u64 {
+        1024 << self.block_log
+    }
+
+    /// The block-aligned byte range whose data a proof carries.
+    pub fn covered(&self) -> Result<(u64, u64), SliceError> {
+        let end = self
+            .offset
+            .checked_add(self.length)
+            .ok_or(SliceError::Range)?;
+        if self.block_log > MAX_BLOCK_LOG || self.length == 0 || end > self.len {
+            return Err(SliceError::Range);
+        }
+        let block_len = self.block_len();
+        let start = self.offset / block_len * block_len;
+        Ok((
+            start,
+            end.div_ceil(block_len)
+                .saturating_mul(block_len)
+                .min(self.len),
+        ))
+    }
+
+    /// Checks a proof against the root, feeding the bytes of the range itself
+    /// to `consume` as they are read from `data`.
+    pub fn verify(
+        &self,
+        nodes: &[ChainingValue],
+        data: &mut impl Read,
+        consume: impl FnMut(&[u8]),
+    ) -> Result<(), SliceError> {
+        let covered = self.covered()?;
+        let mut walk = SliceWalk {
+            slice: self,
+            covered,
+            nodes: nodes.iter(),
+            data,
+            consume,
+        };
+        let root = if self.len <= self.block_len() {
+            let mut hasher = block_hasher(self.context.as_ref(), 0);
+            walk.read_block(&mut hasher, 0, self.len)?;
+            *hasher.finalize().as_bytes()
+        } else {
+            let left = hazmat::left_subtree_len(self.len);
+            let (l, r) = (walk.subtree(0, left)?, walk.subtree(left, self.len - left)?);
+            *hazmat::merge_subtrees_root(&l, &r, hash_mode(self.context.as_ref())).as_bytes()
+        };
+        if walk.nodes.next().is_some()
+            || walk.data.read(&mut [0]).map_err(|_| SliceError::Shape)? != 0
+        {
+            return Err(SliceError::Shape);
+        }
+        if root != self.root {
+            return Err(SliceError::Root);
+        }
+        Ok(())
+    }
+}
+
+struct SliceWalk<'a, R, F> {
+    slice: &'a Slice,
+    covered: (u64, u64),
+    nodes: core::slice::Iter<'a, ChainingValue>,
+    data: &'a mut R,
+    consume: F,
+}
+
+impl<R: Read, F: FnMut(&[u8])> SliceWalk<'_, R, F> {
+    /// The chaining value of the non-root subtree of `size` bytes at `start`.
+    fn subtree(&mut self, start: u64, size: u64) -> Result<ChainingValue, SliceError> {
+        let context = self.slice.context.as_ref();
+        if start + size <= self.covered.0 || start >= self.covered.1 {
+            self.nodes.next().copied().ok_or(SliceError::Shape)
+        } else if size <= self.slice.block_len() {
+            let mut hasher = block_hasher(context, start);
+            self.read_block(&mut hasher, start, size)?;
+            Ok(hasher.finalize_non_root())
+        } else {
+            let left = hazmat::left_subtree_len(size);
+            let l = self.subtree(start, left)?;
+            let r = self.subtree(start + left, size - left)?;
+            Ok(hazmat::merge_subtrees_non_root(&l, &r, hash_mode(context)))
+        }
+    }
+
+    /// Hashes the `size` bytes of the block at `start`, passing the part
+    /// inside the range on to `consume`.
+    fn read_block(&mut self, hasher: &mut Hasher, start: u64, size: u64) -> Result<(), SliceError> {
+        let end = self.slice.offset + self.slice.length;
+        let mut buf = [0u8; 4096];
+        let mut pos = start;
+        while pos < start + size {
+            let n = (start + size - pos).min(buf.len() as u64) as usize;
+            self.data
+                .read_exact(&mut buf[..n])
+                .map_err(|_| SliceError::Shape)?;
+            hasher.update(&buf[..n]);
+            let from = self.slice.offset.clamp(pos, pos + n as u64);
+            let to = end.clamp(pos, pos + n as u64);
+            if from < to {
+                (self.consume)(&buf[(from - pos) as usize..(to - pos) as usize]);
+            }
+            pos += n as u64;
+        }
+        Ok(())
+    }
+}
+
+/// The merge mode matching [`block_hasher`].
+pub fn hash_mode(context: Option<&ContextKey>) -> hazmat::Mode<'_> {
+    match context {
+        Some(context) => hazmat::Mode::DeriveKeyMaterial(context),
+        None => hazmat::Mode::Hash,
+    }
+}
+
+/// A hasher for the chunk or subtree starting `offset` bytes into the input.
+pub fn block_hasher(context: Option<&ContextKey>, offset: u64) -> Hasher {
+    let mut hasher = match context {
+        Some(context) => Hasher::new_from_context_key(context),
+        None => Hasher::new(),
+    };
+    hasher.set_input_offset(offset);
+    hasher
+}
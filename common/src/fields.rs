@@ -0,0 +1,30 @@
+//! Commitments to named fields that can be disclosed one at a time, as made
+//! by [`Mode::Fields`](crate::Mode::Fields).
+//!
+//! The commitment is the root of a sparse tree of [`smt`](crate::smt) holding
+//! each field's value at the key `blake3(name)`. A field is disclosed against
+//! the commitment by its name, its value and the sparse proof of its key,
+//! checked on the host or proven by a later [`Mode::SparseMerkle`] receipt,
+//! while the other fields stay hidden. A proof shows the hashes of subtrees
+//! beside its path, among them the leaf hashes of neighbouring fields, so a
+//! field whose value can be guessed should carry a random salt.
+//!
+//! The guest input for the mode is laid out as for
+//! [`Mode::KeyValue`](crate::Mode::KeyValue), with names as keys, in strictly
+//! increasing name order.
+//!
+//! [`Mode::SparseMerkle`]: crate::Mode::SparseMerkle
+
+use crate::merkle::Hash;
+
+/// Longest field name accepted.
+pub const MAX_NAME_LEN: usize = crate::kv::MAX_KEY_LEN;
+
+/// Most fields accepted. The guest holds one key and value digest per field
+/// to build the tree.
+pub const MAX_FIELDS: usize = 1 << 16;
+
+/// The key of the field `name` in the tree.
+pub fn field_key(name: &[u8]) -> Hash {
+    blake3::hash(name).into()
+}
@@ -0,0 +1,864 @@
+//! Journal layout: the [`Mode`] byte, followed by the mode's output, or
+//! nothing at all for [`Output::Empty`].
+//!
+//! - [`Mode::Single`]: the two-byte [`Padding`] applied to the input, then the
+//!   32-byte digest of the padded input.
+//! - [`Mode::Multi`]: a little-endian `u32` count, the digest of the
+//!   concatenation of all inputs, then one [`Entry`] per input.
+//! - [`Mode::Zstd`]: the digest of the compressed input, then the digest of
+//!   the decompressed input.
+//! - [`Mode::KeyValue`]: a little-endian `u32` entry count, then the digest of
+//!   the map's canonical encoding.
+//! - [`Mode::MerkleExclusion`]: the tree root, the absent key, then the leaf
+//!   hashes of its lower and upper neighbours (zero where there is none).
+//! - [`Mode::SparseMerkle`]: the tree root, the key, then the digest of its
+//!   value (zero if the key is absent).
+//! - [`Mode::Outboard`]: the block size log, the input length as a
+//!   little-endian `u64`, the derive-key context key (zero if unpersonalized),
+//!   the root hash, then the digest of the outboard.
+//! - [`Mode::Slice`]: the derive-key context key (zero if unpersonalized), the
+//!   root hash, the input length, offset and range length as little-endian
+//!   `u64`s, then the digest of the bytes in the range.
+//! - [`Mode::Fields`]: a little-endian `u32` field count, then the root of the
+//!   fields' sparse tree.
+//! - [`Mode::Sha256Chunks`]: the input length as a little-endian `u64`, then
+//!   the digest of [`crate::sha256_chunks`].
+//!
+//! When the mode byte has [`GUEST_VERSION_FLAG`] set, the mode's output is
+//! followed by the [`GuestVersion`] of the guest. When it has [`NONCE_FLAG`]
+//! set, that is followed by the [`Nonce`] sent with the input. When it has
+//! [`INPUT_STATS_FLAG`] set, that is followed by the [`InputStats`] of the
+//! input. When it has [`INPUT_DIGEST_FLAG`] set, the journal ends with the
+//! BLAKE3 digest of the whole guest input.
+
+use core::fmt;
+
+use crate::{
+    padding::Padding, Mode, DIGEST_LEN, GUEST_VERSION_FLAG, INPUT_DIGEST_FLAG, INPUT_STATS_FLAG,
+    NONCE_FLAG,
+};
+
+pub type Digest = [u8; DIGEST_LEN];
+
+/// Size of a [`Nonce`].
+pub const NONCE_LEN: usize = 32;
+
+/// A value chosen by the caller for one proof, typically derived from the
+/// bridge event it is for, so that the proof cannot be replayed for another.
+pub type Nonce = [u8; NONCE_LEN];
+
+/// Version of the journal layout described here, committed in a
+/// [`GuestVersion`]. Bumped whenever the output of a mode changes.
+pub const SCHEMA_VERSION: u16 = 1;
+
+/// Encoded size of a [`GuestVersion`].
+pub const VERSION_LEN: usize = 2 + 2;
+
+/// Which revision of the guest wrote a journal: the journal schema it writes
+/// and the modes it was built with, each as the bit of its mode byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuestVersion {
+    pub schema: u16,
+    pub modes: u16,
+}
+
+impl GuestVersion {
+    /// The version of a guest built from this crate, with
+    /// [`Mode::Sha256Chunks`] if `sha256_chunks`.
+    pub fn current(sha256_chunks: bool) -> Self {
+        let modes = (0..u16::BITS)
+            .filter_map(Mode::from_u32)
+            .filter(|mode| sha256_chunks || *mode != Mode::Sha256Chunks)
+            .fold(0, |modes, mode| modes | (1 << mode as u8));
+        Self {
+            schema: SCHEMA_VERSION,
+            modes,
+        }
+    }
+
+    /// Whether the guest was built with `mode`.
+    pub fn supports(&self, mode: Mode) -> bool {
+        self.modes & (1 << mode as u8) != 0
+    }
+
+    pub fn to_bytes(&self) -> [u8; VERSION_LEN] {
+        let mut bytes = [0u8; VERSION_LEN];
+        bytes[..2].copy_from_slice(&self.schema.to_le_bytes());
+        bytes[2..].copy_from_slice(&self.modes.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; VERSION_LEN]) -> Self {
+        Self {
+            schema: u16::from_le_bytes([bytes[0], bytes[1]]),
+            modes: u16::from_le_bytes([bytes[2], bytes[3]]),
+        }
+    }
+}
+
+/// Encoded size of an [`Entry`].
+pub const ENTRY_LEN: usize = 8 + 8 + DIGEST_LEN;
+
+/// Encoded size of an [`InputStats`].
+pub const STATS_LEN: usize = 8 + 8;
+
+/// How much of its input the guest read as frames: the byte strings it
+/// hashed, decompressed or decoded, but not the fixed-size words around them.
+/// A host that truncates the input it sends changes these.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputStats {
+    /// Bytes across all frames.
+    pub bytes: u64,
+    /// Non-empty frames, which each took one read.
+    pub frames: u64,
+}
+
+impl InputStats {
+    /// Counts one frame of `len` bytes.
+    pub fn add_frame(&mut self, len: usize) {
+        self.bytes += len as u64;
+        self.frames += 1;
+    }
+
+    pub fn to_bytes(&self) -> [u8; STATS_LEN] {
+        let mut bytes = [0u8; STATS_LEN];
+        bytes[..8].copy_from_slice(&self.bytes.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.frames.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; STATS_LEN]) -> Self {
+        Self {
+            bytes: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            frames: u64::from_le_bytes(bytes[8..].try_into().unwrap()),
+        }
+    }
+}
+
+/// One input of a [`Mode::Multi`] journal. Committing the boundaries lets a
+/// verifier tell which bytes of the concatenation belonged to which input, so
+/// shifting bytes between neighbouring inputs changes the journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    /// Position of the input within the concatenation of all inputs.
+    pub offset: u64,
+    pub len: u64,
+    pub digest: Digest,
+}
+
+impl Entry {
+    pub fn to_bytes(&self) -> [u8; ENTRY_LEN] {
+        let mut bytes = [0u8; ENTRY_LEN];
+        bytes[..8].copy_from_slice(&self.offset.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.len.to_le_bytes());
+        bytes[16..].copy_from_slice(&self.digest);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; ENTRY_LEN]) -> Self {
+        Self {
+            offset: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            len: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            digest: bytes[16..].try_into().unwrap(),
+        }
+    }
+}
+
+/// A parsed journal, borrowing from the journal bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output<'a> {
+    /// A journal the guest committed nothing to, as for ping-style liveness
+    /// proofs. It establishes only that the guest ran to completion; its
+    /// public input is the digest of the prefix alone.
+    Empty,
+    Single {
+        padding: Padding,
+        digest: &'a Digest,
+    },
+    Multi {
+        /// Digest of the concatenation of all inputs.
+        combined: &'a Digest,
+        entries: &'a [[u8; ENTRY_LEN]],
+    },
+    Zstd {
+        compressed: &'a Digest,
+        /// The canonical digest, equal to that of an unpadded [`Mode::Single`]
+        /// proof over the uncompressed input.
+        decompressed: &'a Digest,
+    },
+    KeyValue {
+        count: u32,
+        /// Commitment to the map, see [`crate::kv`].
+        root: &'a Digest,
+    },
+    MerkleExclusion {
+        root: &'a Digest,
+        key: &'a Digest,
+        lower: &'a Digest,
+        upper: &'a Digest,
+    },
+    SparseMerkle {
+        root: &'a Digest,
+        key: &'a Digest,
+        value: &'a Digest,
+    },
+    Outboard {
+        block_log: u8,
+        len: u64,
+        context: &'a Digest,
+        /// The BLAKE3 hash of the input, or its derive-key output if
+        /// `context` is set.
+        root: &'a Digest,
+        outboard: &'a Digest,
+    },
+    Slice {
+        context: &'a Digest,
+        root: &'a Digest,
+        len: u64,
+        offset: u64,
+        length: u64,
+        digest: &'a Digest,
+    },
+    Fields {
+        count: u32,
+        /// Root of the sparse tree of the fields, see [`crate::fields`].
+        root: &'a Digest,
+    },
+    Sha256Chunks {
+        len: u64,
+        /// Not the BLAKE3 hash of the input, see [`crate::sha256_chunks`].
+        digest: &'a Digest,
+    },
+}
+
+impl Output<'_> {
+    /// The mode that produced the output, or `None` for [`Output::Empty`].
+    pub fn mode(&self) -> Option<Mode> {
+        Some(match self {
+            Self::Empty => return None,
+            Self::Single { .. } => Mode::Single,
+            Self::Multi { .. } => Mode::Multi,
+            Self::Zstd { .. } => Mode::Zstd,
+            Self::KeyValue { .. } => Mode::KeyValue,
+            Self::MerkleExclusion { .. } => Mode::MerkleExclusion,
+            Self::SparseMerkle { .. } => Mode::SparseMerkle,
+            Self::Outboard { .. } => Mode::Outboard,
+            Self::Slice { .. } => Mode::Slice,
+            Self::Fields { .. } => Mode::Fields,
+            Self::Sha256Chunks { .. } => Mode::Sha256Chunks,
+        })
+    }
+
+    /// Decodes the per-input entries of a [`Mode::Multi`] journal. Yields
+    /// nothing for other modes.
+    pub fn entries(&self) -> impl Iterator<Item = Entry> + '_ {
+        let entries: &[[u8; ENTRY_LEN]] = match self {
+            Self::Multi { entries, .. } => entries,
+            _ => &[],
+        };
+        entries.iter().map(Entry::from_bytes)
+    }
+
+    /// Serializes the output as committed by the guest.
+    pub fn encode(&self) -> Vec<u8> {
+        let Some(mode) = self.mode() else {
+            return Vec::new();
+        };
+        let mut encoded = vec![mode as u8];
+        match self {
+            Self::Empty => {}
+            Self::Single { padding, digest } => {
+                encoded.extend_from_slice(&padding.encode());
+                encoded.extend_from_slice(*digest);
+            }
+            Self::Multi { combined, entries } => {
+                encoded.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+                encoded.extend_from_slice(*combined);
+                encoded.extend_from_slice(entries.as_flattened());
+            }
+            Self::Zstd {
+                compressed,
+                decompressed,
+            } => {
+                encoded.extend_from_slice(*compressed);
+                encoded.extend_from_slice(*decompressed);
+            }
+            Self::KeyValue { count, root } | Self::Fields { count, root } => {
+                encoded.extend_from_slice(&count.to_le_bytes());
+                encoded.extend_from_slice(*root);
+            }
+            Self::MerkleExclusion {
+                root,
+                key,
+                lower,
+                upper,
+            } => {
+                for digest in [root, key, lower, upper] {
+                    encoded.extend_from_slice(*digest);
+                }
+            }
+            Self::SparseMerkle { root, key, value } => {
+                for digest in [root, key, value] {
+                    encoded.extend_from_slice(*digest);
+                }
+            }
+            Self::Outboard {
+                block_log,
+                len,
+                context,
+                root,
+                outboard,
+            } => {
+                encoded.push(*block_log);
+                encoded.extend_from_slice(&len.to_le_bytes());
+                for digest in [context, root, outboard] {
+                    encoded.extend_from_slice(*digest);
+                }
+            }
+            Self::Slice {
+                context,
+                root,
+                len,
+                offset,
+                length,
+                digest,
+            } => {
+                encoded.extend_from_slice(*context);
+                encoded.extend_from_slice(*root);
+                for value in [len, offset, length] {
+                    encoded.extend_from_slice(&value.to_le_bytes());
+                }
+                encoded.extend_from_slice(*digest);
+            }
+            Self::Sha256Chunks { len, digest } => {
+                encoded.extend_from_slice(&len.to_le_bytes());
+                encoded.extend_from_slice(*digest);
+            }
+        }
+        encoded
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnknownMode(u8),
+    UnknownPadding([u8; 2]),
+    /// The journal is not exactly as long as its header says it should be.
+    Length {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownMode(mode) => write!(f, "journal has unknown mode byte {mode}"),
+            Self::UnknownPadding(padding) => {
+                write!(f, "journal has unknown padding {padding:02x?}")
+            }
+            Self::Length { expected, actual } => {
+                write!(f, "journal is {actual} bytes, expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The parts a journal may end with, in the order they follow the mode's
+/// output, each as its flag in the mode byte and its encoded size.
+const TRAILER: [(u8, usize); 4] = [
+    (GUEST_VERSION_FLAG, VERSION_LEN),
+    (NONCE_FLAG, NONCE_LEN),
+    (INPUT_STATS_FLAG, STATS_LEN),
+    (INPUT_DIGEST_FLAG, DIGEST_LEN),
+];
+
+/// The trailer part marked by `flag`, if the journal has it.
+fn trailer_part(journal: &[u8], flag: u8) -> Option<&[u8]> {
+    let (&mode, body) = journal.split_first()?;
+    if mode & flag == 0 {
+        return None;
+    }
+    let mut end = body.len();
+    for &(part, len) in TRAILER.iter().rev() {
+        if mode & part == 0 {
+            continue;
+        }
+        let start = end.checked_sub(len)?;
+        if part == flag {
+            return Some(&body[start..end]);
+        }
+        end = start;
+    }
+    None
+}
+
+/// Appends the version of the guest to an encoded output, and marks it in
+/// the mode byte. Called before [`bind_nonce`], [`bind_stats`] and
+/// [`bind_input`].
+pub fn bind_version(output: &mut Vec<u8>, version: &GuestVersion) {
+    if let Some(mode) = output.first_mut() {
+        *mode |= GUEST_VERSION_FLAG;
+        output.extend_from_slice(&version.to_bytes());
+    }
+}
+
+/// The guest version a journal commits to, if any.
+pub fn guest_version(journal: &[u8]) -> Option<GuestVersion> {
+    trailer_part(journal, GUEST_VERSION_FLAG)
+        .map(|bytes| GuestVersion::from_bytes(bytes.try_into().unwrap()))
+}
+
+/// Appends the nonce the caller sent with the input to an encoded output,
+/// and marks it in the mode byte. Called before [`bind_stats`] and
+/// [`bind_input`].
+pub fn bind_nonce(output: &mut Vec<u8>, nonce: &Nonce) {
+    if let Some(mode) = output.first_mut() {
+        *mode |= NONCE_FLAG;
+        output.extend_from_slice(nonce);
+    }
+}
+
+/// The nonce a journal commits to, if any.
+pub fn nonce(journal: &[u8]) -> Option<&Nonce> {
+    trailer_part(journal, NONCE_FLAG).map(|bytes| bytes.try_into().unwrap())
+}
+
+/// Appends the statistics of the guest input to an encoded output, and marks
+/// them in the mode byte. Called before [`bind_input`], whose digest comes
+/// last.
+pub fn bind_stats(output: &mut Vec<u8>, stats: &InputStats) {
+    if let Some(mode) = output.first_mut() {
+        *mode |= INPUT_STATS_FLAG;
+        output.extend_from_slice(&stats.to_bytes());
+    }
+}
+
+/// The input statistics a journal commits to, if any.
+pub fn input_stats(journal: &[u8]) -> Option<InputStats> {
+    trailer_part(journal, INPUT_STATS_FLAG)
+        .map(|bytes| InputStats::from_bytes(bytes.try_into().unwrap()))
+}
+
+/// Appends the digest of the whole guest input to an encoded output, and
+/// marks it in the mode byte.
+pub fn bind_input(output: &mut Vec<u8>, input_digest: &Digest) {
+    if let Some(mode) = output.first_mut() {
+        *mode |= INPUT_DIGEST_FLAG;
+        output.extend_from_slice(input_digest);
+    }
+}
+
+/// The digest of the whole guest input a journal ends with, if it was
+/// committed.
+pub fn input_digest(journal: &[u8]) -> Option<&Digest> {
+    trailer_part(journal, INPUT_DIGEST_FLAG).map(|bytes| bytes.try_into().unwrap())
+}
+
+/// Parses a journal's output. The guest version, nonce, and the statistics
+/// and digest of the input it may end with are skipped; see
+/// [`guest_version`], [`nonce`], [`input_stats`] and [`input_digest`].
+pub fn parse(journal: &[u8]) -> Result<Output<'_>, ParseError> {
+    let Some((&mode, body)) = journal.split_first() else {
+        return Ok(Output::Empty);
+    };
+    let trailer_len: usize = TRAILER
+        .iter()
+        .filter(|(flag, _)| mode & flag != 0)
+        .map(|(_, len)| len)
+        .sum();
+    if trailer_len == 0 {
+        return parse_output(mode, body);
+    }
+    let body = body
+        .len()
+        .checked_sub(trailer_len)
+        .map(|len| &body[..len])
+        .ok_or(ParseError::Length {
+            expected: 1 + trailer_len,
+            actual: journal.len(),
+        })?;
+    let flags = TRAILER.iter().fold(0, |flags, (flag, _)| flags | flag);
+    parse_output(mode & !flags, body).map_err(|err| match err {
+        ParseError::Length { expected, actual } => ParseError::Length {
+            expected: expected + trailer_len,
+            actual: actual + trailer_len,
+        },
+        err => err,
+    })
+}
+
+/// Parses the output of `mode` from the rest of the journal, `body`.
+fn parse_output(mode: u8, body: &[u8]) -> Result<Output<'_>, ParseError> {
+    let journal_len = 1 + body.len();
+    let mode = Mode::from_u32(mode.into()).ok_or(ParseError::UnknownMode(mode))?;
+    match mode {
+        Mode::Single => {
+            let (padding, digest) = body
+                .split_first_chunk::<2>()
+                .and_then(|(padding, rest)| Some((*padding, rest.try_into().ok()?)))
+                .ok_or(ParseError::Length {
+                    expected: 1 + 2 + DIGEST_LEN,
+                    actual: journal_len,
+                })?;
+            let padding = Padding::decode(padding).ok_or(ParseError::UnknownPadding(padding))?;
+            Ok(Output::Single { padding, digest })
+        }
+        Mode::Multi => {
+            const HEADER_LEN: usize = 1 + 4 + DIGEST_LEN;
+            let (Some((count, body)), Some(combined)) =
+                (body.split_first_chunk::<4>(), body.get(4..4 + DIGEST_LEN))
+            else {
+                return Err(ParseError::Length {
+                    expected: HEADER_LEN,
+                    actual: journal_len,
+                });
+            };
+            let count = u32::from_le_bytes(*count) as usize;
+            let (entries, rest) = body[DIGEST_LEN..].as_chunks::<ENTRY_LEN>();
+            if entries.len() != count || !rest.is_empty() {
+                return Err(ParseError::Length {
+                    expected: count.saturating_mul(ENTRY_LEN).saturating_add(HEADER_LEN),
+                    actual: journal_len,
+                });
+            }
+            Ok(Output::Multi {
+                combined: combined.try_into().unwrap(),
+                entries,
+            })
+        }
+        Mode::Zstd => {
+            let (compressed, decompressed) = body
+                .split_first_chunk()
+                .and_then(|(compressed, rest)| Some((compressed, rest.try_into().ok()?)))
+                .ok_or(ParseError::Length {
+                    expected: 1 + 2 * DIGEST_LEN,
+                    actual: journal_len,
+                })?;
+            Ok(Output::Zstd {
+                compressed,
+                decompressed,
+            })
+        }
+        Mode::KeyValue => {
+            let (count, root) = body
+                .split_first_chunk::<4>()
+                .and_then(|(count, rest)| Some((u32::from_le_bytes(*count), rest.try_into().ok()?)))
+                .ok_or(ParseError::Length {
+                    expected: 1 + 4 + DIGEST_LEN,
+                    actual: journal_len,
+                })?;
+            Ok(Output::KeyValue { count, root })
+        }
+        Mode::Fields => {
+            let (count, root) = body
+                .split_first_chunk::<4>()
+                .and_then(|(count, rest)| Some((u32::from_le_bytes(*count), rest.try_into().ok()?)))
+                .ok_or(ParseError::Length {
+                    expected: 1 + 4 + DIGEST_LEN,
+                    actual: journal_len,
+                })?;
+            Ok(Output::Fields { count, root })
+        }
+        Mode::MerkleExclusion => {
+            let ([root, key, lower, upper], []) = body.as_chunks::<DIGEST_LEN>() else {
+                return Err(ParseError::Length {
+                    expected: 1 + 4 * DIGEST_LEN,
+                    actual: journal_len,
+                });
+            };
+            Ok(Output::MerkleExclusion {
+                root,
+                key,
+                lower,
+                upper,
+            })
+        }
+        Mode::SparseMerkle => {
+            let ([root, key, value], []) = body.as_chunks::<DIGEST_LEN>() else {
+                return Err(ParseError::Length {
+                    expected: 1 + 3 * DIGEST_LEN,
+                    actual: journal_len,
+                });
+            };
+            Ok(Output::SparseMerkle { root, key, value })
+        }
+        Mode::Outboard => {
+            let (block_log, len, [context, root, outboard]) = body
+                .split_first()
+                .and_then(|(&block_log, rest)| {
+                    let (len, rest) = rest.split_first_chunk::<8>()?;
+                    let ([context, root, outboard], []) = rest.as_chunks::<DIGEST_LEN>() else {
+                        return None;
+                    };
+                    Some((
+                        block_log,
+                        u64::from_le_bytes(*len),
+                        [context, root, outboard],
+                    ))
+                })
+                .ok_or(ParseError::Length {
+                    expected: 1 + 1 + 8 + 3 * DIGEST_LEN,
+                    actual: journal_len,
+                })?;
+            Ok(Output::Outboard {
+                block_log,
+                len,
+                context,
+                root,
+                outboard,
+            })
+        }
+        Mode::Slice => {
+            let (context, root, [len, offset, length], digest) = body
+                .split_first_chunk::<DIGEST_LEN>()
+                .and_then(|(context, rest)| {
+                    let (root, rest) = rest.split_first_chunk::<DIGEST_LEN>()?;
+                    let (range, digest) = rest.split_first_chunk::<24>()?;
+                    let ([len, offset, length], []) = range.as_chunks::<8>() else {
+                        unreachable!("24 bytes are three u64s")
+                    };
+                    let range = [len, offset, length].map(|value| u64::from_le_bytes(*value));
+                    Some((context, root, range, digest.try_into().ok()?))
+                })
+                .ok_or(ParseError::Length {
+                    expected: 1 + 3 * DIGEST_LEN + 24,
+                    actual: journal_len,
+                })?;
+            Ok(Output::Slice {
+                context,
+                root,
+                len,
+                offset,
+                length,
+                digest,
+            })
+        }
+        Mode::Sha256Chunks => {
+            let (len, digest) = body
+                .split_first_chunk::<8>()
+                .and_then(|(len, rest)| Some((u64::from_le_bytes(*len), rest.try_into().ok()?)))
+                .ok_or(ParseError::Length {
+                    expected: 1 + 8 + DIGEST_LEN,
+                    actual: journal_len,
+                })?;
+            Ok(Output::Sha256Chunks { len, digest })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: Digest = [0xaa; DIGEST_LEN];
+    const B: Digest = [0xbb; DIGEST_LEN];
+    const C: Digest = [0xcc; DIGEST_LEN];
+    const D: Digest = [0xdd; DIGEST_LEN];
+
+    fn outputs(entries: &[[u8; ENTRY_LEN]]) -> Vec<Output<'_>> {
+        vec![
+            Output::Single {
+                padding: Padding::None,
+                digest: &A,
+            },
+            Output::Single {
+                padding: Padding::ZeroPad { log2: 12 },
+                digest: &A,
+            },
+            Output::Multi {
+                combined: &A,
+                entries: &[],
+            },
+            Output::Multi {
+                combined: &A,
+                entries,
+            },
+            Output::Zstd {
+                compressed: &A,
+                decompressed: &B,
+            },
+            Output::KeyValue { count: 7, root: &A },
+            Output::MerkleExclusion {
+                root: &A,
+                key: &B,
+                lower: &C,
+                upper: &D,
+            },
+            Output::SparseMerkle {
+                root: &A,
+                key: &B,
+                value: &C,
+            },
+            Output::Outboard {
+                block_log: 4,
+                len: 1 << 40,
+                context: &A,
+                root: &B,
+                outboard: &C,
+            },
+            Output::Slice {
+                context: &A,
+                root: &B,
+                len: 5000,
+                offset: 100,
+                length: 200,
+                digest: &C,
+            },
+            Output::Fields { count: 3, root: &A },
+            Output::Sha256Chunks { len: 9, digest: &A },
+        ]
+    }
+
+    fn entries() -> Vec<[u8; ENTRY_LEN]> {
+        [(0, 10, A), (10, 0, B), (10, 5, C)]
+            .map(|(offset, len, digest)| {
+                Entry {
+                    offset,
+                    len,
+                    digest,
+                }
+                .to_bytes()
+            })
+            .to_vec()
+    }
+
+    #[test]
+    fn outputs_round_trip() {
+        let entries = entries();
+        for output in outputs(&entries) {
+            let encoded = output.encode();
+            assert_eq!(encoded[0], output.mode().unwrap() as u8);
+            assert_eq!(parse(&encoded), Ok(output));
+        }
+        assert_eq!(parse(&[]), Ok(Output::Empty));
+        assert!(Output::Empty.encode().is_empty());
+    }
+
+    #[test]
+    fn truncated_or_extended_outputs_are_rejected() {
+        let entries = entries();
+        for output in outputs(&entries) {
+            let encoded = output.encode();
+            assert!(
+                matches!(
+                    parse(&encoded[..encoded.len() - 1]),
+                    Err(ParseError::Length { .. })
+                ),
+                "{output:?}"
+            );
+            let mut extended = encoded;
+            extended.push(0);
+            assert!(
+                matches!(parse(&extended), Err(ParseError::Length { .. })),
+                "{output:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_mode_and_padding_are_rejected() {
+        assert_eq!(parse(&[0x0f]), Err(ParseError::UnknownMode(0x0f)));
+        let mut single = Output::Single {
+            padding: Padding::None,
+            digest: &A,
+        }
+        .encode();
+        single[1] = 9;
+        assert_eq!(parse(&single), Err(ParseError::UnknownPadding([9, 0])));
+    }
+
+    #[test]
+    fn multi_entries_round_trip() {
+        let entries = entries();
+        let encoded = Output::Multi {
+            combined: &D,
+            entries: &entries,
+        }
+        .encode();
+        let output = parse(&encoded).unwrap();
+        let decoded: Vec<Entry> = output.entries().collect();
+        assert_eq!(
+            decoded,
+            [(0, 10, A), (10, 0, B), (10, 5, C)].map(|(offset, len, digest)| Entry {
+                offset,
+                len,
+                digest
+            })
+        );
+        assert_eq!(
+            Output::Zstd {
+                compressed: &A,
+                decompressed: &B
+            }
+            .entries()
+            .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn multi_count_must_match_entries() {
+        let entries = entries();
+        let mut encoded = Output::Multi {
+            combined: &D,
+            entries: &entries,
+        }
+        .encode();
+        encoded[1] = 2;
+        assert_eq!(
+            parse(&encoded),
+            Err(ParseError::Length {
+                expected: 1 + 4 + DIGEST_LEN + 2 * ENTRY_LEN,
+                actual: encoded.len(),
+            })
+        );
+    }
+
+    #[test]
+    fn trailers_round_trip() {
+        let version = GuestVersion::current(false);
+        let request_nonce = [0x11; NONCE_LEN];
+        let stats = InputStats {
+            bytes: 1234,
+            frames: 2,
+        };
+        let output = Output::Zstd {
+            compressed: &A,
+            decompressed: &B,
+        };
+        // Every combination of trailer parts, each bound in its order.
+        for parts in 0..16u8 {
+            let mut journal = output.encode();
+            if parts & 1 != 0 {
+                bind_version(&mut journal, &version);
+            }
+            if parts & 2 != 0 {
+                bind_nonce(&mut journal, &request_nonce);
+            }
+            if parts & 4 != 0 {
+                bind_stats(&mut journal, &stats);
+            }
+            if parts & 8 != 0 {
+                bind_input(&mut journal, &C);
+            }
+            assert_eq!(parse(&journal), Ok(output), "parts {parts:04b}");
+            assert_eq!(guest_version(&journal), (parts & 1 != 0).then_some(version));
+            assert_eq!(nonce(&journal), (parts & 2 != 0).then_some(&request_nonce));
+            assert_eq!(input_stats(&journal), (parts & 4 != 0).then_some(stats));
+            assert_eq!(input_digest(&journal), (parts & 8 != 0).then_some(&C));
+        }
+    }
+
+    #[test]
+    fn short_trailer_is_rejected() {
+        let mut journal = vec![Mode::Zstd as u8 | INPUT_DIGEST_FLAG | NONCE_FLAG];
+        journal.extend_from_slice(&[0; DIGEST_LEN]);
+        assert!(matches!(parse(&journal), Err(ParseError::Length { .. })));
+        journal.truncate(1);
+        assert_eq!(input_digest(&journal), None);
+        assert_eq!(nonce(&journal), None);
+    }
+}
@@ -0,0 +1,24 @@
+//! Canonical encoding of an ordered key-value map, as hashed by
+//! [`Mode::KeyValue`](crate::Mode::KeyValue).
+//!
+//! The commitment is the BLAKE3 digest of the little-endian `u32` entry count,
+//! followed for each entry, in strictly increasing key order, by the key length
+//! as a little-endian `u32`, the key, the value length as a little-endian
+//! `u64`, and the value. Length-prefixing every field makes the encoding
+//! unambiguous, so no two maps share a commitment.
+//!
+//! The guest input for the mode is the `u32` count, then for each entry the
+//! key length, the key, the value length and the value as frames.
+
+/// Longest key accepted. Keys are held in memory to check the ordering.
+pub const MAX_KEY_LEN: usize = 1024;
+
+/// Encodes the fields preceding an entry's key.
+pub fn key_header(key: &[u8]) -> [u8; 4] {
+    (key.len() as u32).to_le_bytes()
+}
+
+/// Encodes the fields preceding an entry's value.
+pub fn value_header(value_len: u64) -> [u8; 8] {
+    value_len.to_le_bytes()
+}
@@ -0,0 +1,145 @@
+//! Definitions shared by the guest and the host.
+//!
+//! The guest input starts with a little-endian `u32` [`Mode`], followed by the
+//! data for that mode. Byte strings are streamed as a sequence of frames, each
+//! a little-endian `u32` length followed by that many bytes, and terminated by
+//! an empty frame. This keeps guest memory use constant no matter how large
+//! the input is.
+//!
+//! A mode word with [`INPUT_DIGEST_FLAG`] set asks the guest to also commit
+//! the BLAKE3 digest of its whole input, mode word included, so that a proof
+//! can be bound to an input that is never revealed. With [`INPUT_STATS_FLAG`]
+//! set, the guest also commits how many bytes and frames it read, so that a
+//! host feeding it a truncated input is caught. With [`GUEST_VERSION_FLAG`]
+//! set, it commits the journal schema it writes and the modes it was built
+//! with, so that a verifier trusting many image IDs can still reject journals
+//! of a guest revision it would misread. With [`NONCE_FLAG`] set, the mode
+//! word is followed by a 32-byte nonce the guest commits, so that each proof
+//! is bound to the request it answers.
+//!
+//! The second byte of the mode word is the log2 of the largest frame in the
+//! input, which is the size of the guest's read buffer, or zero for
+//! [`MAX_FRAME_LEN`]. Smaller frames use less guest memory, larger ones fewer
+//! reads per byte.
+
+pub mod bao;
+pub mod fields;
+pub mod journal;
+pub mod kv;
+pub mod merkle;
+pub mod padding;
+pub mod rv32_blake3;
+pub mod sha256_chunks;
+pub mod smt;
+
+/// Size of a frame's length prefix.
+pub const FRAME_HEADER_LEN: usize = 4;
+
+/// Largest frame payload the guest accepts, and the size the host splits
+/// inputs into by default.
+pub const MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// Smallest frame size an input can choose.
+pub const MIN_FRAME_LEN: usize = 1024;
+
+/// Size of a BLAKE3 digest.
+pub const DIGEST_LEN: usize = 32;
+
+/// Set in the mode word of an input, and in the mode byte of the journal, when
+/// the journal ends with the digest of the whole input.
+pub const INPUT_DIGEST_FLAG: u8 = 0x80;
+
+/// Set in the mode word of an input, and in the mode byte of the journal, when
+/// the journal commits the [`journal::InputStats`] of the input.
+pub const INPUT_STATS_FLAG: u8 = 0x40;
+
+/// Set in the mode word of an input, and in the mode byte of the journal, when
+/// the journal commits the [`journal::GuestVersion`] of the guest.
+pub const GUEST_VERSION_FLAG: u8 = 0x20;
+
+/// Set in the mode word of an input, and in the mode byte of the journal, when
+/// the input carries a [`journal::Nonce`] that the journal commits.
+pub const NONCE_FLAG: u8 = 0x10;
+
+/// What the guest computes over its input. The mode is also the first byte of
+/// the journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Mode {
+    /// Hash one byte string, padded as given by a [`padding::Padding`] word
+    /// following the mode, and commit its digest.
+    Single = 0,
+    /// Hash a `u32` count of byte strings, and commit the digest of their
+    /// concatenation along with the boundaries and digest of each one.
+    Multi = 1,
+    /// Decompress one zstd-compressed byte string, hash the result, and commit
+    /// the digests of both the compressed and the decompressed bytes.
+    Zstd = 2,
+    /// Hash a sorted key-value map in the canonical encoding of [`kv`] and
+    /// commit the entry count and digest.
+    KeyValue = 3,
+    /// Verify a [`merkle::ExclusionProof`], sent as frames, and commit what it
+    /// establishes.
+    MerkleExclusion = 4,
+    /// Compute the root of a sparse tree from a [`smt::SparseProof`], sent as
+    /// frames, and commit it with the key and value digest it proves.
+    SparseMerkle = 5,
+    /// Hash one byte string into the BLAKE3 tree of [`bao`], configured by a
+    /// `[block_log, personalized, 0, 0]` word and, if personalized, a 32-byte
+    /// derive-key context key, and commit its root and outboard digest.
+    Outboard = 6,
+    /// Verify a [`bao::Slice`] proof of a byte range, sent as the same word and
+    /// context key as [`Mode::Outboard`], the root, the input length, offset
+    /// and range length as `u64`s, a `u32` count of chaining values and the
+    /// values themselves, then the covering blocks as frames. Commits the
+    /// range and its digest.
+    Slice = 7,
+    /// Hash named fields, sent as for [`Mode::KeyValue`], into the sparse tree
+    /// of [`fields`] and commit the field count and the tree root, against
+    /// which fields can later be disclosed one at a time.
+    Fields = 8,
+    /// Hash one byte string into the digest of [`sha256_chunks`], which uses
+    /// the zkVM's SHA-256 accelerator for all but 32 bytes per chunk, and
+    /// commit its length and digest. Only built into guests with the
+    /// `sha256-chunks` feature.
+    Sha256Chunks = 9,
+}
+
+impl Mode {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Single),
+            1 => Some(Self::Multi),
+            2 => Some(Self::Zstd),
+            3 => Some(Self::KeyValue),
+            4 => Some(Self::MerkleExclusion),
+            5 => Some(Self::SparseMerkle),
+            6 => Some(Self::Outboard),
+            7 => Some(Self::Slice),
+            8 => Some(Self::Fields),
+            9 => Some(Self::Sha256Chunks),
+            _ => None,
+        }
+    }
+}
+
+/// The frame size given by the second byte of a mode word, if supported.
+pub fn frame_len(frame_log: u8) -> Option<usize> {
+    match frame_log {
+        0 => Some(MAX_FRAME_LEN),
+        log => 1usize
+            .checked_shl(log.into())
+            .filter(|len| (MIN_FRAME_LEN..=MAX_FRAME_LEN).contains(len)),
+    }
+}
+
+/// Appends `data` to `encoded` as a terminated sequence of frames of at most
+/// `frame_len` bytes.
+pub fn encode_frames(encoded: &mut Vec<u8>, data: &[u8], frame_len: usize) {
+    encoded.reserve(data.len() + (data.len().div_ceil(frame_len) + 1) * FRAME_HEADER_LEN);
+    for frame in data.chunks(frame_len) {
+        encoded.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        encoded.extend_from_slice(frame);
+    }
+    encoded.extend_from_slice(&0u32.to_le_bytes());
+}
@@ -0,0 +1,229 @@
+//! Sorted BLAKE3 Merkle trees over 32-byte keys, and the exclusion proofs
+//! verified by [`Mode::MerkleExclusion`](crate::Mode::MerkleExclusion).
+//!
+//! Leaves are the keys in strictly increasing order. The tree has the shape of
+//! RFC 9162: a tree of `n > 1` leaves is split into a left subtree of the
+//! largest power of two smaller than `n` leaves and a right subtree of the
+//! rest. Hashes are domain separated, and the root also commits to `n`, so that
+//! a proof of the first or last leaf shows there is nothing beyond it:
+//!
+//! - leaf: `blake3(0x00 || key)`
+//! - node: `blake3(0x01 || left || right)`
+//! - root: `blake3(0x02 || n as u64 le || top)`, where `top` is the hash of
+//!   the whole tree, or zero for an empty tree.
+
+use core::fmt;
+
+/// Longest authentication path accepted, enough for `2^64` leaves.
+pub const MAX_PATH_LEN: usize = 64;
+
+pub type Hash = [u8; 32];
+
+pub fn leaf_hash(key: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[0]);
+    hasher.update(key);
+    hasher.finalize().into()
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[1]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+pub fn root_hash(leaf_count: u64, top: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[2]);
+    hasher.update(&leaf_count.to_le_bytes());
+    hasher.update(top);
+    hasher.finalize().into()
+}
+
+/// Recomputes the hash of a whole tree of `leaf_count` leaves from the leaf at
+/// `index` and its authentication path, following RFC 9162 section 2.1.3.2.
+pub fn top_from_path(index: u64, leaf_count: u64, leaf: Hash, path: &[Hash]) -> Option<Hash> {
+    if index >= leaf_count {
+        return None;
+    }
+    let (mut fnode, mut snode) = (index, leaf_count - 1);
+    let mut hash = leaf;
+    for sibling in path {
+        if snode == 0 {
+            return None;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            hash = node_hash(sibling, &hash);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    (snode == 0).then_some(hash)
+}
+
+/// A leaf next to an absent key, with its authentication path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighbor {
+    pub index: u64,
+    pub key: Hash,
+    pub path: Vec<Hash>,
+}
+
+/// Shows that `key` is not a leaf of a tree of `leaf_count` leaves: either its
+/// two neighbours are adjacent leaves, or its only neighbour is the first or
+/// last leaf, or the tree is empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExclusionProof {
+    pub key: Hash,
+    pub leaf_count: u64,
+    /// The greatest leaf below `key`, if any.
+    pub lower: Option<Neighbor>,
+    /// The least leaf above `key`, if any.
+    pub upper: Option<Neighbor>,
+}
+
+/// What a verified [`ExclusionProof`] establishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exclusion {
+    pub root: Hash,
+    pub key: Hash,
+    /// Leaf hashes of the neighbours, or zero where there is none.
+    pub lower: Hash,
+    pub upper: Hash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusionError {
+    /// The neighbours do not bracket the key, or are not adjacent leaves.
+    NotNeighbors,
+    /// A path does not lead to a tree of the claimed size, or the two paths
+    /// lead to different trees.
+    BadPath,
+    /// The proof bytes are malformed.
+    Encoding,
+}
+
+impl fmt::Display for ExclusionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotNeighbors => write!(f, "leaves are not the key's neighbours"),
+            Self::BadPath => write!(f, "authentication paths do not match one tree"),
+            Self::Encoding => write!(f, "malformed exclusion proof"),
+        }
+    }
+}
+
+impl std::error::Error for ExclusionError {}
+
+impl ExclusionProof {
+    pub fn verify(&self) -> Result<Exclusion, ExclusionError> {
+        let n = self.leaf_count;
+        let bracketed = match (&self.lower, &self.upper) {
+            (None, None) => n == 0,
+            (Some(lower), None) => lower.index.checked_add(1) == Some(n) && lower.key < self.key,
+            (None, Some(upper)) => upper.index == 0 && self.key < upper.key,
+            (Some(lower), Some(upper)) => {
+                lower.index.checked_add(1) == Some(upper.index)
+                    && lower.key < self.key
+                    && self.key < upper.key
+            }
+        };
+        if !bracketed {
+            return Err(ExclusionError::NotNeighbors);
+        }
+
+        let mut top = None;
+        for neighbor in [&self.lower, &self.upper].into_iter().flatten() {
+            let hash = top_from_path(neighbor.index, n, leaf_hash(&neighbor.key), &neighbor.path)
+                .ok_or(ExclusionError::BadPath)?;
+            if top.is_some_and(|top| top != hash) {
+                return Err(ExclusionError::BadPath);
+            }
+            top = Some(hash);
+        }
+
+        let leaf = |neighbor: &Option<Neighbor>| {
+            neighbor
+                .as_ref()
+                .map_or([0; 32], |neighbor| leaf_hash(&neighbor.key))
+        };
+        Ok(Exclusion {
+            root: root_hash(n, &top.unwrap_or_default()),
+            key: self.key,
+            lower: leaf(&self.lower),
+            upper: leaf(&self.upper),
+        })
+    }
+
+    /// Serializes the proof: the key, the leaf count as a little-endian `u64`,
+    /// then each neighbour as a presence byte followed, if present, by its
+    /// index, key, path length byte and path.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = self.key.to_vec();
+        encoded.extend_from_slice(&self.leaf_count.to_le_bytes());
+        for neighbor in [&self.lower, &self.upper] {
+            match neighbor {
+                None => encoded.push(0),
+                Some(neighbor) => {
+                    encoded.push(1);
+                    encoded.extend_from_slice(&neighbor.index.to_le_bytes());
+                    encoded.extend_from_slice(&neighbor.key);
+                    encoded.push(neighbor.path.len() as u8);
+                    encoded.extend_from_slice(neighbor.path.as_flattened());
+                }
+            }
+        }
+        encoded
+    }
+
+    pub fn decode(mut bytes: &[u8]) -> Result<Self, ExclusionError> {
+        fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], ExclusionError> {
+            let (head, rest) = bytes
+                .split_at_checked(len)
+                .ok_or(ExclusionError::Encoding)?;
+            *bytes = rest;
+            Ok(head)
+        }
+        fn hash(bytes: &mut &[u8]) -> Result<Hash, ExclusionError> {
+            Ok(take(bytes, 32)?.try_into().unwrap())
+        }
+        fn u64_le(bytes: &mut &[u8]) -> Result<u64, ExclusionError> {
+            Ok(u64::from_le_bytes(take(bytes, 8)?.try_into().unwrap()))
+        }
+        fn neighbor(bytes: &mut &[u8]) -> Result<Option<Neighbor>, ExclusionError> {
+            match take(bytes, 1)? {
+                [0] => Ok(None),
+                [1] => {
+                    let index = u64_le(bytes)?;
+                    let key = hash(bytes)?;
+                    let len = take(bytes, 1)?[0] as usize;
+                    if len > MAX_PATH_LEN {
+                        return Err(ExclusionError::Encoding);
+                    }
+                    let path = (0..len).map(|_| hash(bytes)).collect::<Result<_, _>>()?;
+                    Ok(Some(Neighbor { index, key, path }))
+                }
+                _ => Err(ExclusionError::Encoding),
+            }
+        }
+
+        let proof = Self {
+            key: hash(&mut bytes)?,
+            leaf_count: u64_le(&mut bytes)?,
+            lower: neighbor(&mut bytes)?,
+            upper: neighbor(&mut bytes)?,
+        };
+        if !bytes.is_empty() {
+            return Err(ExclusionError::Encoding);
+        }
+        Ok(proof)
+    }
+}
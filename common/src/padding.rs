@@ -0,0 +1,193 @@
+//! Deterministic padding applied to a message before it is hashed, for
+//! consumers such as the BitVM circuit that expect fixed-size message
+//! commitments.
+
+use core::{fmt, str::FromStr};
+
+/// Largest `log2` accepted by [`Padding::ZeroPad`]. Padding bytes are hashed
+/// but never stored, so this only bounds the cycles spent on them; the host
+/// also counts them against its input size limit.
+pub const MAX_ZERO_PAD_LOG2: u8 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    /// Hash the message as is.
+    #[default]
+    None,
+    /// Append zero bytes up to exactly `2^log2` bytes. Longer messages are
+    /// rejected.
+    ZeroPad { log2: u8 },
+    /// Append the message length as a little-endian `u64`.
+    LengthSuffixed,
+}
+
+/// The message does not fit the padded size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooLong {
+    pub len: u64,
+    pub max: u64,
+}
+
+impl fmt::Display for TooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "message of {} bytes does not fit the padded size of {} bytes",
+            self.len, self.max
+        )
+    }
+}
+
+impl std::error::Error for TooLong {}
+
+impl Padding {
+    /// The two-byte form recorded in the journal and sent to the guest.
+    pub fn encode(&self) -> [u8; 2] {
+        match self {
+            Self::None => [0, 0],
+            Self::ZeroPad { log2 } => [1, *log2],
+            Self::LengthSuffixed => [2, 0],
+        }
+    }
+
+    pub fn decode(bytes: [u8; 2]) -> Option<Self> {
+        match bytes {
+            [0, 0] => Some(Self::None),
+            [1, log2] if log2 <= MAX_ZERO_PAD_LOG2 => Some(Self::ZeroPad { log2 }),
+            [2, 0] => Some(Self::LengthSuffixed),
+            _ => None,
+        }
+    }
+
+    /// Checks that a message of `len` bytes can be padded.
+    pub fn check(&self, len: u64) -> Result<(), TooLong> {
+        match self {
+            Self::ZeroPad { log2 } if len > 1 << log2 => Err(TooLong {
+                len,
+                max: 1 << log2,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Feeds the padding for a message of `len` bytes to `update`, in pieces.
+    pub fn apply(&self, len: u64, mut update: impl FnMut(&[u8])) -> Result<(), TooLong> {
+        self.check(len)?;
+        match self {
+            Self::None => {}
+            Self::ZeroPad { log2 } => {
+                const ZEROS: [u8; 4096] = [0; 4096];
+                let mut remaining = (1u64 << log2) - len;
+                while remaining > 0 {
+                    let n = remaining.min(ZEROS.len() as u64);
+                    update(&ZEROS[..n as usize]);
+                    remaining -= n;
+                }
+            }
+            Self::LengthSuffixed => update(&len.to_le_bytes()),
+        }
+        Ok(())
+    }
+}
+
+/// Parses `none`, `zero-pad:<log2>` or `length-suffixed`.
+impl FromStr for Padding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "length-suffixed" => Ok(Self::LengthSuffixed),
+            _ => {
+                let log2 = s
+                    .strip_prefix("zero-pad:")
+                    .and_then(|log2| log2.parse::<u8>().ok())
+                    .filter(|&log2| log2 <= MAX_ZERO_PAD_LOG2)
+                    .ok_or_else(|| {
+                        format!(
+                            "unknown padding {s:?}: expected `none`, `length-suffixed` \
+                             or `zero-pad:<log2>` with log2 at most {MAX_ZERO_PAD_LOG2}"
+                        )
+                    })?;
+                Ok(Self::ZeroPad { log2 })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Padding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::ZeroPad { log2 } => write!(f, "zero-pad:{log2}"),
+            Self::LengthSuffixed => write!(f, "length-suffixed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn padded(padding: Padding, message: &[u8]) -> Result<Vec<u8>, TooLong> {
+        let mut padded = message.to_vec();
+        padding.apply(message.len() as u64, |bytes| {
+            padded.extend_from_slice(bytes)
+        })?;
+        Ok(padded)
+    }
+
+    #[test]
+    fn none_leaves_message() {
+        assert_eq!(padded(Padding::None, b"abc"), Ok(b"abc".to_vec()));
+    }
+
+    #[test]
+    fn zero_pad_fills_to_size() {
+        let padding = Padding::ZeroPad { log2: 13 };
+        for len in [0, 1, 4096, 4097, 8191, 8192] {
+            let message = vec![0xff; len];
+            let padded = padded(padding, &message).unwrap();
+            assert_eq!(padded.len(), 8192);
+            assert_eq!(&padded[..len], &message[..]);
+            assert!(padded[len..].iter().all(|&byte| byte == 0));
+        }
+        assert_eq!(
+            padded(padding, &[0; 8193]),
+            Err(TooLong {
+                len: 8193,
+                max: 8192
+            })
+        );
+    }
+
+    #[test]
+    fn length_suffix_is_little_endian() {
+        assert_eq!(
+            padded(Padding::LengthSuffixed, b"abc"),
+            Ok(b"abc\x03\0\0\0\0\0\0\0".to_vec())
+        );
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        for padding in [
+            Padding::None,
+            Padding::ZeroPad { log2: 0 },
+            Padding::ZeroPad {
+                log2: MAX_ZERO_PAD_LOG2,
+            },
+            Padding::LengthSuffixed,
+        ] {
+            assert_eq!(Padding::decode(padding.encode()), Some(padding));
+            assert_eq!(padding.to_string().parse::<Padding>(), Ok(padding));
+        }
+        for bytes in [[0, 1], [1, MAX_ZERO_PAD_LOG2 + 1], [2, 1], [3, 0]] {
+            assert_eq!(Padding::decode(bytes), None, "{bytes:?}");
+        }
+        let too_large = format!("zero-pad:{}", MAX_ZERO_PAD_LOG2 + 1);
+        for s in ["", "zero-pad", "zero-pad:", "zero-pad:x", &too_large] {
+            assert!(s.parse::<Padding>().is_err(), "{s:?}");
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! BLAKE3 written for the guest's rv32im target.
+//!
+//! The `blake3` crate has no SIMD backend for RISC-V, so in the guest it runs
+//! its portable code, which is written for any target. This implementation
+//! only hashes, with none of the keyed, derive-key or extendable outputs,
+//! and is arranged for a 32-bit core with no vector unit:
+//!
+//! - the message schedule of every round is a constant table, so rounds
+//!   index the block words directly instead of permuting them between rounds;
+//! - the seven rounds and their quarter-rounds are unrolled, leaving the
+//!   state in registers;
+//! - whole blocks are compressed straight from the input, and only the final
+//!   block of each chunk is copied into the buffer;
+//! - there is no CPU feature detection or multi-chunk dispatch.
+//!
+//! Digests are those of [`blake3::hash`]; only the cycle count differs, which
+//! `estimate` reports.
+
+const OUT_LEN: usize = 32;
+const BLOCK_LEN: usize = 64;
+const CHUNK_LEN: usize = 1024;
+/// Deep enough for 2^54 chunks, more than a `u64` length can hold.
+const MAX_DEPTH: usize = 54;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A, 0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
+];
+
+/// The message word each round reads at each position.
+const MSG_SCHEDULE: [[usize; 16]; 7] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8],
+    [3, 4, 10, 12, 13, 2, 7, 14, 6, 5, 9, 0, 11, 15, 8, 1],
+    [10, 7, 12, 9, 14, 3, 13, 15, 4, 0, 11, 2, 5, 8, 1, 6],
+    [12, 13, 9, 11, 15, 10, 14, 8, 7, 2, 5, 3, 0, 1, 6, 4],
+    [9, 14, 11, 5, 8, 12, 15, 1, 13, 3, 0, 10, 2, 6, 4, 7],
+    [11, 15, 5, 0, 1, 9, 8, 6, 14, 10, 2, 12, 3, 4, 7, 13],
+];
+
+#[inline(always)]
+fn g(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(12);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(8);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(7);
+}
+
+#[inline(always)]
+fn round(v: &mut [u32; 16], m: &[u32; 16], s: &[usize; 16]) {
+    g(v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+    g(v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+    g(v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+    g(v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+    g(v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+    g(v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+    g(v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+    g(v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+}
+
+/// The compression function, returning the first half of its output, which
+/// is all that chaining values and a 32-byte digest use.
+fn compress(
+    cv: &[u32; 8],
+    block: &[u8; BLOCK_LEN],
+    counter: u64,
+    len: u32,
+    flags: u32,
+) -> [u32; 8] {
+    let mut m = [0u32; 16];
+    for (word, bytes) in m.iter_mut().zip(block.as_chunks::<4>().0) {
+        *word = u32::from_le_bytes(*bytes);
+    }
+    #[rustfmt::skip]
+    let mut v = [
+        cv[0], cv[1], cv[2], cv[3], cv[4], cv[5], cv[6], cv[7],
+        IV[0], IV[1], IV[2], IV[3],
+        counter as u32, (counter >> 32) as u32, len, flags,
+    ];
+    round(&mut v, &m, &MSG_SCHEDULE[0]);
+    round(&mut v, &m, &MSG_SCHEDULE[1]);
+    round(&mut v, &m, &MSG_SCHEDULE[2]);
+    round(&mut v, &m, &MSG_SCHEDULE[3]);
+    round(&mut v, &m, &MSG_SCHEDULE[4]);
+    round(&mut v, &m, &MSG_SCHEDULE[5]);
+    round(&mut v, &m, &MSG_SCHEDULE[6]);
+    let mut out = [0u32; 8];
+    for (i, word) in out.iter_mut().enumerate() {
+        *word = v[i] ^ v[i + 8];
+    }
+    out
+}
+
+fn parent(left: &[u32; 8], right: &[u32; 8], flags: u32) -> [u32; 8] {
+    let mut block = [0u8; BLOCK_LEN];
+    for (bytes, word) in block
+        .as_chunks_mut::<4>()
+        .0
+        .iter_mut()
+        .zip(left.iter().chain(right))
+    {
+        *bytes = word.to_le_bytes();
+    }
+    compress(&IV, &block, 0, BLOCK_LEN as u32, PARENT | flags)
+}
+
+/// An incremental BLAKE3 hasher, for the guest's hashing of bulk data.
+#[derive(Clone)]
+pub struct Hasher {
+    cv: [u32; 8],
+    chunk_counter: u64,
+    /// The chunk's last block read so far, compressed once more input shows
+    /// it is not the chunk's final block.
+    block: [u8; BLOCK_LEN],
+    block_len: usize,
+    blocks_compressed: usize,
+    stack: [[u32; 8]; MAX_DEPTH],
+    stack_len: usize,
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher {
+    pub const fn new() -> Self {
+        Self {
+            cv: IV,
+            chunk_counter: 0,
+            block: [0; BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+            stack: [[0; 8]; MAX_DEPTH],
+            stack_len: 0,
+        }
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 {
+            CHUNK_START
+        } else {
+            0
+        }
+    }
+
+    fn chunk_len(&self) -> usize {
+        self.blocks_compressed * BLOCK_LEN + self.block_len
+    }
+
+    fn compress_block(&mut self, block: &[u8; BLOCK_LEN]) {
+        self.cv = compress(
+            &self.cv,
+            block,
+            self.chunk_counter,
+            BLOCK_LEN as u32,
+            self.start_flag(),
+        );
+        self.blocks_compressed += 1;
+    }
+
+    /// Merges the finished chunk into the stack of subtree chaining values,
+    /// combining every pair of subtrees of equal size.
+    fn finish_chunk(&mut self) {
+        let flags = self.start_flag() | CHUNK_END;
+        let mut cv = compress(
+            &self.cv,
+            &self.block,
+            self.chunk_counter,
+            self.block_len as u32,
+            flags,
+        );
+        self.chunk_counter += 1;
+        let mut total = self.chunk_counter;
+        while total & 1 == 0 {
+            self.stack_len -= 1;
+            cv = parent(&self.stack[self.stack_len], &cv, 0);
+            total >>= 1;
+        }
+        self.stack[self.stack_len] = cv;
+        self.stack_len += 1;
+
+        self.cv = IV;
+        self.block = [0; BLOCK_LEN];
+        self.block_len = 0;
+        self.blocks_compressed = 0;
+    }
+
+    pub fn update(&mut self, mut input: &[u8]) -> &mut Self {
+        while !input.is_empty() {
+            // A chunk is only finished once more input arrives, since the
+            // last chunk is finalized differently.
+            if self.chunk_len() == CHUNK_LEN {
+                self.finish_chunk();
+            }
+            if self.block_len == BLOCK_LEN {
+                let block = std::mem::replace(&mut self.block, [0; BLOCK_LEN]);
+                self.compress_block(&block);
+                self.block_len = 0;
+            }
+            // Blocks followed by more input of the same chunk are compressed
+            // in place.
+            if self.block_len == 0 {
+                let mut room = CHUNK_LEN - self.chunk_len();
+                while input.len() > BLOCK_LEN && room > BLOCK_LEN {
+                    let (block, rest) = input.split_first_chunk::<BLOCK_LEN>().unwrap();
+                    self.compress_block(block);
+                    input = rest;
+                    room -= BLOCK_LEN;
+                }
+            }
+            let take = input.len().min(BLOCK_LEN - self.block_len);
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&input[..take]);
+            self.block_len += take;
+            input = &input[take..];
+        }
+        self
+    }
+
+    pub fn finalize(&self) -> blake3::Hash {
+        let flags = self.start_flag() | CHUNK_END;
+        let words = if self.stack_len == 0 {
+            compress(
+                &self.cv,
+                &self.block,
+                self.chunk_counter,
+                self.block_len as u32,
+                flags | ROOT,
+            )
+        } else {
+            let mut cv = compress(
+                &self.cv,
+                &self.block,
+                self.chunk_counter,
+                self.block_len as u32,
+                flags,
+            );
+            for (depth, left) in self.stack[..self.stack_len].iter().enumerate().rev() {
+                let root = if depth == 0 { ROOT } else { 0 };
+                cv = parent(left, &cv, root);
+            }
+            cv
+        };
+        let mut digest = [0u8; OUT_LEN];
+        for (bytes, word) in digest.as_chunks_mut::<4>().0.iter_mut().zip(&words) {
+            *bytes = word.to_le_bytes();
+        }
+        blake3::Hash::from(digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENS: [usize; 11] = [
+        0,
+        1,
+        63,
+        64,
+        65,
+        1023,
+        1024,
+        1025,
+        2048,
+        3073,
+        (1 << 20) + 1,
+    ];
+
+    fn input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn matches_blake3() {
+        for len in LENS {
+            let data = input(len);
+            let digest = Hasher::new().update(&data).finalize();
+            assert_eq!(digest, blake3::hash(&data), "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn matches_blake3_in_pieces() {
+        for len in LENS {
+            let data = input(len);
+            let expected = blake3::hash(&data);
+            for piece in [1, 7, 63, 64, 65, 1000, 1024, 4096] {
+                let mut hasher = Hasher::new();
+                for part in data.chunks(piece) {
+                    hasher.update(part);
+                }
+                assert_eq!(hasher.finalize(), expected, "{len} bytes in {piece}s");
+            }
+        }
+    }
+
+    #[test]
+    fn matches_blake3_split_anywhere() {
+        let data = input(3073);
+        let expected = blake3::hash(&data);
+        for at in 0..=data.len() {
+            let (head, tail) = data.split_at(at);
+            let digest = Hasher::new().update(head).update(tail).finalize();
+            assert_eq!(digest, expected, "split at {at}");
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! The digest committed by [`Mode::Sha256Chunks`](crate::Mode::Sha256Chunks):
+//! BLAKE3 over the SHA-256 digests of fixed-size chunks of the input, for
+//! inputs too large to hash with BLAKE3 alone in the guest.
+//!
+//! The zkVM has no BLAKE3 circuit, so the guest computes BLAKE3 with rv32im
+//! instructions, while SHA-256 runs on the zkVM's accelerator circuit at a
+//! small fraction of the cycles per byte. Hashing each [`CHUNK_LEN`]-byte
+//! chunk with SHA-256 leaves BLAKE3 only 32 bytes per chunk:
+//!
+//! ```text
+//! digest = BLAKE3-derive-key(CONTEXT,
+//!     SHA-256(chunk 0) || ... || SHA-256(chunk n-1) || input length as u64 LE)
+//! ```
+//!
+//! where the last chunk may be short and an empty input has no chunks. The
+//! digest is not the BLAKE3 digest of the input: whoever checks the journal
+//! against the data must compute this construction, with [`ChunkHasher`].
+
+/// Bytes hashed with SHA-256 at a time.
+pub const CHUNK_LEN: usize = 4096;
+
+/// BLAKE3 derive-key context of the digest, keeping it apart from plain
+/// BLAKE3 digests of the same bytes.
+pub const CONTEXT: &str = "r0-bitvm-blake3 2025 sha256 chunk digest v1";
+
+/// Computes the digest incrementally, with the SHA-256 implementation of the
+/// caller: the accelerator in the guest, software on the host.
+pub struct ChunkHasher<F> {
+    sha256: F,
+    chunk: Vec<u8>,
+    digests: blake3::Hasher,
+    len: u64,
+}
+
+impl<F: FnMut(&[u8]) -> [u8; 32]> ChunkHasher<F> {
+    pub fn new(sha256: F) -> Self {
+        Self {
+            sha256,
+            chunk: Vec::with_capacity(CHUNK_LEN),
+            digests: blake3::Hasher::new_derive_key(CONTEXT),
+            len: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        while !data.is_empty() {
+            // Whole chunks are hashed in place rather than copied.
+            if self.chunk.is_empty() && data.len() >= CHUNK_LEN {
+                let (chunk, rest) = data.split_at(CHUNK_LEN);
+                self.digests.update(&(self.sha256)(chunk));
+                data = rest;
+                continue;
+            }
+            let take = data.len().min(CHUNK_LEN - self.chunk.len());
+            self.chunk.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.chunk.len() == CHUNK_LEN {
+                self.digests.update(&(self.sha256)(&self.chunk));
+                self.chunk.clear();
+            }
+        }
+    }
+
+    /// The input length and the digest.
+    pub fn finalize(mut self) -> (u64, [u8; 32]) {
+        if !self.chunk.is_empty() {
+            self.digests.update(&(self.sha256)(&self.chunk));
+        }
+        self.digests.update(&self.len.to_le_bytes());
+        (self.len, self.digests.finalize().into())
+    }
+}
@@ -0,0 +1,177 @@
+//! Sparse BLAKE3 Merkle trees over 256-bit keys, and the proofs verified by
+//! [`Mode::SparseMerkle`](crate::Mode::SparseMerkle).
+//!
+//! Every possible key has a leaf at depth [`DEPTH`], reached from the root by
+//! following the key's bits from the most significant one, `0` going left.
+//! Absent keys have the zero leaf, so proving a key absent is the same as
+//! proving its leaf is zero. Nodes are hashed as in [`merkle`](crate::merkle):
+//!
+//! - leaf: `blake3(0x00 || key || blake3(value))`, or zero if the key is absent
+//! - node: `blake3(0x01 || left || right)`
+//!
+//! Almost all subtrees are empty, and the hash of an empty subtree depends
+//! only on its height, so these are computed once into [`Defaults`] and
+//! omitted from proofs.
+
+use crate::merkle::{node_hash, Hash};
+
+/// Number of levels below the root.
+pub const DEPTH: usize = 256;
+
+/// Largest encoded proof: a key, a leaf, the sibling bitmap and every sibling.
+pub const MAX_PROOF_LEN: usize = 32 + 33 + DEPTH / 8 + DEPTH * 32;
+
+/// Hashes of the empty subtree of each height, from the zero leaf up to the
+/// root of the empty tree.
+#[derive(Debug, Clone)]
+pub struct Defaults([Hash; DEPTH + 1]);
+
+impl Defaults {
+    pub fn new() -> Self {
+        let mut hashes = [[0; 32]; DEPTH + 1];
+        for height in 1..=DEPTH {
+            hashes[height] = node_hash(&hashes[height - 1], &hashes[height - 1]);
+        }
+        Self(hashes)
+    }
+
+    /// The hash of an empty subtree `height` levels above the leaves.
+    pub fn get(&self, height: usize) -> &Hash {
+        &self.0[height]
+    }
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn leaf_hash(key: &Hash, value_digest: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[0]);
+    hasher.update(key);
+    hasher.update(value_digest);
+    hasher.finalize().into()
+}
+
+/// The bit of `key` that picks a child at `depth` below the root: `false` for
+/// the left child.
+pub fn key_bit(key: &Hash, depth: usize) -> bool {
+    key[depth / 8] >> (7 - depth % 8) & 1 == 1
+}
+
+/// Hash of the subtree at `depth` holding exactly `leaves`, pairs of keys and
+/// value digests sorted by key and sharing the first `depth` bits of their
+/// keys.
+pub fn subtree_hash(leaves: &[(&Hash, &Hash)], depth: usize, defaults: &Defaults) -> Hash {
+    match leaves {
+        [] => *defaults.get(DEPTH - depth),
+        [(key, value)] => {
+            // Only empty subtrees hang off the path to a lone leaf.
+            let mut hash = leaf_hash(key, value);
+            for level in (depth..DEPTH).rev() {
+                let sibling = defaults.get(DEPTH - 1 - level);
+                hash = if key_bit(key, level) {
+                    node_hash(sibling, &hash)
+                } else {
+                    node_hash(&hash, sibling)
+                };
+            }
+            hash
+        }
+        _ => {
+            let split = leaves.partition_point(|(key, _)| !key_bit(key, depth));
+            let (left, right) = leaves.split_at(split);
+            node_hash(
+                &subtree_hash(left, depth + 1, defaults),
+                &subtree_hash(right, depth + 1, defaults),
+            )
+        }
+    }
+}
+
+/// Shows that `key` maps to `value` in, or is absent from, a sparse tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseProof {
+    pub key: Hash,
+    /// BLAKE3 digest of the value, or `None` if the key is absent.
+    pub value: Option<Hash>,
+    /// Bit `h` is set when the sibling `h` levels above the leaves is not an
+    /// empty subtree.
+    pub bitmap: [u8; DEPTH / 8],
+    /// The non-empty siblings, from the leaf upwards.
+    pub siblings: Vec<Hash>,
+}
+
+impl SparseProof {
+    pub fn has_sibling(&self, height: usize) -> bool {
+        self.bitmap[height / 8] >> (height % 8) & 1 == 1
+    }
+
+    /// Recomputes the root of the tree the proof is for.
+    pub fn root(&self, defaults: &Defaults) -> Hash {
+        let mut hash = match &self.value {
+            Some(value) => leaf_hash(&self.key, value),
+            None => [0; 32],
+        };
+        let mut siblings = self.siblings.iter();
+        for height in 0..DEPTH {
+            let sibling = if self.has_sibling(height) {
+                siblings.next().expect("decoded proofs match their bitmap")
+            } else {
+                defaults.get(height)
+            };
+            hash = if key_bit(&self.key, DEPTH - 1 - height) {
+                node_hash(sibling, &hash)
+            } else {
+                node_hash(&hash, sibling)
+            };
+        }
+        hash
+    }
+
+    /// Serializes the proof: the key, a presence byte followed, if present,
+    /// by the value digest, then the bitmap and the siblings it marks.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = self.key.to_vec();
+        match &self.value {
+            None => encoded.push(0),
+            Some(value) => {
+                encoded.push(1);
+                encoded.extend_from_slice(value);
+            }
+        }
+        encoded.extend_from_slice(&self.bitmap);
+        encoded.extend_from_slice(self.siblings.as_flattened());
+        encoded
+    }
+
+    /// Decodes a proof, rejecting trailing bytes and sibling lists that do not
+    /// match the bitmap.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (key, rest) = bytes.split_first_chunk::<32>()?;
+        let (value, rest) = match rest.split_first()? {
+            (0, rest) => (None, rest),
+            (1, rest) => {
+                let (value, rest) = rest.split_first_chunk::<32>()?;
+                (Some(*value), rest)
+            }
+            _ => return None,
+        };
+        let (bitmap, rest) = rest.split_first_chunk::<{ DEPTH / 8 }>()?;
+        let (siblings, []) = rest.as_chunks::<32>() else {
+            return None;
+        };
+        let marked: u32 = bitmap.iter().map(|byte| byte.count_ones()).sum();
+        if siblings.len() != marked as usize {
+            return None;
+        }
+        Some(Self {
+            key: *key,
+            value,
+            bitmap: *bitmap,
+            siblings: siblings.to_vec(),
+        })
+    }
+}
@@ -0,0 +1,162 @@
+//! Image ID allowlists with validity windows, mirroring how the bridge rotates
+//! guest programs: each release is accepted only between two Bitcoin block
+//! heights.
+//!
+//! ```toml
+//! [[images]]
+//! label = "v1"
+//! image_id = "<hex>"
+//! valid_until = 880000
+//!
+//! [[images]]
+//! label = "v2"
+//! image_id = "<hex>"
+//! valid_from = 870000
+//! ```
+
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context, Result};
+use risc0_zkvm::{sha::Digest, Receipt};
+use serde::Deserialize;
+
+use crate::verifier;
+
+#[derive(Debug, Deserialize)]
+pub struct Allowlist {
+    #[serde(default)]
+    pub images: Vec<AllowedImage>,
+}
+
+/// A trusted image ID and the block heights it is valid for.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AllowedImage {
+    /// Human-readable name of the guest release, used in reports.
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(with = "hex::serde")]
+    pub image_id: [u8; 32],
+    /// First height at which the image is valid. Unbounded if unset.
+    #[serde(default)]
+    pub valid_from: Option<u64>,
+    /// First height at which the image is no longer valid. Unbounded if unset.
+    #[serde(default)]
+    pub valid_until: Option<u64>,
+}
+
+impl AllowedImage {
+    pub fn image_id(&self) -> Digest {
+        self.image_id.into()
+    }
+
+    pub fn is_valid_at(&self, height: u64) -> bool {
+        self.valid_from.is_none_or(|from| height >= from)
+            && self.valid_until.is_none_or(|until| height < until)
+    }
+
+    /// The label if there is one, otherwise the hex image ID.
+    pub fn name(&self) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| hex::encode(self.image_id))
+    }
+}
+
+impl Allowlist {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read allowlist {}", path.display()))?;
+        let allowlist: Self = toml::from_str(&contents)
+            .with_context(|| format!("invalid allowlist {}", path.display()))?;
+        for (i, image) in allowlist.images.iter().enumerate() {
+            // One window per image ID, so a receipt matches one entry.
+            if let Some(other) = allowlist.images[..i]
+                .iter()
+                .find(|other| other.image_id == image.image_id)
+            {
+                bail!(
+                    "images {} and {} have the same image ID {}",
+                    other.name(),
+                    image.name(),
+                    hex::encode(image.image_id)
+                );
+            }
+            if let (Some(from), Some(until)) = (image.valid_from, image.valid_until) {
+                ensure!(
+                    from < until,
+                    "image {} has an empty validity window {from}..{until}",
+                    image.name()
+                );
+            }
+        }
+        Ok(allowlist)
+    }
+
+    /// Image IDs valid at `height`.
+    pub fn active_at(&self, height: u64) -> Vec<Digest> {
+        self.images
+            .iter()
+            .filter(|image| image.is_valid_at(height))
+            .map(AllowedImage::image_id)
+            .collect()
+    }
+
+    /// Verifies `receipt` and returns the allowlist entry it matched, rejecting
+    /// it if that image ID is not valid at `height`.
+    pub fn verify(&self, receipt: &Receipt, height: u64) -> Result<&AllowedImage> {
+        let image_id = verifier::claimed_image_id(receipt)?;
+        let Some(image) = self
+            .images
+            .iter()
+            .find(|image| image.image_id() == image_id)
+        else {
+            bail!("receipt claims image ID {image_id}, which is not in the allowlist");
+        };
+        if !image.is_valid_at(height) {
+            let bound = |bound: Option<u64>| bound.map_or(String::new(), |b| b.to_string());
+            bail!(
+                "image {} is only valid at heights {}..{}, not at {height}",
+                image.name(),
+                bound(image.valid_from),
+                bound(image.valid_until)
+            );
+        }
+        verifier::verify_receipt(receipt, image.image_id())?;
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(contents: &str) -> Result<Allowlist> {
+        let path = std::env::temp_dir().join(format!(
+            "r0-bitvm-allowlist-{}-{}.toml",
+            std::process::id(),
+            blake3::hash(contents.as_bytes()).to_hex()
+        ));
+        std::fs::write(&path, contents)?;
+        let allowlist = Allowlist::load(&path);
+        std::fs::remove_file(&path)?;
+        allowlist
+    }
+
+    #[test]
+    fn rejects_duplicate_image_ids() {
+        let id = hex::encode([7; 32]);
+        let err = load(&format!(
+            "[[images]]\nlabel = \"v1\"\nimage_id = \"{id}\"\nvalid_until = 10\n\n\
+             [[images]]\nlabel = \"v2\"\nimage_id = \"{id}\"\nvalid_from = 20\n"
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("same image ID"), "{err:#}");
+
+        let other = hex::encode([8; 32]);
+        let allowlist = load(&format!(
+            "[[images]]\nimage_id = \"{id}\"\n\n[[images]]\nimage_id = \"{other}\"\n"
+        ))
+        .unwrap();
+        assert_eq!(allowlist.active_at(0).len(), 2);
+    }
+}
@@ -0,0 +1,671 @@
+//! Building the assert transactions as PSBTs for the operator to sign, with
+//! its own key or with an external signer given the key's origin.
+//!
+//! An assert is a package of two transactions. The commit transaction spends
+//! outputs of the operator's P2TR address into a taproot output whose only
+//! tapleaf is [`AssertWitness::tapleaf_script`], and the reveal transaction
+//! spends that output through the leaf, carrying the verifier witness in the
+//! layout [`assert_tx`](crate::assert_tx) reads back. The witness itself
+//! travels in a proprietary field of the reveal input until it is finalized.
+//!
+//! Fees are paid at a given rate, usually the backend's estimate from
+//! [`BitcoinRpc::fee_rate`](crate::bitcoin_rpc::BitcoinRpc::fee_rate), and
+//! both transactions signal replace-by-fee unless asked not to: a package
+//! stuck in the mempool past a challenge deadline loses the challenge, so
+//! [`AssertPackage::bump`] and [`AssertPackage::bump_reveal`] rebuild it at a
+//! higher rate that its replacement is relayed at.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use bitcoin::{
+    absolute::LockTime,
+    key::{Keypair, TapTweak},
+    psbt::{self, raw::ProprietaryKey},
+    secp256k1::{Message, Secp256k1, Signing},
+    sighash::{Prevouts, SighashCache},
+    taproot::{self, ControlBlock, LeafVersion, TapLeafHash, TaprootSpendInfo},
+    transaction::Version,
+    Amount, FeeRate, OutPoint, Psbt, ScriptBuf, Sequence, TapSighash, TapSighashType, Transaction,
+    TxIn, TxOut, Weight, Witness, XOnlyPublicKey,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{assert_tx::AssertWitness, operator::OperatorKey};
+
+/// Value of the reveal transaction's output when none is given: the dust
+/// limit of a P2TR output.
+pub const DEFAULT_REVEAL_VALUE: Amount = Amount::from_sat(330);
+
+/// Fee a replacement must add, per vbyte of it, over what it replaces
+/// (BIP125), at bitcoind's default `-incrementalrelayfee`.
+const INCREMENTAL_RELAY_FEE: FeeRate = FeeRate::from_sat_per_vb_unchecked(1);
+
+/// Identifies the proprietary PSBT field holding the verifier witness.
+const PROPRIETARY_PREFIX: &[u8] = b"r0-bitvm-blake3";
+const WITNESS_SUBTYPE: u8 = 0;
+
+/// Length of a BIP340 signature with the default sighash type.
+const SIGNATURE_LEN: usize = 64;
+
+/// An output of the operator's P2TR address, spent through its key path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Funding {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+}
+
+/// What an assert package is built from.
+#[derive(Debug, Clone)]
+pub struct AssertParams {
+    /// Key of the funding outputs and of the assert tapleaf.
+    pub operator: OperatorKey,
+    pub funding: Vec<Funding>,
+    /// Receives what the commit transaction leaves of the funding, unless it
+    /// would be dust.
+    pub change: ScriptBuf,
+    /// Receives the output of the reveal transaction.
+    pub destination: ScriptBuf,
+    pub reveal_value: Amount,
+    pub fee_rate: FeeRate,
+    /// Signal replace-by-fee on every input.
+    pub rbf: bool,
+}
+
+/// The commit and reveal transactions of an assert, unsigned until the
+/// operator signs them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssertPackage {
+    #[serde(with = "psbt_base64")]
+    pub commit: Psbt,
+    #[serde(with = "psbt_base64")]
+    pub reveal: Psbt,
+}
+
+impl AssertPackage {
+    /// Reads a package written as JSON by `assert-psbt`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let encoded = std::fs::read(path)
+            .with_context(|| format!("failed to read package {}", path.display()))?;
+        serde_json::from_slice(&encoded).context("invalid assert package")
+    }
+
+    pub fn build(params: &AssertParams, witness: &AssertWitness) -> Result<Self> {
+        ensure!(!params.funding.is_empty(), "no funding outputs to spend");
+        let secp = Secp256k1::verification_only();
+        let operator_script = ScriptBuf::new_p2tr(&secp, params.operator.key, None);
+        for funding in &params.funding {
+            ensure!(
+                funding.txout.script_pubkey == operator_script,
+                "funding output {} is not of the operator's P2TR address",
+                funding.outpoint
+            );
+        }
+        let assert_output = AssertOutput::new(&params.operator, witness)?;
+        let sequence = if params.rbf {
+            Sequence::ENABLE_RBF_NO_LOCKTIME
+        } else {
+            Sequence::ENABLE_LOCKTIME_NO_RBF
+        };
+
+        // The reveal transaction's weight does not depend on the outpoint it
+        // spends, so its fee is known before the commit transaction is.
+        let destination = TxOut {
+            value: params.reveal_value,
+            script_pubkey: params.destination.clone(),
+        };
+        ensure!(
+            destination.value >= destination.script_pubkey.minimal_non_dust(),
+            "reveal output of {} is dust",
+            destination.value
+        );
+        let template =
+            assert_output.reveal(OutPoint::null(), Amount::ZERO, &destination, sequence)?;
+        let reveal_fee = fee(params.fee_rate, estimated_weight(&template)?)?;
+        let assert_txout = TxOut {
+            value: params.reveal_value + reveal_fee,
+            script_pubkey: assert_output.script_pubkey(),
+        };
+
+        let commit = commit(params, assert_txout.clone(), sequence)?;
+        let outpoint = OutPoint {
+            txid: commit.unsigned_tx.compute_txid(),
+            vout: 0,
+        };
+        let reveal = assert_output.reveal(outpoint, assert_txout.value, &destination, sequence)?;
+        Ok(Self { commit, reveal })
+    }
+
+    /// The verifier witness the package asserts.
+    pub fn witness(&self) -> Result<AssertWitness> {
+        input_witness(
+            self.reveal
+                .inputs
+                .first()
+                .context("reveal tx has no input")?,
+        )
+    }
+
+    /// The parameters the package was built with, at the fee rate of its
+    /// commit transaction.
+    pub fn params(&self) -> Result<AssertParams> {
+        let commit = &self.commit.unsigned_tx;
+        let funding = commit
+            .input
+            .iter()
+            .zip(&self.commit.inputs)
+            .map(|(input, psbt_input)| {
+                Ok(Funding {
+                    outpoint: input.previous_output,
+                    txout: psbt_input.witness_utxo.clone().with_context(|| {
+                        format!("commit input {} has no UTXO", input.previous_output)
+                    })?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let change = commit
+            .output
+            .get(1)
+            .context("commit tx has no change output to pay a higher fee from")?;
+        let destination = self
+            .reveal
+            .unsigned_tx
+            .output
+            .first()
+            .context("reveal tx has no output")?;
+        Ok(AssertParams {
+            operator: self.operator()?,
+            funding,
+            change: change.script_pubkey.clone(),
+            destination: destination.script_pubkey.clone(),
+            reveal_value: destination.value,
+            fee_rate: fee_rate(&self.commit)?,
+            rbf: commit.is_explicitly_rbf(),
+        })
+    }
+
+    /// The operator's key, with its origin if the package carries one.
+    pub fn operator(&self) -> Result<OperatorKey> {
+        let input = self
+            .reveal
+            .inputs
+            .first()
+            .context("reveal tx has no input")?;
+        let key = input
+            .tap_internal_key
+            .context("reveal input has no internal key")?;
+        Ok(OperatorKey {
+            key,
+            origin: input
+                .tap_key_origins
+                .get(&key)
+                .map(|(_, origin)| origin.clone()),
+        })
+    }
+
+    /// Rebuilds both transactions at `fee_rate`, to replace them while the
+    /// commit transaction is unconfirmed. The higher fees are paid from the
+    /// commit's change, so the commit, and with it the outpoint the reveal
+    /// spends, changes, and both must be signed again.
+    ///
+    /// Replacing the commit evicts the reveal spending it, so the new commit
+    /// must pay more than both did before.
+    pub fn bump(&self, fee_rate: FeeRate) -> Result<Self> {
+        ensure!(
+            self.commit.unsigned_tx.is_explicitly_rbf(),
+            "commit tx does not signal replace-by-fee"
+        );
+        let mut params = self.params()?;
+        params.fee_rate = fee_rate;
+        let bumped = Self::build(&params, &self.witness()?)?;
+        check_replacement(
+            "commit",
+            self.commit.fee()? + self.reveal.fee()?,
+            &bumped.commit,
+        )?;
+        Ok(bumped)
+    }
+
+    /// Rebuilds the reveal transaction alone at `fee_rate`, once the commit
+    /// transaction is confirmed. The higher fee is taken from the reveal's
+    /// output.
+    pub fn bump_reveal(&self, fee_rate: FeeRate) -> Result<Self> {
+        let reveal = &self.reveal.unsigned_tx;
+        ensure!(
+            reveal.is_explicitly_rbf(),
+            "reveal tx does not signal replace-by-fee"
+        );
+        let input = reveal.input.first().context("reveal tx has no input")?;
+        let spent = self.reveal.inputs[0]
+            .witness_utxo
+            .as_ref()
+            .context("reveal input has no UTXO")?;
+        let mut destination = reveal
+            .output
+            .first()
+            .context("reveal tx has no output")?
+            .clone();
+        let assert_output = AssertOutput::new(&self.operator()?, &self.witness()?)?;
+        let template = assert_output.reveal(
+            input.previous_output,
+            spent.value,
+            &destination,
+            input.sequence,
+        )?;
+        let fee = fee(fee_rate, estimated_weight(&template)?)?;
+        destination.value = spent
+            .value
+            .checked_sub(fee)
+            .filter(|value| *value >= destination.script_pubkey.minimal_non_dust())
+            .with_context(|| {
+                format!(
+                    "the assert output of {} cannot pay a fee of {fee} and a non-dust output",
+                    spent.value
+                )
+            })?;
+        let bumped = assert_output.reveal(
+            input.previous_output,
+            spent.value,
+            &destination,
+            input.sequence,
+        )?;
+        check_replacement("reveal", self.reveal.fee()?, &bumped)?;
+        Ok(Self {
+            commit: self.commit.clone(),
+            reveal: bumped,
+        })
+    }
+
+    /// Signs every input of the package with the operator's key: the
+    /// funding inputs through their key path, and the assert input through
+    /// its tapleaf.
+    pub fn sign(&mut self, keypair: &Keypair) -> Result<()> {
+        let operator = self.operator()?.key;
+        ensure!(
+            keypair.x_only_public_key().0 == operator,
+            "key is not the operator's {operator}"
+        );
+        let secp = Secp256k1::new();
+        let tweaked = keypair.tap_tweak(&secp, None).to_inner();
+        for psbt in [&mut self.commit, &mut self.reveal] {
+            let prevouts = prevouts(psbt)?;
+            let prevouts = Prevouts::All(&prevouts);
+            let mut cache = SighashCache::new(&psbt.unsigned_tx);
+            for (index, input) in psbt.inputs.iter_mut().enumerate() {
+                match input.tap_scripts.first_key_value() {
+                    Some((_, (script, version))) => {
+                        let leaf_hash = TapLeafHash::from_script(script, *version);
+                        let sighash = cache.taproot_script_spend_signature_hash(
+                            index,
+                            &prevouts,
+                            leaf_hash,
+                            TapSighashType::Default,
+                        )?;
+                        input
+                            .tap_script_sigs
+                            .insert((operator, leaf_hash), schnorr(&secp, sighash, keypair));
+                    }
+                    None => {
+                        let sighash = cache.taproot_key_spend_signature_hash(
+                            index,
+                            &prevouts,
+                            TapSighashType::Default,
+                        )?;
+                        input.tap_key_sig = Some(schnorr(&secp, sighash, &tweaked));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The commit and reveal transactions with their witnesses, once every
+    /// input is signed. Every signature is checked first, wherever it came
+    /// from.
+    pub fn finalize(&self) -> Result<(Transaction, Transaction)> {
+        Ok((finalize(&self.commit)?, finalize(&self.reveal)?))
+    }
+
+    /// Merges `signed`, the commit or reveal PSBT as signed by an external
+    /// signer, into the package.
+    pub fn combine(&mut self, signed: Psbt) -> Result<()> {
+        let txid = signed.unsigned_tx.compute_txid();
+        let psbt = [&mut self.commit, &mut self.reveal]
+            .into_iter()
+            .find(|psbt| psbt.unsigned_tx.compute_txid() == txid)
+            .with_context(|| {
+                format!("signed PSBT of {txid} is of neither transaction of the package")
+            })?;
+        psbt.combine(signed)?;
+        Ok(())
+    }
+}
+
+/// The taproot output committing to the assert tapleaf.
+struct AssertOutput {
+    operator: OperatorKey,
+    witness: AssertWitness,
+    script: ScriptBuf,
+    spend_info: TaprootSpendInfo,
+}
+
+impl AssertOutput {
+    fn new(operator: &OperatorKey, witness: &AssertWitness) -> Result<Self> {
+        let script = witness.tapleaf_script(operator.key)?;
+        let spend_info = TaprootSpendInfo::with_huffman_tree(
+            &Secp256k1::verification_only(),
+            operator.key,
+            [(1, script.clone())],
+        )?;
+        Ok(Self {
+            operator: operator.clone(),
+            witness: witness.clone(),
+            script,
+            spend_info,
+        })
+    }
+
+    fn script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_p2tr_tweaked(self.spend_info.output_key())
+    }
+
+    fn control_block(&self) -> ControlBlock {
+        self.spend_info
+            .control_block(&(self.script.clone(), LeafVersion::TapScript))
+            .expect("the leaf is in the tree")
+    }
+
+    /// The reveal transaction spending `outpoint`, of `value`, to
+    /// `destination`.
+    fn reveal(
+        &self,
+        outpoint: OutPoint,
+        value: Amount,
+        destination: &TxOut,
+        sequence: Sequence,
+    ) -> Result<Psbt> {
+        let mut psbt = Psbt::from_unsigned_tx(Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence,
+                witness: Witness::new(),
+            }],
+            output: vec![destination.clone()],
+        })?;
+        let input = &mut psbt.inputs[0];
+        input.witness_utxo = Some(TxOut {
+            value,
+            script_pubkey: self.script_pubkey(),
+        });
+        input.tap_internal_key = Some(self.operator.key);
+        if let Some(origin) = &self.operator.origin {
+            let leaf_hash = TapLeafHash::from_script(&self.script, LeafVersion::TapScript);
+            input
+                .tap_key_origins
+                .insert(self.operator.key, (vec![leaf_hash], origin.clone()));
+        }
+        input.tap_merkle_root = self.spend_info.merkle_root();
+        input.tap_scripts.insert(
+            self.control_block(),
+            (self.script.clone(), LeafVersion::TapScript),
+        );
+        input
+            .proprietary
+            .insert(witness_key(), self.witness.to_bytes());
+        Ok(psbt)
+    }
+}
+
+/// The commit transaction paying `assert_txout` from the funding, with
+/// change if it is not dust.
+fn commit(params: &AssertParams, assert_txout: TxOut, sequence: Sequence) -> Result<Psbt> {
+    let funded = params
+        .funding
+        .iter()
+        .try_fold(Amount::ZERO, |sum, funding| {
+            sum.checked_add(funding.txout.value)
+        })
+        .context("funding overflows")?;
+    let change = TxOut {
+        value: Amount::ZERO,
+        script_pubkey: params.change.clone(),
+    };
+    let unfunded = |output: Vec<TxOut>| -> Result<Psbt> {
+        let mut psbt = Psbt::from_unsigned_tx(Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: params
+                .funding
+                .iter()
+                .map(|funding| TxIn {
+                    previous_output: funding.outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output,
+        })?;
+        for (input, funding) in psbt.inputs.iter_mut().zip(&params.funding) {
+            input.witness_utxo = Some(funding.txout.clone());
+            input.tap_internal_key = Some(params.operator.key);
+            if let Some(origin) = &params.operator.origin {
+                input
+                    .tap_key_origins
+                    .insert(params.operator.key, (Vec::new(), origin.clone()));
+            }
+        }
+        Ok(psbt)
+    };
+
+    let mut psbt = unfunded(vec![assert_txout.clone(), change.clone()])?;
+    let needed = assert_txout.value + fee(params.fee_rate, estimated_weight(&psbt)?)?;
+    match funded.checked_sub(needed) {
+        Some(left) if left >= change.script_pubkey.minimal_non_dust() => {
+            psbt.unsigned_tx.output[1].value = left;
+            Ok(psbt)
+        }
+        _ => {
+            // Without change, whatever is left over goes to the fee.
+            let psbt = unfunded(vec![assert_txout.clone()])?;
+            let needed = assert_txout.value + fee(params.fee_rate, estimated_weight(&psbt)?)?;
+            ensure!(
+                funded >= needed,
+                "funding of {funded} is short of the {needed} the assert takes at {}",
+                params.fee_rate
+            );
+            Ok(psbt)
+        }
+    }
+}
+
+/// Weight of the transaction of `psbt` once signed: a key-path spend of
+/// each input, or a spend of its tapleaf carrying the verifier witness.
+pub fn estimated_weight(psbt: &Psbt) -> Result<Weight> {
+    let mut tx = psbt.unsigned_tx.clone();
+    for (txin, input) in tx.input.iter_mut().zip(&psbt.inputs) {
+        txin.witness = match &input.final_script_witness {
+            Some(witness) => witness.clone(),
+            None => satisfying_witness(input, vec![0; SIGNATURE_LEN])?,
+        };
+    }
+    Ok(tx.weight())
+}
+
+/// The witness spending `input` with `signature`: the signature alone for a
+/// key-path spend, or with the verifier witness, the tapleaf script and its
+/// control block for a spend of the assert tapleaf.
+fn satisfying_witness(input: &psbt::Input, signature: Vec<u8>) -> Result<Witness> {
+    let Some((control_block, (script, _))) = input.tap_scripts.first_key_value() else {
+        return Ok(Witness::from_slice(&[signature]));
+    };
+    let mut elements = vec![signature];
+    elements.extend(input_witness(input)?.elements()?);
+    elements.push(script.to_bytes());
+    elements.push(control_block.serialize());
+    Ok(Witness::from_slice(&elements))
+}
+
+fn input_witness(input: &psbt::Input) -> Result<AssertWitness> {
+    AssertWitness::from_bytes(
+        input
+            .proprietary
+            .get(&witness_key())
+            .context("tapleaf input does not hold the verifier witness")?,
+    )
+}
+
+fn finalize(psbt: &Psbt) -> Result<Transaction> {
+    let mut psbt = psbt.clone();
+    for (index, input) in psbt.inputs.iter_mut().enumerate() {
+        // External signers may finalize the inputs they can.
+        if input.final_script_witness.is_some() {
+            continue;
+        }
+        let signature = match input.tap_scripts.first_key_value() {
+            Some((_, (script, version))) => {
+                let leaf_hash = TapLeafHash::from_script(script, *version);
+                input
+                    .tap_script_sigs
+                    .iter()
+                    .find_map(|((_, hash), signature)| (*hash == leaf_hash).then_some(*signature))
+            }
+            None => input.tap_key_sig,
+        }
+        .with_context(|| format!("input {index} is not signed"))?;
+        input.final_script_witness = Some(satisfying_witness(input, signature.to_vec())?);
+    }
+    check_signatures(&psbt)?;
+    Ok(psbt.extract_tx()?)
+}
+
+/// Checks the signature in the final witness of every input against the key
+/// and sighash it must be for, so that a signer returning a signature of
+/// another transaction, input or key is caught before anything is
+/// broadcast.
+fn check_signatures(psbt: &Psbt) -> Result<()> {
+    let secp = Secp256k1::verification_only();
+    let prevouts = prevouts(psbt)?;
+    let prevouts = Prevouts::All(&prevouts);
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        let signature = input
+            .final_script_witness
+            .as_ref()
+            .and_then(|witness| witness.nth(0))
+            .with_context(|| format!("input {index} is not finalized"))?;
+        let signature = taproot::Signature::from_slice(signature)
+            .with_context(|| format!("input {index} carries a malformed signature"))?;
+        let (key, sighash) = match input.tap_scripts.first_key_value() {
+            Some((_, (script, version))) => (
+                input
+                    .tap_internal_key
+                    .with_context(|| format!("input {index} has no internal key"))?,
+                cache.taproot_script_spend_signature_hash(
+                    index,
+                    &prevouts,
+                    TapLeafHash::from_script(script, *version),
+                    signature.sighash_type,
+                )?,
+            ),
+            None => {
+                let script = &input
+                    .witness_utxo
+                    .as_ref()
+                    .expect("prevouts are known")
+                    .script_pubkey;
+                ensure!(
+                    script.is_p2tr(),
+                    "input {index} does not spend a P2TR output"
+                );
+                (
+                    XOnlyPublicKey::from_slice(&script.as_bytes()[2..])?,
+                    cache.taproot_key_spend_signature_hash(
+                        index,
+                        &prevouts,
+                        signature.sighash_type,
+                    )?,
+                )
+            }
+        };
+        secp.verify_schnorr(&signature.signature, &Message::from(sighash), &key)
+            .map_err(|_| anyhow!("signature of input {index} does not verify under {key}"))?;
+    }
+    Ok(())
+}
+
+fn prevouts(psbt: &Psbt) -> Result<Vec<TxOut>> {
+    psbt.inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            input
+                .witness_utxo
+                .clone()
+                .with_context(|| format!("input {index} has no UTXO"))
+        })
+        .collect()
+}
+
+fn schnorr<C: Signing>(
+    secp: &Secp256k1<C>,
+    sighash: TapSighash,
+    keypair: &Keypair,
+) -> taproot::Signature {
+    taproot::Signature {
+        signature: secp.sign_schnorr_no_aux_rand(&Message::from(sighash), keypair),
+        sighash_type: TapSighashType::Default,
+    }
+}
+
+/// The fee rate `psbt` pays once signed.
+pub fn fee_rate(psbt: &Psbt) -> Result<FeeRate> {
+    Ok(psbt.fee()? / estimated_weight(psbt)?)
+}
+
+fn fee(rate: FeeRate, weight: Weight) -> Result<Amount> {
+    rate.fee_vb(weight.to_vbytes_ceil())
+        .ok_or_else(|| anyhow!("fee at {rate} overflows"))
+}
+
+/// Checks that `replacement`, in place of transactions paying `replaced` in
+/// all, is relayed under BIP125: it pays at least as much again as the
+/// incremental relay fee of its own size.
+fn check_replacement(name: &str, replaced: Amount, replacement: &Psbt) -> Result<()> {
+    let paid = replacement.fee()?;
+    let needed = replaced + fee(INCREMENTAL_RELAY_FEE, estimated_weight(replacement)?)?;
+    if paid < needed {
+        bail!(
+            "bumped {name} tx pays {paid}, but replacing what it conflicts with takes {needed}; \
+             raise the fee rate"
+        );
+    }
+    Ok(())
+}
+
+fn witness_key() -> ProprietaryKey {
+    ProprietaryKey {
+        prefix: PROPRIETARY_PREFIX.to_vec(),
+        subtype: WITNESS_SUBTYPE,
+        key: Vec::new(),
+    }
+}
+
+/// PSBTs in their usual base64 encoding.
+mod psbt_base64 {
+    use bitcoin::Psbt;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(psbt: &Psbt, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(psbt)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Psbt, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
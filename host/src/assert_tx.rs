@@ -0,0 +1,138 @@
+//! The layout in which an assert transaction carries the verifier witness,
+//! so that a proof can be checked straight from chain data.
+//!
+//! The input spending the assert tapleaf pushes the operator's signature,
+//! then the chunks of
+//! [`chunks::verifier_witness`](crate::chunks::verifier_witness), the
+//! 256-byte seal followed by the 31-byte public input, split at the script
+//! element limit and in order, then the tapleaf script and its control block
+//! as any script-path spend does. An annex, if present, comes last and is
+//! ignored. The tapleaf, [`AssertWitness::tapleaf_script`], checks every
+//! chunk against its SHA-256 digest, so that the output it is committed to
+//! already commits to the witness and no one relaying the spend can swap it.
+
+use anyhow::{ensure, Context, Result};
+use bitcoin::{
+    hashes::{sha256, Hash},
+    opcodes::all::{OP_CHECKSIG, OP_EQUALVERIFY, OP_SHA256},
+    script::Builder,
+    ScriptBuf, Transaction, Witness, XOnlyPublicKey,
+};
+
+use crate::{
+    chunks::{ChunkSet, MAX_SCRIPT_ELEMENT_LEN},
+    verifier::{self, ScalarPolicy, PUBLIC_INPUT_LEN, SEAL_LEN},
+};
+
+/// Length of the verifier witness carried by an assert transaction.
+pub const WITNESS_LEN: usize = SEAL_LEN + PUBLIC_INPUT_LEN;
+
+/// First byte of a taproot annex.
+const ANNEX_TAG: u8 = 0x50;
+
+/// A seal and the public input it was asserted against, as read from an
+/// assert transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertWitness {
+    pub seal: [u8; SEAL_LEN],
+    pub public_input: [u8; PUBLIC_INPUT_LEN],
+}
+
+impl AssertWitness {
+    /// Splits a verifier witness into the seal and public input.
+    pub fn from_bytes(witness: &[u8]) -> Result<Self> {
+        ensure!(
+            witness.len() == WITNESS_LEN,
+            "verifier witness is {} bytes, expected {WITNESS_LEN}",
+            witness.len()
+        );
+        let (seal, public_input) = witness.split_at(SEAL_LEN);
+        Ok(Self {
+            seal: seal.try_into().unwrap(),
+            public_input: public_input.try_into().unwrap(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.seal.as_slice(), &self.public_input].concat()
+    }
+
+    /// The witness elements pushed before the tapleaf script and control
+    /// block.
+    pub fn elements(&self) -> Result<Vec<Vec<u8>>> {
+        Ok(ChunkSet::split(&self.to_bytes(), MAX_SCRIPT_ELEMENT_LEN)?
+            .chunks
+            .into_iter()
+            .map(|chunk| chunk.data)
+            .collect())
+    }
+
+    /// The assert tapleaf carrying this witness, spendable by `operator`.
+    pub fn tapleaf_script(&self, operator: XOnlyPublicKey) -> Result<ScriptBuf> {
+        // The last chunk is on top of the stack.
+        let mut builder = Builder::new();
+        for element in self.elements()?.iter().rev() {
+            builder = builder
+                .push_opcode(OP_SHA256)
+                .push_slice(sha256::Hash::hash(element).to_byte_array())
+                .push_opcode(OP_EQUALVERIFY);
+        }
+        Ok(builder
+            .push_x_only_key(&operator)
+            .push_opcode(OP_CHECKSIG)
+            .into_script())
+    }
+
+    /// Reads the verifier witness from the witness of a script-path spend of
+    /// an assert tapleaf.
+    pub fn from_witness(witness: &Witness) -> Result<Self> {
+        let mut elements: Vec<&[u8]> = witness.iter().collect();
+        if elements.len() >= 2
+            && elements.last().and_then(|annex| annex.first()) == Some(&ANNEX_TAG)
+        {
+            elements.pop();
+        }
+        ensure!(
+            elements.len() >= 3,
+            "witness of {} elements is not a script-path spend carrying a verifier witness",
+            elements.len()
+        );
+        // The tapleaf script and the control block.
+        elements.truncate(elements.len() - 2);
+        match elements.split_first() {
+            // A BIP340 signature, with or without a sighash type.
+            Some((signature, chunks))
+                if matches!(signature.len(), 64 | 65) && chunks.concat().len() == WITNESS_LEN =>
+            {
+                Self::from_bytes(&chunks.concat())
+            }
+            _ => Self::from_bytes(&elements.concat()),
+        }
+    }
+
+    /// Reads the verifier witness from input `index` of `tx`, or from the
+    /// first input carrying one if no index is given.
+    pub fn from_transaction(tx: &Transaction, index: Option<usize>) -> Result<Self> {
+        if let Some(index) = index {
+            let input = tx
+                .input
+                .get(index)
+                .with_context(|| format!("transaction has no input {index}"))?;
+            return Self::from_witness(&input.witness)
+                .with_context(|| format!("input {index} carries no verifier witness"));
+        }
+        tx.input
+            .iter()
+            .find_map(|input| Self::from_witness(&input.witness).ok())
+            .context("no input of the transaction carries a verifier witness")
+    }
+
+    /// Checks the seal against the public input.
+    pub fn verify(&self, policy: ScalarPolicy) -> Result<()> {
+        ensure!(
+            verifier::verify_seal(&self.seal, &self.public_input, policy)?,
+            "seal does not verify against the public input"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! An append-only log of what a verifier checked, so that operators can show
+//! after the fact which proofs their watchtower saw, when, and what it
+//! concluded.
+//!
+//! Each line is a JSON [`AuditEntry`] holding digests of the seal, journal and
+//! image ID checked, the verdict and a timestamp. Entries are chained by the
+//! BLAKE3 digest of the previous line and signed with the operator's BIP340
+//! key, so a line cannot be altered, dropped or reordered without breaking
+//! the chain or a signature.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use k256::schnorr::{Signature, SigningKey, VerifyingKey};
+use risc0_zkvm::{sha::Digest, Receipt};
+use serde::{Deserialize, Serialize};
+
+use crate::journal::Journal;
+
+/// Key derivation context of the message each entry is signed over.
+const SIGNING_CONTEXT: &str = "r0-bitvm-blake3 2025 verification audit log entry v1";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Unix timestamp of the check, in seconds.
+    pub timestamp: u64,
+    /// The bundle as named by the verifier: its path or stream position.
+    pub bundle: String,
+    /// BLAKE3 digest of the seal, if the bundle had one.
+    pub seal_blake3: Option<String>,
+    /// SHA-256 journal digest committed to by the receipt claim.
+    pub journal_digest: Option<String>,
+    /// Image ID the receipt verified against.
+    pub image_id: Option<String>,
+    pub valid: bool,
+    pub error: Option<String>,
+    /// BLAKE3 digest of the previous line, or zero for the first entry.
+    pub prev: String,
+    /// Hex-encoded x-only public key of the signer.
+    pub signer: String,
+    /// BIP340 signature over every other field.
+    pub signature: String,
+}
+
+/// What a verifier checked, before it is timestamped, chained and signed.
+pub struct Check<'a> {
+    pub bundle: &'a str,
+    pub receipt: Option<&'a Receipt>,
+    pub image_id: Option<Digest>,
+    pub error: Option<String>,
+}
+
+/// An audit log open for appending.
+pub struct AuditLog {
+    path: PathBuf,
+    file: File,
+    key: SigningKey,
+    prev: [u8; 32],
+}
+
+impl AuditLog {
+    /// Opens the log at `path`, creating it if needed, and continues the
+    /// chain from its last line.
+    pub fn open(path: &Path, key: SigningKey) -> Result<Self> {
+        let prev = match fs::read(path) {
+            Ok(contents) => last_line(&contents).map_or([0; 32], |line| blake3::hash(line).into()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => [0; 32],
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()))
+            }
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open audit log {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            key,
+            prev,
+        })
+    }
+
+    /// Appends a signed entry for `check` and syncs it to disk.
+    pub fn record(&mut self, check: Check) -> Result<AuditEntry> {
+        let mut entry = AuditEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("system clock is before the Unix epoch")
+                .as_secs(),
+            bundle: check.bundle.to_string(),
+            seal_blake3: check
+                .receipt
+                .and_then(|receipt| receipt.inner.groth16().ok())
+                .map(|groth_proof| blake3::hash(&groth_proof.seal).to_hex().to_string()),
+            journal_digest: check
+                .receipt
+                .map(|receipt| Journal::of(receipt).digest().to_string()),
+            image_id: check.image_id.map(|image_id| image_id.to_string()),
+            valid: check.error.is_none(),
+            error: check.error,
+            prev: hex::encode(self.prev),
+            signer: hex::encode(self.key.verifying_key().to_bytes()),
+            signature: String::new(),
+        };
+        let mut aux_rand = [0; 32];
+        OsRng.fill_bytes(&mut aux_rand);
+        let signature = self
+            .key
+            .sign_raw(&message(&entry)?, &aux_rand)
+            .map_err(|err| anyhow!("failed to sign audit entry: {err}"))?;
+        entry.signature = hex::encode(signature.to_bytes());
+
+        let mut line = serde_json::to_vec(&entry)?;
+        self.prev = blake3::hash(&line).into();
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .and_then(|()| self.file.sync_data())
+            .with_context(|| format!("failed to append to {}", self.path.display()))?;
+        Ok(entry)
+    }
+}
+
+/// Checks the chain and signatures of every entry of the log at `path`,
+/// returning the number of entries. If `signer` is given, every entry must be
+/// signed by it.
+pub fn verify_log(path: &Path, signer: Option<&VerifyingKey>) -> Result<usize> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut prev = [0; 32];
+    let mut count = 0;
+    for (index, line) in BufReader::new(file).split(b'\n').enumerate() {
+        let line = line?;
+        let entry: AuditEntry = serde_json::from_slice(&line)
+            .with_context(|| format!("line {} is not an audit entry", index + 1))?;
+        ensure!(
+            entry.prev == hex::encode(prev),
+            "line {} does not follow the line before it",
+            index + 1
+        );
+        let key = hex::decode(&entry.signer)
+            .ok()
+            .and_then(|key| VerifyingKey::from_bytes(&key).ok())
+            .with_context(|| format!("line {} has an invalid signer key", index + 1))?;
+        if signer.is_some_and(|signer| *signer != key) {
+            bail!("line {} is signed by {}", index + 1, entry.signer);
+        }
+        let signature = hex::decode(&entry.signature)
+            .ok()
+            .and_then(|signature| Signature::try_from(signature.as_slice()).ok())
+            .with_context(|| format!("line {} has a malformed signature", index + 1))?;
+        if key.verify_raw(&message(&entry)?, &signature).is_err() {
+            bail!("signature of line {} does not verify", index + 1);
+        }
+        prev = blake3::hash(&line).into();
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// The message an entry is signed over: its JSON with the signature blank.
+fn message(entry: &AuditEntry) -> Result<[u8; 32]> {
+    let unsigned = AuditEntry {
+        signature: String::new(),
+        ..entry.clone()
+    };
+    let mut hasher = blake3::Hasher::new_derive_key(SIGNING_CONTEXT);
+    hasher.update(&serde_json::to_vec(&unsigned)?);
+    Ok(hasher.finalize().into())
+}
+
+fn last_line(contents: &[u8]) -> Option<&[u8]> {
+    contents
+        .strip_suffix(b"\n")
+        .unwrap_or(contents)
+        .split(|&byte| byte == b'\n')
+        .next_back()
+        .filter(|line| !line.is_empty())
+}
@@ -0,0 +1,316 @@
+//! Host side of BLAKE3 outboard encodings: computing the outboard that a
+//! [`Mode::Outboard`](common::Mode::Outboard) proof commits to, for
+//! distribution alongside the input, and the slice proofs verified by
+//! [`Mode::Slice`](common::Mode::Slice). The encodings are defined in
+//! [`common::bao`].
+
+use anyhow::{ensure, Context, Result};
+use blake3::hazmat::ChainingValue;
+use common::bao::{OutboardHasher, Slice, MAX_BLOCK_LOG};
+
+use crate::tree::Tree;
+
+/// The outboard of an input and the root it leads to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outboard {
+    pub root: [u8; 32],
+    pub len: u64,
+    /// Parent node pairs in post order.
+    pub pairs: Vec<u8>,
+}
+
+impl Outboard {
+    /// Computes the outboard of `data`, personalized with the derive-key
+    /// `context` if given.
+    pub fn new(data: &[u8], block_log: u8, context: Option<&str>) -> Result<Self> {
+        let mut pairs = Vec::new();
+        let mut hasher = OutboardHasher::new(block_log, context.map(context_key), |pair| {
+            pairs.extend_from_slice(pair)
+        })
+        .with_context(|| format!("block size log {block_log} exceeds {MAX_BLOCK_LOG}"))?;
+        hasher.update(data);
+        let (root, len) = hasher.finalize();
+        Ok(Self { root, len, pairs })
+    }
+
+    /// The outboard digest committed in the journal.
+    pub fn digest(&self) -> [u8; 32] {
+        blake3::hash(&self.pairs).into()
+    }
+}
+
+/// The key the guest hashes with for a derive-key `context` string.
+pub fn context_key(context: &str) -> [u8; 32] {
+    blake3::hazmat::hash_derive_key_context(context)
+}
+
+/// A byte range of an input with everything the guest needs to check it
+/// against the input's root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SliceProof {
+    pub slice: Slice,
+    /// Chaining values of the subtrees outside the covered blocks.
+    pub nodes: Vec<ChainingValue>,
+    /// The blocks covering the range.
+    pub data: Vec<u8>,
+}
+
+impl SliceProof {
+    pub fn new(
+        input: &[u8],
+        block_log: u8,
+        context: Option<&str>,
+        offset: u64,
+        length: u64,
+    ) -> Result<Self> {
+        Self::from_tree(
+            &Tree::new(input, block_log, context)?,
+            input,
+            offset,
+            length,
+        )
+    }
+
+    /// Builds the proof from the already computed tree of `input`.
+    pub fn from_tree(tree: &Tree, input: &[u8], offset: u64, length: u64) -> Result<Self> {
+        ensure!(
+            tree.len() == input.len() as u64,
+            "tree is over {} bytes, not the {} byte input",
+            tree.len(),
+            input.len()
+        );
+        let slice = Slice {
+            root: tree.root(),
+            len: tree.len(),
+            offset,
+            length,
+            block_log: tree.block_log,
+            context: tree.context,
+        };
+        let (start, end) = slice.covered().with_context(|| {
+            format!(
+                "cannot prove {length} bytes at offset {offset} of a {} byte input",
+                input.len()
+            )
+        })?;
+        Ok(Self {
+            nodes: tree.slice_nodes(start, end),
+            data: input[start as usize..end as usize].to_vec(),
+            slice,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blake3::hazmat::{self, HasherExt};
+    use common::bao::{block_hasher, hash_mode, SliceError};
+
+    use super::*;
+
+    const LENS: [usize; 8] = [0, 1, 1024, 1025, 2048, 5000, 16 * 1024, 16 * 1024 + 1];
+
+    fn input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Hashes the non-root subtree of `len` bytes at `start` the slow way,
+    /// appending its parent pairs to `pairs` in post order.
+    fn subtree(
+        data: &[u8],
+        start: u64,
+        len: u64,
+        block_len: u64,
+        context: Option<&[u8; 32]>,
+        pairs: &mut Vec<u8>,
+    ) -> ChainingValue {
+        if len <= block_len {
+            let mut hasher = block_hasher(context, start);
+            hasher.update(&data[start as usize..(start + len) as usize]);
+            return hasher.finalize_non_root();
+        }
+        let left_len = hazmat::left_subtree_len(len);
+        let left = subtree(data, start, left_len, block_len, context, pairs);
+        let right = subtree(
+            data,
+            start + left_len,
+            len - left_len,
+            block_len,
+            context,
+            pairs,
+        );
+        pairs.extend_from_slice(&left);
+        pairs.extend_from_slice(&right);
+        hazmat::merge_subtrees_non_root(&left, &right, hash_mode(context))
+    }
+
+    fn expected_pairs(data: &[u8], block_log: u8, context: Option<&str>) -> Vec<u8> {
+        let block_len = 1024 << block_log;
+        let context = context.map(context_key);
+        let mut pairs = Vec::new();
+        let len = data.len() as u64;
+        if len > block_len {
+            let left_len = hazmat::left_subtree_len(len);
+            let left = subtree(data, 0, left_len, block_len, context.as_ref(), &mut pairs);
+            let right = subtree(
+                data,
+                left_len,
+                len - left_len,
+                block_len,
+                context.as_ref(),
+                &mut pairs,
+            );
+            pairs.extend_from_slice(&left);
+            pairs.extend_from_slice(&right);
+        }
+        pairs
+    }
+
+    #[test]
+    fn root_is_blake3_hash() {
+        for len in LENS {
+            let data = input(len);
+            for block_log in [0, 2, 4] {
+                let outboard = Outboard::new(&data, block_log, None).unwrap();
+                assert_eq!(
+                    outboard.root,
+                    *blake3::hash(&data).as_bytes(),
+                    "{len} bytes"
+                );
+                assert_eq!(outboard.len, len as u64);
+                let outboard = Outboard::new(&data, block_log, Some("ctx")).unwrap();
+                assert_eq!(
+                    outboard.root,
+                    blake3::derive_key("ctx", &data),
+                    "{len} bytes"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_tree() {
+        for len in LENS {
+            let data = input(len);
+            for (block_log, context) in [(0, None), (1, Some("ctx")), (4, None)] {
+                let outboard = Outboard::new(&data, block_log, context).unwrap();
+                let tree = Tree::new(&data, block_log, context).unwrap();
+                assert_eq!(outboard.root, tree.root(), "{len} bytes");
+                assert_eq!(
+                    outboard.pairs.len() as u64,
+                    (tree.block_count() - 1) * 64,
+                    "{len} bytes"
+                );
+                assert_eq!(
+                    outboard.pairs,
+                    expected_pairs(&data, block_log, context),
+                    "{len} bytes, block log {block_log}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_in_pieces() {
+        let data = input(5000);
+        let whole = Outboard::new(&data, 0, None).unwrap();
+        for piece in [1, 100, 1024, 1025] {
+            let mut pairs = Vec::new();
+            let mut hasher =
+                OutboardHasher::new(0, None, |pair| pairs.extend_from_slice(pair)).unwrap();
+            for part in data.chunks(piece) {
+                hasher.update(part);
+            }
+            let (root, len) = hasher.finalize();
+            assert_eq!((root, len), (whole.root, whole.len), "pieces of {piece}");
+            assert_eq!(pairs, whole.pairs, "pieces of {piece}");
+        }
+    }
+
+    /// Verifies `proof`, returning the bytes of the range it fed on.
+    fn verify(proof: &SliceProof) -> Result<Vec<u8>, SliceError> {
+        let mut range = Vec::new();
+        proof
+            .slice
+            .verify(&proof.nodes, &mut proof.data.as_slice(), |bytes| {
+                range.extend_from_slice(bytes)
+            })?;
+        Ok(range)
+    }
+
+    #[test]
+    fn slices_verify() {
+        for len in [1, 1024, 1025, 5000, 16 * 1024 + 1] {
+            let data = input(len);
+            for (block_log, context) in [(0, None), (0, Some("ctx")), (2, None)] {
+                let tree = Tree::new(&data, block_log, context).unwrap();
+                for (offset, length) in [(0, 1), (0, len), (len - 1, 1), (len / 3, len / 2 + 1)] {
+                    let proof =
+                        SliceProof::from_tree(&tree, &data, offset as u64, length as u64).unwrap();
+                    assert_eq!(
+                        verify(&proof),
+                        Ok(data[offset..offset + length].to_vec()),
+                        "{length} bytes at {offset} of {len}"
+                    );
+                }
+            }
+        }
+    }
+
+    fn proof() -> SliceProof {
+        SliceProof::new(&input(5000), 0, None, 2100, 10).unwrap()
+    }
+
+    #[test]
+    fn tampered_slices_are_rejected() {
+        let mut wrong_root = proof();
+        wrong_root.slice.root[0] ^= 1;
+        assert_eq!(verify(&wrong_root), Err(SliceError::Root));
+
+        let mut wrong_data = proof();
+        wrong_data.data[0] ^= 1;
+        assert_eq!(verify(&wrong_data), Err(SliceError::Root));
+
+        let mut wrong_node = proof();
+        wrong_node.nodes[0][0] ^= 1;
+        assert_eq!(verify(&wrong_node), Err(SliceError::Root));
+    }
+
+    #[test]
+    fn misshapen_slices_are_rejected() {
+        let mut missing_node = proof();
+        missing_node.nodes.pop();
+        assert_eq!(verify(&missing_node), Err(SliceError::Shape));
+
+        let mut extra_node = proof();
+        extra_node.nodes.push([0; 32]);
+        assert_eq!(verify(&extra_node), Err(SliceError::Shape));
+
+        let mut short_data = proof();
+        short_data.data.pop();
+        assert_eq!(verify(&short_data), Err(SliceError::Shape));
+
+        let mut long_data = proof();
+        long_data.data.push(0);
+        assert_eq!(verify(&long_data), Err(SliceError::Shape));
+    }
+
+    #[test]
+    fn out_of_range_slices_are_rejected() {
+        let mut empty = proof();
+        empty.slice.length = 0;
+        assert_eq!(verify(&empty), Err(SliceError::Range));
+
+        let mut past_end = proof();
+        past_end.slice.offset = 4995;
+        assert_eq!(verify(&past_end), Err(SliceError::Range));
+
+        assert!(SliceProof::new(&input(5000), 0, None, 5000, 1).is_err());
+    }
+
+    #[test]
+    fn block_log_is_bounded() {
+        assert!(Outboard::new(b"", MAX_BLOCK_LOG, None).is_ok());
+        assert!(Outboard::new(b"", MAX_BLOCK_LOG + 1, None).is_err());
+    }
+}
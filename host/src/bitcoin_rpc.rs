@@ -0,0 +1,409 @@
+//! One configurable client for everything that reads from or writes to
+//! Bitcoin: [`BitcoinRpc`] over a bitcoind node's JSON-RPC interface or an
+//! Esplora HTTP API, selected by a [`BitcoinConfig`].
+//!
+//! bitcoind only serves transactions outside its wallet with `-txindex`, and
+//! Esplora rate-limits public instances; operators acting on chain data
+//! should point either at infrastructure of their own.
+
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use bitcoin::{block, Block, BlockHash, FeeRate, Transaction, Txid};
+use reqwest::blocking::{Client, Response};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Public Esplora API used when no backend is configured.
+pub const DEFAULT_ESPLORA_URL: &str = "https://blockstream.info/api";
+
+/// Confirmations a transaction needs before what it asserts is acted on,
+/// deep enough that a reorg undoing it is unlikely.
+pub const DEFAULT_CONFIRMATIONS: u64 = 6;
+
+/// Where a transaction stands in the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxStatus {
+    /// The block the transaction is confirmed in, and its height, or `None`
+    /// while it is unconfirmed.
+    pub block: Option<(u64, BlockHash)>,
+}
+
+/// The Bitcoin reads and writes the crate needs, over either backend.
+pub trait BitcoinRpc: Send + Sync {
+    /// The transaction `txid`, checked to hash to it.
+    fn transaction(&self, txid: &Txid) -> Result<Transaction>;
+
+    fn transaction_status(&self, txid: &Txid) -> Result<TxStatus>;
+
+    /// Height of the best block.
+    fn tip_height(&self) -> Result<u64>;
+
+    fn block_hash(&self, height: u64) -> Result<BlockHash>;
+
+    fn block_header(&self, hash: &BlockHash) -> Result<block::Header>;
+
+    /// The block `hash` with its transactions, checked to hash to it.
+    fn block(&self, hash: &BlockHash) -> Result<Block>;
+
+    /// The fee rate expected to confirm a transaction within `target_blocks`.
+    fn fee_rate(&self, target_blocks: u16) -> Result<FeeRate>;
+
+    /// Submits `tx` to the network, returning its ID.
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid>;
+}
+
+/// The backend to use, loaded from the `[bitcoin]` table of a configuration
+/// or assembled from command-line flags.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum BitcoinConfig {
+    /// An Esplora API, e.g. `https://blockstream.info/api`.
+    Esplora { url: String },
+    /// A bitcoind JSON-RPC endpoint, e.g. `http://127.0.0.1:8332`,
+    /// authenticated with its cookie file or a user and password.
+    Bitcoind {
+        url: String,
+        #[serde(default)]
+        cookie_file: Option<PathBuf>,
+        #[serde(default)]
+        user: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+}
+
+impl Default for BitcoinConfig {
+    fn default() -> Self {
+        Self::Esplora {
+            url: DEFAULT_ESPLORA_URL.to_string(),
+        }
+    }
+}
+
+impl BitcoinConfig {
+    pub fn connect(&self) -> Result<Box<dyn BitcoinRpc>> {
+        Ok(match self {
+            Self::Esplora { url } => Box::new(Esplora::new(url)?),
+            Self::Bitcoind {
+                url,
+                cookie_file,
+                user,
+                password,
+            } => {
+                let auth = match (cookie_file, user, password) {
+                    (Some(path), None, None) => {
+                        let cookie = std::fs::read_to_string(path).with_context(|| {
+                            format!("failed to read RPC cookie {}", path.display())
+                        })?;
+                        let (user, password) = cookie
+                            .trim()
+                            .split_once(':')
+                            .context("RPC cookie is not of the form user:password")?;
+                        Some((user.to_string(), password.to_string()))
+                    }
+                    (None, Some(user), password) => {
+                        Some((user.clone(), password.clone().unwrap_or_default()))
+                    }
+                    (None, None, None) => None,
+                    _ => bail!("give either an RPC cookie file or an RPC user, not both"),
+                };
+                Box::new(Bitcoind::new(url, auth)?)
+            }
+        })
+    }
+}
+
+/// Decodes a consensus-encoded transaction and checks that it is `txid`.
+fn decode_transaction(encoded: &str, txid: &Txid) -> Result<Transaction> {
+    let tx: Transaction = bitcoin::consensus::deserialize(
+        &hex::decode(encoded.trim()).context("transaction is not valid hex")?,
+    )
+    .context("malformed transaction")?;
+    ensure!(
+        tx.compute_txid() == *txid,
+        "asked for transaction {txid}, got {}",
+        tx.compute_txid()
+    );
+    Ok(tx)
+}
+
+/// Decodes a consensus-encoded block and checks that it is `hash`.
+fn decode_block(encoded: &[u8], hash: &BlockHash) -> Result<Block> {
+    let block: Block = bitcoin::consensus::deserialize(encoded).context("malformed block")?;
+    ensure!(
+        block.block_hash() == *hash && block.check_merkle_root(),
+        "asked for block {hash}, got {} or a block whose transactions it does not commit to",
+        block.block_hash()
+    );
+    Ok(block)
+}
+
+/// An Esplora HTTP API.
+pub struct Esplora {
+    client: Client,
+    url: String,
+}
+
+impl Esplora {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder().timeout(TIMEOUT).build()?,
+            url: url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn get(&self, path: &str) -> Result<Response> {
+        let url = format!("{}{path}", self.url);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .with_context(|| format!("failed to fetch {url}"))?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("{url} answered {status}: {}", response.text()?);
+        }
+        Ok(response)
+    }
+
+    fn get_text(&self, path: &str) -> Result<String> {
+        Ok(self.get(path)?.text()?.trim().to_string())
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.get(path)?
+            .json()
+            .with_context(|| format!("malformed answer from {}{path}", self.url))
+    }
+}
+
+impl BitcoinRpc for Esplora {
+    fn transaction(&self, txid: &Txid) -> Result<Transaction> {
+        decode_transaction(&self.get_text(&format!("/tx/{txid}/hex"))?, txid)
+    }
+
+    fn transaction_status(&self, txid: &Txid) -> Result<TxStatus> {
+        #[derive(Deserialize)]
+        struct Status {
+            confirmed: bool,
+            block_height: Option<u64>,
+            block_hash: Option<BlockHash>,
+        }
+        let status: Status = self.get_json(&format!("/tx/{txid}/status"))?;
+        Ok(TxStatus {
+            block: match (status.confirmed, status.block_height, status.block_hash) {
+                (true, Some(height), Some(hash)) => Some((height, hash)),
+                _ => None,
+            },
+        })
+    }
+
+    fn tip_height(&self) -> Result<u64> {
+        self.get_text("/blocks/tip/height")?
+            .parse()
+            .context("malformed tip height")
+    }
+
+    fn block_hash(&self, height: u64) -> Result<BlockHash> {
+        self.get_text(&format!("/block-height/{height}"))?
+            .parse()
+            .context("malformed block hash")
+    }
+
+    fn block_header(&self, hash: &BlockHash) -> Result<block::Header> {
+        let encoded = self.get_text(&format!("/block/{hash}/header"))?;
+        bitcoin::consensus::deserialize(&hex::decode(encoded)?).context("malformed block header")
+    }
+
+    fn block(&self, hash: &BlockHash) -> Result<Block> {
+        decode_block(&self.get(&format!("/block/{hash}/raw"))?.bytes()?, hash)
+    }
+
+    fn fee_rate(&self, target_blocks: u16) -> Result<FeeRate> {
+        // Targets map to sat/vB; the nearest target at or below the one
+        // asked for is the conservative choice.
+        let estimates: HashMap<String, f64> = self.get_json("/fee-estimates")?;
+        let (_, sat_per_vb) = estimates
+            .iter()
+            .filter_map(|(target, rate)| Some((target.parse::<u16>().ok()?, *rate)))
+            .filter(|(target, _)| *target <= target_blocks.max(1))
+            .max_by_key(|(target, _)| *target)
+            .context("Esplora has no fee estimate for the target")?;
+        Ok(sat_per_vb_rate(sat_per_vb))
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid> {
+        let url = format!("{}/tx", self.url);
+        let response = self
+            .client
+            .post(&url)
+            .body(bitcoin::consensus::encode::serialize_hex(tx))
+            .send()
+            .with_context(|| format!("failed to post to {url}"))?;
+        let status = response.status();
+        let body = response.text()?;
+        ensure!(
+            status.is_success(),
+            "broadcast rejected with {status}: {body}"
+        );
+        body.trim().parse().context("malformed transaction ID")
+    }
+}
+
+/// A bitcoind JSON-RPC endpoint.
+pub struct Bitcoind {
+    client: Client,
+    url: String,
+    auth: Option<(String, String)>,
+}
+
+impl Bitcoind {
+    pub fn new(url: &str, auth: Option<(String, String)>) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder().timeout(TIMEOUT).build()?,
+            url: url.to_string(),
+            auth,
+        })
+    }
+
+    /// Calls `method` with `params`, returning its result. Also serves the
+    /// wallet and regtest calls the trait does not cover.
+    pub fn call<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> Result<T> {
+        #[derive(Deserialize)]
+        struct RpcResponse<T> {
+            result: Option<T>,
+            error: Option<RpcError>,
+        }
+        #[derive(Deserialize)]
+        struct RpcError {
+            code: i64,
+            message: String,
+        }
+        let mut request = self.client.post(&self.url).json(&json!({
+            "jsonrpc": "1.0",
+            "id": "r0-bitvm-blake3",
+            "method": method,
+            "params": params,
+        }));
+        if let Some((user, password)) = &self.auth {
+            request = request.basic_auth(user, Some(password));
+        }
+        let response = request
+            .send()
+            .with_context(|| format!("bitcoind call {method} failed"))?;
+        // bitcoind answers RPC errors with an error status and a JSON body.
+        let status = response.status();
+        let body = response.text()?;
+        let response: RpcResponse<T> = serde_json::from_str(&body)
+            .map_err(|_| anyhow!("bitcoind call {method} answered {status}: {}", body.trim()))?;
+        if let Some(RpcError { code, message }) = response.error {
+            bail!("bitcoind call {method} failed with {code}: {message}");
+        }
+        response
+            .result
+            .with_context(|| format!("bitcoind call {method} returned no result"))
+    }
+}
+
+impl BitcoinRpc for Bitcoind {
+    fn transaction(&self, txid: &Txid) -> Result<Transaction> {
+        let encoded: String = self.call("getrawtransaction", json!([txid, false]))?;
+        decode_transaction(&encoded, txid)
+    }
+
+    fn transaction_status(&self, txid: &Txid) -> Result<TxStatus> {
+        #[derive(Deserialize)]
+        struct Verbose {
+            blockhash: Option<BlockHash>,
+        }
+        #[derive(Deserialize)]
+        struct Header {
+            height: u64,
+        }
+        let verbose: Verbose = self.call("getrawtransaction", json!([txid, true]))?;
+        let block = match verbose.blockhash {
+            Some(hash) => {
+                let header: Header = self.call("getblockheader", json!([hash, true]))?;
+                Some((header.height, hash))
+            }
+            None => None,
+        };
+        Ok(TxStatus { block })
+    }
+
+    fn tip_height(&self) -> Result<u64> {
+        self.call("getblockcount", json!([]))
+    }
+
+    fn block_hash(&self, height: u64) -> Result<BlockHash> {
+        self.call("getblockhash", json!([height]))
+    }
+
+    fn block_header(&self, hash: &BlockHash) -> Result<block::Header> {
+        let encoded: String = self.call("getblockheader", json!([hash, false]))?;
+        bitcoin::consensus::deserialize(&hex::decode(encoded)?).context("malformed block header")
+    }
+
+    fn block(&self, hash: &BlockHash) -> Result<Block> {
+        let encoded: String = self.call("getblock", json!([hash, 0]))?;
+        decode_block(&hex::decode(encoded)?, hash)
+    }
+
+    fn fee_rate(&self, target_blocks: u16) -> Result<FeeRate> {
+        #[derive(Deserialize)]
+        struct Estimate {
+            /// BTC per kvB.
+            feerate: Option<f64>,
+            #[serde(default)]
+            errors: Vec<String>,
+        }
+        let estimate: Estimate = self.call("estimatesmartfee", json!([target_blocks.max(1)]))?;
+        let btc_per_kvb = estimate.feerate.with_context(|| {
+            format!(
+                "bitcoind has no fee estimate: {}",
+                estimate.errors.join("; ")
+            )
+        })?;
+        Ok(sat_per_vb_rate(btc_per_kvb * 100_000_000.0 / 1000.0))
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid> {
+        self.call(
+            "sendrawtransaction",
+            json!([bitcoin::consensus::encode::serialize_hex(tx)]),
+        )
+    }
+}
+
+/// A fee rate given in sat/vB, kept to the sat/kwu precision of [`FeeRate`]
+/// and rounded up.
+pub fn sat_per_vb_rate(sat_per_vb: f64) -> FeeRate {
+    FeeRate::from_sat_per_kwu((sat_per_vb * 250.0).ceil().max(0.0) as u64)
+}
+
+/// Checks that `txid` has at least `confirmations` confirmations in the
+/// best chain, returning how many it has. With `0`, an unconfirmed
+/// transaction is accepted too.
+pub fn require_confirmations(rpc: &dyn BitcoinRpc, txid: &Txid, confirmations: u64) -> Result<u64> {
+    let Some((height, hash)) = rpc.transaction_status(txid)?.block else {
+        ensure!(
+            confirmations == 0,
+            "transaction {txid} is unconfirmed; {confirmations} confirmations are required"
+        );
+        return Ok(0);
+    };
+    // The backend may still report a block a reorg has since replaced.
+    ensure!(
+        rpc.block_hash(height)? == hash,
+        "block {hash} of transaction {txid} is no longer in the best chain"
+    );
+    let confirmed = rpc.tip_height()?.saturating_sub(height) + 1;
+    ensure!(
+        confirmed >= confirmations,
+        "transaction {txid} has {confirmed} confirmations, {confirmations} are required"
+    );
+    Ok(confirmed)
+}
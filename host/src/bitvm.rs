@@ -0,0 +1,107 @@
+//! Conversions to and from the inputs of the BitVM bridge's Groth16 assert
+//! gadgets, which take an arkworks proof, its public inputs as scalars and the
+//! verifying key, and commit each public input as its 32-byte little-endian
+//! encoding.
+//!
+//! BitVM is only distributed from git and pins its own arkworks fork, so it
+//! is not a dependency here; the types below are the arkworks types its
+//! assertion builder takes, and convert into them with `From`/`Into`.
+
+use anyhow::{Context, Result};
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Proof, VerifyingKey};
+use risc0_zkp::core::digest::Digest;
+use risc0_zkvm::Receipt;
+
+use crate::{
+    transform::OutputTransform,
+    verifier::{self, ScalarPolicy, SEAL_LEN},
+};
+
+/// Number of public inputs of the circuit: the journal binding alone.
+pub const NUM_PUBS: usize = 1;
+
+/// Everything a BitVM assertion over one proof is built from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssertionInputs {
+    pub proof: Proof<Bn254>,
+    pub public_inputs: [Fr; NUM_PUBS],
+    pub vk: VerifyingKey<Bn254>,
+}
+
+impl AssertionInputs {
+    /// Decodes the seal of `receipt` and derives its public input with
+    /// `transform`. The receipt is not verified.
+    pub fn new(
+        receipt: &Receipt,
+        image_id: impl Into<Digest>,
+        transform: &dyn OutputTransform,
+    ) -> Result<Self> {
+        let groth_proof = receipt.inner.groth16().context("Not a groth16 proof!!!")?;
+        let public_input = verifier::public_input_scalar(
+            &transform.public_input(image_id.into(), &receipt.journal.bytes),
+            ScalarPolicy::Reject,
+        )?;
+        Ok(Self {
+            proof: verifier::from_seal(&groth_proof.seal)?,
+            public_inputs: [public_input],
+            vk: verifier::get_ark_verifying_key(),
+        })
+    }
+
+    /// The proof as a seal, as stored in receipts.
+    pub fn seal(&self) -> [u8; SEAL_LEN] {
+        verifier::to_seal(&self.proof)
+    }
+
+    /// The public inputs as the assert gadgets commit them.
+    pub fn commitments(&self) -> [PublicInputCommitment; NUM_PUBS] {
+        self.public_inputs.map(PublicInputCommitment::from)
+    }
+}
+
+impl From<AssertionInputs> for (Proof<Bn254>, [Fr; NUM_PUBS], VerifyingKey<Bn254>) {
+    fn from(inputs: AssertionInputs) -> Self {
+        (inputs.proof, inputs.public_inputs, inputs.vk)
+    }
+}
+
+impl From<(Proof<Bn254>, [Fr; NUM_PUBS], VerifyingKey<Bn254>)> for AssertionInputs {
+    fn from(
+        (proof, public_inputs, vk): (Proof<Bn254>, [Fr; NUM_PUBS], VerifyingKey<Bn254>),
+    ) -> Self {
+        Self {
+            proof,
+            public_inputs,
+            vk,
+        }
+    }
+}
+
+/// A public input as committed in a BitVM assertion: the scalar in 32
+/// little-endian bytes. The public input derived from a journal is written
+/// big-endian instead, see [`verifier::expected_output_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PublicInputCommitment(pub [u8; 32]);
+
+impl From<Fr> for PublicInputCommitment {
+    fn from(scalar: Fr) -> Self {
+        Self(scalar.into_bigint().to_bytes_le().try_into().unwrap())
+    }
+}
+
+impl From<PublicInputCommitment> for Fr {
+    /// Reduces values at or above the modulus, which no honest commitment
+    /// holds.
+    fn from(commitment: PublicInputCommitment) -> Self {
+        Fr::from_le_bytes_mod_order(&commitment.0)
+    }
+}
+
+impl From<[u8; verifier::PUBLIC_INPUT_LEN]> for PublicInputCommitment {
+    /// Commits a derived public input, which is always a canonical scalar.
+    fn from(public_input: [u8; verifier::PUBLIC_INPUT_LEN]) -> Self {
+        Fr::from_be_bytes_mod_order(&public_input).into()
+    }
+}
@@ -0,0 +1,267 @@
+//! Remote proving on Bonsai, with retries and resumable jobs.
+//!
+//! Every request to Bonsai is retried with exponential backoff on HTTP and
+//! server errors. Each job is identified by an idempotency key derived from
+//! the image ID and the encoded guest input, and the Bonsai session and snark
+//! IDs of a job are recorded under that key in a state directory as soon as
+//! they are created. Proving the same input again, e.g. after the process is
+//! restarted, resumes polling the recorded session instead of paying for a new
+//! one. The record is removed once the job succeeds or fails.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use bonsai_sdk::{
+    blocking::{Client, SessionId, SnarkId},
+    SdkErr,
+};
+use methods::{METHOD_ELF, METHOD_ID};
+use risc0_zkvm::{sha::Digest, Receipt, VERSION};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    prover::{Backend, ProofStats},
+    storage,
+};
+
+/// Environment variable naming the directory job records are kept in.
+pub const STATE_DIR_ENV: &str = "R0_BITVM_BONSAI_STATE_DIR";
+
+const DEFAULT_STATE_DIR: &str = ".bonsai";
+
+/// How often and how long failed requests are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 6,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Runs `request`, retrying it while it fails with a transient error.
+    pub fn run<T>(&self, what: &str, mut request: impl FnMut() -> Result<T, SdkErr>) -> Result<T> {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            match request() {
+                Ok(value) => return Ok(value),
+                Err(err @ (SdkErr::HttpErr(_) | SdkErr::InternalServerErr(_)))
+                    if attempt < self.max_attempts =>
+                {
+                    tracing::warn!(
+                        "{what} failed (attempt {attempt} of {}), retrying in {backoff:?}: {err}",
+                        self.max_attempts
+                    );
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(self.max_backoff);
+                    attempt += 1;
+                }
+                Err(err) => return Err(err).with_context(|| format!("{what} failed")),
+            }
+        }
+    }
+}
+
+/// The Bonsai jobs started for one input.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JobRecord {
+    session: Option<String>,
+    snark: Option<String>,
+}
+
+/// A Bonsai client that records the jobs it starts.
+pub struct BonsaiProver {
+    client: Client,
+    state_dir: PathBuf,
+    pub retry: RetryPolicy,
+    pub poll_interval: Duration,
+}
+
+/// Whether Bonsai credentials are configured and dev mode is off, in which
+/// case proofs are made remotely.
+pub fn is_configured() -> bool {
+    std::env::var_os("BONSAI_API_URL").is_some()
+        && std::env::var_os("BONSAI_API_KEY").is_some()
+        && !risc0_zkvm::ProverOpts::default().dev_mode()
+}
+
+/// Key identifying a proving job: the BLAKE3 hash of the image ID followed by
+/// the encoded guest input.
+pub fn idempotency_key(image_id: impl Into<Digest>, encoded_input: &[u8]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(image_id.into().as_bytes());
+    hasher.update(encoded_input);
+    hasher.finalize().to_hex().to_string()
+}
+
+impl BonsaiProver {
+    /// Connects using `BONSAI_API_URL` and `BONSAI_API_KEY`, keeping job
+    /// records in the directory named by [`STATE_DIR_ENV`], or `.bonsai`.
+    pub fn from_env() -> Result<Self> {
+        let state_dir = std::env::var_os(STATE_DIR_ENV)
+            .map_or_else(|| PathBuf::from(DEFAULT_STATE_DIR), PathBuf::from);
+        Ok(Self {
+            client: Client::from_env(VERSION)?,
+            state_dir,
+            retry: RetryPolicy::default(),
+            poll_interval: Duration::from_secs(2),
+        })
+    }
+
+    /// Proves the guest over `encoded_input` and returns the Groth16 receipt,
+    /// resuming the job for the same input if one is already recorded.
+    pub fn prove(
+        &self,
+        encoded_input: &[u8],
+        session_limit: Option<u64>,
+    ) -> Result<(Receipt, ProofStats)> {
+        let key = idempotency_key(METHOD_ID, encoded_input);
+        let record_path = self.state_dir.join(format!("{key}.json"));
+        let mut record = load_record(&record_path)?;
+        let outcome = self.run_job(&record_path, &mut record, encoded_input, session_limit);
+        if let Err(err) = fs::remove_file(&record_path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("failed to remove {}: {err}", record_path.display());
+            }
+        }
+        outcome
+    }
+
+    fn run_job(
+        &self,
+        record_path: &Path,
+        record: &mut JobRecord,
+        encoded_input: &[u8],
+        session_limit: Option<u64>,
+    ) -> Result<(Receipt, ProofStats)> {
+        let session = match &record.session {
+            Some(uuid) => {
+                tracing::info!("resuming Bonsai session {uuid}");
+                SessionId::new(uuid.clone())
+            }
+            None => {
+                let image_id = hex::encode(Digest::from(METHOD_ID));
+                self.retry.run("uploading the guest image", || {
+                    self.client.upload_img(&image_id, METHOD_ELF.to_vec())
+                })?;
+                let input_id = self.retry.run("uploading the input", || {
+                    self.client.upload_input(encoded_input.to_vec())
+                })?;
+                let session = self.retry.run("creating a proving session", || {
+                    self.client.create_session_with_limit(
+                        image_id.clone(),
+                        input_id.clone(),
+                        vec![],
+                        false,
+                        session_limit,
+                    )
+                })?;
+                record.session = Some(session.uuid.clone());
+                save_record(record_path, record)?;
+                tracing::info!("started Bonsai session {}", session.uuid);
+                session
+            }
+        };
+
+        let session_stats = loop {
+            let status = self
+                .retry
+                .run("polling the session", || session.status(&self.client))?;
+            match status.status.as_str() {
+                "RUNNING" => thread::sleep(self.poll_interval),
+                "SUCCEEDED" => break status.stats,
+                other => bail!(
+                    "Bonsai session {} ended as {other}: {}",
+                    session.uuid,
+                    status.error_msg.unwrap_or_default()
+                ),
+            }
+        };
+        let stats = match session_stats {
+            Some(stats) => ProofStats {
+                backend: Backend::Bonsai,
+                segments: stats.segments,
+                total_cycles: stats.total_cycles,
+                user_cycles: stats.cycles,
+                stark: None,
+            },
+            None => {
+                tracing::warn!("Bonsai session {} reported no statistics", session.uuid);
+                ProofStats {
+                    backend: Backend::Bonsai,
+                    segments: 0,
+                    total_cycles: 0,
+                    user_cycles: 0,
+                    stark: None,
+                }
+            }
+        };
+
+        let snark = match &record.snark {
+            Some(uuid) => SnarkId::new(uuid.clone()),
+            None => {
+                let snark = self.retry.run("requesting the Groth16 proof", || {
+                    self.client.create_snark(session.uuid.clone())
+                })?;
+                record.snark = Some(snark.uuid.clone());
+                save_record(record_path, record)?;
+                snark
+            }
+        };
+        let receipt_url = loop {
+            let status = self
+                .retry
+                .run("polling the Groth16 proof", || snark.status(&self.client))?;
+            match status.status.as_str() {
+                "RUNNING" => thread::sleep(self.poll_interval),
+                "SUCCEEDED" => {
+                    break status.output.with_context(|| {
+                        format!("Bonsai snark {} succeeded without a receipt", snark.uuid)
+                    })?
+                }
+                other => bail!(
+                    "Bonsai snark {} ended as {other}: {}",
+                    snark.uuid,
+                    status.error_msg.unwrap_or_default()
+                ),
+            }
+        };
+        let encoded = self.retry.run("downloading the receipt", || {
+            self.client.download(&receipt_url)
+        })?;
+        let receipt =
+            bincode::deserialize(&encoded).context("Bonsai returned an invalid receipt")?;
+        Ok((receipt, stats))
+    }
+}
+
+fn load_record(path: &Path) -> Result<JobRecord> {
+    match fs::read(path) {
+        Ok(encoded) => serde_json::from_slice(&encoded)
+            .with_context(|| format!("invalid Bonsai job record {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(JobRecord::default()),
+        Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+fn save_record(path: &Path, record: &JobRecord) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    storage::write_atomic(path, &serde_json::to_vec(record)?)
+}
@@ -0,0 +1,309 @@
+//! Proof bundles: a receipt together with metadata identifying the exact guest
+//! binary that produced it, so proofs can be audited after the fact.
+
+use anyhow::{Context, Result};
+use methods::{GUEST_BUILT_AT, METHOD_ELF, METHOD_ID};
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    da::DaReference,
+    deadline::Escalation,
+    fsck, policy,
+    prover::{Backend, ProofStats, ProverLimits},
+    timestamp::BundleTimestamp,
+};
+
+/// The bincode-encoded unit written to storage and served to clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofBundle {
+    pub metadata: BuildMetadata,
+    pub receipt: Receipt,
+    /// How the receipt was proven, when recorded. Bundles written before
+    /// this was added decode without it.
+    pub run: Option<RunParameters>,
+    /// Where a job due by a deadline was proven, and why.
+    pub escalation: Option<Escalation>,
+    /// OpenTimestamps attestations of the bundle's digest.
+    pub timestamp: Option<BundleTimestamp>,
+    /// Where the full receipts behind the proof were published.
+    pub da: Vec<DaReference>,
+    /// The succinct STARK receipt the Groth16 receipt wraps, when kept, so
+    /// that it can be verified without the Groth16 trusted setup.
+    pub stark: Option<Receipt>,
+}
+
+/// The layout of bundles written before STARK receipts were kept.
+#[derive(Deserialize)]
+struct DaBundle {
+    metadata: BuildMetadata,
+    receipt: Receipt,
+    run: Option<RunParameters>,
+    escalation: Option<Escalation>,
+    timestamp: Option<BundleTimestamp>,
+    da: Vec<DaReference>,
+}
+
+/// The layout of bundles written before data-availability references were
+/// recorded.
+#[derive(Deserialize)]
+struct TimestampBundle {
+    metadata: BuildMetadata,
+    receipt: Receipt,
+    run: Option<RunParameters>,
+    escalation: Option<Escalation>,
+    timestamp: Option<BundleTimestamp>,
+}
+
+/// The layout of bundles written before [`RunParameters`] were recorded.
+#[derive(Deserialize)]
+struct LegacyBundle {
+    metadata: BuildMetadata,
+    receipt: Receipt,
+}
+
+/// The layout of bundles written before timestamps were recorded.
+#[derive(Deserialize)]
+struct EscalationBundle {
+    metadata: BuildMetadata,
+    receipt: Receipt,
+    run: Option<RunParameters>,
+    escalation: Option<Escalation>,
+}
+
+/// The layout of bundles written before escalations were recorded.
+#[derive(Deserialize)]
+struct RunBundle {
+    metadata: BuildMetadata,
+    receipt: Receipt,
+    run: Option<RunParameters>,
+}
+
+/// Describes the guest binary a proof was produced with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildMetadata {
+    /// Hex-encoded image ID of the guest.
+    pub image_id: String,
+    /// Hex-encoded BLAKE3 and SHA-256 digests of the guest ELF.
+    pub elf_blake3: String,
+    pub elf_sha256: String,
+    /// Version of risc0-zkvm the prover was built against.
+    pub risc0_version: String,
+    /// When the guest ELF was built, as RFC 3339.
+    pub built_at: String,
+}
+
+impl BuildMetadata {
+    /// Metadata of the guest embedded in this binary.
+    pub fn current() -> Self {
+        let built_at = GUEST_BUILT_AT
+            .parse()
+            .ok()
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+            .map_or_else(|| GUEST_BUILT_AT.to_string(), |time| time.to_rfc3339());
+        Self {
+            image_id: hex::encode(risc0_zkvm::sha::Digest::from(METHOD_ID)),
+            elf_blake3: blake3::hash(METHOD_ELF).to_hex().to_string(),
+            elf_sha256: hex::encode(Sha256::digest(METHOD_ELF)),
+            risc0_version: risc0_zkvm::VERSION.to_string(),
+            built_at,
+        }
+    }
+}
+
+/// Parameters of a proving run that the receipt does not pin down, so that
+/// two operators whose proofs diverge can compare their pipelines.
+///
+/// risc0 draws the zero-knowledge blinding of every segment, and the Groth16
+/// prover its own randomness, from the operating system, with no way to fix
+/// them; seals therefore differ between otherwise identical runs. The claim
+/// and journal do not.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunParameters {
+    /// Unix timestamp of when the proof was made, in seconds.
+    pub proved_at: u64,
+    /// Number the operator identified the run with, to match it up with
+    /// their own records. It fixes nothing about the proof.
+    pub id: Option<u64>,
+    pub backend: Backend,
+    pub segment_limit_po2: Option<u32>,
+    pub session_limit: Option<u64>,
+    /// `RISC0_PROVER`, which selects the local prover implementation.
+    pub risc0_prover: Option<String>,
+    pub dev_mode: bool,
+    /// Architecture and operating system of the proving host.
+    pub host: String,
+    pub segments: usize,
+    pub total_cycles: u64,
+    pub user_cycles: u64,
+}
+
+impl RunParameters {
+    pub fn new(limits: &ProverLimits, stats: &ProofStats, id: Option<u64>) -> Self {
+        Self {
+            proved_at: policy::unix_now(),
+            id,
+            backend: stats.backend,
+            segment_limit_po2: limits.segment_limit_po2,
+            session_limit: limits.session_limit,
+            risc0_prover: std::env::var("RISC0_PROVER").ok(),
+            dev_mode: risc0_zkvm::ProverOpts::default().dev_mode(),
+            host: format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+            segments: stats.segments,
+            total_cycles: stats.total_cycles,
+            user_cycles: stats.user_cycles,
+        }
+    }
+}
+
+impl ProofBundle {
+    /// Bundles a receipt produced by the guest embedded in this binary.
+    pub fn new(receipt: Receipt) -> Self {
+        Self {
+            metadata: BuildMetadata::current(),
+            receipt,
+            run: None,
+            escalation: None,
+            timestamp: None,
+            da: Vec::new(),
+            stark: None,
+        }
+    }
+
+    /// Records how the receipt was proven.
+    pub fn with_run(mut self, run: RunParameters) -> Self {
+        self.run = Some(run);
+        self
+    }
+
+    /// Records where a job due by a deadline was proven.
+    pub fn with_escalation(mut self, escalation: Escalation) -> Self {
+        self.escalation = Some(escalation);
+        self
+    }
+
+    /// Keeps the succinct STARK receipt the Groth16 receipt wraps.
+    pub fn with_stark(mut self, stark: Receipt) -> Self {
+        self.stark = Some(stark);
+        self
+    }
+
+    /// BLAKE3 digest of the bundle without its timestamp, which is what
+    /// gets timestamped.
+    pub fn digest(&self) -> Result<[u8; 32]> {
+        // Encoded as bundles were before DA references and STARK receipts
+        // were recorded, so that the digest of one without either is
+        // unchanged.
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&bincode::serialize(&(
+            &self.metadata,
+            &self.receipt,
+            &self.run,
+            &self.escalation,
+            None::<BundleTimestamp>,
+        ))?);
+        if !self.da.is_empty() {
+            hasher.update(&bincode::serialize(&self.da)?);
+        }
+        if let Some(stark) = &self.stark {
+            hasher.update(&bincode::serialize(stark)?);
+        }
+        Ok(hasher.finalize().into())
+    }
+
+    /// The bincode encoding, followed by the checksums of its sections.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        fsck::encode(self)
+    }
+
+    /// Decodes a bundle, failing on the first corrupt section if it carries
+    /// checksums.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        fsck::ensure_intact(bytes)?;
+        bincode::deserialize(bytes).or_else(|err| {
+            if let Ok(DaBundle {
+                metadata,
+                receipt,
+                run,
+                escalation,
+                timestamp,
+                da,
+            }) = bincode::deserialize(bytes)
+            {
+                return Ok(Self {
+                    metadata,
+                    receipt,
+                    run,
+                    escalation,
+                    timestamp,
+                    da,
+                    stark: None,
+                });
+            }
+            if let Ok(TimestampBundle {
+                metadata,
+                receipt,
+                run,
+                escalation,
+                timestamp,
+            }) = bincode::deserialize(bytes)
+            {
+                return Ok(Self {
+                    metadata,
+                    receipt,
+                    run,
+                    escalation,
+                    timestamp,
+                    da: Vec::new(),
+                    stark: None,
+                });
+            }
+            if let Ok(EscalationBundle {
+                metadata,
+                receipt,
+                run,
+                escalation,
+            }) = bincode::deserialize(bytes)
+            {
+                return Ok(Self {
+                    metadata,
+                    receipt,
+                    run,
+                    escalation,
+                    timestamp: None,
+                    da: Vec::new(),
+                    stark: None,
+                });
+            }
+            if let Ok(RunBundle {
+                metadata,
+                receipt,
+                run,
+            }) = bincode::deserialize(bytes)
+            {
+                return Ok(Self {
+                    metadata,
+                    receipt,
+                    run,
+                    escalation: None,
+                    timestamp: None,
+                    da: Vec::new(),
+                    stark: None,
+                });
+            }
+            let LegacyBundle { metadata, receipt } = bincode::deserialize(bytes)
+                .map_err(|_| err)
+                .context("invalid proof bundle encoding")?;
+            Ok(Self {
+                metadata,
+                receipt,
+                run: None,
+                escalation: None,
+                timestamp: None,
+                da: Vec::new(),
+                stark: None,
+            })
+        })
+    }
+}
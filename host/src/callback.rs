@@ -0,0 +1,235 @@
+//! Completion callbacks, so orchestrators can be told when a long-running proof
+//! finishes instead of polling for it.
+//!
+//! Callback URLs sent by clients of the server are checked against a
+//! [`CallbackPolicy`] first, so that a client cannot have the server POST to
+//! its own network: unless the operator lists the hosts allowed, only HTTPS
+//! URLs whose host resolves to public addresses are called, at the address
+//! checked, and redirects are never followed.
+
+use std::{
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use reqwest::{blocking::Client, redirect, Url};
+use risc0_zkp::core::digest::Digest;
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+
+use crate::journal::Journal;
+
+const ATTEMPTS: u32 = 3;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Body POSTed as JSON to a callback URL.
+#[derive(Debug, Default, Serialize)]
+pub struct ProofNotification {
+    /// Server-mode job the proof belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    pub succeeded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The proof itself, when sent inline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<ProofSummary>,
+    /// Where the receipt can be downloaded, when sent by reference.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_path: Option<String>,
+}
+
+/// Hex-encoded components of a Groth16 proof and its public input.
+#[derive(Debug, Serialize)]
+pub struct ProofSummary {
+    pub image_id: String,
+    pub journal: String,
+    pub seal: String,
+    pub public_input: String,
+}
+
+impl ProofSummary {
+    pub fn new(receipt: &Receipt, image_id: impl Into<Digest>) -> Result<Self> {
+        let image_id = image_id.into();
+        let journal = Journal::of(receipt);
+        let groth_proof = receipt.inner.groth16().context("Not a groth16 proof!!!")?;
+        Ok(Self {
+            image_id: hex::encode(image_id.as_bytes()),
+            journal: hex::encode(journal),
+            seal: hex::encode(&groth_proof.seal),
+            public_input: hex::encode(journal.public_input(image_id)),
+        })
+    }
+}
+
+/// The `[callbacks]` table of the server configuration: where clients may
+/// have notifications sent.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct CallbackPolicy {
+    /// Hosts callbacks may be sent to, over HTTP or HTTPS. If empty, any HTTPS
+    /// URL whose host only resolves to public addresses is allowed.
+    pub allowed_hosts: Vec<String>,
+}
+
+impl CallbackPolicy {
+    /// Checks that `url` may be called, returning it with the addresses to
+    /// connect to, or no addresses for an allowed host.
+    pub fn check(&self, url: &str) -> Result<(Url, Vec<SocketAddr>)> {
+        let parsed = Url::parse(url).with_context(|| format!("invalid callback URL {url:?}"))?;
+        let host = parsed
+            .host_str()
+            .with_context(|| format!("callback URL {url:?} has no host"))?;
+        if !self.allowed_hosts.is_empty() {
+            ensure!(
+                matches!(parsed.scheme(), "http" | "https"),
+                "callback URL {url:?} is not HTTP"
+            );
+            ensure!(
+                self.allowed_hosts
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(host)),
+                "callback host {host} is not an allowed host"
+            );
+            return Ok((parsed, Vec::new()));
+        }
+        ensure!(
+            parsed.scheme() == "https",
+            "callback URL {url:?} is not HTTPS"
+        );
+        let port = parsed.port_or_known_default().unwrap_or(443);
+        let addrs: Vec<SocketAddr> = (host.trim_matches(['[', ']']), port)
+            .to_socket_addrs()
+            .with_context(|| format!("failed to resolve callback host {host}"))?
+            .collect();
+        ensure!(!addrs.is_empty(), "callback host {host} has no addresses");
+        if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+            bail!(
+                "callback host {host} resolves to {}, which is not a public address",
+                addr.ip()
+            );
+        }
+        Ok((parsed, addrs))
+    }
+}
+
+/// Whether `ip` is reachable on the public internet, rather than loopback,
+/// link-local, private, shared or otherwise reserved for local use.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || first == 0
+                || (first == 100 && second & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(ip.into()),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// POSTs `notification` to `url`, retrying transient failures a few times.
+pub fn notify(url: &str, notification: &ProofNotification) -> Result<()> {
+    post(url, notification)
+}
+
+/// Like [`notify`], for a URL sent by a client: fails unless `policy` allows
+/// it.
+pub fn notify_checked(
+    policy: &CallbackPolicy,
+    url: &str,
+    notification: &ProofNotification,
+) -> Result<()> {
+    let (parsed, addrs) = policy.check(url)?;
+    let mut client = Client::builder()
+        .timeout(TIMEOUT)
+        .redirect(redirect::Policy::none());
+    if let Some(host) = parsed.host_str().filter(|_| !addrs.is_empty()) {
+        // Connect to the addresses checked, not whatever the name resolves to
+        // by the time the request is sent.
+        client = client.resolve_to_addrs(host, &addrs);
+    }
+    send(&client.build()?, url, notification)
+}
+
+/// POSTs `body` to `url` as JSON, retrying transient failures a few times.
+pub fn post(url: &str, body: &impl Serialize) -> Result<()> {
+    let client = Client::builder().timeout(TIMEOUT).build()?;
+    send(&client, url, body)
+}
+
+fn send(client: &Client, url: &str, body: &impl Serialize) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        let result = client
+            .post(url)
+            .json(body)
+            .send()
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return Ok(()),
+            Err(err) if attempt < ATTEMPTS => {
+                tracing::warn!("callback to {url} failed (attempt {attempt}): {err}");
+                thread::sleep(Duration::from_secs(2u64.pow(attempt)));
+                attempt += 1;
+            }
+            Err(err) => return Err(err).with_context(|| format!("callback to {url} failed")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_addresses_are_refused() {
+        let policy = CallbackPolicy::default();
+        for url in [
+            "http://1.1.1.1/hook",
+            "https://127.0.0.1/hook",
+            "https://10.0.0.1/hook",
+            "https://192.168.1.1/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://100.64.0.1/hook",
+            "https://0.0.0.0/hook",
+            "https://[::1]/hook",
+            "https://[fd00::1]/hook",
+            "https://[fe80::1]/hook",
+            "https://[::ffff:127.0.0.1]/hook",
+            "file:///etc/passwd",
+        ] {
+            assert!(policy.check(url).is_err(), "{url}");
+        }
+        let (_, addrs) = policy.check("https://1.1.1.1:8443/hook").unwrap();
+        assert_eq!(addrs, ["1.1.1.1:8443".parse::<SocketAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn allowed_hosts_replace_address_checks() {
+        let policy = CallbackPolicy {
+            allowed_hosts: vec!["hooks.internal".into(), "127.0.0.1".into()],
+        };
+        assert!(policy.check("http://127.0.0.1:9000/hook").is_ok());
+        assert!(policy.check("https://HOOKS.internal/hook").is_ok());
+        assert!(policy.check("https://1.1.1.1/hook").is_err());
+        assert!(policy.check("ftp://hooks.internal/hook").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Splitting exported verifier witness data into chunks small enough for a
+//! Bitcoin script, such as the 520-byte limit on a stack element or the
+//! stack budget of one tapleaf, since a single assert output cannot carry the
+//! whole witness.
+//!
+//! Chunks hold consecutive ranges of the data, all of the maximum length but
+//! the last. The set records the total length and BLAKE3 digest of the data,
+//! so that a reassembled witness can be checked before use.
+
+use anyhow::{ensure, Context, Result};
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+
+use crate::{transform::OutputTransform, verifier};
+
+/// Stack element size limit of Bitcoin script.
+pub const MAX_SCRIPT_ELEMENT_LEN: usize = 520;
+
+/// The witness a BitVM verifier checks: the 256-byte Groth16 seal followed by
+/// the public input binding the journal to `image_id` with `transform`.
+pub fn verifier_witness(
+    receipt: &Receipt,
+    image_id: impl Into<risc0_zkvm::sha::Digest>,
+    transform: &dyn OutputTransform,
+) -> Result<Vec<u8>> {
+    let groth_proof = receipt.inner.groth16().context("Not a groth16 proof!!!")?;
+    verifier::from_seal(&groth_proof.seal)?;
+    let mut witness = groth_proof.seal.clone();
+    witness.extend(transform.public_input(image_id.into(), &receipt.journal.bytes));
+    Ok(witness)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chunk {
+    /// Zero-based position of the chunk.
+    pub index: usize,
+    /// Offset of the chunk within the data.
+    pub offset: usize,
+    #[serde(with = "hex::serde")]
+    pub data: Vec<u8>,
+}
+
+/// Data split into chunks, with what is needed to put it back together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkSet {
+    pub total_len: usize,
+    pub max_chunk_len: usize,
+    /// BLAKE3 digest of the whole data.
+    #[serde(with = "hex::serde")]
+    pub digest: [u8; 32],
+    pub chunks: Vec<Chunk>,
+}
+
+impl ChunkSet {
+    pub fn split(data: &[u8], max_chunk_len: usize) -> Result<Self> {
+        ensure!(max_chunk_len > 0, "chunks must hold at least one byte");
+        let chunks = data
+            .chunks(max_chunk_len)
+            .enumerate()
+            .map(|(index, data)| Chunk {
+                index,
+                offset: index * max_chunk_len,
+                data: data.to_vec(),
+            })
+            .collect();
+        Ok(Self {
+            total_len: data.len(),
+            max_chunk_len,
+            digest: blake3::hash(data).into(),
+            chunks,
+        })
+    }
+
+    /// Puts the data back together from its chunks, in any order, checking
+    /// that none is missing, repeated or altered.
+    pub fn reassemble(&self) -> Result<Vec<u8>> {
+        // `total_len` comes from the set as read, so it only sizes the buffer
+        // once the chunks are known to add up to it.
+        let chunks_len = self
+            .chunks
+            .iter()
+            .try_fold(0usize, |len, chunk| len.checked_add(chunk.data.len()))
+            .context("chunks overflow the data length")?;
+        ensure!(
+            chunks_len == self.total_len,
+            "chunks hold {chunks_len} bytes, expected {}",
+            self.total_len
+        );
+        let mut chunks: Vec<&Chunk> = self.chunks.iter().collect();
+        chunks.sort_by_key(|chunk| chunk.index);
+        let mut data = Vec::with_capacity(self.total_len);
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            ensure!(
+                chunk.index == index && chunk.offset == data.len(),
+                "chunk {index} is missing or repeated"
+            );
+            ensure!(
+                chunk.data.len() <= self.max_chunk_len,
+                "chunk {index} exceeds the {} byte limit",
+                self.max_chunk_len
+            );
+            data.extend(&chunk.data);
+        }
+        ensure!(
+            blake3::hash(&data) == self.digest,
+            "reassembled data does not match its digest"
+        );
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> Vec<u8> {
+        (0..1300u32).map(|i| (i * 7) as u8).collect()
+    }
+
+    #[test]
+    fn split_reassemble_round_trip() {
+        let data = data();
+        let set = ChunkSet::split(&data, MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        assert_eq!(set.chunks.len(), 3);
+        assert!(set.chunks[..2]
+            .iter()
+            .all(|chunk| chunk.data.len() == MAX_SCRIPT_ELEMENT_LEN));
+        assert_eq!(set.reassemble().unwrap(), data);
+
+        let empty = ChunkSet::split(&[], MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        assert!(empty.chunks.is_empty());
+        assert!(empty.reassemble().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reassembles_chunks_in_any_order() {
+        let data = data();
+        let mut set = ChunkSet::split(&data, MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        set.chunks.reverse();
+        assert_eq!(set.reassemble().unwrap(), data);
+    }
+
+    #[test]
+    fn rejects_missing_chunks() {
+        let mut set = ChunkSet::split(&data(), MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        set.chunks.remove(1);
+        let err = set.reassemble().unwrap_err();
+        assert!(err.to_string().contains("expected 1300"), "{err}");
+
+        // A chunk missing from a set whose length was made to add up.
+        let mut set = ChunkSet::split(&data(), MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        set.chunks.remove(1);
+        set.total_len -= MAX_SCRIPT_ELEMENT_LEN;
+        let err = set.reassemble().unwrap_err();
+        assert_eq!(err.to_string(), "chunk 1 is missing or repeated");
+    }
+
+    #[test]
+    fn rejects_reordered_chunks() {
+        let mut set = ChunkSet::split(&data(), MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        let (first, second) = set.chunks.split_at_mut(1);
+        std::mem::swap(&mut first[0].data, &mut second[0].data);
+        let err = set.reassemble().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "reassembled data does not match its digest"
+        );
+
+        let mut set = ChunkSet::split(&data(), MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        set.chunks[0].index = 1;
+        let err = set.reassemble().unwrap_err();
+        assert_eq!(err.to_string(), "chunk 0 is missing or repeated");
+    }
+
+    #[test]
+    fn rejects_tampered_chunks() {
+        let mut set = ChunkSet::split(&data(), MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        set.chunks[2].data[0] ^= 1;
+        let err = set.reassemble().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "reassembled data does not match its digest"
+        );
+
+        let mut set = ChunkSet::split(&data(), MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        set.chunks[2].data.push(0);
+        let err = set.reassemble().unwrap_err();
+        assert!(err.to_string().contains("expected 1300"), "{err}");
+    }
+
+    #[test]
+    fn rejects_lengths_the_chunks_do_not_hold() {
+        let mut set = ChunkSet::split(&data(), MAX_SCRIPT_ELEMENT_LEN).unwrap();
+        set.total_len = usize::MAX;
+        let err = set.reassemble().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("chunks hold 1300 bytes, expected {}", usize::MAX)
+        );
+    }
+}
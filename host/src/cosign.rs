@@ -0,0 +1,636 @@
+//! Co-signed verdicts: several watchtowers sign the verdict they each reached
+//! on a bundle with one MuSig2 (BIP327) signature, so that a bridge contract
+//! or committee can trust a single BIP340 signature under an aggregate key
+//! instead of collecting and counting one signature per watchtower.
+//!
+//! Signing takes two rounds, run through a coordinator that needs no secrets:
+//! every watchtower publishes a public nonce for its verdict, then signs it
+//! once it has the nonces of all the others, and the coordinator aggregates
+//! the partial signatures. A partial signature only combines with the others
+//! if its signer reached the same verdict, and the coordinator names any
+//! signer that did not.
+//!
+//! MuSig2 needs every named signer, but a committee can still require only a
+//! threshold of its members: [`AttestedVerdict::check`] accepts any large
+//! enough subset of the committee as the signers.
+
+use std::{collections::BTreeSet, fmt};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use k256::{
+    elliptic_curve::{
+        ops::Reduce,
+        point::AffineCoordinates,
+        sec1::{FromEncodedPoint, ToEncodedPoint},
+        PrimeField,
+    },
+    schnorr::{Signature, SigningKey, VerifyingKey},
+    sha2::{Digest, Sha256},
+    AffinePoint, EncodedPoint, FieldBytes, ProjectivePoint, Scalar, U256,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::verify_stream::Verdict;
+
+/// Key derivation context of the message a verdict is signed over.
+const SIGNING_CONTEXT: &str = "r0-bitvm-blake3 2025 co-signed verification verdict v1";
+
+/// What the watchtowers sign: the parts of a [`Verdict`] that do not depend
+/// on which watchtower reached it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerdictStatement {
+    /// BLAKE3 digest of the encoded bundle.
+    pub bundle_blake3: String,
+    pub valid: bool,
+    pub image_id: Option<String>,
+    pub journal_digest: Option<String>,
+}
+
+/// A watchtower's public nonce for one verdict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerNonce {
+    /// Hex-encoded x-only public key of the watchtower.
+    pub signer: String,
+    /// Hex-encoded BIP327 public nonce.
+    pub nonce: String,
+}
+
+/// A watchtower's partial signature of a verdict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub signer: String,
+    pub partial: String,
+}
+
+/// A verdict signed by every one of `signers` together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestedVerdict {
+    pub statement: VerdictStatement,
+    /// Hex-encoded x-only public keys of the watchtowers, sorted.
+    pub signers: Vec<String>,
+    /// Hex-encoded x-only MuSig2 aggregate of the signers' keys.
+    pub aggregate_key: String,
+    /// BIP340 signature of the statement under the aggregate key.
+    pub signature: String,
+}
+
+/// A secret nonce, used for exactly one partial signature.
+pub struct SecretNonce([u8; 97]);
+
+/// Why a verdict could not be co-signed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CosignError {
+    /// The signer's partial signature is not of the same statement, or not
+    /// made with its key and nonce.
+    InvalidPartial(String),
+    /// No nonce or partial signature from the signer.
+    Missing(String),
+    /// Two nonces or partial signatures from the signer.
+    Duplicate(String),
+}
+
+impl fmt::Display for CosignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPartial(signer) => write!(
+                f,
+                "partial signature of {signer} does not verify; it may have reached another verdict"
+            ),
+            Self::Missing(signer) => write!(f, "nothing from signer {signer}"),
+            Self::Duplicate(signer) => write!(f, "signer {signer} appears twice"),
+        }
+    }
+}
+
+impl std::error::Error for CosignError {}
+
+impl VerdictStatement {
+    /// The statement of `verdict`, which must name its bundle's digest.
+    pub fn of(verdict: &Verdict) -> Result<Self> {
+        Ok(Self {
+            bundle_blake3: verdict
+                .bundle_blake3
+                .clone()
+                .context("verdict does not name the digest of its bundle")?,
+            valid: verdict.valid,
+            image_id: verdict.image_id.clone(),
+            journal_digest: verdict.journal_digest.clone(),
+        })
+    }
+
+    /// The message the statement is signed over.
+    pub fn message(&self) -> Result<[u8; 32]> {
+        let mut hasher = blake3::Hasher::new_derive_key(SIGNING_CONTEXT);
+        hasher.update(&serde_json::to_vec(self)?);
+        Ok(hasher.finalize().into())
+    }
+}
+
+/// The MuSig2 aggregate of `signers`, which must be sorted and distinct.
+struct KeyAgg {
+    keys: Vec<[u8; 33]>,
+    list_hash: [u8; 32],
+    second: Option<[u8; 33]>,
+    aggregate: AffinePoint,
+}
+
+impl KeyAgg {
+    fn new(signers: &[VerifyingKey]) -> Result<Self> {
+        ensure!(!signers.is_empty(), "no signers");
+        let keys: Vec<[u8; 33]> = signers.iter().map(plain_key).collect();
+        let list_hash = tagged_hash("KeyAgg list", &[&keys.concat()]);
+        let second = keys.iter().copied().find(|key| *key != keys[0]);
+        let mut agg = Self {
+            keys,
+            list_hash,
+            second,
+            aggregate: AffinePoint::IDENTITY,
+        };
+        let aggregate = signers
+            .iter()
+            .zip(&agg.keys)
+            .fold(ProjectivePoint::IDENTITY, |sum, (signer, key)| {
+                sum + ProjectivePoint::from(*signer.as_affine()) * agg.coefficient(key)
+            })
+            .to_affine();
+        ensure!(aggregate != AffinePoint::IDENTITY, "signer keys cancel out");
+        agg.aggregate = aggregate;
+        Ok(agg)
+    }
+
+    fn coefficient(&self, key: &[u8; 33]) -> Scalar {
+        if Some(*key) == self.second {
+            return Scalar::ONE;
+        }
+        scalar(tagged_hash("KeyAgg coefficient", &[&self.list_hash, key]))
+    }
+
+    fn key(&self) -> Result<VerifyingKey> {
+        VerifyingKey::from_bytes(&self.aggregate.x())
+            .map_err(|_| anyhow!("aggregate key is not a valid BIP340 key"))
+    }
+}
+
+/// The values every signer derives from the aggregate nonce.
+struct Session {
+    agg: KeyAgg,
+    /// Coefficient of the second nonce.
+    b: Scalar,
+    r: AffinePoint,
+    e: Scalar,
+}
+
+impl Session {
+    fn new(signers: &[VerifyingKey], nonces: &[[u8; 66]], message: &[u8; 32]) -> Result<Self> {
+        let agg = KeyAgg::new(signers)?;
+        let mut aggnonce = [0; 66];
+        for half in 0..2 {
+            let sum = nonces
+                .iter()
+                .try_fold(ProjectivePoint::IDENTITY, |sum, nonce| {
+                    Ok::<_, anyhow::Error>(sum + point(&nonce[33 * half..33 * (half + 1)])?)
+                })?;
+            aggnonce[33 * half..33 * (half + 1)].copy_from_slice(&encode_point_ext(sum));
+        }
+        let b = scalar(tagged_hash(
+            "MuSig/noncecoef",
+            &[&aggnonce, &agg.aggregate.x(), message],
+        ));
+        let r1 = point_ext(&aggnonce[..33])?;
+        let r2 = point_ext(&aggnonce[33..])?;
+        let mut r = (r1 + r2 * b).to_affine();
+        if r == AffinePoint::IDENTITY {
+            r = AffinePoint::GENERATOR;
+        }
+        let e = scalar(tagged_hash(
+            "BIP0340/challenge",
+            &[&r.x(), &agg.aggregate.x(), message],
+        ));
+        Ok(Self { agg, b, r, e })
+    }
+
+    /// The factor of a signer's secret key: its key coefficient, negated if
+    /// the aggregate key has odd y.
+    fn key_factor(&self, key: &[u8; 33]) -> Result<Scalar> {
+        ensure!(
+            self.agg.keys.contains(key),
+            "signer is not one of the signers"
+        );
+        let a = self.agg.coefficient(key);
+        Ok(if bool::from(self.agg.aggregate.y_is_odd()) {
+            -a
+        } else {
+            a
+        })
+    }
+
+    fn verify_partial(
+        &self,
+        partial: &Scalar,
+        nonce: &[u8; 66],
+        key: &VerifyingKey,
+    ) -> Result<bool> {
+        let mut r = point(&nonce[..33])? + point(&nonce[33..])? * self.b;
+        if bool::from(self.r.y_is_odd()) {
+            r = -r;
+        }
+        let plain = plain_key(key);
+        let expected =
+            r + ProjectivePoint::from(*key.as_affine()) * (self.e * self.key_factor(&plain)?);
+        Ok(ProjectivePoint::GENERATOR * partial == expected)
+    }
+}
+
+impl SecretNonce {
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    pub fn from_hex(encoded: &str) -> Result<Self> {
+        let bytes = hex::decode(encoded.trim()).context("secret nonce is not hex")?;
+        Ok(Self(
+            bytes
+                .try_into()
+                .map_err(|_| anyhow!("secret nonce is not 97 bytes"))?,
+        ))
+    }
+}
+
+/// Generates a nonce pair for `key` to sign `message` with, as in BIP327
+/// `NonceGen`.
+pub fn nonce(key: &SigningKey, message: &[u8; 32]) -> (SecretNonce, SignerNonce) {
+    let mut random = [0; 32];
+    OsRng.fill_bytes(&mut random);
+    let aux = tagged_hash("MuSig/aux", &[&random]);
+    let secret: [u8; 32] = key.to_bytes().into();
+    let seed: Vec<u8> = secret.iter().zip(aux).map(|(s, a)| s ^ a).collect();
+    let plain = plain_key(key.verifying_key());
+    let mut secnonce = [0; 97];
+    let mut pubnonce = [0; 66];
+    for i in 0..2u8 {
+        let k = scalar(tagged_hash(
+            "MuSig/nonce",
+            &[
+                &seed,
+                &[33],
+                &plain,
+                &[0],
+                &[1],
+                &32u64.to_be_bytes(),
+                message,
+                &0u32.to_be_bytes(),
+                &[i],
+            ],
+        ));
+        let i = i as usize;
+        secnonce[32 * i..32 * (i + 1)].copy_from_slice(&k.to_bytes());
+        pubnonce[33 * i..33 * (i + 1)].copy_from_slice(
+            (ProjectivePoint::GENERATOR * k)
+                .to_affine()
+                .to_encoded_point(true)
+                .as_bytes(),
+        );
+    }
+    secnonce[64..].copy_from_slice(&plain);
+    (
+        SecretNonce(secnonce),
+        SignerNonce {
+            signer: hex::encode(key.verifying_key().to_bytes()),
+            nonce: hex::encode(pubnonce),
+        },
+    )
+}
+
+/// Signs `message` with `key` and the secret nonce it published the nonce of,
+/// given the nonces of every signer. The secret nonce is consumed, since
+/// signing twice with it would reveal the key.
+pub fn sign(
+    key: &SigningKey,
+    secnonce: SecretNonce,
+    nonces: &[SignerNonce],
+    message: &[u8; 32],
+) -> Result<PartialSignature> {
+    let plain = plain_key(key.verifying_key());
+    ensure!(
+        secnonce.0[64..] == plain,
+        "secret nonce was generated for another key"
+    );
+    let (signers, nonces) = parse_nonces(nonces)?;
+    let session = Session::new(&signers, &nonces, message)?;
+    let mut k1 = strict_scalar(&secnonce.0[..32]).context("invalid secret nonce")?;
+    let mut k2 = strict_scalar(&secnonce.0[32..64]).context("invalid secret nonce")?;
+    if bool::from(session.r.y_is_odd()) {
+        k1 = -k1;
+        k2 = -k2;
+    }
+    let d = *key.as_nonzero_scalar().as_ref();
+    let s = k1 + session.b * k2 + session.e * session.key_factor(&plain)? * d;
+    let own = signers
+        .iter()
+        .position(|signer| signer == key.verifying_key())
+        .context("signer's nonce is not among the nonces")?;
+    ensure!(
+        session.verify_partial(&s, &nonces[own], key.verifying_key())?,
+        "own nonce among the nonces is not the one generated with the secret nonce"
+    );
+    Ok(PartialSignature {
+        signer: hex::encode(key.verifying_key().to_bytes()),
+        partial: hex::encode(s.to_bytes()),
+    })
+}
+
+/// Aggregates the partial signatures of every signer of `statement` into one
+/// signature.
+pub fn aggregate(
+    statement: VerdictStatement,
+    nonces: &[SignerNonce],
+    partials: &[PartialSignature],
+) -> Result<AttestedVerdict> {
+    let message = statement.message()?;
+    let (signers, nonces) = parse_nonces(nonces)?;
+    let session = Session::new(&signers, &nonces, &message)?;
+    let mut s = Scalar::ZERO;
+    for (signer, nonce) in signers.iter().zip(&nonces) {
+        let name = hex::encode(signer.to_bytes());
+        let mut matching = partials.iter().filter(|partial| partial.signer == name);
+        let partial = matching.next().ok_or(CosignError::Missing(name.clone()))?;
+        ensure!(matching.next().is_none(), CosignError::Duplicate(name));
+        let partial = hex::decode(&partial.partial)
+            .ok()
+            .and_then(|partial| strict_scalar(&partial))
+            .ok_or(CosignError::InvalidPartial(name.clone()))?;
+        ensure!(
+            session.verify_partial(&partial, nonce, signer)?,
+            CosignError::InvalidPartial(name)
+        );
+        s += partial;
+    }
+    if let Some(extra) = partials.iter().find(|partial| {
+        !signers
+            .iter()
+            .any(|signer| hex::encode(signer.to_bytes()) == partial.signer)
+    }) {
+        bail!(
+            "partial signature from {}, which sent no nonce",
+            extra.signer
+        );
+    }
+    let mut signature = session.r.x().to_vec();
+    signature.extend_from_slice(&s.to_bytes());
+    let attested = AttestedVerdict {
+        statement,
+        signers: signers
+            .iter()
+            .map(|signer| hex::encode(signer.to_bytes()))
+            .collect(),
+        aggregate_key: hex::encode(session.agg.key()?.to_bytes()),
+        signature: hex::encode(signature),
+    };
+    attested.check(None, 0)?;
+    Ok(attested)
+}
+
+impl AttestedVerdict {
+    /// Checks the signature. With a `committee`, the signers must also be at
+    /// least `threshold` members of it.
+    pub fn check(&self, committee: Option<&[VerifyingKey]>, threshold: usize) -> Result<()> {
+        let signers = self
+            .signers
+            .iter()
+            .map(|signer| crate::manifest::parse_public_key(signer))
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(
+            signers
+                .windows(2)
+                .all(|pair| pair[0].to_bytes() < pair[1].to_bytes()),
+            "signers are not sorted and distinct"
+        );
+        if let Some(committee) = committee {
+            if let Some(outsider) = signers.iter().find(|signer| !committee.contains(signer)) {
+                bail!(
+                    "signer {} is not a member of the committee",
+                    hex::encode(outsider.to_bytes())
+                );
+            }
+            ensure!(
+                signers.len() >= threshold,
+                "{} signers are fewer than the threshold of {threshold}",
+                signers.len()
+            );
+        }
+        let key = KeyAgg::new(&signers)?.key()?;
+        ensure!(
+            hex::encode(key.to_bytes()) == self.aggregate_key,
+            "aggregate key is not the aggregate of the signers"
+        );
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|signature| Signature::try_from(signature.as_slice()).ok())
+            .context("malformed signature")?;
+        key.verify_raw(&self.statement.message()?, &signature)
+            .map_err(|_| anyhow!("signature does not verify under the aggregate key"))
+    }
+}
+
+/// The signers of `nonces` in key order, with their nonces.
+fn parse_nonces(nonces: &[SignerNonce]) -> Result<(Vec<VerifyingKey>, Vec<[u8; 66]>)> {
+    let mut parsed = nonces
+        .iter()
+        .map(|nonce| {
+            let signer = crate::manifest::parse_public_key(&nonce.signer)?;
+            let bytes: [u8; 66] = hex::decode(&nonce.nonce)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .with_context(|| {
+                    format!("nonce of {} is not 66 hex-encoded bytes", nonce.signer)
+                })?;
+            Ok((signer, bytes))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    parsed.sort_by_key(|(signer, _)| signer.to_bytes());
+    let mut seen = BTreeSet::new();
+    for (signer, _) in &parsed {
+        let name = hex::encode(signer.to_bytes());
+        ensure!(seen.insert(name.clone()), CosignError::Duplicate(name));
+    }
+    Ok(parsed.into_iter().unzip())
+}
+
+fn tagged_hash(tag: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let tag = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag);
+    hasher.update(tag);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// The integer `bytes` encode, reduced modulo the group order.
+fn scalar(bytes: [u8; 32]) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(&FieldBytes::from(bytes))
+}
+
+/// The integer `bytes` encode, if it is less than the group order.
+fn strict_scalar(bytes: &[u8]) -> Option<Scalar> {
+    let bytes: [u8; 32] = bytes.try_into().ok()?;
+    Scalar::from_repr(bytes.into()).into()
+}
+
+/// The compressed point of an x-only key, which stands for the point with
+/// even y as in BIP340.
+fn plain_key(key: &VerifyingKey) -> [u8; 33] {
+    let mut plain = [0x02; 33];
+    plain[1..].copy_from_slice(&key.to_bytes());
+    plain
+}
+
+/// A compressed point.
+fn point(bytes: &[u8]) -> Result<ProjectivePoint> {
+    ensure!(
+        bytes.len() == 33 && matches!(bytes[0], 0x02 | 0x03),
+        "nonce is not of compressed points"
+    );
+    let encoded = EncodedPoint::from_bytes(bytes).map_err(|_| anyhow!("invalid nonce point"))?;
+    Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&encoded))
+        .map(ProjectivePoint::from)
+        .context("nonce point is not on the curve")
+}
+
+/// A compressed point, or the point at infinity encoded as zeros.
+fn point_ext(bytes: &[u8]) -> Result<ProjectivePoint> {
+    if bytes.iter().all(|&byte| byte == 0) {
+        return Ok(ProjectivePoint::IDENTITY);
+    }
+    point(bytes)
+}
+
+fn encode_point_ext(point: ProjectivePoint) -> [u8; 33] {
+    let mut encoded = [0; 33];
+    if point != ProjectivePoint::IDENTITY {
+        encoded.copy_from_slice(point.to_affine().to_encoded_point(true).as_bytes());
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<SigningKey> {
+        (1..=3u8)
+            .map(|i| SigningKey::from_bytes(&[i; 32]).unwrap())
+            .collect()
+    }
+
+    fn statement(valid: bool) -> VerdictStatement {
+        VerdictStatement {
+            bundle_blake3: "ab".repeat(32),
+            valid,
+            image_id: None,
+            journal_digest: None,
+        }
+    }
+
+    /// Runs both signing rounds, with signer `i` signing `statements[i]`.
+    fn cosign(
+        keys: &[SigningKey],
+        statements: &[VerdictStatement],
+    ) -> (Vec<SignerNonce>, Vec<PartialSignature>) {
+        let messages: Vec<_> = statements
+            .iter()
+            .map(|statement| statement.message().unwrap())
+            .collect();
+        let (secrets, nonces): (Vec<_>, Vec<_>) = keys
+            .iter()
+            .zip(&messages)
+            .map(|(key, message)| nonce(key, message))
+            .unzip();
+        let partials = keys
+            .iter()
+            .zip(secrets)
+            .zip(&messages)
+            .map(|((key, secret), message)| sign(key, secret, &nonces, message).unwrap())
+            .collect();
+        (nonces, partials)
+    }
+
+    fn name(key: &SigningKey) -> String {
+        hex::encode(key.verifying_key().to_bytes())
+    }
+
+    #[test]
+    fn key_aggregation_matches_bip327() {
+        // The `[0, 0, 0]` case of the BIP327 key aggregation vectors, the one
+        // whose keys all have even y.
+        let key = VerifyingKey::from_bytes(
+            &hex::decode("F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9")
+                .unwrap(),
+        )
+        .unwrap();
+        let agg = KeyAgg::new(&[key, key, key]).unwrap();
+        assert_eq!(
+            hex::encode_upper(agg.aggregate.x()),
+            "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935"
+        );
+    }
+
+    #[test]
+    fn cosigned_verdict_verifies() {
+        let keys = keys();
+        let (nonces, partials) =
+            cosign(&keys, &[statement(true), statement(true), statement(true)]);
+        let attested = aggregate(statement(true), &nonces, &partials).unwrap();
+        let committee: Vec<_> = keys.iter().map(|key| *key.verifying_key()).collect();
+        attested.check(Some(&committee), 3).unwrap();
+        assert!(attested.check(Some(&committee), 4).is_err());
+        assert!(attested.check(Some(&committee[1..]), 2).is_err());
+
+        let mut tampered = attested.clone();
+        tampered.statement.valid = false;
+        assert!(tampered.check(None, 0).is_err());
+    }
+
+    #[test]
+    fn signer_of_another_verdict_is_named() {
+        let keys = keys();
+        let (nonces, partials) =
+            cosign(&keys, &[statement(true), statement(false), statement(true)]);
+        let err = aggregate(statement(true), &nonces, &partials).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CosignError>(),
+            Some(&CosignError::InvalidPartial(name(&keys[1])))
+        );
+    }
+
+    #[test]
+    fn missing_and_duplicate_partials_are_rejected() {
+        let keys = keys();
+        let (nonces, mut partials) =
+            cosign(&keys, &[statement(true), statement(true), statement(true)]);
+        let dropped = partials.pop().unwrap();
+        let err = aggregate(statement(true), &nonces, &partials).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CosignError>(),
+            Some(&CosignError::Missing(name(&keys[2])))
+        );
+        partials.push(dropped.clone());
+        partials.push(dropped);
+        let err = aggregate(statement(true), &nonces, &partials).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CosignError>(),
+            Some(&CosignError::Duplicate(name(&keys[2])))
+        );
+    }
+
+    #[test]
+    fn secret_nonce_is_bound_to_its_key() {
+        let keys = keys();
+        let message = statement(true).message().unwrap();
+        let (secret, nonce_0) = nonce(&keys[0], &message);
+        let (_, nonce_1) = nonce(&keys[1], &message);
+        assert!(sign(&keys[1], secret, &[nonce_0, nonce_1], &message).is_err());
+    }
+}
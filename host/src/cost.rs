@@ -0,0 +1,85 @@
+//! Estimates of what proofs cost to make, so operators can bill bridge users
+//! for the cycles their inputs take.
+//!
+//! Prices are per million cycles of the proven session, padding included,
+//! and the figure for a proof depends on where it was made: locally, at the
+//! running cost plus any amortized GPU purchase, or on Bonsai, at its price.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::prover::{Backend, ProofStats};
+
+/// Prices used to estimate the cost of a proof, in USD. All default to zero.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct CostModel {
+    /// Running cost of local proving, such as power and hosting, per million
+    /// cycles.
+    pub local_usd_per_mcycle: f64,
+    /// Bonsai's price per million cycles.
+    pub bonsai_usd_per_mcycle: f64,
+    /// Purchase price of the local proving hardware, spread over the cycles
+    /// it proves in its lifetime.
+    pub gpu: Option<GpuAmortization>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct GpuAmortization {
+    pub purchase_usd: f64,
+    /// Hours of proving the hardware is expected to last.
+    pub lifetime_hours: f64,
+    /// Million cycles the hardware proves per hour.
+    pub mcycles_per_hour: f64,
+}
+
+impl GpuAmortization {
+    pub fn usd_per_mcycle(&self) -> f64 {
+        self.purchase_usd / (self.lifetime_hours * self.mcycles_per_hour)
+    }
+}
+
+/// The estimated cost of one proof.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CostEstimate {
+    pub backend: Backend,
+    pub total_cycles: u64,
+    pub usd: f64,
+}
+
+impl CostModel {
+    /// Loads a model from a file of the same TOML form as the `[cost]`
+    /// section of the server configuration.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read cost model {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("failed to parse cost model {}", path.display()))
+    }
+
+    /// Price per million cycles on `backend`.
+    pub fn usd_per_mcycle(&self, backend: Backend) -> f64 {
+        match backend {
+            Backend::Local => {
+                self.local_usd_per_mcycle
+                    + self
+                        .gpu
+                        .as_ref()
+                        .map_or(0.0, GpuAmortization::usd_per_mcycle)
+            }
+            Backend::Bonsai => self.bonsai_usd_per_mcycle,
+        }
+    }
+
+    pub fn estimate(&self, stats: &ProofStats) -> CostEstimate {
+        CostEstimate {
+            backend: stats.backend,
+            total_cycles: stats.total_cycles,
+            usd: stats.total_cycles as f64 / 1e6 * self.usd_per_mcycle(stats.backend),
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Publication of the full receipts behind a proof bundle to a data
+//! availability layer.
+//!
+//! A bundle carries the Groth16 receipt a verifier checks, but the succinct
+//! or composite receipt it was compressed from is what a challenger needs to
+//! re-derive it, and is too large to keep in every bundle. It is published
+//! to a [`DaLayer`] instead, and the bundle records a [`DaReference`]: where
+//! the blob was published and its BLAKE3 digest, which [`fetch`] checks the
+//! retrieved blob against.
+//!
+//! The layer is chosen in a TOML file:
+//!
+//! ```toml
+//! layer = "celestia"
+//! url = "http://127.0.0.1:26658"
+//! auth_token_file = "celestia.token"
+//! namespace = "72302d6269746d7600"
+//! ```
+//!
+//! or `layer = "ipfs"` with the `api_url` of an IPFS node, or `layer =
+//! "store"` with the `uri` of a receipt store, for deployments that serve the
+//! receipts themselves.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::blocking::Client;
+use risc0_zkvm::{sha::Digestible, InnerReceipt, Receipt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+
+#[cfg(feature = "experimental")]
+use crate::ipfs::{self, Ipfs};
+use crate::storage::{self, Store};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Storage key prefix of blobs published to a receipt store.
+const STORE_PREFIX: &str = "da/";
+
+/// Length of a Celestia namespace ID; namespaces of version 0 are the ID
+/// left-padded with zeros to [`CELESTIA_NAMESPACE_LEN`] bytes.
+const CELESTIA_ID_LEN: usize = 10;
+const CELESTIA_NAMESPACE_LEN: usize = 29;
+
+/// What a published blob holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DaContent {
+    /// A bincode-encoded succinct receipt of the bundle's claim.
+    SuccinctReceipt,
+    /// A bincode-encoded composite receipt of the bundle's claim.
+    CompositeReceipt,
+}
+
+impl DaContent {
+    /// What `receipt` would be published as.
+    pub fn of_receipt(receipt: &Receipt) -> Result<Self> {
+        match &receipt.inner {
+            InnerReceipt::Succinct(_) => Ok(Self::SuccinctReceipt),
+            InnerReceipt::Composite(_) => Ok(Self::CompositeReceipt),
+            _ => bail!("only succinct and composite receipts are published"),
+        }
+    }
+}
+
+impl fmt::Display for DaContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::SuccinctReceipt => "succinct receipt",
+            Self::CompositeReceipt => "composite receipt",
+        })
+    }
+}
+
+/// Where a blob was published, as recorded in a bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaReference {
+    pub content: DaContent,
+    /// Name of the layer, as returned by [`DaLayer::name`].
+    pub layer: String,
+    /// Where on the layer the blob is, in the layer's own format.
+    pub locator: String,
+    #[serde(with = "hex::serde")]
+    pub blake3: [u8; 32],
+    pub len: u64,
+}
+
+/// A data availability layer blobs are published to.
+pub trait DaLayer: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Publishes `blob`, returning where it can be fetched from.
+    fn publish(&self, blob: &[u8]) -> Result<String>;
+
+    /// Fetches the blob at `locator` with BLAKE3 digest `digest`.
+    fn fetch(&self, locator: &str, digest: &[u8; 32]) -> Result<Vec<u8>>;
+}
+
+/// Configuration of the layer, loaded from a TOML file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "layer", rename_all = "snake_case")]
+pub enum DaConfig {
+    /// A receipt store, as accepted by `--store`.
+    Store { uri: String },
+    /// A Celestia node's JSON-RPC API.
+    Celestia {
+        url: String,
+        /// File holding the node's auth token, with write permission.
+        #[serde(default)]
+        auth_token_file: Option<PathBuf>,
+        /// Hex-encoded namespace ID, of at most 10 bytes.
+        namespace: String,
+        /// Gas price to pay, in utia; the node estimates one if unset.
+        #[serde(default)]
+        gas_price: Option<f64>,
+    },
+    /// An IPFS node's HTTP RPC API, pinning what it publishes. Locators are
+    /// CIDs.
+    #[cfg(feature = "experimental")]
+    Ipfs {
+        #[serde(default = "default_ipfs_api_url")]
+        api_url: String,
+    },
+}
+
+#[cfg(feature = "experimental")]
+fn default_ipfs_api_url() -> String {
+    ipfs::DEFAULT_API_URL.to_string()
+}
+
+impl DaConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read DA config {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("failed to parse DA config {}", path.display()))
+    }
+
+    pub fn connect(&self) -> Result<Box<dyn DaLayer>> {
+        Ok(match self {
+            Self::Store { uri } => Box::new(StoreLayer {
+                store: storage::open(uri)?,
+            }),
+            Self::Celestia {
+                url,
+                auth_token_file,
+                namespace,
+                gas_price,
+            } => {
+                let token = auth_token_file
+                    .as_ref()
+                    .map(|path| {
+                        std::fs::read_to_string(path)
+                            .map(|token| token.trim().to_string())
+                            .with_context(|| {
+                                format!("failed to read Celestia auth token {}", path.display())
+                            })
+                    })
+                    .transpose()?;
+                Box::new(Celestia::new(url, token, namespace, *gas_price)?)
+            }
+            #[cfg(feature = "experimental")]
+            Self::Ipfs { api_url } => Box::new(Ipfs::new(api_url)?),
+        })
+    }
+}
+
+/// Publishes `blob` to `layer` as `content`.
+pub fn publish(layer: &dyn DaLayer, content: DaContent, blob: &[u8]) -> Result<DaReference> {
+    let locator = layer.publish(blob)?;
+    Ok(DaReference {
+        content,
+        layer: layer.name().to_string(),
+        locator,
+        blake3: *blake3::hash(blob).as_bytes(),
+        len: blob.len() as u64,
+    })
+}
+
+/// Publishes `receipt`, which must prove the same claim as `bundled`, the
+/// receipt of the bundle the reference is recorded in.
+pub fn publish_receipt(
+    layer: &dyn DaLayer,
+    receipt: &Receipt,
+    bundled: &Receipt,
+) -> Result<DaReference> {
+    ensure!(
+        receipt.claim()?.digest() == bundled.claim()?.digest(),
+        "the receipt does not prove the bundle's claim"
+    );
+    publish(
+        layer,
+        DaContent::of_receipt(receipt)?,
+        &bincode::serialize(receipt)?,
+    )
+}
+
+/// Fetches the blob `reference` points to from `layer`, checking it is the
+/// one published.
+pub fn fetch(layer: &dyn DaLayer, reference: &DaReference) -> Result<Vec<u8>> {
+    ensure!(
+        reference.layer == layer.name(),
+        "{} was published to {}, not {}",
+        reference.content,
+        reference.layer,
+        layer.name()
+    );
+    let blob = layer.fetch(&reference.locator, &reference.blake3)?;
+    ensure!(
+        blob.len() as u64 == reference.len && *blake3::hash(&blob).as_bytes() == reference.blake3,
+        "{} at {} does not match the digest recorded in the bundle",
+        reference.content,
+        reference.locator
+    );
+    Ok(blob)
+}
+
+/// Blobs kept in a receipt store, keyed by their digest.
+struct StoreLayer {
+    store: Box<dyn Store>,
+}
+
+impl DaLayer for StoreLayer {
+    fn name(&self) -> &'static str {
+        "store"
+    }
+
+    fn publish(&self, blob: &[u8]) -> Result<String> {
+        let key = format!("{STORE_PREFIX}{}", blake3::hash(blob).to_hex());
+        self.store.put(&key, blob)?;
+        Ok(key)
+    }
+
+    fn fetch(&self, locator: &str, _digest: &[u8; 32]) -> Result<Vec<u8>> {
+        ensure!(
+            locator.starts_with(STORE_PREFIX),
+            "{locator} is not a DA key"
+        );
+        self.store
+            .get(locator)?
+            .with_context(|| format!("{locator} is not in the store"))
+    }
+}
+
+/// A Celestia node, publishing blobs under one namespace. Locators are
+/// `<height>/<namespace>`.
+pub struct Celestia {
+    client: Client,
+    url: String,
+    token: Option<String>,
+    namespace: [u8; CELESTIA_NAMESPACE_LEN],
+    gas_price: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CelestiaBlob {
+    namespace: String,
+    data: String,
+    share_version: u8,
+}
+
+impl Celestia {
+    pub fn new(
+        url: &str,
+        token: Option<String>,
+        namespace: &str,
+        gas_price: Option<f64>,
+    ) -> Result<Self> {
+        let id = hex::decode(namespace).context("namespace is not hex")?;
+        ensure!(
+            !id.is_empty() && id.len() <= CELESTIA_ID_LEN,
+            "a namespace ID is 1 to {CELESTIA_ID_LEN} bytes"
+        );
+        let mut padded = [0; CELESTIA_NAMESPACE_LEN];
+        padded[CELESTIA_NAMESPACE_LEN - id.len()..].copy_from_slice(&id);
+        Ok(Self {
+            client: Client::builder().timeout(TIMEOUT).build()?,
+            url: url.to_string(),
+            token,
+            namespace: padded,
+            gas_price,
+        })
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> Result<T> {
+        #[derive(Deserialize)]
+        struct RpcResponse<T> {
+            result: Option<T>,
+            error: Option<RpcError>,
+        }
+        #[derive(Deserialize)]
+        struct RpcError {
+            code: i64,
+            message: String,
+        }
+        let mut request = self.client.post(&self.url).json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .with_context(|| format!("Celestia call {method} failed"))?;
+        let status = response.status();
+        let body = response.text()?;
+        let response: RpcResponse<T> = serde_json::from_str(&body)
+            .map_err(|_| anyhow!("Celestia call {method} answered {status}: {}", body.trim()))?;
+        if let Some(RpcError { code, message }) = response.error {
+            bail!("Celestia call {method} failed with {code}: {message}");
+        }
+        response
+            .result
+            .with_context(|| format!("Celestia call {method} returned no result"))
+    }
+}
+
+impl DaLayer for Celestia {
+    fn name(&self) -> &'static str {
+        "celestia"
+    }
+
+    fn publish(&self, blob: &[u8]) -> Result<String> {
+        let blob = CelestiaBlob {
+            namespace: BASE64.encode(self.namespace),
+            data: BASE64.encode(blob),
+            share_version: 0,
+        };
+        let options = match self.gas_price {
+            Some(gas_price) => json!({ "gas_price": gas_price, "is_gas_price_set": true }),
+            None => json!({}),
+        };
+        let height: u64 = self.call("blob.Submit", json!([[blob], options]))?;
+        Ok(format!("{height}/{}", hex::encode(self.namespace)))
+    }
+
+    fn fetch(&self, locator: &str, digest: &[u8; 32]) -> Result<Vec<u8>> {
+        let (height, namespace) = locator
+            .split_once('/')
+            .with_context(|| format!("malformed Celestia locator {locator}"))?;
+        let height: u64 = height
+            .parse()
+            .with_context(|| format!("malformed Celestia locator {locator}"))?;
+        let namespace = hex::decode(namespace).context("malformed Celestia namespace")?;
+        let blobs: Vec<CelestiaBlob> =
+            self.call("blob.GetAll", json!([height, [BASE64.encode(&namespace)]]))?;
+        for blob in blobs {
+            let data = BASE64.decode(&blob.data).context("malformed blob data")?;
+            if blake3::hash(&data).as_bytes() == digest {
+                return Ok(data);
+            }
+        }
+        bail!("no blob at height {height} has the recorded digest")
+    }
+}
@@ -0,0 +1,166 @@
+//! Deadline-aware proving: a job due by a deadline is first executed without
+//! proving to measure its cycles, and the time proving it would take on each
+//! backend is projected from their configured throughput. The job is proven
+//! locally if that meets the deadline, and escalated to the proving farm or
+//! to Bonsai otherwise, whichever is the first to meet it.
+//!
+//! The decision is recorded in the bundle as an [`Escalation`], so that an
+//! operator can later tell why a proof was paid for on a faster backend.
+
+use std::fmt;
+
+use anyhow::Result;
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bonsai,
+    input::GuestInput,
+    policy,
+    prover::{self, ProofStats, ProverLimits},
+};
+
+/// Throughput of each backend a job may be escalated to, from the
+/// `[escalation]` section of the server configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct EscalationConfig {
+    /// Million cycles proven per hour by this machine.
+    pub local_mcycles_per_hour: f64,
+    /// Workers of a proving farm, typically GPU machines, running `worker`.
+    #[serde(default)]
+    pub farm: Vec<String>,
+    /// Million cycles proven per hour by the farm as a whole.
+    #[serde(default)]
+    pub farm_mcycles_per_hour: Option<f64>,
+    /// Million cycles proven per hour by Bonsai. Bonsai is only escalated to
+    /// when its credentials are configured.
+    #[serde(default)]
+    pub bonsai_mcycles_per_hour: Option<f64>,
+    /// Seconds kept in hand when projecting, for the Groth16 wrap, uploads
+    /// and error in the throughput figures.
+    #[serde(default)]
+    pub margin_secs: u64,
+}
+
+/// Where a job was proven.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Route {
+    Local,
+    Farm,
+    Bonsai,
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local => write!(f, "local"),
+            Self::Farm => write!(f, "farm"),
+            Self::Bonsai => write!(f, "Bonsai"),
+        }
+    }
+}
+
+/// Why a job due by a deadline was proven where it was.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Escalation {
+    /// Unix timestamp the proof was due by, in seconds.
+    pub deadline: u64,
+    /// Unix timestamp the route was chosen at, after the cycles were
+    /// measured.
+    pub decided_at: u64,
+    /// Cycles the session was measured to take, padding included.
+    pub estimated_cycles: u64,
+    /// Seconds proving locally was projected to take.
+    pub projected_local_secs: u64,
+    pub route: Route,
+    /// Seconds proving on `route` was projected to take.
+    pub projected_secs: u64,
+    /// Whether `route` was projected to finish by the deadline. When no
+    /// backend was, the fastest is used.
+    pub meets_deadline: bool,
+}
+
+impl EscalationConfig {
+    /// The routes available in the order they are preferred, with the
+    /// throughput of each.
+    fn routes(&self) -> Vec<(Route, f64)> {
+        let mut routes = vec![(Route::Local, self.local_mcycles_per_hour)];
+        if let (false, Some(rate)) = (self.farm.is_empty(), self.farm_mcycles_per_hour) {
+            routes.push((Route::Farm, rate));
+        }
+        if let (true, Some(rate)) = (bonsai::is_configured(), self.bonsai_mcycles_per_hour) {
+            routes.push((Route::Bonsai, rate));
+        }
+        routes
+    }
+
+    /// Picks the route for `cycles` due by `deadline`, at time `now`.
+    pub fn decide(&self, cycles: u64, deadline: u64, now: u64) -> Escalation {
+        let project = |rate: f64| -> u64 {
+            let secs = cycles as f64 / 1e6 / rate * 3600.0;
+            (secs.ceil() as u64).saturating_add(self.margin_secs)
+        };
+        let routes: Vec<_> = self
+            .routes()
+            .into_iter()
+            .map(|(route, rate)| (route, project(rate)))
+            .collect();
+        let left = deadline.saturating_sub(now);
+        let (route, projected_secs) = routes
+            .iter()
+            .copied()
+            .find(|&(_, secs)| secs <= left)
+            .unwrap_or_else(|| {
+                routes
+                    .iter()
+                    .copied()
+                    .min_by_key(|&(_, secs)| secs)
+                    .expect("local proving is always a route")
+            });
+        Escalation {
+            deadline,
+            decided_at: now,
+            estimated_cycles: cycles,
+            projected_local_secs: routes[0].1,
+            route,
+            projected_secs,
+            meets_deadline: projected_secs <= left,
+        }
+    }
+}
+
+/// Proves `input` on the first backend projected to finish it by
+/// `deadline`, and returns the receipt with the decision.
+pub fn prove_by(
+    input: &GuestInput,
+    limits: &ProverLimits,
+    deadline: u64,
+    config: &EscalationConfig,
+) -> Result<(Receipt, ProofStats, Escalation)> {
+    let estimate = prover::estimate_cycles(input, limits)?;
+    let escalation = config.decide(estimate.total_cycles, deadline, policy::unix_now());
+    if escalation.meets_deadline {
+        tracing::info!(
+            "proving {} cycles on the {} prover, projected to take {}s of the {}s left",
+            escalation.estimated_cycles,
+            escalation.route,
+            escalation.projected_secs,
+            deadline.saturating_sub(escalation.decided_at)
+        );
+    } else {
+        tracing::warn!(
+            "no prover is projected to prove {} cycles by the deadline; using the {} prover, \
+             projected to take {}s",
+            escalation.estimated_cycles,
+            escalation.route,
+            escalation.projected_secs
+        );
+    }
+    let (receipt, stats) = match escalation.route {
+        Route::Local => prover::prove_locally(input, limits)?,
+        Route::Farm => prover::prove_on_farm(input, limits, &config.farm)?,
+        Route::Bonsai => prover::prove_on_bonsai(input, limits)?,
+    };
+    Ok((receipt, stats, escalation))
+}
@@ -0,0 +1,167 @@
+//! Field-by-field comparison of two proof bundles, for tracking down why one
+//! verifies and another does not without comparing hex dumps by hand.
+//!
+//! Fields are listed roughly in the order a verifier depends on them: the
+//! claim, the journal and the public input derived from both, the verifier
+//! parameters and the seal, then the build metadata. The first field that
+//! differs is usually the cause of the others.
+
+use hex::FromHex;
+use risc0_zkvm::{
+    sha::{Digest, Digestible},
+    InnerReceipt, Receipt, ReceiptClaim,
+};
+
+use crate::{bundle::ProofBundle, journal::Journal, verifier::SEAL_LEN};
+
+/// Names of the field elements of a seal, in the layout read by
+/// [`from_seal`](crate::verifier::from_seal).
+const SEAL_ELEMENTS: [&str; 8] = [
+    "a.x", "a.y", "b.x.c1", "b.x.c0", "b.y.c1", "b.y.c0", "c.x", "c.y",
+];
+
+/// One compared field and its value in each bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    pub field: String,
+    pub a: String,
+    pub b: String,
+}
+
+impl FieldDiff {
+    fn new(field: impl Into<String>, a: impl ToString, b: impl ToString) -> Self {
+        Self {
+            field: field.into(),
+            a: a.to_string(),
+            b: b.to_string(),
+        }
+    }
+
+    pub fn differs(&self) -> bool {
+        self.a != self.b
+    }
+}
+
+/// Compares every field of `a` and `b`, equal or not.
+pub fn diff(a: &ProofBundle, b: &ProofBundle) -> Vec<FieldDiff> {
+    let (ra, rb) = (&a.receipt, &b.receipt);
+    let mut fields = vec![
+        FieldDiff::new("receipt.kind", kind(ra), kind(rb)),
+        FieldDiff::new(
+            "claim.image_id",
+            claimed(ra, image_id),
+            claimed(rb, image_id),
+        ),
+        FieldDiff::new(
+            "claim.exit_code",
+            claimed(ra, exit_code),
+            claimed(rb, exit_code),
+        ),
+    ];
+
+    let (ja, jb) = (Journal::of(ra), Journal::of(rb));
+    fields.push(FieldDiff::new("journal.len", ja.len(), jb.len()));
+    fields.push(FieldDiff::new("journal.digest", ja.digest(), jb.digest()));
+    let (bytes_a, bytes_b) = (ja.as_bytes(), jb.as_bytes());
+    if let Some(offset) = (0..bytes_a.len().max(bytes_b.len()))
+        .find(|&offset| bytes_a.get(offset) != bytes_b.get(offset))
+    {
+        let byte = |bytes: &[u8]| {
+            bytes
+                .get(offset)
+                .map_or("end".into(), |byte| format!("{byte:02x}"))
+        };
+        fields.push(FieldDiff::new(
+            format!("journal[{offset}]"),
+            byte(bytes_a),
+            byte(bytes_b),
+        ));
+    }
+    fields.push(FieldDiff::new(
+        "public_input",
+        hex::encode(ja.public_input(claimed_image_id(a))),
+        hex::encode(jb.public_input(claimed_image_id(b))),
+    ));
+
+    let (ga, gb) = (groth16(ra), groth16(rb));
+    fields.push(FieldDiff::new(
+        "verifier_parameters",
+        ga.map_or("none".into(), |(_, params)| params.to_string()),
+        gb.map_or("none".into(), |(_, params)| params.to_string()),
+    ));
+    let (sa, sb) = (
+        ga.map_or(&[][..], |(seal, _)| seal),
+        gb.map_or(&[][..], |(seal, _)| seal),
+    );
+    fields.push(FieldDiff::new("seal.len", sa.len(), sb.len()));
+    if sa.len() == SEAL_LEN && sb.len() == SEAL_LEN {
+        for (index, name) in SEAL_ELEMENTS.iter().enumerate() {
+            let element = |seal: &[u8]| hex::encode(&seal[index * 32..(index + 1) * 32]);
+            fields.push(FieldDiff::new(
+                format!("seal.{name}"),
+                element(sa),
+                element(sb),
+            ));
+        }
+    }
+
+    let (ma, mb) = (&a.metadata, &b.metadata);
+    fields.extend([
+        FieldDiff::new("metadata.image_id", &ma.image_id, &mb.image_id),
+        FieldDiff::new("metadata.elf_blake3", &ma.elf_blake3, &mb.elf_blake3),
+        FieldDiff::new("metadata.elf_sha256", &ma.elf_sha256, &mb.elf_sha256),
+        FieldDiff::new(
+            "metadata.risc0_version",
+            &ma.risc0_version,
+            &mb.risc0_version,
+        ),
+        FieldDiff::new("metadata.built_at", &ma.built_at, &mb.built_at),
+    ]);
+    fields
+}
+
+fn kind(receipt: &Receipt) -> &'static str {
+    match &receipt.inner {
+        InnerReceipt::Groth16(_) => "groth16",
+        InnerReceipt::Succinct(_) => "succinct",
+        InnerReceipt::Composite(_) => "composite",
+        InnerReceipt::Fake(_) => "fake",
+        _ => "unknown",
+    }
+}
+
+fn groth16(receipt: &Receipt) -> Option<(&[u8], Digest)> {
+    let groth_proof = receipt.inner.groth16().ok()?;
+    Some((&groth_proof.seal, groth_proof.verifier_parameters))
+}
+
+fn image_id(claim: &ReceiptClaim) -> String {
+    claim.pre.digest().to_string()
+}
+
+fn exit_code(claim: &ReceiptClaim) -> String {
+    format!("{:?}", claim.exit_code)
+}
+
+/// A field of the receipt's claim, or why it is unavailable.
+fn claimed(receipt: &Receipt, field: fn(&ReceiptClaim) -> String) -> String {
+    match receipt.claim() {
+        Ok(claim) => match claim.as_value() {
+            Ok(claim) => field(claim),
+            Err(_) => "pruned".into(),
+        },
+        Err(err) => format!("unavailable: {err}"),
+    }
+}
+
+/// The image ID the public input is derived for: the claimed one, or the one
+/// recorded in the metadata when the claim is pruned.
+fn claimed_image_id(bundle: &ProofBundle) -> Digest {
+    let claim = bundle.receipt.claim().ok();
+    claim
+        .as_ref()
+        .and_then(|claim| claim.as_value().ok())
+        .map(|claim| claim.pre.digest())
+        .or_else(|| Digest::from_hex(&bundle.metadata.image_id).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,205 @@
+//! Assembling the witness a challenger spends a disprove tapleaf with, once
+//! an operator's assert is found to be fraudulent.
+//!
+//! The disprove leaves are BitVM's, built outside this crate along with the
+//! connector output committing to them, so a [`DisproveTree`] is loaded
+//! from what that tooling exports: the internal key and, in depth-first
+//! order, each leaf's depth, script and the fraud it disproves. A leaf takes
+//! the value the operator committed that it shows to be wrong as its only
+//! stack element, see [`Commitments::value`]: the prefix or journal hash as
+//! committed, the public input as the 32 little-endian bytes BitVM commits,
+//! or the proof chunk as asserted.
+
+use std::{fmt, path::Path, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::{
+    absolute::LockTime,
+    secp256k1::Secp256k1,
+    taproot::{ControlBlock, LeafVersion, TaprootBuilder, TaprootSpendInfo},
+    transaction::Version,
+    Amount, FeeRate, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness,
+    XOnlyPublicKey,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{chunks::MAX_SCRIPT_ELEMENT_LEN, fraud::Commitments};
+
+/// What a disprove leaf shows the operator got wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fraud {
+    /// The committed succinct output prefix is not the one the image ID
+    /// derives.
+    Prefix,
+    /// The committed journal hash is not `blake3(prefix || journal)`.
+    JournalHash,
+    /// The asserted public input, the scalar, is not the one the journal
+    /// hash derives.
+    PublicInput,
+    /// The asserted chunk of the verifier witness, at this index, is not of
+    /// a valid proof.
+    ProofChunk(usize),
+}
+
+impl fmt::Display for Fraud {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prefix => write!(f, "prefix"),
+            Self::JournalHash => write!(f, "journal-hash"),
+            Self::PublicInput => write!(f, "public-input"),
+            Self::ProofChunk(index) => write!(f, "proof-chunk:{index}"),
+        }
+    }
+}
+
+impl FromStr for Fraud {
+    type Err = anyhow::Error;
+
+    fn from_str(fraud: &str) -> Result<Self> {
+        match fraud.split_once(':') {
+            None if fraud == "prefix" => Ok(Self::Prefix),
+            None if fraud == "journal-hash" => Ok(Self::JournalHash),
+            None if fraud == "public-input" => Ok(Self::PublicInput),
+            Some(("proof-chunk", index)) => Ok(Self::ProofChunk(
+                index.parse().context("invalid proof chunk index")?,
+            )),
+            _ => bail!(
+                "unknown fraud {fraud}, expected prefix, journal-hash, public-input or \
+                 proof-chunk:<index>"
+            ),
+        }
+    }
+}
+
+/// A disprove leaf of the connector output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisproveLeaf {
+    pub fraud: Fraud,
+    /// Depth of the leaf in the script tree.
+    pub depth: u8,
+    #[serde(with = "hex::serde")]
+    pub script: Vec<u8>,
+}
+
+/// The script tree of the connector output a challenger spends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisproveTree {
+    pub internal_key: XOnlyPublicKey,
+    /// The leaves in depth-first order.
+    pub leaves: Vec<DisproveLeaf>,
+}
+
+impl DisproveTree {
+    /// Reads a script tree exported as JSON.
+    pub fn load(path: &Path) -> Result<Self> {
+        let encoded = std::fs::read(path)
+            .with_context(|| format!("failed to read script tree {}", path.display()))?;
+        serde_json::from_slice(&encoded).context("invalid script tree")
+    }
+
+    pub fn spend_info(&self) -> Result<TaprootSpendInfo> {
+        let mut builder = TaprootBuilder::new();
+        for leaf in &self.leaves {
+            builder = builder.add_leaf(leaf.depth, ScriptBuf::from_bytes(leaf.script.clone()))?;
+        }
+        builder
+            .finalize(&Secp256k1::verification_only(), self.internal_key)
+            .map_err(|_| anyhow!("disprove leaves do not form a complete script tree"))
+    }
+
+    /// The connector output's script, to check the tree against the chain.
+    pub fn script_pubkey(&self) -> Result<ScriptBuf> {
+        Ok(ScriptBuf::new_p2tr_tweaked(self.spend_info()?.output_key()))
+    }
+
+    /// The witness disproving `fraud` in what the operator committed.
+    pub fn witness(&self, fraud: Fraud, commitments: &Commitments) -> Result<DisproveWitness> {
+        let leaf = self
+            .leaves
+            .iter()
+            .find(|leaf| leaf.fraud == fraud)
+            .with_context(|| format!("the connector has no leaf disproving {fraud}"))?;
+        let value = commitments
+            .value(fraud)
+            .with_context(|| format!("the operator committed no value for {fraud}"))?;
+        let script = ScriptBuf::from_bytes(leaf.script.clone());
+        let control_block = self
+            .spend_info()?
+            .control_block(&(script.clone(), LeafVersion::TapScript))
+            .expect("the leaf is in the tree");
+        Ok(DisproveWitness {
+            fraud,
+            values: vec![value],
+            script,
+            control_block,
+        })
+    }
+}
+
+/// The witness spending a disprove leaf: the committed values it takes, the
+/// leaf script and its control block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisproveWitness {
+    pub fraud: Fraud,
+    /// The stack elements, the last on top.
+    pub values: Vec<Vec<u8>>,
+    pub script: ScriptBuf,
+    pub control_block: ControlBlock,
+}
+
+impl DisproveWitness {
+    pub fn to_witness(&self) -> Result<Witness> {
+        if let Some(value) = self
+            .values
+            .iter()
+            .find(|value| value.len() > MAX_SCRIPT_ELEMENT_LEN)
+        {
+            bail!(
+                "committed value of {} bytes exceeds the script element limit",
+                value.len()
+            );
+        }
+        let mut witness = Witness::from_slice(&self.values);
+        witness.push(self.script.as_bytes());
+        witness.push(self.control_block.serialize());
+        Ok(witness)
+    }
+
+    /// The transaction spending the connector output `connector`, of
+    /// `value`, through the disprove leaf, paying what is left after the fee
+    /// to `payout`. Disprove leaves need no signature, so it can be
+    /// broadcast as it is.
+    pub fn transaction(
+        &self,
+        connector: OutPoint,
+        value: Amount,
+        payout: ScriptBuf,
+        fee_rate: FeeRate,
+    ) -> Result<Transaction> {
+        let mut tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: connector,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: self.to_witness()?,
+            }],
+            output: vec![TxOut {
+                value: Amount::ZERO,
+                script_pubkey: payout,
+            }],
+        };
+        let fee = fee_rate
+            .fee_vb(tx.weight().to_vbytes_ceil())
+            .ok_or_else(|| anyhow!("fee at {fee_rate} overflows"))?;
+        tx.output[0].value = value
+            .checked_sub(fee)
+            .filter(|left| *left >= tx.output[0].script_pubkey.minimal_non_dust())
+            .with_context(|| {
+                format!("the connector output of {value} cannot pay a fee of {fee} and a non-dust payout")
+            })?;
+        Ok(tx)
+    }
+}
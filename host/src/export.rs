@@ -0,0 +1,217 @@
+//! Exports of a receipt in the formats expected by other verifiers of the same
+//! claim.
+
+use anyhow::{ensure, Context, Result};
+use risc0_zkp::core::digest::Digest;
+use risc0_zkvm::Receipt;
+use serde::Serialize;
+
+use crate::{
+    journal::Journal,
+    transform::OutputTransform,
+    verifier::{self, ScalarPolicy},
+};
+
+/// Arguments of `IRiscZeroVerifier.verify(seal, imageId, journalDigest)` from
+/// risc0's Solidity contracts, as `0x`-prefixed hex.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvmVerifierInput {
+    /// The Groth16 seal prefixed with the 4-byte selector of the verifier
+    /// parameters, which `RiscZeroVerifierRouter` uses to pick a verifier.
+    pub seal: String,
+    pub image_id: String,
+    /// SHA-256 of the journal.
+    pub journal_digest: String,
+}
+
+impl EvmVerifierInput {
+    /// Fails if the seal is not canonically encoded, see
+    /// [`verifier::normalize_seal`].
+    pub fn new(receipt: &Receipt, image_id: impl Into<Digest>) -> Result<Self> {
+        let groth_proof = receipt.inner.groth16().context("Not a groth16 proof!!!")?;
+        verifier::from_seal(&groth_proof.seal)?;
+        let selector = &groth_proof.verifier_parameters.as_bytes()[..4];
+        Ok(Self {
+            seal: hex_0x([selector, groth_proof.seal.as_slice()].concat()),
+            image_id: hex_0x(image_id.into()),
+            journal_digest: hex_0x(Journal::of(receipt).digest()),
+        })
+    }
+}
+
+/// Input signals of a Groth16 verifier written in circom, such as the
+/// `verifyProof` template of circom-pairing: the negated `A` point and the `B`
+/// and `C` points of the proof as arrays of `limb_bits`-bit limbs, least
+/// significant first, and the public input, all as decimal strings. Fields of
+/// `Fq2` coordinates are listed as `[c0, c1]`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircomInput {
+    pub negpa: [Vec<String>; 2],
+    pub pb: [[Vec<String>; 2]; 2],
+    pub pc: [Vec<String>; 2],
+    pub pub_input: [String; 1],
+}
+
+/// Limb layout of circom-pairing's BN254 circuits.
+pub const CIRCOM_LIMB_BITS: u32 = 43;
+pub const CIRCOM_LIMBS: usize = 6;
+
+impl CircomInput {
+    pub fn new(
+        receipt: &Receipt,
+        image_id: impl Into<Digest>,
+        transform: &dyn OutputTransform,
+        limb_bits: u32,
+        limbs: usize,
+    ) -> Result<Self> {
+        ensure!(
+            (1..=64).contains(&limb_bits) && limb_bits as usize * limbs >= 254,
+            "{limbs} limbs of {limb_bits} bits cannot hold a BN254 field element"
+        );
+        let groth_proof = receipt.inner.groth16().context("Not a groth16 proof!!!")?;
+        let proof = verifier::from_seal(&groth_proof.seal)?;
+        let public_input = verifier::public_input_scalar(
+            &transform.public_input(image_id.into(), &receipt.journal.bytes),
+            ScalarPolicy::Reject,
+        )?;
+
+        let split = |element: ark_bn254::Fq| circom_limbs(element, limb_bits, limbs);
+        let a = -proof.a;
+        Ok(Self {
+            negpa: [split(a.x), split(a.y)],
+            pb: [
+                [split(proof.b.x.c0), split(proof.b.x.c1)],
+                [split(proof.b.y.c0), split(proof.b.y.c1)],
+            ],
+            pc: [split(proof.c.x), split(proof.c.y)],
+            pub_input: [public_input.to_string()],
+        })
+    }
+}
+
+fn circom_limbs(element: ark_bn254::Fq, limb_bits: u32, limbs: usize) -> Vec<String> {
+    use ark_ff::{BigInteger, PrimeField};
+
+    let bits = element.into_bigint().to_bits_le();
+    (0..limbs)
+        .map(|limb| {
+            let start = limb * limb_bits as usize;
+            let value = bits
+                .iter()
+                .skip(start)
+                .take(limb_bits as usize)
+                .rev()
+                .fold(0u64, |value, &bit| value << 1 | bit as u64);
+            value.to_string()
+        })
+        .collect()
+}
+
+/// A proof and its public witness in the binary encodings written by gnark's
+/// `groth16.Proof.WriteTo` and `witness.Witness.WriteTo` for BN254, readable
+/// with the matching `ReadFrom` methods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GnarkExport {
+    /// `Ar`, `Bs` and `Krs` as compressed points, followed by an empty list of
+    /// commitments and the point at infinity as their proof of knowledge.
+    pub proof: Vec<u8>,
+    /// The public input as a gnark witness with one public and no secret
+    /// values.
+    pub public_witness: Vec<u8>,
+}
+
+/// Flags gnark-crypto stores in the top bits of a compressed point.
+const GNARK_COMPRESSED_SMALLEST: u8 = 0b10 << 6;
+const GNARK_COMPRESSED_LARGEST: u8 = 0b11 << 6;
+const GNARK_COMPRESSED_INFINITY: u8 = 0b01 << 6;
+
+impl GnarkExport {
+    pub fn new(
+        receipt: &Receipt,
+        image_id: impl Into<Digest>,
+        transform: &dyn OutputTransform,
+    ) -> Result<Self> {
+        let groth_proof = receipt.inner.groth16().context("Not a groth16 proof!!!")?;
+        let proof = verifier::from_seal(&groth_proof.seal)?;
+        let public_input = verifier::public_input_scalar(
+            &transform.public_input(image_id.into(), &receipt.journal.bytes),
+            ScalarPolicy::Reject,
+        )?;
+
+        let mut encoded = Vec::new();
+        encoded.extend(gnark_g1(&proof.a));
+        encoded.extend(gnark_g2(&proof.b));
+        encoded.extend(gnark_g1(&proof.c));
+        encoded.extend(0u32.to_be_bytes());
+        encoded.extend(gnark_g1(&ark_bn254::G1Affine::identity()));
+
+        let mut public_witness = Vec::new();
+        public_witness.extend(1u32.to_be_bytes());
+        public_witness.extend(0u32.to_be_bytes());
+        public_witness.extend(1u32.to_be_bytes());
+        public_witness.extend(ark_ff::BigInteger::to_bytes_be(
+            &ark_ff::PrimeField::into_bigint(public_input),
+        ));
+        Ok(Self {
+            proof: encoded,
+            public_witness,
+        })
+    }
+}
+
+/// Whether `y` is the larger of `y` and `-y`, which gnark-crypto records to
+/// recover `y` from `x`.
+fn gnark_largest(y: ark_bn254::Fq) -> bool {
+    use ark_ff::PrimeField;
+
+    y.into_bigint() > ark_bn254::Fq::MODULUS_MINUS_ONE_DIV_TWO
+}
+
+fn gnark_fq(element: ark_bn254::Fq) -> Vec<u8> {
+    use ark_ff::{BigInteger, PrimeField};
+
+    element.into_bigint().to_bytes_be()
+}
+
+fn gnark_g1(point: &ark_bn254::G1Affine) -> [u8; 32] {
+    let mut encoded = [0; 32];
+    if point.infinity {
+        encoded[0] = GNARK_COMPRESSED_INFINITY;
+        return encoded;
+    }
+    encoded.copy_from_slice(&gnark_fq(point.x));
+    encoded[0] |= if gnark_largest(point.y) {
+        GNARK_COMPRESSED_LARGEST
+    } else {
+        GNARK_COMPRESSED_SMALLEST
+    };
+    encoded
+}
+
+/// Encodes `x` with its imaginary part first, as gnark-crypto does.
+fn gnark_g2(point: &ark_bn254::G2Affine) -> [u8; 64] {
+    let mut encoded = [0; 64];
+    if point.infinity {
+        encoded[0] = GNARK_COMPRESSED_INFINITY;
+        return encoded;
+    }
+    encoded[..32].copy_from_slice(&gnark_fq(point.x.c1));
+    encoded[32..].copy_from_slice(&gnark_fq(point.x.c0));
+    let largest = if point.y.c1 == ark_bn254::Fq::from(0u8) {
+        gnark_largest(point.y.c0)
+    } else {
+        gnark_largest(point.y.c1)
+    };
+    encoded[0] |= if largest {
+        GNARK_COMPRESSED_LARGEST
+    } else {
+        GNARK_COMPRESSED_SMALLEST
+    };
+    encoded
+}
+
+fn hex_0x(bytes: impl AsRef<[u8]>) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
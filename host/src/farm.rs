@@ -0,0 +1,111 @@
+//! Proving farm: a coordinator that executes a session and shards its
+//! segments across worker machines, cutting the wall-clock time of very large
+//! inputs to roughly that of the slowest worker's share.
+//!
+//! Each worker runs `worker`, an HTTP service whose `POST /segments` takes a
+//! bincode-encoded segment as the body, proves and lifts it, and returns the
+//! bincode-encoded succinct receipt. The coordinator runs the
+//! [`pipeline`](crate::pipeline) with one segment prover per worker, so
+//! segments go to idle workers as soon as the executor produces them, and
+//! joins and wraps the lifted receipts locally. Workers do not authenticate
+//! their clients, so they belong on a private network.
+
+use std::{io::Read, net::SocketAddr, time::Duration};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use risc0_zkvm::{ApiClient, Receipt, ReceiptClaim, SuccinctReceipt};
+use tiny_http::{Method, Request, Response, Server};
+
+use crate::{
+    input::GuestInput,
+    pipeline::{self, SegmentProver},
+    prover::{ProofStats, ProverLimits},
+};
+
+/// Largest segment a worker accepts, in bytes.
+const MAX_SEGMENT_LEN: u64 = 1024 * 1024 * 1024;
+/// How long the coordinator waits for a worker to prove one segment.
+const WORKER_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Serves segment proving requests on `listen` until the process is stopped.
+pub fn serve_worker(listen: SocketAddr) -> Result<()> {
+    let client = pipeline::r0vm()?;
+    let server = Server::http(listen).map_err(|err| anyhow!(err))?;
+    tracing::info!("proving worker listening on {listen}");
+    for mut request in server.incoming_requests() {
+        let response = match (request.method(), request.url()) {
+            (Method::Post, "/segments") => match prove_segment(&client, &mut request) {
+                Ok(receipt) => Response::from_data(receipt),
+                Err(err) => {
+                    tracing::warn!("failed to prove segment: {err:#}");
+                    Response::from_string(format!("{err:#}")).with_status_code(500)
+                }
+            },
+            _ => Response::from_string("not found").with_status_code(404),
+        };
+        if let Err(err) = request.respond(response) {
+            tracing::warn!("failed to send response: {err}");
+        }
+    }
+    Ok(())
+}
+
+fn prove_segment(client: &ApiClient, request: &mut Request) -> Result<Vec<u8>> {
+    let mut segment = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_SEGMENT_LEN)
+        .read_to_end(&mut segment)
+        .context("failed to read segment")?;
+    let receipt = pipeline::prove_segment(client, &segment)?;
+    Ok(bincode::serialize(&receipt)?)
+}
+
+/// Executes the guest over `input`, proving its segments on `workers`, and
+/// returns the Groth16-wrapped receipt.
+pub(crate) fn prove_session(
+    input: &GuestInput,
+    limits: &ProverLimits,
+    workers: &[String],
+) -> Result<(Receipt, ProofStats)> {
+    ensure!(
+        !workers.is_empty(),
+        "a proving farm needs at least one worker"
+    );
+    let http = reqwest::blocking::Client::builder()
+        .timeout(WORKER_TIMEOUT)
+        .build()?;
+    let provers: Vec<SegmentProver> = workers
+        .iter()
+        .map(|worker| -> SegmentProver {
+            let http = &http;
+            Box::new(move |segment| {
+                prove_remote(http, worker, segment).with_context(|| format!("worker {worker}"))
+            })
+        })
+        .collect();
+    pipeline::prove_session(input, limits, &provers)
+}
+
+/// Sends `segment` to `worker` and checks the lifted receipt it returns.
+fn prove_remote(
+    http: &reqwest::blocking::Client,
+    worker: &str,
+    segment: &[u8],
+) -> Result<SuccinctReceipt<ReceiptClaim>> {
+    let response = http
+        .post(format!("{}/segments", worker.trim_end_matches('/')))
+        .body(segment.to_vec())
+        .send()?;
+    let status = response.status();
+    let body = response.bytes()?;
+    if !status.is_success() {
+        bail!("{status}: {}", String::from_utf8_lossy(&body));
+    }
+    let receipt: SuccinctReceipt<ReceiptClaim> =
+        bincode::deserialize(&body).context("malformed lifted receipt")?;
+    receipt
+        .verify_integrity()
+        .context("lifted receipt does not verify")?;
+    Ok(receipt)
+}
@@ -0,0 +1,167 @@
+//! Fetching the verifying key of the circuit risc0 wraps receipts with from
+//! the artifacts of a risc0 release, so that a deployment can pin the key of
+//! the release its operators prove with instead of trusting the constants
+//! compiled into this binary to be current.
+//!
+//! The artifact is the key in snarkjs' JSON form. It is checked against a
+//! SHA-256 digest the caller takes from the release notes, never one fetched
+//! from the host serving the key, which could swap both, then decoded with
+//! every point checked to be on the curve and in its subgroup.
+
+use std::{str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G2Affine};
+use ark_groth16::VerifyingKey;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Where the key of a release is published; `{version}` is replaced by the
+/// release, e.g. `2.0.2`.
+pub const DEFAULT_URL: &str =
+    "https://github.com/risc0/risc0/releases/download/v{version}/verification_key.json";
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Largest artifact fetched; the key of a circuit with one public input is a
+/// few kilobytes.
+const MAX_ARTIFACT_LEN: usize = 1024 * 1024;
+
+/// A verifying key fetched from a release.
+pub struct FetchedKey {
+    pub url: String,
+    /// SHA-256 digest of the artifact, as checked.
+    pub sha256: [u8; 32],
+    pub verifying_key: VerifyingKey<Bn254>,
+}
+
+/// Fetches the key of risc0 release `version` from `url_template`, checking
+/// it against `expected`.
+pub fn fetch(version: &str, url_template: &str, expected: [u8; 32]) -> Result<FetchedKey> {
+    ensure!(
+        !version.is_empty()
+            && version
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+')),
+        "{version:?} is not a risc0 release version"
+    );
+    let url = url_template.replace("{version}", version.trim_start_matches('v'));
+    let client = Client::builder().timeout(TIMEOUT).build()?;
+    let artifact = get(&client, &url)?;
+    let actual: [u8; 32] = Sha256::digest(&artifact).into();
+    ensure!(
+        actual == expected,
+        "{url} has SHA-256 {}, not the expected {}",
+        hex::encode(actual),
+        hex::encode(expected)
+    );
+    let verifying_key =
+        from_snarkjs(&artifact).with_context(|| format!("invalid verifying key at {url}"))?;
+    Ok(FetchedKey {
+        url,
+        sha256: actual,
+        verifying_key,
+    })
+}
+
+fn get(client: &Client, url: &str) -> Result<Vec<u8>> {
+    let response = client
+        .get(url)
+        .send()
+        .with_context(|| format!("failed to fetch {url}"))?
+        .error_for_status()
+        .with_context(|| format!("failed to fetch {url}"))?;
+    let body = response.bytes()?;
+    ensure!(
+        body.len() <= MAX_ARTIFACT_LEN,
+        "{url} is larger than {MAX_ARTIFACT_LEN} bytes"
+    );
+    Ok(body.to_vec())
+}
+
+/// A Groth16 verifying key in snarkjs' `verification_key.json` form.
+#[derive(Deserialize)]
+struct SnarkjsKey {
+    protocol: String,
+    curve: String,
+    #[serde(rename = "nPublic")]
+    n_public: usize,
+    vk_alpha_1: Vec<String>,
+    vk_beta_2: Vec<Vec<String>>,
+    vk_gamma_2: Vec<Vec<String>>,
+    vk_delta_2: Vec<Vec<String>>,
+    #[serde(rename = "IC")]
+    ic: Vec<Vec<String>>,
+}
+
+/// Decodes a key in snarkjs' JSON form, whose points are decimal
+/// coordinates, projective with a unit `z` or affine.
+pub fn from_snarkjs(json: &[u8]) -> Result<VerifyingKey<Bn254>> {
+    let key: SnarkjsKey = serde_json::from_slice(json)?;
+    ensure!(
+        key.protocol == "groth16",
+        "protocol is {:?}, not groth16",
+        key.protocol
+    );
+    ensure!(key.curve == "bn128", "curve is {:?}, not bn128", key.curve);
+    ensure!(
+        key.n_public == 1 && key.ic.len() == 2,
+        "key is for a circuit with {} public inputs, not one",
+        key.n_public
+    );
+    Ok(VerifyingKey {
+        alpha_g1: g1(&key.vk_alpha_1).context("vk_alpha_1")?,
+        beta_g2: g2(&key.vk_beta_2).context("vk_beta_2")?,
+        gamma_g2: g2(&key.vk_gamma_2).context("vk_gamma_2")?,
+        delta_g2: g2(&key.vk_delta_2).context("vk_delta_2")?,
+        gamma_abc_g1: key
+            .ic
+            .iter()
+            .enumerate()
+            .map(|(i, point)| g1(point).with_context(|| format!("IC[{i}]")))
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn fq(decimal: &str) -> Result<Fq> {
+    ensure!(
+        !decimal.is_empty() && decimal.bytes().all(|b| b.is_ascii_digit()),
+        "{decimal:?} is not a decimal field element"
+    );
+    Fq::from_str(decimal).map_err(|()| anyhow!("{decimal} is not below the modulus"))
+}
+
+fn g1(coordinates: &[String]) -> Result<G1Affine> {
+    let (x, y) = match coordinates {
+        [x, y] => (x, y),
+        [x, y, z] if z == "1" => (x, y),
+        _ => bail!("not an affine G1 point"),
+    };
+    let point = G1Affine::new_unchecked(fq(x)?, fq(y)?);
+    ensure!(
+        point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve(),
+        "not a point of G1"
+    );
+    Ok(point)
+}
+
+fn g2(coordinates: &[Vec<String>]) -> Result<G2Affine> {
+    let fq2 = |c: &[String]| -> Result<Fq2> {
+        match c {
+            [c0, c1] => Ok(Fq2::new(fq(c0)?, fq(c1)?)),
+            _ => bail!("not a coordinate in Fq2"),
+        }
+    };
+    let (x, y) = match coordinates {
+        [x, y] => (x, y),
+        [x, y, z] if z.len() == 2 && z[0] == "1" && z[1] == "0" => (x, y),
+        _ => bail!("not an affine G2 point"),
+    };
+    let point = G2Affine::new_unchecked(fq2(x)?, fq2(y)?);
+    ensure!(
+        point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve(),
+        "not a point of G2"
+    );
+    Ok(point)
+}
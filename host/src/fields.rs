@@ -0,0 +1,158 @@
+//! Host side of field commitments: building the commitment the guest proves
+//! for a set of named fields, and disclosing some of the fields against it
+//! later, for staged disclosure in dispute games. The tree is defined in
+//! [`common::fields`].
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use common::{
+    fields::{self, MAX_FIELDS, MAX_NAME_LEN},
+    merkle::Hash,
+    smt::{Defaults, SparseProof},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::smt::SparseTree;
+
+/// Field values by name, ordered the way the guest requires.
+pub type FieldMap = BTreeMap<String, Vec<u8>>;
+
+/// Checks that the guest will accept `map`.
+pub fn validate(map: &FieldMap) -> Result<()> {
+    ensure!(
+        map.len() <= MAX_FIELDS,
+        "{} fields exceed the maximum of {MAX_FIELDS}",
+        map.len()
+    );
+    if let Some(name) = map.keys().find(|name| name.len() > MAX_NAME_LEN) {
+        bail!(
+            "field name of {} bytes exceeds the maximum of {MAX_NAME_LEN}",
+            name.len()
+        );
+    }
+    Ok(())
+}
+
+/// The sparse tree of the fields of `map`.
+pub fn tree(map: &FieldMap) -> SparseTree {
+    let mut tree = SparseTree::new();
+    for (name, value) in map {
+        tree.insert(fields::field_key(name.as_bytes()), value);
+    }
+    tree
+}
+
+/// The root the guest commits for `map`.
+pub fn commitment(map: &FieldMap) -> Hash {
+    tree(map).root()
+}
+
+/// Appends `map` to a guest input in the layout read by the guest, with
+/// values split into frames of at most `frame_len` bytes.
+pub fn encode_input(encoded: &mut Vec<u8>, map: &FieldMap, frame_len: usize) {
+    encoded.extend_from_slice(&(map.len() as u32).to_le_bytes());
+    for (name, value) in map {
+        encoded.extend_from_slice(&common::kv::key_header(name.as_bytes()));
+        encoded.extend_from_slice(name.as_bytes());
+        encoded.extend_from_slice(&common::kv::value_header(value.len() as u64));
+        common::encode_frames(encoded, value, frame_len);
+    }
+}
+
+/// Loads fields from a JSON object of names and hex-encoded values.
+pub fn load(path: &Path) -> Result<FieldMap> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("failed to read fields {}", path.display()))?;
+    let encoded: BTreeMap<String, String> = serde_json::from_slice(&contents)
+        .with_context(|| format!("invalid fields {}", path.display()))?;
+    let map = encoded
+        .into_iter()
+        .map(|(name, value)| Ok((name, hex::decode(value)?)))
+        .collect::<Result<FieldMap>>()
+        .with_context(|| format!("invalid hex in fields {}", path.display()))?;
+    validate(&map)?;
+    Ok(map)
+}
+
+/// Some fields of a committed set, each with the proof of its place in the
+/// tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Disclosure {
+    /// Hex-encoded root of the tree the fields are disclosed against.
+    pub root: String,
+    pub fields: Vec<DisclosedField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisclosedField {
+    pub name: String,
+    /// Hex-encoded value.
+    pub value: String,
+    /// Hex-encoded sparse proof of the field's key, also accepted as a
+    /// `sparse_merkle` guest input to prove the disclosure.
+    pub proof: String,
+}
+
+impl Disclosure {
+    /// Discloses the fields `names` of `map`.
+    pub fn new(map: &FieldMap, names: &[String]) -> Result<Self> {
+        let tree = tree(map);
+        let fields = names
+            .iter()
+            .map(|name| {
+                let value = map
+                    .get(name)
+                    .with_context(|| format!("no field {name:?} to disclose"))?;
+                let proof = tree.proof(fields::field_key(name.as_bytes()));
+                Ok(DisclosedField {
+                    name: name.clone(),
+                    value: hex::encode(value),
+                    proof: hex::encode(proof.encode()),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            root: hex::encode(tree.root()),
+            fields,
+        })
+    }
+
+    /// Checks that every disclosed field is in the tree with root `root`, and
+    /// returns the fields.
+    pub fn check(&self, root: &Hash) -> Result<FieldMap> {
+        ensure!(
+            hex::decode(&self.root)? == root,
+            "disclosure is against root {}, not the committed root {}",
+            self.root,
+            hex::encode(root)
+        );
+        let defaults = Defaults::new();
+        let mut disclosed = FieldMap::new();
+        for field in &self.fields {
+            let value = hex::decode(&field.value)
+                .with_context(|| format!("value of field {:?} is not hex", field.name))?;
+            let proof = hex::decode(&field.proof)
+                .ok()
+                .and_then(|proof| SparseProof::decode(&proof))
+                .with_context(|| format!("malformed proof for field {:?}", field.name))?;
+            ensure!(
+                proof.key == fields::field_key(field.name.as_bytes()),
+                "proof for field {:?} is for another field",
+                field.name
+            );
+            ensure!(
+                proof.value == Some(blake3::hash(&value).into()),
+                "value of field {:?} does not match its proof",
+                field.name
+            );
+            ensure!(
+                proof.root(&defaults) == *root,
+                "field {:?} is not in the committed fields",
+                field.name
+            );
+            disclosed.insert(field.name.clone(), value);
+        }
+        Ok(disclosed)
+    }
+}
@@ -0,0 +1,175 @@
+//! Challenger-side fraud detection: re-deriving the public input from the
+//! public data of a claim and comparing every step with what the operator
+//! committed on chain, to find the commitment a disprove leaf can refute.
+//!
+//! The public input is derived as `prefix`, then `blake3(prefix || journal)`,
+//! then its first 31 bytes read as a scalar. Besides the public input its
+//! assert carries, the operator may commit the prefix and the journal hash
+//! as intermediate values. Each step is checked against the operator's own
+//! value for the step before it, as its disprove leaf checks it, so the
+//! finding names the first commitment that does not follow, not just any
+//! that differs from the honest derivation. A public input that follows but
+//! that the seal does not verify against points at the proof chunk holding
+//! the bad part of the seal.
+
+use anyhow::Result;
+use risc0_zkvm::sha::Digest;
+use serde::Serialize;
+use verifier_core::SealError;
+
+use crate::{
+    assert_tx::AssertWitness,
+    chunks::MAX_SCRIPT_ELEMENT_LEN,
+    disprove::Fraud,
+    verifier::{self, ScalarPolicy, PUBLIC_INPUT_LEN},
+};
+
+/// What the operator committed on chain to about one claim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commitments {
+    /// The seal and public input of its assert transaction.
+    pub assert: AssertWitness,
+    /// The succinct output prefix, if committed.
+    pub prefix: Option<[u8; 32]>,
+    /// `blake3(prefix || journal)`, if committed.
+    pub journal_hash: Option<[u8; 32]>,
+}
+
+impl From<AssertWitness> for Commitments {
+    fn from(assert: AssertWitness) -> Self {
+        Self {
+            assert,
+            prefix: None,
+            journal_hash: None,
+        }
+    }
+}
+
+impl Commitments {
+    /// The value committed for the step `fraud` refutes, as pushed to its
+    /// disprove leaf, or `None` if the operator did not commit it.
+    pub fn value(&self, fraud: Fraud) -> Option<Vec<u8>> {
+        match fraud {
+            Fraud::Prefix => self.prefix.map(Vec::from),
+            Fraud::JournalHash => self.journal_hash.map(Vec::from),
+            Fraud::PublicInput => Some(
+                crate::bitvm::PublicInputCommitment::from(self.assert.public_input)
+                    .0
+                    .to_vec(),
+            ),
+            Fraud::ProofChunk(index) => self.assert.elements().ok()?.into_iter().nth(index),
+        }
+    }
+}
+
+/// An inconsistent commitment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub fraud: Fraud,
+    /// What the operator committed.
+    #[serde(with = "hex::serde")]
+    pub committed: Vec<u8>,
+    /// What the step derives instead, if it derives a value.
+    #[serde(serialize_with = "serialize_optional_hex")]
+    pub expected: Option<Vec<u8>>,
+    pub reason: String,
+}
+
+/// Re-derives the public input of the claim that `journal` was output by
+/// the guest `image_id` and checks the operator's `commitments` against it,
+/// returning the first that is inconsistent, or `None` if the assert is
+/// honest.
+pub fn detect(
+    commitments: &Commitments,
+    image_id: Digest,
+    journal: &[u8],
+) -> Result<Option<Finding>> {
+    let prefix = verifier::calculate_succinct_output_prefix(image_id);
+    if let Some(committed) = commitments.prefix {
+        if committed != prefix {
+            return Ok(Some(Finding {
+                fraud: Fraud::Prefix,
+                committed: committed.to_vec(),
+                expected: Some(prefix.to_vec()),
+                reason: "committed prefix is not the one the image ID and control root derive"
+                    .to_string(),
+            }));
+        }
+    }
+
+    let journal_hash: [u8; 32] = blake3::Hasher::new()
+        .update(&prefix)
+        .update(journal)
+        .finalize()
+        .into();
+    if let Some(committed) = commitments.journal_hash {
+        if committed != journal_hash {
+            return Ok(Some(Finding {
+                fraud: Fraud::JournalHash,
+                committed: committed.to_vec(),
+                expected: Some(journal_hash.to_vec()),
+                reason: "committed journal hash is not blake3(prefix || journal)".to_string(),
+            }));
+        }
+    }
+
+    let public_input = &commitments.journal_hash.unwrap_or(journal_hash)[..PUBLIC_INPUT_LEN];
+    if commitments.assert.public_input != public_input {
+        return Ok(Some(Finding {
+            fraud: Fraud::PublicInput,
+            committed: commitments.assert.public_input.to_vec(),
+            expected: Some(public_input.to_vec()),
+            reason: "asserted public input is not the truncated journal hash".to_string(),
+        }));
+    }
+
+    let seal = &commitments.assert.seal;
+    let (offset, reason) = match verifier_core::from_seal(seal) {
+        Err(SealError::NonCanonical { offset }) => (
+            offset,
+            format!("seal field element at byte {offset} is not canonical"),
+        ),
+        Err(SealError::InvalidPoint(point)) => (
+            point_offset(point),
+            format!("seal point `{point}` is not a valid point"),
+        ),
+        Err(err) => (0, err.to_string()),
+        Ok(_) => {
+            if verifier::verify_seal(seal, public_input, ScalarPolicy::Reject)? {
+                return Ok(None);
+            }
+            (
+                0,
+                "seal does not verify against the public input".to_string(),
+            )
+        }
+    };
+    let index = offset / MAX_SCRIPT_ELEMENT_LEN;
+    Ok(Some(Finding {
+        fraud: Fraud::ProofChunk(index),
+        committed: commitments
+            .value(Fraud::ProofChunk(index))
+            .unwrap_or_default(),
+        expected: None,
+        reason,
+    }))
+}
+
+/// Offset in the seal of the named point.
+fn point_offset(point: &str) -> usize {
+    match point {
+        "b" => 64,
+        "c" => 192,
+        _ => 0,
+    }
+}
+
+fn serialize_optional_hex<S: serde::Serializer>(
+    value: &Option<Vec<u8>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.serialize_some(&hex::encode(value)),
+        None => serializer.serialize_none(),
+    }
+}
@@ -0,0 +1,266 @@
+//! Per-section checksums of an encoded proof bundle, so that a corrupt bundle
+//! is reported by the section that is corrupt rather than by a bincode error
+//! somewhere past it.
+//!
+//! The bincode encoding of a bundle is the concatenation of the encodings of
+//! its fields, and that of its receipt of the receipt's, so it can be cut
+//! into sections without a format of its own: the metadata, the seal, the
+//! claim, the verifying key reference, the journal and so on. The encoding is
+//! followed by a table of the sections, each with its byte range and BLAKE3
+//! digest:
+//!
+//! ```text
+//! <bundle> <table> <BLAKE3 of table: 32> <table length: u64 LE> "R0BVMCK1"
+//! ```
+//!
+//! Readers of the bundle ignore the trailing table, so bundles with one still
+//! decode where checksums are not known, and bundles written before there
+//! were checksums are checked by decoding them alone.
+
+use std::{fmt, ops::Range};
+
+use anyhow::{bail, ensure, Context, Result};
+use risc0_zkvm::InnerReceipt;
+use serde::{Deserialize, Serialize};
+
+use crate::bundle::ProofBundle;
+
+/// Marks the end of a bundle followed by a section table.
+const MAGIC: &[u8; 8] = b"R0BVMCK1";
+const TRAILER_LEN: usize = 32 + 8 + MAGIC.len();
+
+/// A named byte range of an encoded bundle and its digest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Section {
+    pub name: String,
+    pub offset: u64,
+    pub len: u64,
+    pub blake3: [u8; 32],
+}
+
+impl Section {
+    fn range(&self) -> Range<usize> {
+        self.offset as usize..self.offset.saturating_add(self.len) as usize
+    }
+}
+
+/// What checking a section found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Corrupt,
+    /// The section extends past the end of the bundle.
+    Truncated,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ok => "ok",
+            Self::Corrupt => "corrupt",
+            Self::Truncated => "truncated",
+        })
+    }
+}
+
+/// The outcome of checking an encoded bundle.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// Each section with what checking it found, or `None` if the bundle
+    /// carries no section table.
+    pub sections: Option<Vec<(Section, Status)>>,
+    /// Why the bundle does not decode, if it does not.
+    pub decode_error: Option<String>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.decode_error.is_none()
+            && self
+                .sections
+                .iter()
+                .flatten()
+                .all(|(_, status)| *status == Status::Ok)
+    }
+
+    /// The sections that did not check out.
+    pub fn corrupt(&self) -> impl Iterator<Item = &(Section, Status)> {
+        self.sections
+            .iter()
+            .flatten()
+            .filter(|(_, status)| *status != Status::Ok)
+    }
+}
+
+/// The encoding of `bundle` followed by its section table.
+pub fn encode(bundle: &ProofBundle) -> Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    let mut sections = Vec::new();
+    for (name, part) in parts(bundle)? {
+        sections.push(Section {
+            name: name.to_string(),
+            offset: encoded.len() as u64,
+            len: part.len() as u64,
+            blake3: *blake3::hash(&part).as_bytes(),
+        });
+        encoded.extend_from_slice(&part);
+    }
+    let table = bincode::serialize(&sections)?;
+    encoded.extend_from_slice(&table);
+    encoded.extend_from_slice(blake3::hash(&table).as_bytes());
+    encoded.extend_from_slice(&(table.len() as u64).to_le_bytes());
+    encoded.extend_from_slice(MAGIC);
+    Ok(encoded)
+}
+
+/// The section table at the end of `encoded` and the length of the bundle
+/// before it, or `None` if there is none.
+pub fn table(encoded: &[u8]) -> Result<Option<(Vec<Section>, usize)>> {
+    let Some(trailer_start) = encoded.len().checked_sub(TRAILER_LEN) else {
+        return Ok(None);
+    };
+    let trailer = &encoded[trailer_start..];
+    if &trailer[40..] != MAGIC {
+        return Ok(None);
+    }
+    let table_len = u64::from_le_bytes(trailer[32..40].try_into().unwrap());
+    let table_start = usize::try_from(table_len)
+        .ok()
+        .and_then(|len| trailer_start.checked_sub(len))
+        .context("section table is truncated")?;
+    let table = &encoded[table_start..trailer_start];
+    ensure!(
+        blake3::hash(table).as_bytes() == &trailer[..32],
+        "section table is corrupt"
+    );
+    let sections = bincode::deserialize(table).context("section table is corrupt")?;
+    Ok(Some((sections, table_start)))
+}
+
+/// Checks every section of `encoded` against its digest, then decodes it.
+pub fn check(encoded: &[u8]) -> Result<Report> {
+    let Some((sections, bundle_len)) = table(encoded)? else {
+        return Ok(Report {
+            sections: None,
+            decode_error: ProofBundle::decode(encoded)
+                .err()
+                .map(|err| format!("{err:#}")),
+        });
+    };
+    let bundle = &encoded[..bundle_len];
+    let sections = sections
+        .into_iter()
+        .map(|section| {
+            let status = status(bundle, &section);
+            (section, status)
+        })
+        .collect();
+    // Decoded without the table, for the error bincode gives.
+    let decode_error = ProofBundle::decode(bundle)
+        .err()
+        .map(|err| format!("{err:#}"));
+    Ok(Report {
+        sections: Some(sections),
+        decode_error,
+    })
+}
+
+/// Fails naming the first corrupt section of `encoded`, if it carries a
+/// section table.
+pub fn ensure_intact(encoded: &[u8]) -> Result<()> {
+    let Some((sections, bundle_len)) = table(encoded)? else {
+        return Ok(());
+    };
+    for section in sections {
+        match status(&encoded[..bundle_len], &section) {
+            Status::Ok => {}
+            status => bail!(
+                "bundle section {} (bytes {}..{}) is {status}",
+                section.name,
+                section.offset,
+                section.offset + section.len
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn status(bundle: &[u8], section: &Section) -> Status {
+    match bundle.get(section.range()) {
+        None => Status::Truncated,
+        Some(part) if blake3::hash(part).as_bytes() == &section.blake3 => Status::Ok,
+        Some(_) => Status::Corrupt,
+    }
+}
+
+/// The encodings of the sections of `bundle`, in order, which concatenated
+/// are its bincode encoding.
+fn parts(bundle: &ProofBundle) -> Result<Vec<(&'static str, Vec<u8>)>> {
+    let whole = bincode::serialize(bundle)?;
+    let receipt = &bundle.receipt;
+    let mut parts = vec![("metadata", bincode::serialize(&bundle.metadata)?)];
+    parts.extend(inner_parts(&receipt.inner)?);
+    parts.extend([
+        ("journal", bincode::serialize(&receipt.journal)?),
+        ("receipt_metadata", bincode::serialize(&receipt.metadata)?),
+        ("run", bincode::serialize(&bundle.run)?),
+        ("escalation", bincode::serialize(&bundle.escalation)?),
+        ("timestamp", bincode::serialize(&bundle.timestamp)?),
+        ("da", bincode::serialize(&bundle.da)?),
+        ("stark", bincode::serialize(&bundle.stark)?),
+    ]);
+    if concat(&parts) != whole {
+        // The receipt is not laid out as expected; check it as a whole.
+        parts.retain(|(name, _)| !RECEIPT_PARTS.contains(name));
+        parts.insert(1, ("receipt", bincode::serialize(receipt)?));
+    }
+    ensure!(
+        concat(&parts) == whole,
+        "bundle encoding is not the concatenation of its fields"
+    );
+    Ok(parts)
+}
+
+fn concat(parts: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    parts
+        .iter()
+        .flat_map(|(_, part)| part.iter().copied())
+        .collect()
+}
+
+/// The sections the receipt is cut into.
+const RECEIPT_PARTS: [&str; 6] = [
+    "seal",
+    "claim",
+    "vk_ref",
+    "inner",
+    "journal",
+    "receipt_metadata",
+];
+
+/// The sections of a receipt's inner receipt: for a Groth16 receipt, its
+/// variant and seal, its claim and the digest of the verifier parameters it
+/// was proven against.
+fn inner_parts(inner: &InnerReceipt) -> Result<Vec<(&'static str, Vec<u8>)>> {
+    let encoded = bincode::serialize(inner)?;
+    let InnerReceipt::Groth16(groth16) = inner else {
+        return Ok(vec![("inner", encoded)]);
+    };
+    let seal = bincode::serialize(&groth16.seal)?;
+    let claim = bincode::serialize(&groth16.claim)?;
+    let vk_ref = bincode::serialize(&groth16.verifier_parameters)?;
+    // A bincode enum variant is its index as a u32.
+    let variant_len = encoded
+        .len()
+        .saturating_sub(seal.len() + claim.len() + vk_ref.len());
+    if variant_len != 4
+        || encoded[4..] != [seal.as_slice(), claim.as_slice(), vk_ref.as_slice()].concat()
+    {
+        return Ok(vec![("inner", encoded)]);
+    }
+    Ok(vec![
+        ("seal", [&encoded[..4], seal.as_slice()].concat()),
+        ("claim", claim),
+        ("vk_ref", vk_ref),
+    ])
+}
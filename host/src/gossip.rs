@@ -0,0 +1,390 @@
+//! Announcements of finished proofs over a gossip transport, so challengers
+//! can discover operators' proofs without polling a central API.
+//!
+//! An [`Announcement`] references a bundle by its SHA-256 digest and a URL it
+//! can be downloaded from, and by its IPFS CID if it was published there,
+//! which challengers check the download against. On
+//! nostr it is published to every configured relay as a NIP-94 file metadata
+//! event (kind 1063) tagged `r0-bitvm-blake3`, signed with the operator's
+//! BIP340 key, so that challengers can subscribe by tag and author. On NATS it
+//! is published as JSON to a subject.
+//!
+//! Only the subset of the WebSocket protocol needed to publish an event to a
+//! relay is implemented, over TCP or TLS.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use k256::schnorr::SigningKey;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::{bundle::ProofBundle, journal::Journal, manifest, nats, policy};
+
+/// Kind of NIP-94 file metadata events.
+const FILE_METADATA_KIND: u32 = 1063;
+/// Topic tag challengers subscribe to.
+const TOPIC: &str = "r0-bitvm-blake3";
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Largest relay message read, so that a misbehaving relay cannot make the
+/// publisher allocate without bound.
+const MAX_FRAME_LEN: u64 = 1024 * 1024;
+
+/// Where announcements are published, loaded from the `[gossip]` table of the
+/// server config or from a TOML file of its own.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct GossipConfig {
+    /// Where announced bundles can be downloaded, with `{hash}` replaced by
+    /// the hex SHA-256 digest of the bundle and `{id}` by its job ID, or by
+    /// its file name when announced from the command line.
+    pub retrieval_url: String,
+    #[serde(flatten)]
+    pub transport: Transport,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "transport", rename_all = "snake_case")]
+pub enum Transport {
+    /// Publish to every relay, as `ws://` or `wss://` URLs.
+    Nostr {
+        relays: Vec<String>,
+        /// File holding the hex-encoded BIP340 key events are signed with.
+        secret_key_file: PathBuf,
+    },
+    /// Publish to `subject` on the NATS server at `url`.
+    Nats { url: String, subject: String },
+}
+
+impl GossipConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read gossip config {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("failed to parse gossip config {}", path.display()))
+    }
+
+    /// The announcement of the encoded bundle `encoded`, named `id` in the
+    /// retrieval URL.
+    pub fn announcement(&self, id: &str, encoded: &[u8]) -> Result<Announcement> {
+        let bundle = ProofBundle::decode(encoded)?;
+        let sha256 = hex::encode(Sha256::digest(encoded));
+        Ok(Announcement {
+            url: self
+                .retrieval_url
+                .replace("{hash}", &sha256)
+                .replace("{id}", id),
+            sha256,
+            size: encoded.len(),
+            image_id: bundle.metadata.image_id,
+            journal_digest: Journal::of(&bundle.receipt).digest().to_string(),
+            cid: None,
+        })
+    }
+}
+
+/// A reference to a proof bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Announcement {
+    pub url: String,
+    /// Hex SHA-256 digest of the encoded bundle.
+    pub sha256: String,
+    pub size: usize,
+    pub image_id: String,
+    /// Hex SHA-256 digest of the journal.
+    pub journal_digest: String,
+    /// CID of the bundle on IPFS, if it was published there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+}
+
+/// Publishes `announcement` on `transport`. On nostr, succeeds if at least
+/// one relay accepted the event; rejections by the others are logged.
+pub fn announce(transport: &Transport, announcement: &Announcement) -> Result<()> {
+    match transport {
+        Transport::Nostr {
+            relays,
+            secret_key_file,
+        } => {
+            ensure!(!relays.is_empty(), "no nostr relays are configured");
+            let event = nostr_event(
+                announcement,
+                &manifest::load_signing_key(secret_key_file)?,
+                policy::unix_now(),
+            )?;
+            let mut accepted = 0;
+            for relay in relays {
+                match publish_event(relay, &event) {
+                    Ok(()) => accepted += 1,
+                    Err(err) => tracing::warn!("relay {relay}: {err:#}"),
+                }
+            }
+            ensure!(accepted > 0, "no relay accepted the announcement");
+            tracing::info!(
+                "announced {} on {accepted} of {} relays",
+                announcement.sha256,
+                relays.len()
+            );
+            Ok(())
+        }
+        Transport::Nats { url, subject } => {
+            nats::Connection::connect(url)?.publish(subject, &serde_json::to_vec(announcement)?)
+        }
+    }
+}
+
+/// A signed NIP-01 event carrying `announcement` as NIP-94 file metadata.
+fn nostr_event(
+    announcement: &Announcement,
+    key: &SigningKey,
+    created_at: u64,
+) -> Result<serde_json::Value> {
+    let pubkey = hex::encode(key.verifying_key().to_bytes());
+    let mut tags = json!([
+        ["url", announcement.url],
+        ["m", "application/octet-stream"],
+        ["x", announcement.sha256],
+        ["size", announcement.size.to_string()],
+        ["t", TOPIC],
+        ["image_id", announcement.image_id],
+        ["journal_digest", announcement.journal_digest],
+    ]);
+    if let (Some(cid), Some(tags)) = (&announcement.cid, tags.as_array_mut()) {
+        tags.push(json!(["cid", cid]));
+    }
+    let content = format!("r0-bitvm-blake3 proof bundle {}", announcement.sha256);
+    let serialized = serde_json::to_string(&json!([
+        0,
+        pubkey,
+        created_at,
+        FILE_METADATA_KIND,
+        tags,
+        content
+    ]))?;
+    let id: [u8; 32] = Sha256::digest(serialized.as_bytes()).into();
+    let mut aux_rand = [0; 32];
+    OsRng.fill_bytes(&mut aux_rand);
+    let sig = key
+        .sign_raw(&id, &aux_rand)
+        .map_err(|err| anyhow!("failed to sign announcement: {err}"))?;
+    Ok(json!({
+        "id": hex::encode(id),
+        "pubkey": pubkey,
+        "created_at": created_at,
+        "kind": FILE_METADATA_KIND,
+        "tags": tags,
+        "content": content,
+        "sig": hex::encode(sig.to_bytes()),
+    }))
+}
+
+/// Sends `event` to `relay` and waits for it to be accepted.
+fn publish_event(relay: &str, event: &serde_json::Value) -> Result<()> {
+    let mut socket = WebSocket::connect(relay)?;
+    socket.send_text(&serde_json::to_string(&json!(["EVENT", event]))?)?;
+    loop {
+        let message: serde_json::Value =
+            serde_json::from_str(&socket.read_text()?).context("malformed relay message")?;
+        match message.get(0).and_then(|kind| kind.as_str()) {
+            Some("OK") if message.get(1) == event.get("id") => {
+                return match message.get(2).and_then(|accepted| accepted.as_bool()) {
+                    Some(true) => Ok(()),
+                    _ => bail!(
+                        "event rejected: {}",
+                        message
+                            .get(3)
+                            .and_then(|reason| reason.as_str())
+                            .unwrap_or("")
+                    ),
+                };
+            }
+            Some("NOTICE") => tracing::info!("relay {relay} notice: {}", message[1]),
+            _ => {}
+        }
+    }
+}
+
+trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+/// A client connection to a WebSocket server.
+struct WebSocket {
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+/// Appended to the client key to derive the key the server must accept.
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+impl WebSocket {
+    fn connect(url: &str) -> Result<Self> {
+        let (tls, location) = if let Some(location) = url.strip_prefix("wss://") {
+            (true, location)
+        } else if let Some(location) = url.strip_prefix("ws://") {
+            (false, location)
+        } else {
+            bail!("relay URL {url:?} does not start with ws:// or wss://");
+        };
+        let (authority, path) = match location.find('/') {
+            Some(slash) => location.split_at(slash),
+            None => (location, "/"),
+        };
+        let (host, address) = match authority.rsplit_once(':') {
+            Some((host, _)) => (host, authority.to_string()),
+            None => (
+                authority,
+                format!("{authority}:{}", if tls { 443 } else { 80 }),
+            ),
+        };
+
+        let tcp = TcpStream::connect(&address)
+            .with_context(|| format!("failed to connect to relay {address}"))?;
+        tcp.set_read_timeout(Some(TIMEOUT))?;
+        tcp.set_write_timeout(Some(TIMEOUT))?;
+        let stream: Box<dyn Stream> = if tls {
+            let roots = rustls::RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.into(),
+            };
+            let config = rustls::ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            let name = rustls::pki_types::ServerName::try_from(host.to_string())
+                .with_context(|| format!("invalid relay host {host:?}"))?;
+            let connection = rustls::ClientConnection::new(Arc::new(config), name)?;
+            Box::new(rustls::StreamOwned::new(connection, tcp))
+        } else {
+            Box::new(tcp)
+        };
+        let mut stream = BufReader::new(stream);
+
+        let mut nonce = [0; 16];
+        OsRng.fill_bytes(&mut nonce);
+        let key = BASE64.encode(nonce);
+        write!(
+            stream.get_mut(),
+            "GET {path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Key: {key}\r\n\
+             Sec-WebSocket-Version: 13\r\n\r\n"
+        )?;
+        stream.get_mut().flush()?;
+
+        let status = read_line(&mut stream)?;
+        ensure!(
+            status.split(' ').nth(1) == Some("101"),
+            "relay refused the WebSocket upgrade: {status}"
+        );
+        let expected = BASE64.encode(<sha1::Sha1 as sha1::Digest>::digest(format!(
+            "{key}{ACCEPT_GUID}"
+        )));
+        let mut accepted = false;
+        loop {
+            let line = read_line(&mut stream)?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("sec-websocket-accept") {
+                    accepted = value.trim() == expected;
+                }
+            }
+        }
+        ensure!(accepted, "relay did not accept the WebSocket key");
+        Ok(Self { stream })
+    }
+
+    fn send_text(&mut self, text: &str) -> Result<()> {
+        self.send(OPCODE_TEXT, text.as_bytes())
+    }
+
+    /// Sends one masked frame, as clients must.
+    fn send(&mut self, opcode: u8, payload: &[u8]) -> Result<()> {
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len @ 126..=0xffff => {
+                frame.push(0x80 | 126);
+                frame.extend((len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend((len as u64).to_be_bytes());
+            }
+        }
+        let mut mask = [0; 4];
+        OsRng.fill_bytes(&mut mask);
+        frame.extend(mask);
+        frame.extend(
+            payload
+                .iter()
+                .zip(mask.iter().cycle())
+                .map(|(byte, mask)| byte ^ mask),
+        );
+        let stream = self.stream.get_mut();
+        stream.write_all(&frame)?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    /// Reads the next text message, answering pings on the way.
+    fn read_text(&mut self) -> Result<String> {
+        loop {
+            let mut header = [0; 2];
+            self.stream.read_exact(&mut header)?;
+            ensure!(
+                header[0] & 0x80 != 0,
+                "fragmented relay messages are not supported"
+            );
+            let opcode = header[0] & 0x0f;
+            let len = match header[1] & 0x7f {
+                126 => {
+                    let mut len = [0; 2];
+                    self.stream.read_exact(&mut len)?;
+                    u16::from_be_bytes(len) as u64
+                }
+                127 => {
+                    let mut len = [0; 8];
+                    self.stream.read_exact(&mut len)?;
+                    u64::from_be_bytes(len)
+                }
+                len => len as u64,
+            };
+            ensure!(
+                len <= MAX_FRAME_LEN,
+                "relay message of {len} bytes exceeds the {MAX_FRAME_LEN} byte limit"
+            );
+            let mut payload = vec![0; len as usize];
+            self.stream.read_exact(&mut payload)?;
+            match opcode {
+                OPCODE_TEXT => {
+                    return String::from_utf8(payload).context("relay sent invalid UTF-8")
+                }
+                OPCODE_PING => self.send(OPCODE_PONG, &payload)?,
+                OPCODE_CLOSE => bail!("relay closed the connection"),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = String::new();
+    ensure!(
+        reader.read_line(&mut line)? > 0,
+        "relay closed the connection"
+    );
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -0,0 +1,47 @@
+//! Reproducible guest builds with risc0's docker builder, so that independent
+//! parties can confirm they are proving and verifying the identical ELF.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use risc0_binfmt::ProgramBinary;
+use risc0_build::{BuildStatus, DockerOptionsBuilder, GuestOptionsBuilder, TARGET_DIR};
+use risc0_zkp::core::digest::Digest;
+use serde::Serialize;
+
+/// Package and binary name of the guest crate.
+const GUEST_NAME: &str = "method";
+
+/// The outcome of a reproducible build, as recorded by `build-guest --record`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GuestBuild {
+    pub elf_path: PathBuf,
+    #[serde(serialize_with = "hex::serialize")]
+    pub image_id: Digest,
+}
+
+/// Builds the guest below the repository `root` in risc0's guest builder
+/// container and computes the image ID of the resulting ELF.
+pub fn docker_build(root: &Path) -> Result<GuestBuild> {
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("invalid repository root {}", root.display()))?;
+    let manifest_path = root.join("methods/guest/Cargo.toml");
+    let docker = DockerOptionsBuilder::default().root_dir(&root).build()?;
+    let options = GuestOptionsBuilder::default().use_docker(docker).build()?;
+
+    match risc0_build::docker_build(&manifest_path, &options)? {
+        BuildStatus::Success => {}
+        BuildStatus::Skipped => bail!("guest build was skipped because RISC0_SKIP_BUILD is set"),
+    }
+
+    let elf_path = root
+        .join(TARGET_DIR)
+        .join(GUEST_NAME)
+        .join("riscv32im-risc0-zkvm-elf/docker")
+        .join(GUEST_NAME);
+    let elf = std::fs::read(&elf_path)
+        .with_context(|| format!("failed to read built ELF {}", elf_path.display()))?;
+    let image_id = ProgramBinary::new(&elf, &options.kernel()).compute_image_id()?;
+    Ok(GuestBuild { elf_path, image_id })
+}
@@ -0,0 +1,127 @@
+//! Signing PSBTs on a hardware wallet through HWI, the hardware wallet
+//! interface of the Bitcoin Core project, so that an operator approves every
+//! assert or disprove transaction on the device that holds its key.
+//!
+//! HWI is run as the `hwi` program, which must be installed separately. What
+//! the device returns is not trusted: the signatures are checked against the
+//! transaction when the package is finalized, see
+//! [`AssertPackage::finalize`](crate::assert_psbt::AssertPackage::finalize).
+
+use std::{path::PathBuf, process::Command};
+
+use anyhow::{bail, Context, Result};
+use bitcoin::{bip32::Fingerprint, Network, Psbt};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::assert_psbt::AssertPackage;
+
+/// Program run when none is given.
+pub const DEFAULT_PROGRAM: &str = "hwi";
+
+/// A hardware wallet HWI sees.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Device {
+    #[serde(rename = "type")]
+    pub device_type: String,
+    pub path: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+}
+
+/// HWI, talking to one device.
+#[derive(Debug, Clone)]
+pub struct Hwi {
+    program: PathBuf,
+    /// Master key fingerprint of the device to use, or the only one
+    /// connected if `None`.
+    fingerprint: Option<Fingerprint>,
+    network: Network,
+}
+
+impl Hwi {
+    pub fn new(
+        program: impl Into<PathBuf>,
+        fingerprint: Option<Fingerprint>,
+        network: Network,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            fingerprint,
+            network,
+        }
+    }
+
+    /// The devices connected, whether or not they are the one used.
+    pub fn enumerate(&self) -> Result<Vec<Device>> {
+        self.run(&["enumerate"])
+    }
+
+    /// Has the device sign `psbt`, after the operator approves it there, and
+    /// returns the PSBT with the device's signatures added.
+    pub fn sign(&self, psbt: &Psbt) -> Result<Psbt> {
+        #[derive(Deserialize)]
+        struct Signed {
+            psbt: String,
+            #[serde(default)]
+            signed: Option<bool>,
+        }
+        let signed: Signed = self.run(&["signtx", &psbt.to_string()])?;
+        if signed.signed == Some(false) {
+            bail!("device did not sign the PSBT; was it declined, or is the key not its own?");
+        }
+        signed
+            .psbt
+            .parse()
+            .context("device returned a malformed PSBT")
+    }
+
+    /// Has the device sign both transactions of `package`, one approval
+    /// each, and adds its signatures to the package.
+    pub fn sign_package(&self, package: &mut AssertPackage) -> Result<()> {
+        let commit = self.sign(&package.commit)?;
+        let reveal = self.sign(&package.reveal)?;
+        package.combine(commit)?;
+        package.combine(reveal)
+    }
+
+    fn run<T: DeserializeOwned>(&self, args: &[&str]) -> Result<T> {
+        let chain = match self.network {
+            Network::Bitcoin => "main",
+            Network::Testnet => "test",
+            Network::Signet => "signet",
+            Network::Regtest => "regtest",
+            network => bail!("HWI does not support {network}"),
+        };
+        let mut command = Command::new(&self.program);
+        command.args(["--chain", chain]);
+        if let Some(fingerprint) = self.fingerprint {
+            command.args(["--fingerprint", &fingerprint.to_string()]);
+        }
+        let output = command
+            .args(args)
+            .output()
+            .with_context(|| format!("failed to run {}", self.program.display()))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // HWI reports errors as a JSON object on stdout.
+        if let Ok(error) = serde_json::from_str::<HwiError>(&stdout) {
+            bail!("HWI failed with {}: {}", error.code, error.error);
+        }
+        if !output.status.success() {
+            bail!(
+                "{} exited with {}: {}",
+                self.program.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        serde_json::from_str(&stdout).context("malformed answer from HWI")
+    }
+}
+
+#[derive(Deserialize)]
+struct HwiError {
+    error: String,
+    code: i64,
+}
@@ -0,0 +1,1012 @@
+//! Inputs accepted by the guest, and their encoding as guest stdin.
+//!
+//! Inputs are also read from JSON documents holding the version of their
+//! schema next to the input, e.g.
+//! `{"version": 1, "input": {"single": {"data": "00ff", "padding": "none"}}}`,
+//! and validated before they are written to the guest, so that a malformed
+//! input is reported with the line or the field at fault instead of as a
+//! guest panic.
+
+use std::{fmt, io::Read};
+
+use anyhow::{bail, Context, Result};
+use common::{
+    bao::MAX_BLOCK_LOG,
+    journal::{self, Entry, GuestVersion, InputStats, Nonce, Output},
+    merkle::ExclusionProof,
+    padding::Padding,
+    sha256_chunks::ChunkHasher,
+    smt::{Defaults, SparseProof},
+    Mode, GUEST_VERSION_FLAG, INPUT_DIGEST_FLAG, INPUT_STATS_FLAG, MAX_FRAME_LEN, MIN_FRAME_LEN,
+    NONCE_FLAG,
+};
+use ruzstd::decoding::StreamingDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::Digest as _;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    bao::{self, Outboard, SliceProof},
+    fields::{self, FieldMap},
+    kv::{self, KvMap},
+};
+
+/// Version of the input document schema read by [`GuestInput::from_json`].
+pub const INPUT_VERSION: u32 = 1;
+
+/// A guest input as written to a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputDocument {
+    /// Version of the schema the document is written in, [`INPUT_VERSION`].
+    pub version: u32,
+    pub input: GuestInput,
+}
+
+/// Why an input document, or an input, was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputError {
+    /// The document is not JSON, or does not match the schema.
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    UnsupportedVersion(u32),
+    /// A field holds a value the guest would reject, named by its path in
+    /// the input, e.g. `outboard.block_log`.
+    Invalid {
+        field: &'static str,
+        message: String,
+    },
+}
+
+impl InputError {
+    fn syntax(err: serde_json::Error) -> Self {
+        let message = err.to_string();
+        let location = format!(" at line {} column {}", err.line(), err.column());
+        Self::Syntax {
+            line: err.line(),
+            column: err.column(),
+            message: message
+                .strip_suffix(&location)
+                .unwrap_or(&message)
+                .to_string(),
+        }
+    }
+
+    fn invalid(field: &'static str, err: impl fmt::Display) -> Self {
+        Self::Invalid {
+            field,
+            message: format!("{err:#}"),
+        }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "line {line}, column {column}: {message}"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "input version {version} is not supported; this build reads version \
+                 {INPUT_VERSION}"
+            ),
+            Self::Invalid { field, message } => write!(f, "`{field}`: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// The data to prove, together with the guest mode that processes it. Its
+/// `Debug` form shows only the mode and size, so that inputs which are secret
+/// never reach a log.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum GuestInput {
+    /// One byte string, padded and hashed into a single digest.
+    Single {
+        #[serde(with = "hex_bytes")]
+        data: Vec<u8>,
+        #[serde(default, with = "padding_str")]
+        padding: Padding,
+    },
+    /// Several byte strings, each hashed into its own digest within the same
+    /// proof, alongside the digest of their concatenation.
+    Multi(#[serde(with = "hex_list")] Vec<Vec<u8>>),
+    /// One zstd-compressed byte string, decompressed by the guest before
+    /// hashing.
+    Zstd(#[serde(with = "hex_bytes")] Vec<u8>),
+    /// A key-value map, committed to in its canonical encoding.
+    KeyValue(#[serde(with = "hex_map")] KvMap),
+    /// A proof that a key is absent from a sorted Merkle tree.
+    MerkleExclusion(#[serde(with = "exclusion_hex")] ExclusionProof),
+    /// A proof of a key's value, or absence, in a sparse Merkle tree.
+    SparseMerkle(#[serde(with = "sparse_hex")] SparseProof),
+    /// One byte string hashed into a BLAKE3 tree of `1024 << block_log` byte
+    /// blocks, committing to its outboard.
+    Outboard {
+        #[serde(with = "hex_bytes")]
+        data: Vec<u8>,
+        #[serde(default)]
+        block_log: u8,
+        /// Derive-key context string personalizing the hash.
+        #[serde(default)]
+        context: Option<String>,
+    },
+    /// A byte range of a larger input, proven to belong to the BLAKE3 tree of
+    /// the whole input. Only the blocks covering the range and the chaining
+    /// values of the rest of the tree are sent to the guest.
+    Slice {
+        #[serde(with = "hex_bytes")]
+        data: Vec<u8>,
+        #[serde(default)]
+        block_log: u8,
+        #[serde(default)]
+        context: Option<String>,
+        offset: u64,
+        length: u64,
+    },
+    /// Named fields, committed to as a sparse tree against which they can
+    /// later be disclosed one at a time; see [`fields`].
+    Fields(#[serde(with = "hex_fields")] FieldMap),
+    /// One byte string hashed in chunks on the zkVM's SHA-256 accelerator,
+    /// committing to the digest of [`common::sha256_chunks`] rather than its
+    /// BLAKE3 hash. Needs a guest built with the `sha256-chunks` feature.
+    Sha256Chunks(#[serde(with = "hex_bytes")] Vec<u8>),
+    /// Another input, with the BLAKE3 digest of its whole encoding as guest
+    /// stdin also committed to the journal, so that a proof can be bound to
+    /// an input that stays private; see [`GuestInput::input_digest`].
+    BindInput(Box<GuestInput>),
+    /// Another input, with the number of bytes and frames the guest read also
+    /// committed to the journal, so that a journal of a truncated input does
+    /// not match the input the host was given.
+    WithStats(Box<GuestInput>),
+    /// Another input, with the journal schema and modes of the guest also
+    /// committed to the journal, so that verifiers can reject journals of a
+    /// guest revision they would misread whatever image IDs they trust.
+    WithVersion(Box<GuestInput>),
+    /// Another input, preceded by a nonce that is also committed to the
+    /// journal, so that a proof made for one request, such as a bridge event,
+    /// cannot be passed off as the answer to another with the same data.
+    WithNonce {
+        #[serde(with = "hex_bytes")]
+        nonce: Nonce,
+        input: Box<GuestInput>,
+    },
+    /// Another input, sent in frames of at most `frame_len` bytes, a power of
+    /// two from [`MIN_FRAME_LEN`] to [`MAX_FRAME_LEN`], rather than
+    /// [`MAX_FRAME_LEN`]. The guest's read buffer is that size: small frames
+    /// keep guest memory low and large ones take fewer reads. The journal is
+    /// the same either way.
+    Framed {
+        frame_len: usize,
+        input: Box<GuestInput>,
+    },
+}
+
+impl fmt::Debug for GuestInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuestInput")
+            .field("mode", &self.mode())
+            .field("data_len", &self.data_len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Overwrites the byte strings of the input, for inputs that are secret,
+/// such as the preimage of a digest, once they are proven. Merkle proofs are
+/// left as they are: their nodes are public.
+impl Zeroize for GuestInput {
+    fn zeroize(&mut self) {
+        match self {
+            Self::Single { data, .. }
+            | Self::Zstd(data)
+            | Self::Sha256Chunks(data)
+            | Self::Outboard { data, .. }
+            | Self::Slice { data, .. } => data.zeroize(),
+            Self::Multi(items) => items.zeroize(),
+            Self::KeyValue(map) => {
+                // Keys cannot be written in place, so the entries are taken
+                // out of the map.
+                for (mut key, mut value) in std::mem::take(map) {
+                    key.zeroize();
+                    value.zeroize();
+                }
+            }
+            Self::Fields(map) => {
+                for (mut name, mut value) in std::mem::take(map) {
+                    name.zeroize();
+                    value.zeroize();
+                }
+            }
+            Self::MerkleExclusion(_) | Self::SparseMerkle(_) => {}
+            Self::BindInput(input)
+            | Self::WithStats(input)
+            | Self::WithVersion(input)
+            | Self::WithNonce { input, .. }
+            | Self::Framed { input, .. } => input.zeroize(),
+        }
+    }
+}
+
+impl GuestInput {
+    /// Parses an [`InputDocument`] and validates its input.
+    pub fn from_json(json: &str) -> Result<Self, InputError> {
+        // The version is read on its own first, so that a document written
+        // for another schema is reported as such rather than as malformed.
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } = serde_json::from_str(json).map_err(InputError::syntax)?;
+        if version != INPUT_VERSION {
+            return Err(InputError::UnsupportedVersion(version));
+        }
+        let document: InputDocument = serde_json::from_str(json).map_err(InputError::syntax)?;
+        document.input.validate()?;
+        Ok(document.input)
+    }
+
+    pub fn mode(&self) -> Mode {
+        match self {
+            Self::Single { .. } => Mode::Single,
+            Self::Multi(_) => Mode::Multi,
+            Self::Zstd(_) => Mode::Zstd,
+            Self::KeyValue(_) => Mode::KeyValue,
+            Self::MerkleExclusion(_) => Mode::MerkleExclusion,
+            Self::SparseMerkle(_) => Mode::SparseMerkle,
+            Self::Outboard { .. } => Mode::Outboard,
+            Self::Slice { .. } => Mode::Slice,
+            Self::Fields(_) => Mode::Fields,
+            Self::Sha256Chunks(_) => Mode::Sha256Chunks,
+            Self::BindInput(input)
+            | Self::WithStats(input)
+            | Self::WithVersion(input)
+            | Self::WithNonce { input, .. }
+            | Self::Framed { input, .. } => input.mode(),
+        }
+    }
+
+    /// The digest the journal commits to when this input is bound with
+    /// [`GuestInput::BindInput`]: that of the bound input's encoding, whose
+    /// mode word carries [`INPUT_DIGEST_FLAG`].
+    pub fn input_digest(&self) -> [u8; 32] {
+        let mut encoded = Zeroizing::new(self.encode());
+        encoded[0] |= INPUT_DIGEST_FLAG;
+        blake3::hash(&encoded).into()
+    }
+
+    /// Total number of data bytes across all byte strings, as sent to the
+    /// guest.
+    pub fn data_len(&self) -> usize {
+        match self {
+            Self::Single { data, .. }
+            | Self::Zstd(data)
+            | Self::Sha256Chunks(data)
+            | Self::Outboard { data, .. }
+            | Self::Slice { data, .. } => data.len(),
+            Self::Multi(items) => items.iter().map(Vec::len).sum(),
+            Self::KeyValue(map) => map.iter().map(|(key, value)| key.len() + value.len()).sum(),
+            Self::Fields(map) => map
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum(),
+            Self::MerkleExclusion(proof) => proof.encode().len(),
+            Self::SparseMerkle(proof) => proof.encode().len(),
+            Self::BindInput(input)
+            | Self::WithStats(input)
+            | Self::WithVersion(input)
+            | Self::WithNonce { input, .. }
+            | Self::Framed { input, .. } => input.data_len(),
+        }
+    }
+
+    /// Number of bytes the guest hashes: the data plus any padding applied
+    /// to it.
+    pub fn hashed_len(&self) -> u64 {
+        match self {
+            Self::Single { data, padding } => match padding {
+                Padding::None => data.len() as u64,
+                Padding::ZeroPad { log2 } => (data.len() as u64).max(1 << log2),
+                Padding::LengthSuffixed => data.len() as u64 + 8,
+            },
+            Self::BindInput(input)
+            | Self::WithStats(input)
+            | Self::WithVersion(input)
+            | Self::WithNonce { input, .. }
+            | Self::Framed { input, .. } => input.hashed_len(),
+            _ => self.data_len() as u64,
+        }
+    }
+
+    /// Encodes the input as the guest reads it from stdin.
+    pub fn encode(&self) -> Vec<u8> {
+        self.encode_frames_of(MAX_FRAME_LEN)
+    }
+
+    fn encode_frames_of(&self, frame_len: usize) -> Vec<u8> {
+        match self {
+            Self::BindInput(input) => {
+                let mut encoded = input.encode_frames_of(frame_len);
+                encoded[0] |= INPUT_DIGEST_FLAG;
+                return encoded;
+            }
+            Self::WithStats(input) => {
+                let mut encoded = input.encode_frames_of(frame_len);
+                encoded[0] |= INPUT_STATS_FLAG;
+                return encoded;
+            }
+            Self::WithVersion(input) => {
+                let mut encoded = input.encode_frames_of(frame_len);
+                encoded[0] |= GUEST_VERSION_FLAG;
+                return encoded;
+            }
+            Self::WithNonce { nonce, input } => {
+                // The guest reads the nonce right after the mode word.
+                let mut encoded = input.encode_frames_of(frame_len);
+                encoded[0] |= NONCE_FLAG;
+                encoded.splice(4..4, *nonce);
+                return encoded;
+            }
+            Self::Framed { frame_len, input } => {
+                let mut encoded = input.encode_frames_of(*frame_len);
+                encoded[1] = frame_len.trailing_zeros() as u8;
+                return encoded;
+            }
+            _ => {}
+        }
+        let mut encoded = (self.mode() as u32).to_le_bytes().to_vec();
+        match self {
+            Self::Single { data, padding } => {
+                let [kind, param] = padding.encode();
+                encoded.extend_from_slice(&[kind, param, 0, 0]);
+                common::encode_frames(&mut encoded, data, frame_len);
+            }
+            Self::Zstd(data) | Self::Sha256Chunks(data) => {
+                common::encode_frames(&mut encoded, data, frame_len)
+            }
+            Self::Outboard {
+                data,
+                block_log,
+                context,
+            } => {
+                encoded.extend_from_slice(&[*block_log, context.is_some().into(), 0, 0]);
+                if let Some(context) = context {
+                    encoded.extend_from_slice(&bao::context_key(context));
+                }
+                common::encode_frames(&mut encoded, data, frame_len);
+            }
+            Self::Slice {
+                data,
+                block_log,
+                context,
+                offset,
+                length,
+            } => {
+                let SliceProof { slice, nodes, data } =
+                    SliceProof::new(data, *block_log, context.as_deref(), *offset, *length)
+                        .expect("slice is checked by validate");
+                let data = Zeroizing::new(data);
+                encoded.extend_from_slice(&[slice.block_log, slice.context.is_some().into(), 0, 0]);
+                if let Some(context) = &slice.context {
+                    encoded.extend_from_slice(context);
+                }
+                encoded.extend_from_slice(&slice.root);
+                for value in [slice.len, slice.offset, slice.length] {
+                    encoded.extend_from_slice(&value.to_le_bytes());
+                }
+                encoded.extend_from_slice(&(nodes.len() as u32).to_le_bytes());
+                encoded.extend_from_slice(nodes.as_flattened());
+                common::encode_frames(&mut encoded, &data, frame_len);
+            }
+            Self::KeyValue(map) => kv::encode_input(&mut encoded, map, frame_len),
+            Self::Fields(map) => fields::encode_input(&mut encoded, map, frame_len),
+            Self::MerkleExclusion(proof) => {
+                common::encode_frames(&mut encoded, &Zeroizing::new(proof.encode()), frame_len)
+            }
+            Self::SparseMerkle(proof) => {
+                common::encode_frames(&mut encoded, &Zeroizing::new(proof.encode()), frame_len)
+            }
+            Self::Multi(items) => {
+                encoded.extend_from_slice(&(items.len() as u32).to_le_bytes());
+                for item in items {
+                    common::encode_frames(&mut encoded, item, frame_len);
+                }
+            }
+            Self::BindInput(_)
+            | Self::WithStats(_)
+            | Self::WithVersion(_)
+            | Self::WithNonce { .. }
+            | Self::Framed { .. } => {
+                unreachable!("wrapped inputs are encoded above")
+            }
+        }
+        encoded
+    }
+
+    /// Checks that the guest will accept this input. A zstd input is only
+    /// decompressed by [`Self::decompressed_len`], which bounds how far it
+    /// goes.
+    pub fn validate(&self) -> Result<(), InputError> {
+        match self {
+            Self::Single { data, padding } => padding
+                .check(data.len() as u64)
+                .map_err(|err| InputError::invalid("single.padding", err)),
+            Self::KeyValue(map) => {
+                kv::validate(map).map_err(|err| InputError::invalid("key_value", err))
+            }
+            Self::Fields(map) => {
+                fields::validate(map).map_err(|err| InputError::invalid("fields", err))
+            }
+            Self::Outboard { block_log, .. } if *block_log > MAX_BLOCK_LOG => {
+                Err(InputError::invalid(
+                    "outboard.block_log",
+                    format_args!("block size log {block_log} exceeds {MAX_BLOCK_LOG}"),
+                ))
+            }
+            Self::Slice {
+                data,
+                block_log,
+                context,
+                offset,
+                length,
+            } => SliceProof::new(data, *block_log, context.as_deref(), *offset, *length)
+                .map(drop)
+                .map_err(|err| InputError::invalid("slice", err)),
+            Self::MerkleExclusion(proof) => proof
+                .verify()
+                .map(drop)
+                .map_err(|err| InputError::invalid("merkle_exclusion", err)),
+            Self::Sha256Chunks(_) if !methods::SHA256_CHUNKS => Err(InputError::invalid(
+                "sha256_chunks",
+                "the guest was built without the sha256-chunks feature",
+            )),
+            Self::BindInput(input) if input.wraps(|input| matches!(input, Self::BindInput(_))) => {
+                Err(InputError::invalid(
+                    "bind_input",
+                    "a bound input cannot be bound again",
+                ))
+            }
+            Self::BindInput(input) => input.validate(),
+            Self::WithStats(input) if input.wraps(|input| matches!(input, Self::WithStats(_))) => {
+                Err(InputError::invalid(
+                    "with_stats",
+                    "the statistics of the input are already committed",
+                ))
+            }
+            Self::WithStats(input) => input.validate(),
+            Self::WithVersion(input)
+                if input.wraps(|input| matches!(input, Self::WithVersion(_))) =>
+            {
+                Err(InputError::invalid(
+                    "with_version",
+                    "the version of the guest is already committed",
+                ))
+            }
+            Self::WithVersion(input) => input.validate(),
+            Self::WithNonce { input, .. }
+                if input.wraps(|input| matches!(input, Self::WithNonce { .. })) =>
+            {
+                Err(InputError::invalid(
+                    "with_nonce",
+                    "the input already carries a nonce",
+                ))
+            }
+            Self::WithNonce { input, .. } => input.validate(),
+            Self::Framed { frame_len, .. }
+                if !frame_len.is_power_of_two()
+                    || !(MIN_FRAME_LEN..=MAX_FRAME_LEN).contains(frame_len) =>
+            {
+                Err(InputError::invalid(
+                    "framed.frame_len",
+                    format_args!(
+                        "frame size {frame_len} is not a power of two from {MIN_FRAME_LEN} \
+                         to {MAX_FRAME_LEN}"
+                    ),
+                ))
+            }
+            Self::Framed { input, .. }
+                if input.wraps(|input| matches!(input, Self::Framed { .. })) =>
+            {
+                Err(InputError::invalid(
+                    "framed",
+                    "a framed input cannot be framed again",
+                ))
+            }
+            Self::Framed { input, .. } => input.validate(),
+            Self::Multi(_)
+            | Self::Outboard { .. }
+            | Self::SparseMerkle(_)
+            | Self::Zstd(_)
+            | Self::Sha256Chunks(_) => Ok(()),
+        }
+    }
+
+    /// The journal the guest commits for this input.
+    pub fn expected_journal(&self) -> Result<Vec<u8>> {
+        Ok(match self {
+            Self::Single { data, padding } => {
+                let mut hasher = blake3::Hasher::new();
+                hasher.update(data);
+                padding.apply(data.len() as u64, |data| {
+                    hasher.update(data);
+                })?;
+                Output::Single {
+                    padding: *padding,
+                    digest: hasher.finalize().as_bytes(),
+                }
+                .encode()
+            }
+            Self::Multi(items) => {
+                let mut combined = blake3::Hasher::new();
+                let mut offset = 0;
+                let entries: Vec<_> = items
+                    .iter()
+                    .map(|item| {
+                        combined.update(item);
+                        let entry = Entry {
+                            offset,
+                            len: item.len() as u64,
+                            digest: blake3::hash(item).into(),
+                        };
+                        offset += entry.len;
+                        entry.to_bytes()
+                    })
+                    .collect();
+                Output::Multi {
+                    combined: combined.finalize().as_bytes(),
+                    entries: &entries,
+                }
+                .encode()
+            }
+            Self::Zstd(data) => Output::Zstd {
+                compressed: blake3::hash(data).as_bytes(),
+                decompressed: decompressed_digest(data, u64::MAX)?.0.as_bytes(),
+            }
+            .encode(),
+            Self::KeyValue(map) => Output::KeyValue {
+                count: map.len() as u32,
+                root: &kv::commitment(map),
+            }
+            .encode(),
+            Self::Fields(map) => Output::Fields {
+                count: map.len() as u32,
+                root: &fields::commitment(map),
+            }
+            .encode(),
+            Self::Sha256Chunks(data) => {
+                let mut hasher = ChunkHasher::new(|chunk| sha2::Sha256::digest(chunk).into());
+                hasher.update(data);
+                let (len, digest) = hasher.finalize();
+                Output::Sha256Chunks {
+                    len,
+                    digest: &digest,
+                }
+                .encode()
+            }
+            Self::MerkleExclusion(proof) => {
+                let exclusion = proof.verify()?;
+                Output::MerkleExclusion {
+                    root: &exclusion.root,
+                    key: &exclusion.key,
+                    lower: &exclusion.lower,
+                    upper: &exclusion.upper,
+                }
+                .encode()
+            }
+            Self::Outboard {
+                data,
+                block_log,
+                context,
+            } => {
+                let outboard = Outboard::new(data, *block_log, context.as_deref())?;
+                Output::Outboard {
+                    block_log: *block_log,
+                    len: outboard.len,
+                    context: &context.as_deref().map(bao::context_key).unwrap_or_default(),
+                    root: &outboard.root,
+                    outboard: &outboard.digest(),
+                }
+                .encode()
+            }
+            Self::Slice {
+                data,
+                block_log,
+                context,
+                offset,
+                length,
+            } => {
+                let slice =
+                    SliceProof::new(data, *block_log, context.as_deref(), *offset, *length)?.slice;
+                Output::Slice {
+                    context: &slice.context.unwrap_or_default(),
+                    root: &slice.root,
+                    len: slice.len,
+                    offset: *offset,
+                    length: *length,
+                    digest: blake3::hash(&data[*offset as usize..(*offset + *length) as usize])
+                        .as_bytes(),
+                }
+                .encode()
+            }
+            Self::SparseMerkle(proof) => Output::SparseMerkle {
+                root: &proof.root(&Defaults::new()),
+                key: &proof.key,
+                value: &proof.value.unwrap_or_default(),
+            }
+            .encode(),
+            // The wrappers may be nested in any order, but the guest always
+            // commits its version, then the nonce, then the statistics, then
+            // the input digest.
+            Self::BindInput(_)
+            | Self::WithStats(_)
+            | Self::WithVersion(_)
+            | Self::WithNonce { .. }
+            | Self::Framed { .. } => {
+                let (input, frame_len) = self.unwrapped();
+                let mut journal = input.expected_journal()?;
+                if self.wraps(|input| matches!(input, Self::WithVersion(_))) {
+                    let version = GuestVersion::current(methods::SHA256_CHUNKS);
+                    journal::bind_version(&mut journal, &version);
+                }
+                if let Some(nonce) = self.nonce() {
+                    journal::bind_nonce(&mut journal, nonce);
+                }
+                if self.wraps(|input| matches!(input, Self::WithStats(_))) {
+                    journal::bind_stats(&mut journal, &input.stats_in_frames_of(frame_len));
+                }
+                if self.wraps(|input| matches!(input, Self::BindInput(_))) {
+                    journal::bind_input(&mut journal, &self.input_digest());
+                }
+                journal
+            }
+        })
+    }
+
+    /// The statistics the guest commits for this input when it is wrapped
+    /// in [`GuestInput::WithStats`].
+    pub fn input_stats(&self) -> InputStats {
+        let (input, frame_len) = self.unwrapped();
+        input.stats_in_frames_of(frame_len)
+    }
+
+    fn stats_in_frames_of(&self, frame_len: usize) -> InputStats {
+        let mut stats = InputStats::default();
+        let mut count = |data: &[u8]| {
+            for frame in data.chunks(frame_len) {
+                stats.add_frame(frame.len());
+            }
+        };
+        match self {
+            Self::Single { data, .. }
+            | Self::Zstd(data)
+            | Self::Sha256Chunks(data)
+            | Self::Outboard { data, .. } => count(data),
+            Self::Slice {
+                data,
+                block_log,
+                context,
+                offset,
+                length,
+            } => {
+                if let Ok(proof) =
+                    SliceProof::new(data, *block_log, context.as_deref(), *offset, *length)
+                {
+                    count(&proof.data);
+                }
+            }
+            Self::Multi(items) => items.iter().for_each(|item| count(item)),
+            Self::KeyValue(map) => map.values().for_each(|value| count(value)),
+            Self::Fields(map) => map.values().for_each(|value| count(value)),
+            Self::MerkleExclusion(proof) => count(&proof.encode()),
+            Self::SparseMerkle(proof) => count(&proof.encode()),
+            Self::BindInput(_)
+            | Self::WithStats(_)
+            | Self::WithVersion(_)
+            | Self::WithNonce { .. }
+            | Self::Framed { .. } => {
+                unreachable!("wrapped inputs are unwrapped by input_stats")
+            }
+        }
+        stats
+    }
+
+    /// The nonce this input is sent with, if it or one it wraps is a
+    /// [`GuestInput::WithNonce`].
+    pub fn nonce(&self) -> Option<&Nonce> {
+        let mut input = self;
+        loop {
+            input = match input {
+                Self::WithNonce { nonce, .. } => return Some(nonce),
+                Self::BindInput(input)
+                | Self::WithStats(input)
+                | Self::WithVersion(input)
+                | Self::Framed { input, .. } => input,
+                _ => return None,
+            };
+        }
+    }
+
+    /// The input inside any wrappers, and the frame size it is sent in.
+    fn unwrapped(&self) -> (&Self, usize) {
+        match self {
+            Self::BindInput(input)
+            | Self::WithStats(input)
+            | Self::WithVersion(input)
+            | Self::WithNonce { input, .. } => input.unwrapped(),
+            Self::Framed { frame_len, input } => (input.unwrapped().0, *frame_len),
+            input => (input, MAX_FRAME_LEN),
+        }
+    }
+
+    /// Number of bytes a zstd input, or the one this input wraps, decompresses
+    /// to, or `None` if it is not compressed. Fails without decompressing
+    /// further once it exceeds `max_len` bytes.
+    pub fn decompressed_len(&self, max_len: u64) -> Result<Option<u64>> {
+        match self.unwrapped().0 {
+            Self::Zstd(data) => Ok(Some(decompressed_digest(data, max_len)?.1)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether this input or one it wraps matches `wrapper`.
+    fn wraps(&self, wrapper: impl Fn(&Self) -> bool) -> bool {
+        let mut input = self;
+        loop {
+            if wrapper(input) {
+                return true;
+            }
+            input = match input {
+                Self::BindInput(input)
+                | Self::WithStats(input)
+                | Self::WithVersion(input)
+                | Self::WithNonce { input, .. }
+                | Self::Framed { input, .. } => input,
+                _ => return false,
+            };
+        }
+    }
+}
+
+/// Hashes the decompression of a sequence of concatenated zstd frames as it
+/// streams, alongside its length, failing once it exceeds `max_len` bytes.
+fn decompressed_digest(mut data: &[u8], max_len: u64) -> Result<(blake3::Hash, u64)> {
+    let mut hasher = blake3::Hasher::new();
+    let mut len = 0;
+    while !data.is_empty() {
+        let decoder = StreamingDecoder::new(&mut data).context("input is not valid zstd")?;
+        len += std::io::copy(
+            &mut decoder.take((max_len - len).saturating_add(1)),
+            &mut hasher,
+        )
+        .context("input is not valid zstd")?;
+        if len > max_len {
+            bail!("input decompresses to more than {max_len} bytes");
+        }
+    }
+    Ok((hasher.finalize(), len))
+}
+
+/// (De)serializes bytes as a hex string in human-readable formats such as
+/// JSON, and as they are in binary ones such as bincode, which the job queue
+/// stores inputs in.
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, T: AsRef<[u8]>>(
+        bytes: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(bytes))
+        } else {
+            bytes.as_ref().serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<Vec<u8>>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let bytes = if deserializer.is_human_readable() {
+            hex::decode(String::deserialize(deserializer)?).map_err(serde::de::Error::custom)?
+        } else {
+            Vec::deserialize(deserializer)?
+        };
+        T::try_from(bytes).map_err(|_| serde::de::Error::custom("wrong number of bytes"))
+    }
+}
+
+/// (De)serializes a list of byte strings as a list of hex strings, in
+/// human-readable formats.
+mod hex_list {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(items: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return items.serialize(serializer);
+        }
+        let encoded: Vec<String> = items.iter().map(hex::encode).collect();
+        encoded.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<u8>>, D::Error> {
+        if !deserializer.is_human_readable() {
+            return Vec::deserialize(deserializer);
+        }
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|item| hex::decode(item).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+/// (De)serializes a [`Padding`] in its string form, e.g. `zero-pad:10`.
+mod padding_str {
+    use common::padding::Padding;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(padding: &Padding, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(padding)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Padding, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// (De)serializes a [`KvMap`] as an object of hex-encoded keys and values, in
+/// human-readable formats.
+mod hex_map {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::kv::KvMap;
+
+    pub fn serialize<S: Serializer>(map: &KvMap, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return map.serialize(serializer);
+        }
+        let encoded: BTreeMap<String, String> = map
+            .iter()
+            .map(|(key, value)| (hex::encode(key), hex::encode(value)))
+            .collect();
+        encoded.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KvMap, D::Error> {
+        if !deserializer.is_human_readable() {
+            return KvMap::deserialize(deserializer);
+        }
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .iter()
+            .map(|(key, value)| Ok((hex::decode(key)?, hex::decode(value)?)))
+            .collect::<Result<_, hex::FromHexError>>()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// (De)serializes a [`FieldMap`] as an object of names and hex-encoded
+/// values, in human-readable formats.
+mod hex_fields {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::fields::FieldMap;
+
+    pub fn serialize<S: Serializer>(map: &FieldMap, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return map.serialize(serializer);
+        }
+        let encoded: BTreeMap<&str, String> = map
+            .iter()
+            .map(|(name, value)| (name.as_str(), hex::encode(value)))
+            .collect();
+        encoded.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FieldMap, D::Error> {
+        if !deserializer.is_human_readable() {
+            return FieldMap::deserialize(deserializer);
+        }
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, value)| Ok((name, hex::decode(value)?)))
+            .collect::<Result<_, hex::FromHexError>>()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// (De)serializes an [`ExclusionProof`] as its binary encoding, in hex in
+/// human-readable formats.
+mod exclusion_hex {
+    use common::merkle::ExclusionProof;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        proof: &ExclusionProof,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        super::hex_bytes::serialize(&proof.encode(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ExclusionProof, D::Error> {
+        let encoded: Vec<u8> = super::hex_bytes::deserialize(deserializer)?;
+        ExclusionProof::decode(&encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// (De)serializes a [`SparseProof`] as its binary encoding, in hex in
+/// human-readable formats.
+mod sparse_hex {
+    use common::smt::SparseProof;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(proof: &SparseProof, serializer: S) -> Result<S::Ok, S::Error> {
+        super::hex_bytes::serialize(&proof.encode(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SparseProof, D::Error> {
+        let encoded: Vec<u8> = super::hex_bytes::deserialize(deserializer)?;
+        SparseProof::decode(&encoded)
+            .ok_or_else(|| serde::de::Error::custom("malformed sparse Merkle proof"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+    use super::*;
+
+    #[test]
+    fn decompressed_digest_streams_every_frame() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let mut compressed = compress_to_vec(&data[..60_000], CompressionLevel::Fastest);
+        compressed.extend(compress_to_vec(&data[60_000..], CompressionLevel::Fastest));
+        let (digest, len) = decompressed_digest(&compressed, data.len() as u64).unwrap();
+        assert_eq!(digest, blake3::hash(&data));
+        assert_eq!(len, data.len() as u64);
+    }
+
+    #[test]
+    fn binary_encoding_keeps_bytes_raw() {
+        let input = GuestInput::WithNonce {
+            nonce: [9; 32],
+            input: Box::new(GuestInput::KeyValue(
+                [(b"key".to_vec(), vec![0xab; 1000])].into(),
+            )),
+        };
+        let encoded = bincode::serialize(&input).unwrap();
+        assert!(encoded.len() < 1100, "{} bytes", encoded.len());
+        assert_eq!(bincode::deserialize::<GuestInput>(&encoded).unwrap(), input);
+
+        let json = serde_json::to_string(&input).unwrap();
+        assert!(json.contains(&hex::encode([9; 32])));
+        assert_eq!(serde_json::from_str::<GuestInput>(&json).unwrap(), input);
+    }
+
+    #[test]
+    fn decompressed_len_is_bounded() {
+        let compressed = compress_to_vec(&[0u8; 1 << 20][..], CompressionLevel::Fastest);
+        let input = GuestInput::WithStats(Box::new(GuestInput::Zstd(compressed)));
+        assert_eq!(input.decompressed_len(1 << 20).unwrap(), Some(1 << 20));
+        assert!(input.decompressed_len((1 << 20) - 1).is_err());
+        assert_eq!(GuestInput::Multi(vec![]).decompressed_len(0).unwrap(), None);
+    }
+}
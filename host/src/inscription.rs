@@ -0,0 +1,196 @@
+//! A whole proof bundle as an ordinals-style inscription, for deployments
+//! that want the proof itself, not just its verifier witness, retrievable
+//! from Bitcoin.
+//!
+//! The envelope is the one the `ord` indexer reads from a tapscript:
+//!
+//! ```text
+//! OP_FALSE OP_IF
+//!   "ord"
+//!   1 <content type>
+//!   7 <metaprotocol>
+//!   0 <body chunk> <body chunk> ...
+//! OP_ENDIF
+//! ```
+//!
+//! where the body is the encoded bundle split into pushes of at most
+//! [`MAX_SCRIPT_ELEMENT_LEN`] bytes. The envelope is never executed, so it
+//! is placed after the `<key> OP_CHECKSIG` of a tapleaf, which is revealed
+//! by spending an output committed to it.
+
+use anyhow::{bail, ensure, Context, Result};
+use bitcoin::{
+    opcodes::{
+        all::{OP_CHECKSIG, OP_ENDIF, OP_IF},
+        OP_FALSE,
+    },
+    script::{Builder, Instruction, PushBytes},
+    secp256k1::Secp256k1,
+    taproot::TaprootBuilder,
+    Address, Network, Script, ScriptBuf, Transaction, XOnlyPublicKey,
+};
+
+use crate::{bundle::ProofBundle, chunks::MAX_SCRIPT_ELEMENT_LEN};
+
+/// Content type of an inscribed proof bundle.
+pub const CONTENT_TYPE: &str = "application/vnd.r0-bitvm-blake3.bundle";
+
+/// Metaprotocol an inscribed proof bundle is marked with, so that indexers
+/// can find them.
+pub const METAPROTOCOL: &str = "r0-bitvm-blake3";
+
+const PROTOCOL_ID: &[u8] = b"ord";
+const CONTENT_TYPE_TAG: u8 = 1;
+const METAPROTOCOL_TAG: u8 = 7;
+
+/// An inscription: its content type, metaprotocol and body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inscription {
+    pub content_type: String,
+    pub metaprotocol: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Inscription {
+    /// The inscription of `bundle`, in its bincode encoding.
+    pub fn of_bundle(bundle: &ProofBundle) -> Result<Self> {
+        Ok(Self {
+            content_type: CONTENT_TYPE.to_string(),
+            metaprotocol: Some(METAPROTOCOL.to_string()),
+            body: bundle.encode()?,
+        })
+    }
+
+    /// Decodes the body as a proof bundle.
+    pub fn bundle(&self) -> Result<ProofBundle> {
+        ensure!(
+            self.content_type == CONTENT_TYPE,
+            "inscription is {}, not a proof bundle",
+            self.content_type
+        );
+        ProofBundle::decode(&self.body)
+    }
+
+    /// Number of pushes the body is split into.
+    pub fn body_pushes(&self) -> usize {
+        self.body.len().div_ceil(MAX_SCRIPT_ELEMENT_LEN)
+    }
+
+    /// The envelope alone.
+    pub fn envelope(&self) -> Result<ScriptBuf> {
+        Ok(self.append_envelope(Builder::new())?.into_script())
+    }
+
+    /// The tapleaf revealing the inscription: a signature check by `key`
+    /// followed by the envelope.
+    pub fn tapscript(&self, key: XOnlyPublicKey) -> Result<ScriptBuf> {
+        let builder = Builder::new()
+            .push_x_only_key(&key)
+            .push_opcode(OP_CHECKSIG);
+        Ok(self.append_envelope(builder)?.into_script())
+    }
+
+    /// The address committing to the tapleaf of `key`, whose output is spent
+    /// through that leaf to reveal the inscription.
+    pub fn commit_address(&self, key: XOnlyPublicKey, network: Network) -> Result<Address> {
+        let secp = Secp256k1::verification_only();
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(0, self.tapscript(key)?)?
+            .finalize(&secp, key)
+            .map_err(|_| anyhow::anyhow!("a single leaf is a complete script tree"))?;
+        Ok(Address::p2tr(&secp, key, spend_info.merkle_root(), network))
+    }
+
+    fn append_envelope(&self, builder: Builder) -> Result<Builder> {
+        let mut builder = builder
+            .push_opcode(OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_slice(push_bytes(PROTOCOL_ID)?)
+            .push_slice(push_bytes(&[CONTENT_TYPE_TAG])?)
+            .push_slice(push_bytes(self.content_type.as_bytes())?);
+        if let Some(metaprotocol) = &self.metaprotocol {
+            builder = builder
+                .push_slice(push_bytes(&[METAPROTOCOL_TAG])?)
+                .push_slice(push_bytes(metaprotocol.as_bytes())?);
+        }
+        // The empty push separating the fields from the body.
+        builder = builder.push_slice(push_bytes(&[])?);
+        for chunk in self.body.chunks(MAX_SCRIPT_ELEMENT_LEN) {
+            builder = builder.push_slice(push_bytes(chunk)?);
+        }
+        Ok(builder.push_opcode(OP_ENDIF))
+    }
+
+    /// Reads the first inscription enveloped in `script`.
+    pub fn from_script(script: &Script) -> Result<Self> {
+        let mut instructions = script.instructions().peekable();
+        // Find `OP_FALSE OP_IF "ord"`.
+        loop {
+            match instructions.next().transpose()? {
+                None => bail!("script holds no inscription envelope"),
+                Some(Instruction::PushBytes(empty)) if empty.is_empty() => {}
+                Some(_) => continue,
+            }
+            if !matches!(instructions.peek(), Some(Ok(Instruction::Op(OP_IF)))) {
+                continue;
+            }
+            instructions.next();
+            if let Some(Ok(Instruction::PushBytes(id))) = instructions.next() {
+                if id.as_bytes() == PROTOCOL_ID {
+                    break;
+                }
+            }
+        }
+
+        let mut content_type = None;
+        let mut metaprotocol = None;
+        let mut body = Vec::new();
+        let mut in_body = false;
+        loop {
+            let push = match instructions.next().transpose()? {
+                Some(Instruction::Op(OP_ENDIF)) => break,
+                Some(Instruction::PushBytes(push)) => push.as_bytes(),
+                Some(Instruction::Op(op)) => bail!("unexpected {op} in inscription envelope"),
+                None => bail!("unterminated inscription envelope"),
+            };
+            if in_body {
+                body.extend_from_slice(push);
+                continue;
+            }
+            if push.is_empty() {
+                in_body = true;
+                continue;
+            }
+            let value = match instructions.next().transpose()? {
+                Some(Instruction::PushBytes(value)) => value.as_bytes(),
+                _ => bail!("inscription field without a value"),
+            };
+            match push {
+                [CONTENT_TYPE_TAG] => content_type = Some(String::from_utf8(value.to_vec())?),
+                [METAPROTOCOL_TAG] => metaprotocol = Some(String::from_utf8(value.to_vec())?),
+                // Odd tags may be ignored; even ones change what the
+                // inscription means.
+                [tag] if tag % 2 == 1 => {}
+                tag => bail!("unknown even inscription field {}", hex::encode(tag)),
+            }
+        }
+        Ok(Self {
+            content_type: content_type.context("inscription has no content type")?,
+            metaprotocol,
+            body,
+        })
+    }
+
+    /// Reads the first inscription revealed by an input of `tx`.
+    pub fn from_transaction(tx: &Transaction) -> Result<Self> {
+        tx.input
+            .iter()
+            .filter_map(|input| input.witness.tapscript())
+            .find_map(|script| Self::from_script(script).ok())
+            .context("no input of the transaction reveals an inscription")
+    }
+}
+
+fn push_bytes(bytes: &[u8]) -> Result<&PushBytes> {
+    <&PushBytes>::try_from(bytes).context("push too large")
+}
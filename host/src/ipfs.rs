@@ -0,0 +1,146 @@
+//! Publication and retrieval of proof bundles over IPFS, through the HTTP RPC
+//! API of a node such as Kubo, so that challengers can fetch an operator's
+//! full bundle by the CID it announces.
+//!
+//! Bundles are added as CIDv1 with raw leaves and pinned on the operator's
+//! node. The node checks every block it retrieves against its hash, so a
+//! bundle fetched by CID is the one published; challengers can pin it on
+//! their own node to keep it available. A node can also serve as a
+//! [data availability layer](crate::da) for the receipts behind a bundle.
+
+use std::{io::Read, time::Duration};
+
+use anyhow::{bail, ensure, Context, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use reqwest::blocking::{Client, Response};
+use serde::Deserialize;
+
+use crate::da::DaLayer;
+
+/// API of a local Kubo node.
+pub const DEFAULT_API_URL: &str = "http://127.0.0.1:5001";
+
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Largest object fetched, well above the largest bundle, so that a
+/// malicious CID cannot make the fetcher allocate without bound.
+pub const MAX_OBJECT_LEN: u64 = 64 * 1024 * 1024;
+
+/// A node's HTTP RPC API, e.g. `http://127.0.0.1:5001`.
+pub struct Ipfs {
+    client: Client,
+    url: String,
+}
+
+impl Ipfs {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder().timeout(TIMEOUT).build()?,
+            url: url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Adds `contents` as a file named `name`, pinning it if `pin`, and
+    /// returns its CID.
+    pub fn add(&self, name: &str, contents: &[u8], pin: bool) -> Result<String> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Added {
+            hash: String,
+        }
+        let mut nonce = [0; 16];
+        OsRng.fill_bytes(&mut nonce);
+        let boundary = format!("r0-bitvm-blake3-{}", hex::encode(nonce));
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            name.replace(['"', '\r', '\n'], "_")
+        )
+        .into_bytes();
+        body.extend_from_slice(contents);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+        let response = self
+            .client
+            .post(format!("{}/api/v0/add", self.url))
+            .query(&[
+                ("cid-version", "1"),
+                ("raw-leaves", "true"),
+                ("pin", if pin { "true" } else { "false" }),
+            ])
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(body)
+            .send()
+            .context("IPFS add failed")?;
+        let added: Added = serde_json::from_str(&checked(response, "add")?.text()?)
+            .context("malformed IPFS add response")?;
+        Ok(added.hash)
+    }
+
+    /// Fetches the file `cid`, of at most [`MAX_OBJECT_LEN`] bytes.
+    pub fn cat(&self, cid: &str) -> Result<Vec<u8>> {
+        let length = (MAX_OBJECT_LEN + 1).to_string();
+        let response = self
+            .client
+            .post(format!("{}/api/v0/cat", self.url))
+            .query(&[("arg", cid), ("length", length.as_str())])
+            .send()
+            .with_context(|| format!("IPFS cat of {cid} failed"))?;
+        let mut contents = Vec::new();
+        checked(response, "cat")?
+            .take(MAX_OBJECT_LEN + 1)
+            .read_to_end(&mut contents)?;
+        ensure!(
+            contents.len() as u64 <= MAX_OBJECT_LEN,
+            "{cid} is larger than {MAX_OBJECT_LEN} bytes"
+        );
+        Ok(contents)
+    }
+
+    /// Pins `cid` on the node, fetching whatever it does not hold yet.
+    pub fn pin(&self, cid: &str) -> Result<()> {
+        let response = self
+            .client
+            .post(format!("{}/api/v0/pin/add", self.url))
+            .query(&[("arg", cid)])
+            .send()
+            .with_context(|| format!("IPFS pin of {cid} failed"))?;
+        checked(response, "pin/add")?;
+        Ok(())
+    }
+}
+
+impl DaLayer for Ipfs {
+    fn name(&self) -> &'static str {
+        "ipfs"
+    }
+
+    fn publish(&self, blob: &[u8]) -> Result<String> {
+        self.add(&blake3::hash(blob).to_hex(), blob, true)
+    }
+
+    fn fetch(&self, locator: &str, _digest: &[u8; 32]) -> Result<Vec<u8>> {
+        self.cat(locator)
+    }
+}
+
+/// `response` if it succeeded; the node answers errors with a JSON body
+/// holding a `Message`.
+fn checked(response: Response, command: &str) -> Result<Response> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct ApiError {
+        message: String,
+    }
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().unwrap_or_default();
+    match serde_json::from_str::<ApiError>(&body) {
+        Ok(ApiError { message }) => bail!("IPFS {command} failed with {status}: {message}"),
+        Err(_) => bail!("IPFS {command} answered {status}: {}", body.trim()),
+    }
+}
@@ -0,0 +1,282 @@
+//! Borrowed access to a receipt's journal, so large journals can be inspected
+//! and exported without being copied or decoded into owned values first.
+
+use anyhow::{bail, ensure, Context, Result};
+use common::journal::Output;
+use risc0_zkp::core::digest::Digest;
+use risc0_zkvm::{sha::Sha256, Receipt};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::{input::GuestInput, verifier};
+
+/// Size of one word of the risc0 serde encoding used by `env::commit`.
+pub const WORD_LEN: usize = 4;
+
+/// A view over journal bytes borrowed from a receipt or another buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct Journal<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Journal<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn of(receipt: &'a Receipt) -> Self {
+        Self::new(&receipt.journal.bytes)
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Parses the guest output, borrowing the digests from the journal.
+    pub fn output(&self) -> Result<Output<'a>> {
+        Ok(common::journal::parse(self.bytes)?)
+    }
+
+    /// The digest of the whole guest input the journal is bound to, if the
+    /// input was proven with [`GuestInput::BindInput`](crate::input::GuestInput::BindInput).
+    pub fn input_digest(&self) -> Option<&'a common::journal::Digest> {
+        common::journal::input_digest(self.bytes)
+    }
+
+    /// Checks a multi-input journal against the concatenation of its inputs:
+    /// the combined digest must match, and splitting `data` at the committed
+    /// boundaries must yield inputs with the committed digests. This rejects
+    /// journals whose entries do not tile `data` exactly, so bytes cannot be
+    /// moved from one input to its neighbour.
+    pub fn check_concatenation(&self, data: &[u8]) -> Result<()> {
+        let output = self.output()?;
+        let Output::Multi { combined, .. } = output else {
+            bail!("journal is not a multi-input journal");
+        };
+        ensure!(
+            blake3::hash(data).as_bytes() == combined,
+            "combined digest does not match the data"
+        );
+        let mut expected_offset = 0;
+        for (index, entry) in output.entries().enumerate() {
+            ensure!(
+                entry.offset == expected_offset,
+                "input {index} starts at {}, expected {expected_offset}",
+                entry.offset
+            );
+            let end = entry
+                .offset
+                .checked_add(entry.len)
+                .filter(|&end| end <= data.len() as u64)
+                .with_context(|| format!("input {index} extends past the end of the data"))?;
+            let item = &data[entry.offset as usize..end as usize];
+            ensure!(
+                blake3::hash(item).as_bytes() == &entry.digest,
+                "digest of input {index} does not match the data"
+            );
+            expected_offset = end;
+        }
+        ensure!(
+            expected_offset == data.len() as u64,
+            "inputs cover {expected_offset} of {} bytes",
+            data.len()
+        );
+        Ok(())
+    }
+
+    /// The version of the guest the journal commits to, if the input was
+    /// proven with [`GuestInput::WithVersion`].
+    pub fn guest_version(&self) -> Option<common::journal::GuestVersion> {
+        common::journal::guest_version(self.bytes)
+    }
+
+    /// The nonce the journal commits to, if the input was proven with
+    /// [`GuestInput::WithNonce`].
+    pub fn nonce(&self) -> Option<&common::journal::Nonce> {
+        common::journal::nonce(self.bytes)
+    }
+
+    /// The statistics of the guest input the journal commits to, if the input
+    /// was proven with [`GuestInput::WithStats`].
+    pub fn input_stats(&self) -> Option<common::journal::InputStats> {
+        common::journal::input_stats(self.bytes)
+    }
+
+    /// Checks the input statistics the journal commits to, if any, against
+    /// those of `input`, naming what differs.
+    pub fn check_stats(&self, input: &GuestInput) -> Result<()> {
+        let Some(committed) = self.input_stats() else {
+            return Ok(());
+        };
+        let expected = input.input_stats();
+        ensure!(
+            committed == expected,
+            "guest read {} bytes in {} frames, but the input has {} bytes in {} frames",
+            committed.bytes,
+            committed.frames,
+            expected.bytes,
+            expected.frames
+        );
+        Ok(())
+    }
+
+    /// The SHA-256 journal digest committed to by the receipt claim.
+    pub fn digest(&self) -> Digest {
+        *risc0_zkvm::sha::Impl::hash_bytes(self.bytes)
+    }
+
+    /// The 31-byte BN254 public input binding this journal to `image_id`.
+    pub fn public_input(&self, image_id: impl Into<Digest>) -> [u8; verifier::PUBLIC_INPUT_LEN] {
+        verifier::expected_output_bytes(image_id.into(), self.bytes)
+    }
+
+    /// The guest output as JSON, with digests hex-encoded and the mode named
+    /// as in [`GuestInput`](crate::input::GuestInput), and the input
+    /// statistics and digest, the guest version and the nonce the journal
+    /// commits to, if any.
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        let mut output = self.output_json()?;
+        if let Some(version) = self.guest_version() {
+            output["guest_version"] = json!({
+                "schema": version.schema,
+                "modes": format!("{:#06x}", version.modes),
+            });
+        }
+        if let Some(nonce) = self.nonce() {
+            output["nonce"] = hex::encode(nonce).into();
+        }
+        if let Some(stats) = self.input_stats() {
+            output["input_stats"] = json!({ "bytes": stats.bytes, "frames": stats.frames });
+        }
+        if let Some(digest) = self.input_digest() {
+            output["input_digest"] = hex::encode(digest).into();
+        }
+        Ok(output)
+    }
+
+    fn output_json(&self) -> Result<serde_json::Value> {
+        Ok(match self.output()? {
+            Output::Empty => json!({ "mode": "empty" }),
+            Output::Single { padding, digest } => json!({
+                "mode": "single",
+                "padding": padding.to_string(),
+                "digest": hex::encode(digest),
+            }),
+            output @ Output::Multi { combined, .. } => json!({
+                "mode": "multi",
+                "combined": hex::encode(combined),
+                "entries": output
+                    .entries()
+                    .map(|entry| json!({
+                        "offset": entry.offset,
+                        "len": entry.len,
+                        "digest": hex::encode(entry.digest),
+                    }))
+                    .collect::<Vec<_>>(),
+            }),
+            Output::Zstd {
+                compressed,
+                decompressed,
+            } => json!({
+                "mode": "zstd",
+                "compressed": hex::encode(compressed),
+                "decompressed": hex::encode(decompressed),
+            }),
+            Output::KeyValue { count, root } => json!({
+                "mode": "key_value",
+                "count": count,
+                "root": hex::encode(root),
+            }),
+            Output::Fields { count, root } => json!({
+                "mode": "fields",
+                "count": count,
+                "root": hex::encode(root),
+            }),
+            Output::Sha256Chunks { len, digest } => json!({
+                "mode": "sha256_chunks",
+                "len": len,
+                "digest": hex::encode(digest),
+            }),
+            Output::MerkleExclusion {
+                root,
+                key,
+                lower,
+                upper,
+            } => json!({
+                "mode": "merkle_exclusion",
+                "root": hex::encode(root),
+                "key": hex::encode(key),
+                "lower": hex::encode(lower),
+                "upper": hex::encode(upper),
+            }),
+            Output::SparseMerkle { root, key, value } => json!({
+                "mode": "sparse_merkle",
+                "root": hex::encode(root),
+                "key": hex::encode(key),
+                "value": hex::encode(value),
+            }),
+            Output::Outboard {
+                block_log,
+                len,
+                context,
+                root,
+                outboard,
+            } => json!({
+                "mode": "outboard",
+                "block_log": block_log,
+                "len": len,
+                "context": hex::encode(context),
+                "root": hex::encode(root),
+                "outboard": hex::encode(outboard),
+            }),
+            Output::Slice {
+                context,
+                root,
+                len,
+                offset,
+                length,
+                digest,
+            } => json!({
+                "mode": "slice",
+                "context": hex::encode(context),
+                "root": hex::encode(root),
+                "len": len,
+                "offset": offset,
+                "length": length,
+                "digest": hex::encode(digest),
+            }),
+        })
+    }
+
+    /// Iterates over the little-endian words of the risc0 serde encoding
+    /// without copying the journal. A trailing partial word is not yielded.
+    pub fn fields(&self) -> impl Iterator<Item = u32> + 'a {
+        let (words, _) = self.bytes.as_chunks::<WORD_LEN>();
+        words.iter().map(|word| u32::from_le_bytes(*word))
+    }
+
+    /// Decodes the journal with the risc0 deserializer. An empty journal
+    /// decodes as `()`.
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T> {
+        ensure!(
+            self.bytes.len().is_multiple_of(WORD_LEN),
+            "journal length {} is not a whole number of words",
+            self.bytes.len()
+        );
+        Ok(risc0_zkvm::serde::from_slice(self.bytes)?)
+    }
+}
+
+impl AsRef<[u8]> for Journal<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes
+    }
+}
@@ -0,0 +1,114 @@
+//! Host side of key-value commitments: building the commitment the guest
+//! proves for a map, and diffing two maps, e.g. consecutive bridge state
+//! snapshots.
+
+use std::{cmp::Ordering, collections::BTreeMap, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use common::kv::{self, MAX_KEY_LEN};
+
+/// A map ordered the way the guest requires: by key, bytewise.
+pub type KvMap = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// Checks that the guest will accept `map`.
+pub fn validate(map: &KvMap) -> Result<()> {
+    ensure!(u32::try_from(map.len()).is_ok(), "map has too many entries");
+    if let Some(key) = map.keys().find(|key| key.len() > MAX_KEY_LEN) {
+        bail!(
+            "key of {} bytes exceeds the maximum of {MAX_KEY_LEN}",
+            key.len()
+        );
+    }
+    Ok(())
+}
+
+/// The digest the guest commits for `map`.
+pub fn commitment(map: &KvMap) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&(map.len() as u32).to_le_bytes());
+    for (key, value) in map {
+        hasher.update(&kv::key_header(key));
+        hasher.update(key);
+        hasher.update(&kv::value_header(value.len() as u64));
+        hasher.update(value);
+    }
+    hasher.finalize().into()
+}
+
+/// Appends `map` to a guest input in the layout read by the guest, with
+/// values split into frames of at most `frame_len` bytes.
+pub fn encode_input(encoded: &mut Vec<u8>, map: &KvMap, frame_len: usize) {
+    encoded.extend_from_slice(&(map.len() as u32).to_le_bytes());
+    for (key, value) in map {
+        encoded.extend_from_slice(&kv::key_header(key));
+        encoded.extend_from_slice(key);
+        encoded.extend_from_slice(&kv::value_header(value.len() as u64));
+        common::encode_frames(encoded, value, frame_len);
+    }
+}
+
+/// Loads a map from a JSON object of hex-encoded keys and values.
+pub fn load(path: &Path) -> Result<KvMap> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("failed to read map {}", path.display()))?;
+    let encoded: BTreeMap<String, String> = serde_json::from_slice(&contents)
+        .with_context(|| format!("invalid map {}", path.display()))?;
+    let map = encoded
+        .iter()
+        .map(|(key, value)| Ok((hex::decode(key)?, hex::decode(value)?)))
+        .collect::<Result<KvMap>>()
+        .with_context(|| format!("invalid hex in map {}", path.display()))?;
+    validate(&map)?;
+    Ok(map)
+}
+
+/// One difference between two maps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<'a> {
+    Added {
+        key: &'a [u8],
+        value: &'a [u8],
+    },
+    Removed {
+        key: &'a [u8],
+        value: &'a [u8],
+    },
+    Changed {
+        key: &'a [u8],
+        old: &'a [u8],
+        new: &'a [u8],
+    },
+}
+
+/// Lists the changes turning `old` into `new`, in key order.
+pub fn diff<'a>(old: &'a KvMap, new: &'a KvMap) -> Vec<Change<'a>> {
+    let mut changes = Vec::new();
+    let mut old_entries = old.iter().peekable();
+    let mut new_entries = new.iter().peekable();
+    loop {
+        let order = match (old_entries.peek(), new_entries.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((old_key, _)), Some((new_key, _))) => old_key.cmp(new_key),
+        };
+        match order {
+            Ordering::Less => {
+                let (key, value) = old_entries.next().unwrap();
+                changes.push(Change::Removed { key, value });
+            }
+            Ordering::Greater => {
+                let (key, value) = new_entries.next().unwrap();
+                changes.push(Change::Added { key, value });
+            }
+            Ordering::Equal => {
+                let (key, old) = old_entries.next().unwrap();
+                let (_, new) = new_entries.next().unwrap();
+                if old != new {
+                    changes.push(Change::Changed { key, old, new });
+                }
+            }
+        }
+    }
+    changes
+}
@@ -0,0 +1,61 @@
+pub mod allowlist;
+pub mod assert_psbt;
+pub mod assert_tx;
+pub mod audit;
+pub mod bao;
+pub mod bitcoin_rpc;
+pub mod bitvm;
+pub mod bonsai;
+pub mod bundle;
+pub mod callback;
+pub mod chunks;
+pub mod cosign;
+pub mod cost;
+pub mod da;
+pub mod deadline;
+pub mod diff;
+pub mod disprove;
+pub mod export;
+pub mod farm;
+pub mod fetch_vk;
+pub mod fields;
+pub mod fraud;
+pub mod fsck;
+pub mod gossip;
+pub mod guest_build;
+pub mod hwi;
+pub mod input;
+#[cfg(feature = "experimental")]
+pub mod inscription;
+#[cfg(feature = "experimental")]
+pub mod ipfs;
+pub mod journal;
+pub mod kv;
+pub mod lifecycle;
+pub mod loadtest;
+pub mod manifest;
+pub mod merkle;
+pub mod method_id;
+pub mod monitor;
+pub mod nats;
+pub mod nonces;
+pub mod operator;
+pub mod pipeline;
+pub mod policy;
+pub mod precommit;
+pub mod profile;
+pub mod prover;
+pub mod recheck;
+pub mod regtest;
+pub mod report;
+pub mod selftest;
+pub mod server;
+pub mod smt;
+pub mod storage;
+pub mod timestamp;
+pub mod transform;
+pub mod tree;
+pub mod verifier;
+pub mod verify_stream;
+pub mod watch;
+mod wrap;
@@ -0,0 +1,230 @@
+//! The lifecycle of a bridge claim, from the input an operator receives to
+//! the resolution of its assert on Bitcoin, recorded as an event log in the
+//! receipt store next to the proof bundles.
+//!
+//! A claim is identified by the BLAKE3 digest of its encoded guest input, the
+//! `{input_hash}` of a storage [`Layout`](crate::storage::Layout), and its
+//! log is kept under `lifecycle/<claim>.json`. Every event moves the claim to
+//! a later [`Stage`]: proving may pass through several stages in one action,
+//! but a claim is only challenged once committed on chain, and resolved once
+//! committed or challenged. The log is written by the actions that move a
+//! claim on, and can be read back with `lifecycle-show` and `lifecycle-list`.
+//! An event read from the chain is [rolled back](rollback) if a reorg takes
+//! its transaction out of the chain.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{input::GuestInput, storage::Store};
+
+/// Storage key prefix of the claim logs.
+const PREFIX: &str = "lifecycle/";
+
+/// Where a claim stands, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// The operator has the guest input.
+    InputReceived,
+    /// The guest ran over the input.
+    Executed,
+    /// The execution was proven in a STARK receipt.
+    Proven,
+    /// The receipt was wrapped in Groth16 and verified.
+    Wrapped,
+    /// Its assert transactions were broadcast.
+    Committed,
+    /// A challenger spent the assert's connector.
+    Challenged,
+    /// The challenge window closed or the challenge was settled.
+    Resolved,
+}
+
+impl Stage {
+    /// Whether a claim at `self` may move to `next`.
+    pub fn can_move_to(self, next: Stage) -> bool {
+        match next {
+            Self::Challenged => self == Self::Committed,
+            Self::Resolved => matches!(self, Self::Committed | Self::Challenged),
+            next => next > self,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InputReceived => "input-received",
+            Self::Executed => "executed",
+            Self::Proven => "proven",
+            Self::Wrapped => "wrapped",
+            Self::Committed => "committed",
+            Self::Challenged => "challenged",
+            Self::Resolved => "resolved",
+        })
+    }
+}
+
+impl FromStr for Stage {
+    type Err = anyhow::Error;
+
+    fn from_str(stage: &str) -> Result<Self> {
+        Ok(match stage {
+            "input-received" => Self::InputReceived,
+            "executed" => Self::Executed,
+            "proven" => Self::Proven,
+            "wrapped" => Self::Wrapped,
+            "committed" => Self::Committed,
+            "challenged" => Self::Challenged,
+            "resolved" => Self::Resolved,
+            _ => bail!(
+                "unknown stage {stage}, expected input-received, executed, proven, wrapped, \
+                 committed, challenged or resolved"
+            ),
+        })
+    }
+}
+
+/// One transition of a claim.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    pub stage: Stage,
+    /// Unix timestamp, in seconds.
+    pub at: u64,
+    /// What moved the claim on, e.g. the storage key of its bundle or the ID
+    /// of its transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// The event log of one claim.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claim {
+    pub id: String,
+    /// The events, oldest first; never empty.
+    pub events: Vec<Event>,
+}
+
+impl Claim {
+    pub fn stage(&self) -> Stage {
+        self.events
+            .last()
+            .expect("a claim has at least one event")
+            .stage
+    }
+}
+
+/// The ID of the claim proving `input`.
+pub fn claim_id(input: &GuestInput) -> String {
+    blake3::hash(&input.encode()).to_hex().to_string()
+}
+
+/// Reads the log of claim `id`, or `None` if nothing was recorded for it.
+pub fn load(store: &dyn Store, id: &str) -> Result<Option<Claim>> {
+    store
+        .get(&key(id)?)?
+        .map(|encoded| {
+            serde_json::from_slice(&encoded).with_context(|| format!("corrupt log of claim {id}"))
+        })
+        .transpose()
+}
+
+/// Every claim with a log in `store`, most recently moved first.
+pub fn list(store: &dyn Store) -> Result<Vec<Claim>> {
+    let mut claims = Vec::new();
+    for key in store.list()? {
+        let Some(id) = key
+            .strip_prefix(PREFIX)
+            .and_then(|name| name.strip_suffix(".json"))
+        else {
+            continue;
+        };
+        claims.extend(load(store, id)?);
+    }
+    claims.sort_by_key(|claim| std::cmp::Reverse(claim.events.last().map(|event| event.at)));
+    Ok(claims)
+}
+
+/// Moves claim `id` to `stage`, starting its log if it has none, and returns
+/// the updated log. Recording the stage a claim is already at again is a
+/// no-op, so that a retried action does not fail; moving it back is an
+/// error.
+pub fn record(
+    store: &dyn Store,
+    id: &str,
+    stage: Stage,
+    detail: Option<String>,
+    now: u64,
+) -> Result<Claim> {
+    let event = Event {
+        stage,
+        at: now,
+        detail,
+    };
+    let claim = match load(store, id)? {
+        Some(claim) if claim.stage() == stage => return Ok(claim),
+        Some(mut claim) => {
+            if !claim.stage().can_move_to(stage) {
+                bail!("claim {id} is {}, it cannot become {stage}", claim.stage());
+            }
+            claim.events.push(event);
+            claim
+        }
+        None => Claim {
+            id: id.to_string(),
+            events: vec![event],
+        },
+    };
+    store.put(&key(id)?, &serde_json::to_vec_pretty(&claim)?)?;
+    Ok(claim)
+}
+
+/// Moves claim `id` through each of `stages` that is past its current one,
+/// for an action that takes the claim through several at once.
+pub fn record_all(
+    store: &dyn Store,
+    id: &str,
+    stages: &[Stage],
+    detail: Option<String>,
+    now: u64,
+) -> Result<Option<Claim>> {
+    let mut claim = load(store, id)?;
+    for &stage in stages {
+        if claim.as_ref().is_none_or(|claim| claim.stage() < stage) {
+            claim = Some(record(store, id, stage, detail.clone(), now)?);
+        }
+    }
+    Ok(claim)
+}
+
+/// Undoes the latest event of claim `id` if it moved the claim to `stage`
+/// because of `detail`, e.g. a challenge whose transaction a reorg took out
+/// of the chain, and returns whether it did.
+pub fn rollback(store: &dyn Store, id: &str, stage: Stage, detail: &str) -> Result<bool> {
+    let Some(mut claim) = load(store, id)? else {
+        return Ok(false);
+    };
+    let undone = claim.events.len() > 1
+        && claim
+            .events
+            .last()
+            .is_some_and(|event| event.stage == stage && event.detail.as_deref() == Some(detail));
+    if undone {
+        claim.events.pop();
+        store.put(&key(id)?, &serde_json::to_vec_pretty(&claim)?)?;
+    }
+    Ok(undone)
+}
+
+fn key(id: &str) -> Result<String> {
+    if id.is_empty()
+        || !id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+    {
+        bail!("invalid claim ID {id:?}");
+    }
+    Ok(format!("{PREFIX}{id}.json"))
+}
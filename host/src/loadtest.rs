@@ -0,0 +1,197 @@
+//! `loadtest` mode: drives a running `serve` instance with concurrent
+//! requests and reports their latency, so operators can size verification and
+//! proving services before putting them in front of a bridge.
+//!
+//! The verify workload POSTs the same cached bundle to `/verify` over and
+//! over, measuring verification alone. The prove workload submits small
+//! random inputs to `/jobs` and polls each job until it finishes, measuring
+//! the time from submission to a stored proof; the tenant's quotas apply to
+//! it as to any other client.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+
+const TIMEOUT: Duration = Duration::from_secs(600);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What each request does.
+pub enum Workload {
+    /// Verify an encoded proof bundle.
+    Verify(Vec<u8>),
+    /// Prove a random input of `input_len` bytes.
+    Prove { input_len: usize },
+}
+
+pub struct LoadOptions {
+    /// Base URL of the server, e.g. `http://127.0.0.1:8080`.
+    pub url: String,
+    pub api_key: String,
+    pub workload: Workload,
+    /// Number of requests in flight at once.
+    pub concurrency: usize,
+    /// Total number of requests sent.
+    pub requests: usize,
+}
+
+/// Latencies of the successful requests, in milliseconds.
+#[derive(Debug, Serialize)]
+pub struct Latency {
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoadReport {
+    pub requests: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// The first failures, with their errors.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    pub elapsed_secs: f64,
+    /// Successful requests per second.
+    pub throughput: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<Latency>,
+}
+
+/// Most errors kept in a [`LoadReport`].
+const MAX_ERRORS: usize = 10;
+
+#[derive(Deserialize)]
+struct Submitted {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct JobState {
+    status: String,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Sends `options.requests` requests from `options.concurrency` threads and
+/// reports how long they took. Failed requests are counted, not fatal.
+pub fn run(options: &LoadOptions) -> Result<LoadReport> {
+    ensure!(options.concurrency > 0, "concurrency must be at least 1");
+    let client = Client::builder().timeout(TIMEOUT).build()?;
+    let next = AtomicUsize::new(0);
+    let latencies = Mutex::new(Vec::with_capacity(options.requests));
+    let errors = Mutex::new(Vec::new());
+    let failed = AtomicUsize::new(0);
+
+    let started = Instant::now();
+    thread::scope(|scope| {
+        for _ in 0..options.concurrency.min(options.requests) {
+            scope.spawn(|| {
+                while next.fetch_add(1, Ordering::Relaxed) < options.requests {
+                    let sent = Instant::now();
+                    match request(&client, options) {
+                        Ok(()) => latencies.lock().unwrap().push(sent.elapsed()),
+                        Err(err) => {
+                            failed.fetch_add(1, Ordering::Relaxed);
+                            let mut errors = errors.lock().unwrap();
+                            if errors.len() < MAX_ERRORS {
+                                errors.push(format!("{err:#}"));
+                            }
+                        }
+                    }
+                }
+            });
+        }
+    });
+    let elapsed = started.elapsed();
+
+    let mut latencies = latencies.into_inner().unwrap();
+    latencies.sort();
+    Ok(LoadReport {
+        requests: options.requests,
+        succeeded: latencies.len(),
+        failed: failed.into_inner(),
+        errors: errors.into_inner().unwrap(),
+        elapsed_secs: elapsed.as_secs_f64(),
+        throughput: latencies.len() as f64 / elapsed.as_secs_f64(),
+        latency: latency(&latencies),
+    })
+}
+
+fn request(client: &Client, options: &LoadOptions) -> Result<()> {
+    let url = options.url.trim_end_matches('/');
+    match &options.workload {
+        Workload::Verify(bundle) => {
+            let response = client
+                .post(format!("{url}/verify"))
+                .header("X-Api-Key", &options.api_key)
+                .body(bundle.clone())
+                .send()?;
+            check_status(response)?;
+            Ok(())
+        }
+        Workload::Prove { input_len } => {
+            let mut input = vec![0; *input_len];
+            OsRng.fill_bytes(&mut input);
+            let response = client
+                .post(format!("{url}/jobs"))
+                .header("X-Api-Key", &options.api_key)
+                .json(&serde_json::json!({ "input": hex::encode(input) }))
+                .send()?;
+            let Submitted { id } = check_status(response)?.json()?;
+            loop {
+                let response = client
+                    .get(format!("{url}/jobs/{id}"))
+                    .header("X-Api-Key", &options.api_key)
+                    .send()?;
+                let job: JobState = check_status(response)?.json()?;
+                match job.status.as_str() {
+                    "succeeded" => return Ok(()),
+                    "failed" => {
+                        bail!("job {id} failed: {}", job.error.unwrap_or_default())
+                    }
+                    _ => thread::sleep(POLL_INTERVAL),
+                }
+            }
+        }
+    }
+}
+
+/// Fails with the server's error message for a non-success response.
+fn check_status(response: reqwest::blocking::Response) -> Result<reqwest::blocking::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().context("failed to read error response")?;
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|body| body.get("error")?.as_str().map(str::to_string))
+        .unwrap_or(body);
+    bail!("{status}: {message}")
+}
+
+/// Summarizes `sorted` latencies, or `None` if no request succeeded.
+fn latency(sorted: &[Duration]) -> Option<Latency> {
+    let max = sorted.last()?;
+    let millis = |duration: &Duration| duration.as_secs_f64() * 1000.0;
+    let percentile = |p: usize| millis(&sorted[(sorted.len() * p).div_ceil(100).max(1) - 1]);
+    Some(Latency {
+        mean: sorted.iter().map(millis).sum::<f64>() / sorted.len() as f64,
+        p50: percentile(50),
+        p90: percentile(90),
+        p99: percentile(99),
+        max: millis(max),
+    })
+}
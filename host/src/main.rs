@@ -1,271 +1,3165 @@
-// These constants represent the RISC-V ELF and the image ID generated by risc0-build.
-// The ELF is used for proving and the ID is used for verification.
-use methods::{
-    METHOD_ELF, METHOD_ID
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
 };
-use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts};
-use risc0_zkp::core::{digest::Digest as Risc0Digest};
-use risc0_binfmt::Digestible;
-use sha2::{Digest, Sha256};
 
-fn main() {
-    // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
-        .init();
+use anyhow::{bail, ensure, Context, Result};
+use bitcoin::address::NetworkUnchecked;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use common::{
+    journal::{Nonce, Output},
+    padding::Padding,
+};
+use host::{
+    allowlist::Allowlist,
+    assert_psbt::{self, AssertPackage, AssertParams, Funding},
+    assert_tx::AssertWitness,
+    audit::{self, AuditLog, Check},
+    bao::Outboard,
+    bitcoin_rpc::{self, BitcoinConfig, DEFAULT_ESPLORA_URL},
+    bundle::{ProofBundle, RunParameters},
+    callback::{self, ProofNotification, ProofSummary},
+    chunks::{self, ChunkSet},
+    cosign::{self, AttestedVerdict, SecretNonce, VerdictStatement},
+    cost::CostModel,
+    da::{self, DaConfig},
+    diff,
+    disprove::{DisproveTree, Fraud},
+    export::{self, CircomInput, EvmVerifierInput, GnarkExport},
+    farm, fetch_vk,
+    fields::{self, Disclosure},
+    fraud::{self, Commitments},
+    fsck,
+    gossip::{self, GossipConfig},
+    guest_build,
+    hwi::{self, Hwi},
+    input::GuestInput,
+    journal::Journal,
+    kv,
+    lifecycle::{self, Stage},
+    loadtest::{self, LoadOptions, Workload},
+    manifest::{self, Manifest},
+    method_id::MethodId,
+    monitor::{self, MonitorConfig},
+    nats::{self, ConsumerOptions},
+    nonces,
+    operator::OperatorKey,
+    policy::{self, VerifierPolicy},
+    precommit::{self, PreCommitment},
+    profile::{self, Profile},
+    prover::{self, ProverLimits},
+    recheck,
+    report::{ReportedAttestation, VerificationReport, VerifyParameters},
+    selftest,
+    server::{self, ServerConfig},
+    storage::{self, Layout},
+    timestamp::{self, Attestation, BundleTimestamp},
+    transform::{BindingHash, OutputTransform},
+    verifier::{self, ScalarPolicy, VerifyMode},
+    verify_stream::{self, Source, StreamOptions, Verdict},
+    watch::{self, WatchOptions},
+};
+#[cfg(feature = "experimental")]
+use host::{
+    gossip::Announcement,
+    inscription::Inscription,
+    ipfs::{self, Ipfs},
+};
+// This constant represents the image ID generated by risc0-build and is used for
+// verification.
+use methods::METHOD_ID;
+use risc0_zkvm::{
+    sha::{Digest, Digestible},
+    Receipt,
+};
+use zeroize::Zeroizing;
 
-    // An executor environment describes the configurations for the zkVM
-    // including program inputs.
-    // A default ExecutorEnv can be created like so:
-    // `let env = ExecutorEnv::builder().build().unwrap();`
-    // However, this `env` does not have any inputs.
-    //
-    // To add guest input to the executor environment, use
-    // ExecutorEnvBuilder::write().
-    // To access this method, you'll need to use ExecutorEnv::builder(), which
-    // creates an ExecutorEnvBuilder. When you're done adding input, call
-    // ExecutorEnvBuilder::build().
-
-    // For example:
-    let input: u32 = 15 * u32::pow(2, 27) + 1;
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .unwrap()
-        .build()
-        .unwrap();
-
-    // Obtain the default prover.
-    let prover = default_prover();
-
-    // Proof information by proving the specified ELF binary.
-    // This struct contains the receipt along with statistics about execution of the guest
-    let prove_info = prover
-        .prove_with_opts(env, METHOD_ELF, &ProverOpts::groth16())
-        .unwrap();
-
-    // extract the receipt.
-    let receipt = prove_info.receipt;
-
-    // TODO: Implement code for retrieving receipt journal here.
-
-    // For example:
-    let _output: u32 = receipt.journal.decode().unwrap();
-
-    receipt
-        .verify(METHOD_ID)
-        .unwrap();
-
-    let expected_output_bytes: [u8; 31] = {
-        let mut hasher = blake3::Hasher::new();
-        hasher.update(&calculate_succinct_output_prefix(METHOD_ID));
-        hasher.update(&receipt.journal.bytes);
-        let expected_output = hasher.finalize();
-        let expected_output_bytes: [u8; 32] = expected_output.into();
-        expected_output_bytes[..31].try_into().unwrap()
-    };
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Threads proofs are verified on: the pairings and multi-scalar
+    /// multiplications of each proof, and the bundles of `verify-stream` and
+    /// `recheck`. Defaults to one per CPU.
+    #[arg(long, global = true, value_name = "N")]
+    verify_threads: Option<usize>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
 
-    // Verify the proof
-    {
-        use ark_ff::PrimeField;
+#[derive(Subcommand)]
+enum Command {
+    /// Prove the guest over an input and verify the Groth16 proof.
+    Prove(ProveArgs),
+    /// Execute the guest over an input without proving it and print the
+    /// cycles and segments a proof would take as JSON.
+    Estimate {
+        #[command(flatten)]
+        input: InputArgs,
+        /// JSON input document of any guest mode, in place of the input and
+        /// padding flags.
+        #[arg(long, conflicts_with_all = ["input_hex", "input_file", "input_str", "padding"])]
+        input_json: Option<PathBuf>,
+        /// Also commit the BLAKE3 digest of the whole input, as for `prove`.
+        #[arg(long)]
+        bind_input: bool,
+        /// Also commit the bytes and frames read, as for `prove`.
+        #[arg(long)]
+        with_stats: bool,
+        /// Also commit the version of the guest, as for `prove`.
+        #[arg(long)]
+        with_version: bool,
+        /// Hex-encoded nonce to also commit, as for `prove`.
+        #[arg(long, value_parser = parse_hash)]
+        nonce: Option<Nonce>,
+        /// Frame size of the input, as for `prove`.
+        #[arg(long)]
+        frame_len: Option<usize>,
+        /// Padding applied before hashing, as for `prove`.
+        #[arg(long, default_value_t)]
+        padding: Padding,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    /// Print the input digest a proof made with `--bind-input` commits to, so
+    /// that a journal can be checked against an input held out of band.
+    InputDigest {
+        #[command(flatten)]
+        input: InputArgs,
+        /// Frame size of the input, as for `prove`. The digest is of the
+        /// input as framed.
+        #[arg(long)]
+        frame_len: Option<usize>,
+        /// The proof also commits the bytes and frames read, as for `prove`,
+        /// which the digest covers.
+        #[arg(long)]
+        with_stats: bool,
+        /// The proof also commits the version of the guest, as for `prove`,
+        /// which the digest covers.
+        #[arg(long)]
+        with_version: bool,
+        /// Hex-encoded nonce the proof also commits, as for `prove`, which
+        /// the digest covers.
+        #[arg(long, value_parser = parse_hash)]
+        nonce: Option<Nonce>,
+        /// JSON input document of any guest mode, in place of the input and
+        /// padding flags.
+        #[arg(long, conflicts_with_all = ["input_hex", "input_file", "input_str", "padding"])]
+        input_json: Option<PathBuf>,
+        /// Padding applied before hashing, as for `prove`.
+        #[arg(long, default_value_t)]
+        padding: Padding,
+    },
+    /// Print the pre-commitment to proving an input, to put on Bitcoin
+    /// before the proof exists, as JSON: the payload, its `OP_RETURN` script
+    /// and, given an operator key, its tapleaf script and leaf hash.
+    Precommit {
+        #[command(flatten)]
+        input: InputArgs,
+        /// JSON input document of any guest mode, in place of the input and
+        /// padding flags.
+        #[arg(long, conflicts_with_all = ["input_hex", "input_file", "input_str", "padding"])]
+        input_json: Option<PathBuf>,
+        /// Frame size of the input, as for `prove`.
+        #[arg(long)]
+        frame_len: Option<usize>,
+        /// The proof also commits the bytes and frames read, as for `prove`.
+        #[arg(long)]
+        with_stats: bool,
+        /// The proof also commits the version of the guest, as for `prove`.
+        #[arg(long)]
+        with_version: bool,
+        /// Hex-encoded nonce the proof also commits, as for `prove`.
+        #[arg(long, value_parser = parse_hash)]
+        nonce: Option<Nonce>,
+        /// Padding applied before hashing, as for `prove`.
+        #[arg(long, default_value_t)]
+        padding: Padding,
+        /// Hex-encoded image ID the input will be proven with. Defaults to
+        /// the image ID of the guest built into this binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+        /// Hex-encoded x-only public key of the operator spending the
+        /// tapleaf.
+        #[arg(long)]
+        operator: Option<String>,
+    },
+    /// Check that a proof bundle proves the claim of a pre-commitment, and
+    /// print the public input it verifies against.
+    CheckPrecommit {
+        /// Path to the proof bundle, proven with `--bind-input`.
+        bundle: PathBuf,
+        /// Hex-encoded pre-commitment: its payload, `OP_RETURN` script or
+        /// tapleaf script.
+        #[arg(long)]
+        commitment: String,
+    },
+    /// Verify a proof bundle against a set of trusted image IDs.
+    Verify(VerifyArgs),
+    /// Verify many proof bundles in parallel, printing a JSON verdict line for
+    /// each.
+    VerifyStream {
+        /// Directory of bundles to verify. Without it, bundles are read from
+        /// stdin, each preceded by its length as a little-endian u32.
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Hex-encoded image ID to accept; may be repeated. Defaults to the
+        /// image ID of the guest built into this binary.
+        #[arg(long = "image-id", value_parser = parse_digest, conflicts_with = "profile")]
+        image_ids: Vec<Digest>,
+        /// Largest journal accepted, in bytes.
+        #[arg(
+            long,
+            default_value_t = verifier::DEFAULT_MAX_JOURNAL_LEN,
+            conflicts_with = "profile"
+        )]
+        max_journal_len: usize,
+        #[command(flatten)]
+        profile: ProfileArgs,
+        /// Number of threads bundles are verified on, overriding
+        /// `--verify-threads` for this command.
+        #[arg(long, hide = true)]
+        threads: Option<usize>,
+        #[command(flatten)]
+        audit: AuditArgs,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Print the lifecycle of a claim recorded in a store as JSON.
+    LifecycleShow {
+        /// ID of the claim: the BLAKE3 digest of its encoded guest input.
+        claim: String,
+        #[command(flatten)]
+        store: ClaimStoreArgs,
+    },
+    /// List the claims recorded in a store and the stage each is at, most
+    /// recently moved first.
+    LifecycleList {
+        /// Only list the claims at this stage.
+        #[arg(long)]
+        stage: Option<Stage>,
+        #[command(flatten)]
+        store: ClaimStoreArgs,
+    },
+    /// Move a claim to a later stage of its lifecycle, for the actions taken
+    /// outside this binary.
+    LifecycleRecord {
+        /// ID of the claim.
+        claim: String,
+        /// `input-received`, `executed`, `proven`, `wrapped`, `committed`,
+        /// `challenged` or `resolved`.
+        stage: Stage,
+        /// What moved the claim on, e.g. a transaction ID.
+        #[arg(long)]
+        detail: Option<String>,
+        #[command(flatten)]
+        store: ClaimStoreArgs,
+    },
+    /// Re-verify every proof bundle archived in a store, printing a JSON
+    /// verdict line for each that no longer verifies.
+    Recheck {
+        /// Storage URI the bundles are archived in: a directory or
+        /// `s3://<bucket>/<prefix>`.
+        #[arg(long)]
+        store: String,
+        /// File holding the hex-encoded key the bundles are encrypted with.
+        /// Defaults to the `R0_BITVM_STORAGE_KEY` environment variable.
+        #[arg(long)]
+        encryption_key_file: Option<PathBuf>,
+        /// Hex-encoded image ID to accept; may be repeated. Defaults to the
+        /// image ID of the guest built into this binary.
+        #[arg(long = "image-id", value_parser = parse_digest, conflicts_with = "profile")]
+        image_ids: Vec<Digest>,
+        /// Largest journal accepted, in bytes.
+        #[arg(
+            long,
+            default_value_t = verifier::DEFAULT_MAX_JOURNAL_LEN,
+            conflicts_with = "profile"
+        )]
+        max_journal_len: usize,
+        #[command(flatten)]
+        profile: ProfileArgs,
+        /// Number of threads bundles are verified on, overriding
+        /// `--verify-threads` for this command.
+        #[arg(long, hide = true)]
+        threads: Option<usize>,
+        /// Seconds between passes. Without it, the store is checked once and
+        /// the command fails if any bundle no longer verifies.
+        #[arg(long)]
+        interval: Option<u64>,
+        #[command(flatten)]
+        audit: AuditArgs,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Prove a BLAKE3 test vector with a minimal guest and check the journal
+    /// against the known answer, to validate the toolchain before production
+    /// use.
+    Selftest {
+        /// Length of the test vector input, in bytes.
+        #[arg(long, default_value_t = 1024)]
+        len: usize,
+        /// Make a real Groth16 proof instead of a dev-mode receipt. Needs
+        /// `r0vm` and Docker.
+        #[arg(long)]
+        no_dev_mode: bool,
+    },
+    /// Check the chain and signatures of an audit log written by `verify` or
+    /// `verify-stream`.
+    AuditVerify {
+        /// Path to the audit log.
+        log: PathBuf,
+        /// Hex-encoded x-only public key every line must be signed by.
+        #[arg(long)]
+        signer: Option<String>,
+    },
+    /// Publish a nonce to co-sign a verdict printed by `verify-stream` with,
+    /// the first round of co-signing. Prints the public nonce as a JSON line.
+    CosignNonce {
+        /// File holding the verdict line.
+        verdict: PathBuf,
+        /// File holding the hex-encoded BIP340 secret key of this watchtower.
+        #[arg(long)]
+        key_file: PathBuf,
+        /// File to write the secret nonce to, which must not exist yet.
+        #[arg(long)]
+        secret_nonce: PathBuf,
+    },
+    /// Sign a verdict given the nonces of every co-signer, the second round of
+    /// co-signing. Consumes the secret nonce and prints the partial signature
+    /// as a JSON line.
+    CosignSign {
+        /// File holding the verdict line this watchtower reached.
+        verdict: PathBuf,
+        #[arg(long)]
+        key_file: PathBuf,
+        /// File written by `cosign-nonce`, removed before signing.
+        #[arg(long)]
+        secret_nonce: PathBuf,
+        /// File of the JSON nonce lines of every co-signer, this one included.
+        #[arg(long)]
+        nonces: PathBuf,
+    },
+    /// Aggregate the partial signatures of every co-signer of a verdict and
+    /// print the attested verdict as JSON.
+    CosignAggregate {
+        /// File holding the verdict line.
+        verdict: PathBuf,
+        /// File of the JSON nonce lines of every co-signer.
+        #[arg(long)]
+        nonces: PathBuf,
+        /// File of the JSON partial signature lines of every co-signer.
+        #[arg(long)]
+        partials: PathBuf,
+    },
+    /// Check the signature of an attested verdict printed by
+    /// `cosign-aggregate`.
+    CosignCheck {
+        /// File holding the attested verdict.
+        attested: PathBuf,
+        /// Hex-encoded x-only public key of a committee member; may be
+        /// repeated. With members given, every signer must be one.
+        #[arg(long = "member")]
+        members: Vec<String>,
+        /// Fewest committee members that must have signed. Defaults to all of
+        /// them.
+        #[arg(long, requires = "members")]
+        threshold: Option<usize>,
+    },
+    /// Compare two proof bundles field by field and report the first field
+    /// that differs.
+    Diff {
+        /// Path to the first proof bundle.
+        #[arg(long)]
+        a: PathBuf,
+        /// Path to the second proof bundle.
+        #[arg(long)]
+        b: PathBuf,
+    },
+    /// Sign a proof bundle as a committee member and add it to a
+    /// multi-operator manifest, creating the manifest if it does not exist.
+    ManifestAdd {
+        /// Path to the manifest.
+        #[arg(long)]
+        manifest: PathBuf,
+        /// Path to the proof bundle, recorded relative to the manifest.
+        #[arg(long)]
+        bundle: PathBuf,
+        /// File holding the operator's hex-encoded BIP340 secret key.
+        #[arg(long)]
+        key_file: PathBuf,
+        /// Hex-encoded x-only public key of a committee member; may be
+        /// repeated. Required when creating the manifest.
+        #[arg(long = "committee")]
+        committee: Vec<String>,
+        /// Number of members needed for a quorum. Required when creating the
+        /// manifest.
+        #[arg(long)]
+        threshold: Option<usize>,
+        /// Hex-encoded image ID the bundles are proven for, when creating the
+        /// manifest. Defaults to the image ID of the guest built into this
+        /// binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+    },
+    /// Verify every entry of a multi-operator manifest, print the report as
+    /// JSON and fail unless a quorum of the committee proved the claim.
+    ManifestVerify {
+        /// Path to the manifest.
+        manifest: PathBuf,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Announce a proof bundle on a gossip transport so that challengers can
+    /// find it, and print the announcement as JSON.
+    Announce {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// Path to the gossip TOML configuration.
+        #[arg(long)]
+        config: PathBuf,
+    },
+    /// Add a proof bundle to IPFS through a node's HTTP RPC API, pinned
+    /// there, and print its CID; announce it with that CID if a gossip
+    /// configuration is given.
+    #[cfg(feature = "experimental")]
+    PublishIpfs {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// HTTP RPC API of the IPFS node.
+        #[arg(long, default_value = ipfs::DEFAULT_API_URL)]
+        api_url: String,
+        /// Add the bundle without pinning it.
+        #[arg(long)]
+        no_pin: bool,
+        /// Path to a gossip TOML configuration to announce the bundle with.
+        #[arg(long)]
+        gossip: Option<PathBuf>,
+    },
+    /// Fetch a proof bundle from IPFS by its CID, or by the CID of a gossip
+    /// announcement, verify it and write it to a file.
+    #[cfg(feature = "experimental")]
+    FetchIpfs {
+        /// CID of the bundle.
+        #[arg(required_unless_present = "announcement")]
+        cid: Option<String>,
+        /// JSON file holding an announcement of the bundle, whose digest and
+        /// size the bundle is also checked against.
+        #[arg(long, conflicts_with = "cid")]
+        announcement: Option<PathBuf>,
+        /// HTTP RPC API of the IPFS node.
+        #[arg(long, default_value = ipfs::DEFAULT_API_URL)]
+        api_url: String,
+        /// Hex-encoded image ID the receipt must be produced for. Defaults
+        /// to the image ID of the guest built into this binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+        /// Pin the bundle on the node once verified.
+        #[arg(long)]
+        pin: bool,
+        /// Where to write the bundle.
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Verify a bare Groth16 seal against a public input, such as those sent
+    /// in a callback notification.
+    VerifySeal {
+        /// Hex-encoded 256-byte seal.
+        #[arg(long)]
+        seal: String,
+        /// Hex-encoded big-endian public input of at most 32 bytes.
+        #[arg(long)]
+        public_input: String,
+        /// What to do with a public input at or above the BN254 scalar field
+        /// modulus: `reject` or `reduce`.
+        #[arg(long, default_value_t)]
+        scalar_policy: ScalarPolicy,
+    },
+    /// Verify the seal and public input carried by an assert transaction,
+    /// fetched by its ID.
+    VerifyFromTx {
+        /// ID of the assert transaction.
+        txid: bitcoin::Txid,
+        #[command(flatten)]
+        bitcoin: BitcoinArgs,
+        /// Confirmations the assert transaction needs before it is read;
+        /// `0` also reads it unconfirmed.
+        #[arg(long, default_value_t = bitcoin_rpc::DEFAULT_CONFIRMATIONS)]
+        confirmations: u64,
+        /// Input carrying the verifier witness. Defaults to the first that
+        /// carries one.
+        #[arg(long)]
+        input: Option<usize>,
+        /// Also check that the asserted public input is the one a proof
+        /// bundle's journal derives.
+        #[arg(long)]
+        bundle: Option<PathBuf>,
+        /// Hex-encoded image ID the bundle was proven for. Defaults to the
+        /// image ID of the guest built into this binary.
+        #[arg(long, value_parser = parse_digest, requires = "bundle")]
+        image_id: Option<Digest>,
+    },
+    /// Build the commit and reveal transactions asserting a proof bundle's
+    /// verifier witness as PSBTs for the operator to sign, and write them as
+    /// JSON.
+    AssertPsbt {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// The operator's key, whose P2TR address funds the package: a hex
+        /// public key, or a `tr()` descriptor or key expression with the
+        /// origin an external signer derives it from, e.g.
+        /// `[d34db33f/86'/0'/0']xpub…/0/0`.
+        #[arg(long)]
+        operator: OperatorKey,
+        /// Output of the operator's P2TR address to fund the package with, as
+        /// `txid:vout`; may be repeated.
+        #[arg(long = "funding", required = true)]
+        funding: Vec<bitcoin::OutPoint>,
+        /// Address receiving the change of the commit transaction.
+        #[arg(long)]
+        change_address: bitcoin::Address<NetworkUnchecked>,
+        /// Address receiving the output of the reveal transaction. Defaults
+        /// to the change address.
+        #[arg(long)]
+        destination_address: Option<bitcoin::Address<NetworkUnchecked>>,
+        /// Value of the reveal transaction's output, in satoshis.
+        #[arg(long, default_value_t = assert_psbt::DEFAULT_REVEAL_VALUE.to_sat())]
+        reveal_value: u64,
+        /// Network the addresses are of.
+        #[arg(long, default_value_t = bitcoin::Network::Bitcoin)]
+        network: bitcoin::Network,
+        #[command(flatten)]
+        fee: FeeArgs,
+        /// Do not signal replace-by-fee, so that the package cannot be
+        /// bumped.
+        #[arg(long)]
+        no_rbf: bool,
+        /// Hex-encoded image ID the receipt was produced for. Defaults to the
+        /// image ID of the guest built into this binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+        /// File to write the package to.
+        #[arg(long)]
+        out: PathBuf,
+        #[command(flatten)]
+        bitcoin: BitcoinArgs,
+    },
+    /// Merge the PSBTs of an assert package as signed externally, finalize
+    /// its transactions, and print them as hex or broadcast them.
+    FinalizeAssert {
+        /// Path to the package.
+        package: PathBuf,
+        /// File of a signed commit or reveal PSBT, in base64 or binary; may
+        /// be repeated.
+        #[arg(long = "signed")]
+        signed: Vec<PathBuf>,
+        /// Broadcast the commit and then the reveal transaction.
+        #[arg(long)]
+        broadcast: bool,
+        /// ID of the claim the package asserts, recorded as committed in
+        /// `--store` once broadcast.
+        #[arg(long, requires_all = ["broadcast", "store"])]
+        claim: Option<String>,
+        #[command(flatten)]
+        store: ClaimStoreArgs,
+        #[command(flatten)]
+        hwi: HwiArgs,
+        #[command(flatten)]
+        bitcoin: BitcoinArgs,
+    },
+    /// Re-derive the public input of a claim and check the operator's
+    /// commitments on chain against it, printing the first inconsistent one
+    /// as JSON, with the witness disproving it given the connector's script
+    /// tree. Exits with an error if the assert is fraudulent.
+    DetectFraud {
+        /// ID of the assert transaction.
+        #[arg(long)]
+        assert_tx: bitcoin::Txid,
+        /// Confirmations the assert transaction needs before it is read;
+        /// `0` also reads it unconfirmed.
+        #[arg(long, default_value_t = bitcoin_rpc::DEFAULT_CONFIRMATIONS)]
+        confirmations: u64,
+        /// Proof bundle whose journal is the claim's.
+        #[arg(long, required_unless_present = "journal", conflicts_with = "journal")]
+        bundle: Option<PathBuf>,
+        /// File of the claim's journal, as published.
+        #[arg(long)]
+        journal: Option<PathBuf>,
+        /// Hex-encoded image ID of the claim. Defaults to the image ID of the
+        /// guest built into this binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+        #[command(flatten)]
+        commitments: CommitmentArgs,
+        /// JSON script tree of the connector output, as for
+        /// `disprove-witness`.
+        #[arg(long)]
+        tree: Option<PathBuf>,
+        #[command(flatten)]
+        bitcoin: BitcoinArgs,
+    },
+    /// Assemble the witness disproving a fraudulent assert through a disprove
+    /// leaf of its connector output, and print it as JSON, with the
+    /// transaction spending the connector if one is given.
+    DisproveWitness {
+        /// JSON script tree of the connector output: its internal key and
+        /// its leaves, each with its depth, script and fraud.
+        #[arg(long)]
+        tree: PathBuf,
+        /// ID of the assert transaction.
+        #[arg(long)]
+        assert_tx: bitcoin::Txid,
+        /// Confirmations the assert transaction needs before it is read;
+        /// `0` also reads it unconfirmed.
+        #[arg(long, default_value_t = bitcoin_rpc::DEFAULT_CONFIRMATIONS)]
+        confirmations: u64,
+        /// The fraud to disprove: `prefix`, `journal-hash`, `public-input` or
+        /// `proof-chunk:<index>`.
+        #[arg(long)]
+        fraud: Fraud,
+        #[command(flatten)]
+        commitments: CommitmentArgs,
+        /// Connector output to spend, as `txid:vout`.
+        #[arg(long, requires = "payout_address")]
+        connector: Option<bitcoin::OutPoint>,
+        /// Address receiving what the connector output leaves after the fee.
+        #[arg(long, requires = "connector")]
+        payout_address: Option<bitcoin::Address<NetworkUnchecked>>,
+        /// Network the payout address is of.
+        #[arg(long, default_value_t = bitcoin::Network::Bitcoin)]
+        network: bitcoin::Network,
+        #[command(flatten)]
+        fee: FeeArgs,
+        #[command(flatten)]
+        bitcoin: BitcoinArgs,
+    },
+    /// Rebuild an assert package written by `assert-psbt` at a higher fee
+    /// rate, to replace its transactions stuck in the mempool.
+    BumpAssert {
+        /// Path to the package.
+        package: PathBuf,
+        #[command(flatten)]
+        fee: FeeArgs,
+        /// The commit transaction is confirmed: bump the reveal transaction
+        /// alone, paying from its output.
+        #[arg(long)]
+        commit_confirmed: bool,
+        /// File to write the bumped package to. Defaults to replacing the
+        /// package.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        bitcoin: BitcoinArgs,
+    },
+    /// Print the `(seal, imageId, journalDigest)` arguments of risc0's
+    /// on-chain `RiscZeroVerifier` for a receipt, as JSON.
+    ExportEvm {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// Hex-encoded image ID the receipt was produced for. Defaults to the
+        /// image ID of the guest built into this binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+        /// Rewrite a seal with non-canonical field elements into its canonical
+        /// encoding instead of rejecting it.
+        #[arg(long)]
+        normalize_seal: bool,
+    },
+    /// Write the succinct STARK receipt kept in a proof bundle, bincode-encoded
+    /// as risc0 reads receipts, once it verifies, so that it can be checked
+    /// without the Groth16 trusted setup.
+    ExportStark {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// File to write the receipt to.
+        #[arg(long)]
+        out: PathBuf,
+        /// Hex-encoded image ID the receipt was produced for. Defaults to the
+        /// image ID of the guest built into this binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+    },
+    /// Wrap a whole proof bundle in an ordinals-style inscription envelope
+    /// and print it as JSON, with the tapleaf revealing it and the address
+    /// committing to that leaf if a key is given.
+    #[cfg(feature = "experimental")]
+    ExportInscription {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// Hex-encoded image ID the receipt was produced for. Defaults to the
+        /// image ID of the guest built into this binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+        /// X-only public key signing the reveal.
+        #[arg(long)]
+        key: Option<bitcoin::XOnlyPublicKey>,
+        /// Network of the commit address.
+        #[arg(long, default_value_t = bitcoin::Network::Bitcoin, requires = "key")]
+        network: bitcoin::Network,
+    },
+    /// Split a receipt's verifier witness, the seal followed by the public
+    /// input, into chunks and print them with reassembly metadata as JSON.
+    SplitWitness {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// Largest chunk, in bytes.
+        #[arg(
+            long,
+            default_value_t = chunks::MAX_SCRIPT_ELEMENT_LEN,
+            conflicts_with = "profile"
+        )]
+        max_chunk_len: usize,
+        /// Hex-encoded image ID the receipt was produced for. Defaults to the
+        /// image ID of the guest built into this binary, or the first of the
+        /// profile's.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+        /// Split the witness under the verifying key, public input binding and
+        /// chunk size of a named profile instead of the built-in ones.
+        #[command(flatten)]
+        profile: ProfileArgs,
+    },
+    /// Check every section of a proof bundle against the checksums recorded
+    /// in it and report which are corrupt, then decode it.
+    BundleFsck {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+    },
+    /// Submit the digest of a proof bundle to OpenTimestamps calendars and
+    /// record their timestamps in the bundle, or upgrade the timestamps it
+    /// records once the calendars have anchored them in Bitcoin.
+    Timestamp {
+        /// Path to the proof bundle, rewritten in place.
+        bundle: PathBuf,
+        /// Calendar to submit to; may be repeated.
+        #[arg(long = "calendar", default_values = timestamp::DEFAULT_CALENDARS)]
+        calendars: Vec<String>,
+        /// Upgrade the pending attestations of the recorded timestamps
+        /// instead of submitting again.
+        #[arg(long, conflicts_with = "calendars")]
+        upgrade: bool,
+    },
+    /// Publish the succinct or composite receipt a proof bundle's Groth16
+    /// receipt was compressed from to a data availability layer, and record
+    /// where in the bundle.
+    PublishDa {
+        /// Path to the proof bundle, rewritten in place.
+        bundle: PathBuf,
+        /// Path to the bincode-encoded receipt to publish.
+        #[arg(long)]
+        receipt: PathBuf,
+        /// TOML file choosing the layer.
+        #[arg(long, default_value = "da.toml")]
+        config: PathBuf,
+    },
+    /// Fetch the receipts a proof bundle records as published to a data
+    /// availability layer, checking each against its recorded digest.
+    FetchDa {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// TOML file choosing the layer.
+        #[arg(long, default_value = "da.toml")]
+        config: PathBuf,
+        /// Directory to write the fetched receipts to, named by their
+        /// digest. If unset, they are only checked.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Print a verifier profile in the form of the profile file, as JSON.
+    ShowProfile {
+        /// Name of the profile.
+        #[arg(default_value = profile::BUILTIN)]
+        name: String,
+        /// TOML file of verifier profiles. Defaults to the `R0_BITVM_PROFILES`
+        /// environment variable.
+        #[arg(long)]
+        profiles: Option<PathBuf>,
+    },
+    /// Fetch the verifying key of a risc0 release, check it against the
+    /// SHA-256 digest given for it and write it into a profile.
+    FetchVk {
+        /// risc0 release the key is fetched for, e.g. `2.0.2`.
+        #[arg(long)]
+        risc0_version: String,
+        /// Profile the key is written to. Defaults to `risc0-<version>`.
+        #[arg(long)]
+        profile: Option<String>,
+        /// TOML file of verifier profiles. Defaults to the `R0_BITVM_PROFILES`
+        /// environment variable.
+        #[arg(long)]
+        profiles: Option<PathBuf>,
+        /// URL of the key in snarkjs' JSON form, with `{version}` standing for
+        /// the release.
+        #[arg(long, default_value = fetch_vk::DEFAULT_URL)]
+        url: String,
+        /// Hex-encoded SHA-256 digest of the key, as published in the release
+        /// notes. Required: a digest fetched from the host serving the key
+        /// proves nothing.
+        #[arg(long, value_parser = parse_hash)]
+        sha256: [u8; 32],
+    },
+    /// Reassemble chunks printed by `split-witness` and print the witness as
+    /// hex.
+    JoinWitness {
+        /// JSON file of the chunks.
+        chunks: PathBuf,
+    },
+    /// Print the input signals of a circom Groth16 verifier for a receipt's
+    /// proof, as JSON.
+    ExportCircom {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// Bits per limb of each field element.
+        #[arg(long, default_value_t = export::CIRCOM_LIMB_BITS)]
+        limb_bits: u32,
+        /// Number of limbs per field element.
+        #[arg(long, default_value_t = export::CIRCOM_LIMBS)]
+        limbs: usize,
+        /// Hex-encoded image ID the receipt was produced for. Defaults to the
+        /// image ID of the guest built into this binary, or the first of the
+        /// profile's.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+        /// Export under the verifying key and public input binding of a named
+        /// profile, such as one of a circuit binding the journal with SHA-256.
+        #[command(flatten)]
+        profile: ProfileArgs,
+    },
+    /// Write a receipt's proof and public witness in gnark's binary format.
+    ExportGnark {
+        /// Path to the proof bundle.
+        bundle: PathBuf,
+        /// File to write the `groth16.Proof` to.
+        #[arg(long)]
+        proof: PathBuf,
+        /// File to write the public `witness.Witness` to.
+        #[arg(long)]
+        public_witness: PathBuf,
+        /// Hex-encoded image ID the receipt was produced for. Defaults to the
+        /// image ID of the guest built into this binary, or the first of the
+        /// profile's.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+        /// Export under the verifying key and public input binding of a named
+        /// profile, such as one of a circuit binding the journal with SHA-256.
+        #[command(flatten)]
+        profile: ProfileArgs,
+    },
+    /// Build the guest reproducibly in risc0's docker builder and print its
+    /// image ID.
+    BuildGuest {
+        /// Root of this repository, used as the docker build context.
+        #[arg(long, default_value = ".")]
+        root: PathBuf,
+        /// File to write the image ID and ELF path to, as JSON.
+        #[arg(long)]
+        record: Option<PathBuf>,
+    },
+    /// Print each intermediate value of the public input derivation of a
+    /// receipt, from the succinct control root to the BN254 scalar, without
+    /// verifying it.
+    ExplainInput {
+        /// Path to the proof bundle, or to a bare bincode-encoded receipt.
+        #[arg(long)]
+        receipt: PathBuf,
+        /// Hex-encoded image ID to derive the public input for. Defaults to
+        /// the image ID of the guest built into this binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+    },
+    /// Print the journal of a receipt without verifying it.
+    Journal {
+        /// Path to the proof bundle, or to a bare bincode-encoded receipt.
+        #[arg(long)]
+        receipt: PathBuf,
+        /// How to print the journal.
+        #[arg(long = "as", value_enum, default_value_t = JournalFormat::Hex)]
+        format: JournalFormat,
+    },
+    /// Print the commitments of two key-value maps and the changes between them.
+    /// Each map is a JSON object of hex-encoded keys and values.
+    KvDiff { old: PathBuf, new: PathBuf },
+    /// Disclose some of a set of committed fields, printing them with the
+    /// proofs of their place in the committed tree as JSON.
+    Disclose {
+        /// JSON object of field names and hex-encoded values, as proven in
+        /// `fields` mode.
+        fields: PathBuf,
+        /// Name of a field to disclose; may be repeated.
+        #[arg(long = "reveal", required = true)]
+        names: Vec<String>,
+    },
+    /// Check fields printed by `disclose` against the fields committed by a
+    /// verified receipt, and print them.
+    CheckDisclosure {
+        /// Path to the proof bundle, or to a bare bincode-encoded receipt.
+        #[arg(long)]
+        receipt: PathBuf,
+        /// JSON disclosure printed by `disclose`.
+        disclosure: PathBuf,
+        /// Hex-encoded image ID the receipt was produced for. Defaults to the
+        /// image ID of the guest built into this binary.
+        #[arg(long, value_parser = parse_digest)]
+        image_id: Option<Digest>,
+    },
+    /// Write the BLAKE3 outboard of an input, against which consumers can
+    /// verify downloaded ranges of it, and print its root.
+    Outboard {
+        #[command(flatten)]
+        input: InputArgs,
+        /// Log2 of the block size in KiB: 0 for bao, 4 for Iroh.
+        #[arg(long, default_value_t = 0)]
+        block_log: u8,
+        /// Derive-key context string personalizing the hash.
+        #[arg(long)]
+        context: Option<String>,
+        /// File to write the outboard to.
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Prove each file that appears in a directory and store its bundle.
+    Watch {
+        /// Directory to take inputs from.
+        #[arg(long)]
+        dir: PathBuf,
+        /// Storage URI to write proof bundles to: a directory or
+        /// `s3://<bucket>/<prefix>`.
+        #[arg(long)]
+        out: String,
+        /// Where proven inputs are moved. Defaults to `<dir>/processed`.
+        #[arg(long)]
+        processed: Option<PathBuf>,
+        /// Key to write each bundle under, as for `prove --layout`.
+        #[arg(long, default_value_t)]
+        layout: Layout,
+        /// File holding a hex-encoded key to encrypt stored bundles with.
+        /// Defaults to the `R0_BITVM_STORAGE_KEY` environment variable.
+        #[arg(long)]
+        encryption_key_file: Option<PathBuf>,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    /// Prove requests taken from a NATS subject and publish their bundles.
+    Consume {
+        /// NATS server, as `nats://[user:password@|token@]host[:port]`.
+        #[arg(long, default_value = "nats://127.0.0.1:4222")]
+        url: String,
+        /// Subject prove requests are published to.
+        #[arg(long, default_value = "prove.requests")]
+        subject: String,
+        /// Subject to publish proof bundles to.
+        #[arg(long, default_value = "prove.results")]
+        results: String,
+        /// Queue group shared by all workers consuming the subject.
+        #[arg(long, default_value = "provers")]
+        queue_group: String,
+        /// Largest journal accepted, in bytes.
+        #[arg(long, default_value_t = verifier::DEFAULT_MAX_JOURNAL_LEN)]
+        max_journal_len: usize,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    /// Run the multi-tenant HTTP proving service.
+    Serve {
+        /// Path to the server TOML configuration.
+        #[arg(long, default_value = "server.toml")]
+        config: PathBuf,
+    },
+    /// Watch the chain for challenges to the configured claims and answer
+    /// them, reporting each to a webhook.
+    Monitor {
+        /// Path to the monitor TOML configuration.
+        #[arg(long, default_value = "monitor.toml")]
+        config: PathBuf,
+    },
+    /// Prove like `prove`, sharding the segments of the session across
+    /// proving farm workers and joining their receipts here.
+    Coordinator {
+        /// Base URL of a worker, e.g. `http://10.0.0.2:8090`; may be repeated.
+        #[arg(long = "worker", required = true)]
+        workers: Vec<String>,
+        #[command(flatten)]
+        prove: ProveArgs,
+    },
+    /// Serve segment proving requests from a coordinator.
+    Worker {
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8090")]
+        listen: SocketAddr,
+    },
+    /// Send concurrent requests to a running `serve` instance and print
+    /// their latency percentiles as JSON.
+    Loadtest {
+        /// Base URL of the server.
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        url: String,
+        /// API key of the tenant the requests are sent as.
+        #[arg(long)]
+        api_key: String,
+        /// Whether each request verifies a bundle or proves an input.
+        #[arg(long, value_enum, default_value_t = LoadWorkload::Verify)]
+        workload: LoadWorkload,
+        /// Proof bundle sent by every verify request.
+        #[arg(long, required_if_eq("workload", "verify"))]
+        bundle: Option<PathBuf>,
+        /// Length of the random input of every prove request, in bytes.
+        #[arg(long, default_value_t = 32)]
+        input_len: usize,
+        /// Number of requests in flight at once.
+        #[arg(long, default_value_t = 8)]
+        concurrency: usize,
+        /// Total number of requests sent.
+        #[arg(long, default_value_t = 100)]
+        requests: usize,
+    },
+}
 
-        let groth_proof = receipt.inner.groth16().expect("Not a groth16 proof!!!");
-        let ark_proof = from_seal(&groth_proof.seal.as_slice());
-        let public_input_scalar =
-            ark_bn254::Fr::from_be_bytes_mod_order(&expected_output_bytes);
+#[derive(Args, Default)]
+struct ProveArgs {
+    #[command(flatten)]
+    input: InputArgs,
+    /// JSON input document of any guest mode, in place of the input and
+    /// padding flags.
+    #[arg(long, conflicts_with_all = ["input_hex", "input_file", "input_str", "padding"])]
+    input_json: Option<PathBuf>,
+    /// Also commit the BLAKE3 digest of the whole input, as the guest reads
+    /// it, so that the proof can be bound to an input that stays private.
+    #[arg(long)]
+    bind_input: bool,
+    /// Also commit the number of bytes and frames the guest read, which is
+    /// checked against the input once proven.
+    #[arg(long)]
+    with_stats: bool,
+    /// Also commit the journal schema and modes of the guest, so that
+    /// verifiers can reject journals of guest revisions they do not read.
+    #[arg(long)]
+    with_version: bool,
+    /// Hex-encoded 32-byte nonce to send with the input and commit, such as
+    /// one derived from the bridge event the proof is for, so that the proof
+    /// cannot be replayed for another event.
+    #[arg(long, value_parser = parse_hash)]
+    nonce: Option<Nonce>,
+    /// Largest frame the input is sent to the guest in, and so the size of
+    /// its read buffer: a power of two from 1024 to 65536 bytes.
+    #[arg(long)]
+    frame_len: Option<usize>,
+    /// URL to POST the finished proof to.
+    #[arg(long)]
+    callback_url: Option<String>,
+    /// Storage URI to write the proof bundle to: a directory or
+    /// `s3://<bucket>/<prefix>`.
+    #[arg(long)]
+    out: Option<String>,
+    /// Key to write the bundle under, with `{image_id}`, `{input_hash}` and
+    /// `{bundle_hash}` filled in.
+    #[arg(long, default_value_t)]
+    layout: Layout,
+    /// File holding a hex-encoded key to encrypt the stored bundle with.
+    /// Defaults to the `R0_BITVM_STORAGE_KEY` environment variable.
+    #[arg(long, requires = "out")]
+    encryption_key_file: Option<PathBuf>,
+    /// Padding applied before hashing: `none`, `length-suffixed` or
+    /// `zero-pad:<log2>`.
+    #[arg(long, default_value_t)]
+    padding: Padding,
+    /// TOML cost model to estimate the price of the proof with.
+    #[arg(long)]
+    cost_model: Option<PathBuf>,
+    /// Number identifying the run, recorded in the bundle with the other
+    /// parameters of the run. It does not make the proof reproducible.
+    #[arg(long)]
+    run_id: Option<u64>,
+    /// Submit the digest of the bundle to OpenTimestamps calendars before it
+    /// is stored, and record their timestamps in it.
+    #[arg(long, requires = "out")]
+    timestamp: bool,
+    /// Calendar to submit to with `--timestamp`; may be repeated.
+    #[arg(
+        long = "calendar",
+        requires = "timestamp",
+        default_values = timestamp::DEFAULT_CALENDARS
+    )]
+    calendars: Vec<String>,
+    #[command(flatten)]
+    limits: LimitArgs,
+}
 
-        let ark_vk = get_ark_verifying_key();
-        let ark_pvk = ark_groth16::prepare_verifying_key(&ark_vk);
+#[derive(Args)]
+struct VerifyArgs {
+    /// Path to the proof bundle.
+    bundle: PathBuf,
+    /// Hex-encoded image ID to accept; may be repeated. Defaults to the image ID
+    /// of the guest built into this binary.
+    #[arg(long = "image-id", value_parser = parse_digest, conflicts_with_all = ["allowlist", "profile"])]
+    image_ids: Vec<Digest>,
+    /// TOML allowlist of image IDs and the block heights each is valid for.
+    #[arg(long, requires = "height", conflicts_with = "profile")]
+    allowlist: Option<PathBuf>,
+    /// Block height the receipt is checked at against `--allowlist`.
+    #[arg(long, requires = "allowlist")]
+    height: Option<u64>,
+    /// Also verify the seal with risc0's own Groth16 verifier, and fail if it
+    /// disagrees with the arkworks verifier.
+    #[arg(long, conflicts_with = "profile")]
+    cross_check: bool,
+    /// Rewrite a seal with non-canonical field elements into its canonical
+    /// encoding before verifying it, instead of rejecting it.
+    #[arg(long)]
+    normalize_seal: bool,
+    /// Receipts of the bundle to verify: `groth16`, the receipt it carries;
+    /// `stark`, only the succinct STARK receipt that receipt wraps, kept with
+    /// `prove --keep-stark`, with risc0's verifier and without the Groth16
+    /// trusted setup; or `both`.
+    #[arg(long, default_value_t)]
+    verify_mode: VerifyMode,
+    /// Shorthand for `--verify-mode both`.
+    #[arg(long, conflicts_with = "verify_mode")]
+    stark: bool,
+    /// Largest journal accepted, in bytes.
+    #[arg(
+        long,
+        default_value_t = verifier::DEFAULT_MAX_JOURNAL_LEN,
+        conflicts_with = "profile"
+    )]
+    max_journal_len: usize,
+    /// Verify under every parameter of a named profile instead of the flags
+    /// above. `--cross-check` is not supported with it.
+    #[command(flatten)]
+    profile: ProfileArgs,
+    #[command(flatten)]
+    audit: AuditArgs,
+    #[command(flatten)]
+    policy: PolicyArgs,
+    /// Fail unless the journal commits to this hex-encoded nonce, the one
+    /// sent with the request the proof answers.
+    #[arg(long, value_parser = parse_hash)]
+    expect_nonce: Option<Nonce>,
+    /// Record the nonce the journal commits to in the store given by
+    /// `--store`, and fail if a proof was already accepted for it.
+    #[arg(long, requires = "store")]
+    register_nonce: bool,
+    #[command(flatten)]
+    store: ClaimStoreArgs,
+    /// Check each Bitcoin attestation of the bundle's timestamp against the
+    /// block header at its height, read from the Bitcoin backend below.
+    /// Without it, attestations are printed as unverified.
+    #[arg(long)]
+    check_timestamp: bool,
+    #[command(flatten)]
+    bitcoin: BitcoinArgs,
+    /// File to write a JSON report of the verification to, with the
+    /// parameters, digests, stage timings, verdict and software versions,
+    /// whether or not the bundle verifies.
+    #[arg(long)]
+    report: Option<PathBuf>,
+}
 
-        let res = ark_groth16::Groth16::<ark_bn254::Bn254>::verify_proof(
-            &ark_pvk,
-            &ark_proof,
-            &[public_input_scalar],
-        )
-        .unwrap();
+/// The store claim lifecycles and accepted nonces are recorded in.
+#[derive(Args)]
+struct ClaimStoreArgs {
+    /// Storage URI of the receipt store: a directory or
+    /// `s3://<bucket>/<prefix>`.
+    #[arg(long = "store", id = "store")]
+    uri: Option<String>,
+    /// File holding the hex-encoded key the store is encrypted with.
+    /// Defaults to the `R0_BITVM_STORAGE_KEY` environment variable.
+    #[arg(long = "store-key-file", requires = "store")]
+    key_file: Option<PathBuf>,
+}
 
-        assert!(res, "proof verification failed for receipt");
+impl ClaimStoreArgs {
+    fn open(&self) -> Result<Box<dyn storage::Store>> {
+        let uri = self.uri.as_deref().context("--store is required")?;
+        let key = storage::load_encryption_key(self.key_file.as_deref())?;
+        Ok(storage::with_encryption(storage::open(uri)?, key))
     }
 }
 
-pub fn calculate_succinct_output_prefix(method_id: &[u8]) -> [u8; 32] {
-    let succinct_verifier_params = risc0_zkvm::SuccinctReceiptVerifierParameters::default();
-    let succinct_control_root = succinct_verifier_params.control_root;
-    let mut succinct_control_root_bytes: [u8; 32] =
-        succinct_control_root.as_bytes().try_into().unwrap();
-    for byte in succinct_control_root_bytes.iter_mut() {
-        *byte = byte.reverse_bits();
+#[derive(Args)]
+struct AuditArgs {
+    /// File to append a signed JSON line to for every bundle checked.
+    #[arg(long, requires = "audit_key_file")]
+    audit_log: Option<PathBuf>,
+    /// File holding the hex-encoded BIP340 secret key audit lines are signed
+    /// with.
+    #[arg(long, requires = "audit_log")]
+    audit_key_file: Option<PathBuf>,
+}
+
+impl AuditArgs {
+    fn open(&self) -> Result<Option<AuditLog>> {
+        match (&self.audit_log, &self.audit_key_file) {
+            (Some(path), Some(key_file)) => Ok(Some(AuditLog::open(
+                path,
+                manifest::load_signing_key(key_file)?,
+            )?)),
+            _ => Ok(None),
+        }
     }
-    let pre_state_bytes = method_id.to_vec();
-    let control_id_bytes: [u8; 32] =
-        risc0_circuit_recursion::control_id::BN254_IDENTITY_CONTROL_ID.into();
-
-    // Expected post state for an execution that halted successfully
-    let post_state = risc0_zkvm::SystemState {
-        pc: 0,
-        merkle_root: Risc0Digest::default(),
-    };
-    let post_state_bytes: [u8; 32] = post_state.digest::<risc0_zkvm::sha::Impl>().into();
-
-    let mut hasher = Sha256::new();
-    hasher.update(&succinct_control_root_bytes);
-    hasher.update(&pre_state_bytes);
-    hasher.update(&post_state_bytes);
-    hasher.update(&control_id_bytes);
-    let result: [u8; 32] = hasher
-        .finalize()
+}
+
+/// The Bitcoin backend to read chain data from and broadcast to: an Esplora
+/// API, or a bitcoind node given `--bitcoind-url`.
+#[derive(Args)]
+struct BitcoinArgs {
+    /// Esplora API to use.
+    #[arg(long, default_value = DEFAULT_ESPLORA_URL, conflicts_with = "bitcoind_url")]
+    esplora_url: String,
+    /// bitcoind JSON-RPC endpoint to use instead, e.g. `http://127.0.0.1:8332`.
+    #[arg(long)]
+    bitcoind_url: Option<String>,
+    /// bitcoind's `.cookie` file to authenticate with.
+    #[arg(long, requires = "bitcoind_url", conflicts_with = "rpc_user")]
+    rpc_cookie_file: Option<PathBuf>,
+    /// RPC user to authenticate with.
+    #[arg(long, requires = "bitcoind_url")]
+    rpc_user: Option<String>,
+    /// RPC password of `--rpc-user`.
+    #[arg(long, requires = "rpc_user")]
+    rpc_password: Option<String>,
+}
+
+impl BitcoinArgs {
+    fn config(&self) -> BitcoinConfig {
+        match &self.bitcoind_url {
+            Some(url) => BitcoinConfig::Bitcoind {
+                url: url.clone(),
+                cookie_file: self.rpc_cookie_file.clone(),
+                user: self.rpc_user.clone(),
+                password: self.rpc_password.clone(),
+            },
+            None => BitcoinConfig::Esplora {
+                url: self.esplora_url.clone(),
+            },
+        }
+    }
+}
+
+/// Intermediate values of the public input derivation the operator
+/// committed besides its assert, as read from its BitVM assert transactions.
+#[derive(Args)]
+struct CommitmentArgs {
+    /// Hex-encoded succinct output prefix the operator committed.
+    #[arg(long, value_parser = parse_hash)]
+    committed_prefix: Option<[u8; 32]>,
+    /// Hex-encoded journal hash the operator committed.
+    #[arg(long, value_parser = parse_hash)]
+    committed_journal_hash: Option<[u8; 32]>,
+}
+
+impl CommitmentArgs {
+    fn with(&self, assert: AssertWitness) -> Commitments {
+        Commitments {
+            assert,
+            prefix: self.committed_prefix,
+            journal_hash: self.committed_journal_hash,
+        }
+    }
+}
+
+/// Signing on a hardware wallet through HWI.
+#[derive(Args)]
+struct HwiArgs {
+    /// Sign the package on a hardware wallet through HWI, approving each
+    /// transaction on the device.
+    #[arg(long)]
+    hwi: bool,
+    /// HWI program to run.
+    #[arg(long, default_value = hwi::DEFAULT_PROGRAM, requires = "hwi")]
+    hwi_program: PathBuf,
+    /// Master key fingerprint of the device to sign with. Defaults to the
+    /// one in the operator key's origin, or the only device connected.
+    #[arg(long, requires = "hwi")]
+    hwi_fingerprint: Option<bitcoin::bip32::Fingerprint>,
+    /// Network the device signs for.
+    #[arg(long, default_value_t = bitcoin::Network::Bitcoin)]
+    network: bitcoin::Network,
+}
+
+/// The fee rate to pay: given, or estimated by the Bitcoin backend.
+#[derive(Args)]
+struct FeeArgs {
+    /// Fee rate in sat/vB. Defaults to the backend's estimate for
+    /// `--target-blocks`.
+    #[arg(long, conflicts_with = "target_blocks")]
+    fee_rate: Option<f64>,
+    /// Blocks to confirm within, for the fee estimate.
+    #[arg(long, default_value_t = 6)]
+    target_blocks: u16,
+}
+
+impl FeeArgs {
+    fn fee_rate(&self, bitcoin: &BitcoinArgs) -> Result<bitcoin::FeeRate> {
+        match self.fee_rate {
+            Some(sat_per_vb) => Ok(bitcoin_rpc::sat_per_vb_rate(sat_per_vb)),
+            None => bitcoin.config().connect()?.fee_rate(self.target_blocks),
+        }
+    }
+}
+
+#[derive(Args)]
+struct PolicyArgs {
+    /// TOML verifier policy of denied image IDs and operators, maximum proof
+    /// age and required metadata, checked before verifying.
+    #[arg(long)]
+    policy: Option<PathBuf>,
+}
+
+impl PolicyArgs {
+    fn load(&self) -> Result<VerifierPolicy> {
+        self.policy
+            .as_deref()
+            .map_or(Ok(VerifierPolicy::default()), VerifierPolicy::load)
+    }
+}
+
+#[derive(Args)]
+struct ProfileArgs {
+    /// Named verifier profile to verify under; `local-dev` is built in.
+    #[arg(long)]
+    profile: Option<String>,
+    /// TOML file of verifier profiles. Defaults to the `R0_BITVM_PROFILES`
+    /// environment variable.
+    #[arg(long, requires = "profile")]
+    profiles: Option<PathBuf>,
+}
+
+impl ProfileArgs {
+    /// The selected profile, or the built-in one accepting `image_ids` and
+    /// journals of up to `max_journal_len` bytes when none is selected.
+    fn load(&self, image_ids: Vec<Digest>, max_journal_len: usize) -> Result<Profile> {
+        let Some(name) = &self.profile else {
+            let mut profile = Profile::builtin();
+            if !image_ids.is_empty() {
+                profile.image_ids = image_ids;
+            }
+            profile.max_journal_len = max_journal_len;
+            return Ok(profile);
+        };
+        Profile::load(name, self.profiles.as_deref())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LoadWorkload {
+    /// POST a cached proof bundle to `/verify`.
+    Verify,
+    /// Submit a random input to `/jobs` and wait for it to be proven.
+    Prove,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum JournalFormat {
+    /// The raw journal bytes.
+    Hex,
+    /// The little-endian words of the journal, one per line.
+    U32,
+    /// The SHA-256 journal digest committed to by the receipt claim.
+    Digest,
+    /// The guest output decoded by mode.
+    Json,
+}
+
+fn decode_hex(encoded: &str) -> Result<Vec<u8>, hex::FromHexError> {
+    let encoded = encoded.trim();
+    hex::decode(encoded.strip_prefix("0x").unwrap_or(encoded))
+}
+
+fn parse_digest(encoded: &str) -> Result<Digest, String> {
+    encoded.parse::<MethodId>().map(Digest::from)
+}
+
+fn parse_hash(encoded: &str) -> Result<[u8; 32], String> {
+    decode_hex(encoded)
+        .map_err(|err| err.to_string())?
         .try_into()
-        .expect("SHA256 should produce a 32-byte output");
+        .map_err(|bytes: Vec<u8>| format!("expected 32 bytes, got {}", bytes.len()))
+}
 
-    result
+/// Where the bytes to prove come from. At most one may be given; without any,
+/// the example input is proven.
+#[derive(Args, Default)]
+#[group(multiple = false)]
+struct InputArgs {
+    /// Hex-encoded input bytes, optionally prefixed with `0x`.
+    #[arg(long)]
+    input_hex: Option<String>,
+    /// File whose raw contents are the input.
+    #[arg(long)]
+    input_file: Option<PathBuf>,
+    /// UTF-8 string whose bytes are the input.
+    #[arg(long)]
+    input_str: Option<String>,
 }
 
-fn from_seal(seal_bytes: &[u8]) -> ark_groth16::Proof<ark_bn254::Bn254> {
-    use ark_bn254::{Fq, Fq2, G1Affine, G2Affine};
-    use ark_ff::{Field, PrimeField};
+impl InputArgs {
+    fn read(&self) -> Result<Vec<u8>> {
+        if let Some(encoded) = &self.input_hex {
+            let encoded = encoded.trim();
+            let encoded = encoded.strip_prefix("0x").unwrap_or(encoded);
+            let data = hex::decode(encoded).context("--input-hex is not valid hex")?;
+            if !data.is_empty() && data.iter().all(u8::is_ascii_hexdigit) {
+                tracing::warn!(
+                    "--input-hex decodes to hex text; if the input was encoded twice, \
+                     pass the original hex instead"
+                );
+            }
+            return Ok(data);
+        }
+        if let Some(path) = &self.input_file {
+            return std::fs::read(path)
+                .with_context(|| format!("failed to read input file {}", path.display()));
+        }
+        if let Some(text) = &self.input_str {
+            return Ok(text.as_bytes().to_vec());
+        }
+        // For example:
+        Ok((15 * u32::pow(2, 27) + 1).to_le_bytes().to_vec())
+    }
 
-    let a = G1Affine::new(
-        Fq::from_be_bytes_mod_order(&seal_bytes[0..32]),
-        Fq::from_be_bytes_mod_order(&seal_bytes[32..64]),
-    );
+    /// The input to prove: the document at `input_json` if given, and the
+    /// input of these flags with `padding` otherwise, bound if `bind_input`,
+    /// with its statistics if `with_stats`, the guest version if
+    /// `with_version` and `nonce` if given, and sent in frames of `frame_len`
+    /// if given.
+    #[allow(clippy::too_many_arguments)]
+    fn guest_input(
+        &self,
+        input_json: Option<&Path>,
+        padding: Padding,
+        bind_input: bool,
+        with_stats: bool,
+        with_version: bool,
+        nonce: Option<Nonce>,
+        frame_len: Option<usize>,
+    ) -> Result<GuestInput> {
+        let input = match input_json {
+            Some(path) => {
+                let json = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read input document {}", path.display()))?;
+                GuestInput::from_json(&json)
+                    .with_context(|| format!("invalid input document {}", path.display()))?
+            }
+            None => GuestInput::Single {
+                data: self.read()?,
+                padding,
+            },
+        };
+        let input = if bind_input {
+            GuestInput::BindInput(Box::new(input))
+        } else {
+            input
+        };
+        let input = if with_stats {
+            GuestInput::WithStats(Box::new(input))
+        } else {
+            input
+        };
+        let input = if with_version {
+            GuestInput::WithVersion(Box::new(input))
+        } else {
+            input
+        };
+        let input = match nonce {
+            Some(nonce) => GuestInput::WithNonce {
+                nonce,
+                input: Box::new(input),
+            },
+            None => input,
+        };
+        Ok(match frame_len {
+            Some(frame_len) => GuestInput::Framed {
+                frame_len,
+                input: Box::new(input),
+            },
+            None => input,
+        })
+    }
+}
 
-    let b = G2Affine::new(
-        Fq2::from_base_prime_field_elems(&[
-            Fq::from_be_bytes_mod_order(&seal_bytes[96..128]),
-            Fq::from_be_bytes_mod_order(&seal_bytes[64..96]),
-        ])
-        .unwrap(),
-        Fq2::from_base_prime_field_elems(&[
-            Fq::from_be_bytes_mod_order(&seal_bytes[160..192]),
-            Fq::from_be_bytes_mod_order(&seal_bytes[128..160]),
-        ])
-        .unwrap(),
-    );
+#[derive(Args, Default)]
+struct LimitArgs {
+    /// Segment size as a power of two cycles; lower values reduce prover memory.
+    #[arg(long)]
+    segment_limit_po2: Option<u32>,
+    /// Maximum number of cycles the session may take.
+    #[arg(long)]
+    session_limit: Option<u64>,
+    /// Largest input accepted, in bytes.
+    #[arg(long)]
+    max_input_len: Option<u64>,
+    /// Keep the receipt of a guest that exits with a non-zero code, as proof
+    /// of the fault, instead of failing.
+    #[arg(long)]
+    prove_faults: bool,
+    /// Memory the Groth16 wrap may use, in bytes; beyond it the prover spills
+    /// to swap instead of being killed.
+    #[arg(long)]
+    max_mem: Option<u64>,
+    /// Docker image of the Groth16 prover run under --max-mem. Defaults to
+    /// the one of the risc0 release this binary is built against.
+    #[arg(long, requires = "max_mem")]
+    wrap_image: Option<String>,
+    /// Keep the succinct STARK receipt the Groth16 proof wraps in the bundle,
+    /// for verifiers who do not trust the Groth16 trusted setup.
+    #[arg(long)]
+    keep_stark: bool,
+}
 
-    let c = G1Affine::new(
-        Fq::from_be_bytes_mod_order(&seal_bytes[192..224]),
-        Fq::from_be_bytes_mod_order(&seal_bytes[224..256]),
-    );
+impl From<&LimitArgs> for ProverLimits {
+    fn from(args: &LimitArgs) -> Self {
+        let mut limits = ProverLimits::default();
+        limits.segment_limit_po2 = args.segment_limit_po2;
+        limits.session_limit = args.session_limit;
+        if let Some(max_input_len) = args.max_input_len {
+            limits.max_input_len = max_input_len;
+        }
+        limits.prove_faults = args.prove_faults;
+        limits.max_mem = args.max_mem;
+        limits.wrap_image = args.wrap_image.clone();
+        limits.keep_stark = args.keep_stark;
+        limits
+    }
+}
 
-    ark_groth16::Proof {
-        a: a.into(),
-        b: b.into(),
-        c: c.into(),
+fn main() -> Result<()> {
+    // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
+        .init();
+
+    let cli = Cli::parse();
+    verifier::init_verify_threads(cli.verify_threads)?;
+    match cli.command.unwrap_or(Command::Prove(ProveArgs::default())) {
+        Command::Prove(args) => prove(&args, &[]),
+        Command::Estimate {
+            input,
+            input_json,
+            bind_input,
+            with_stats,
+            with_version,
+            nonce,
+            frame_len,
+            padding,
+            limits,
+        } => {
+            let input = input.guest_input(
+                input_json.as_deref(),
+                padding,
+                bind_input,
+                with_stats,
+                with_version,
+                nonce,
+                frame_len,
+            )?;
+            let estimate = prover::estimate_cycles(&input, &(&limits).into())?;
+            println!("{}", serde_json::to_string_pretty(&estimate)?);
+            Ok(())
+        }
+        Command::InputDigest {
+            input,
+            input_json,
+            frame_len,
+            with_stats,
+            with_version,
+            nonce,
+            padding,
+        } => {
+            let input = input.guest_input(
+                input_json.as_deref(),
+                padding,
+                false,
+                with_stats,
+                with_version,
+                nonce,
+                frame_len,
+            )?;
+            input.validate()?;
+            println!("{}", hex::encode(input.input_digest()));
+            Ok(())
+        }
+        Command::Precommit {
+            input,
+            input_json,
+            frame_len,
+            with_stats,
+            with_version,
+            nonce,
+            padding,
+            image_id,
+            operator,
+        } => {
+            let input = input.guest_input(
+                input_json.as_deref(),
+                padding,
+                false,
+                with_stats,
+                with_version,
+                nonce,
+                frame_len,
+            )?;
+            input.validate()?;
+            let commitment = PreCommitment::new(image_id.unwrap_or(METHOD_ID.into()), &input);
+            let mut printed = serde_json::json!({
+                "image_id": hex::encode(commitment.image_id),
+                "input_digest": hex::encode(commitment.input_digest),
+                "payload": hex::encode(commitment.payload()),
+                "op_return_script": hex::encode(commitment.op_return_script().as_bytes()),
+            });
+            if let Some(operator) = operator {
+                let operator = manifest::parse_public_key(&operator)?;
+                printed["tapleaf_script"] =
+                    hex::encode(commitment.tapleaf_script(&operator)?.as_bytes()).into();
+                printed["tapleaf_hash"] = commitment.tapleaf_hash(&operator)?.to_string().into();
+            }
+            println!("{}", serde_json::to_string_pretty(&printed)?);
+            Ok(())
+        }
+        Command::CheckPrecommit { bundle, commitment } => {
+            let encoded = decode_hex(&commitment).context("--commitment is not valid hex")?;
+            let commitment = match encoded.len() {
+                precommit::PAYLOAD_LEN => PreCommitment::from_payload(&encoded)?,
+                _ => PreCommitment::from_script(bitcoin::Script::from_bytes(&encoded))?,
+            };
+            let public_input = commitment.check(&read_bundle(&bundle)?.receipt)?;
+            println!(
+                "bundle proves the pre-committed claim, public input {}",
+                hex::encode(public_input)
+            );
+            Ok(())
+        }
+        Command::Verify(args) => verify(&args),
+        Command::VerifyStream {
+            dir,
+            image_ids,
+            max_journal_len,
+            profile,
+            threads,
+            audit,
+            policy,
+        } => {
+            let source = dir.map_or(Source::Stdin, Source::Dir);
+            let options = StreamOptions {
+                profile: profile.load(image_ids, max_journal_len)?,
+                threads,
+                audit: audit.open()?.map(Mutex::new),
+                policy: policy.load()?,
+            };
+            let summary = verify_stream::verify_stream(&source, &options, std::io::stdout())?;
+            ensure!(
+                summary.invalid == 0,
+                "{} of {} bundles failed verification",
+                summary.invalid,
+                summary.valid + summary.invalid
+            );
+            Ok(())
+        }
+        Command::Recheck {
+            store,
+            encryption_key_file,
+            image_ids,
+            max_journal_len,
+            profile,
+            threads,
+            interval,
+            audit,
+            policy,
+        } => {
+            let key = storage::load_encryption_key(encryption_key_file.as_deref())?;
+            let store = storage::with_encryption(storage::open(&store)?, key);
+            let options = StreamOptions {
+                profile: profile.load(image_ids, max_journal_len)?,
+                threads,
+                audit: audit.open()?.map(Mutex::new),
+                policy: policy.load()?,
+            };
+            let Some(interval) = interval else {
+                let summary = recheck::recheck(store.as_ref(), &options, std::io::stdout())?;
+                ensure!(
+                    summary.invalid == 0,
+                    "{} of {} archived bundles no longer verify",
+                    summary.invalid,
+                    summary.valid + summary.invalid
+                );
+                return Ok(());
+            };
+            loop {
+                match recheck::recheck(store.as_ref(), &options, std::io::stdout()) {
+                    Ok(summary) => tracing::info!(
+                        "recheck pass done: {} valid, {} no longer verify",
+                        summary.valid,
+                        summary.invalid
+                    ),
+                    Err(err) => tracing::error!("recheck pass failed: {err:#}"),
+                }
+                std::thread::sleep(Duration::from_secs(interval));
+            }
+        }
+        Command::Selftest { len, no_dev_mode } => {
+            let vector = selftest::Vector::find(len)?;
+            selftest::run(&vector, !no_dev_mode)?;
+            println!(
+                "BLAKE3 test vector of {len} bytes proven{} and verified: {}",
+                if no_dev_mode { "" } else { " in dev mode" },
+                vector.hash
+            );
+            Ok(())
+        }
+        Command::Diff { a, b } => {
+            let fields = diff::diff(&read_bundle(&a)?, &read_bundle(&b)?);
+            for field in &fields {
+                if field.differs() {
+                    println!("! {}\n    a: {}\n    b: {}", field.field, field.a, field.b);
+                } else {
+                    println!("  {}: {}", field.field, field.a);
+                }
+            }
+            let differing: Vec<&str> = fields
+                .iter()
+                .filter(|field| field.differs())
+                .map(|field| field.field.as_str())
+                .collect();
+            match differing.first() {
+                None => {
+                    println!("bundles are identical in every compared field");
+                    Ok(())
+                }
+                Some(first) => bail!(
+                    "bundles differ in {} fields, first in {first}",
+                    differing.len()
+                ),
+            }
+        }
+        Command::ManifestAdd {
+            manifest: path,
+            bundle,
+            key_file,
+            committee,
+            threshold,
+            image_id,
+        } => {
+            let key = manifest::load_signing_key(&key_file)?;
+            let encoded = std::fs::read(&bundle)
+                .with_context(|| format!("failed to read proof bundle {}", bundle.display()))?;
+            let mut manifest = if path.exists() {
+                ensure!(
+                    committee.is_empty() && threshold.is_none() && image_id.is_none(),
+                    "--committee, --threshold and --image-id only apply to a new manifest"
+                );
+                Manifest::load(&path)?
+            } else {
+                let threshold = threshold.context("a new manifest needs --threshold")?;
+                let committee = committee
+                    .iter()
+                    .map(|key| manifest::parse_public_key(key))
+                    .collect::<Result<Vec<_>>>()?;
+                let image_id = image_id.unwrap_or(METHOD_ID.into());
+                let receipt = ProofBundle::decode(&encoded)?.receipt;
+                let public_input = Journal::of(&receipt).public_input(image_id);
+                Manifest::new(image_id, public_input, &committee, threshold)?
+            };
+            let relative = relative_path(&bundle, path.parent().unwrap_or(Path::new("")))?;
+            let entry = manifest.sign(&relative, &encoded, &key)?;
+            println!("{} signed {}", entry.operator, entry.bundle.display());
+            manifest.save(&path)
+        }
+        Command::ManifestVerify {
+            manifest: path,
+            policy,
+        } => {
+            let manifest = Manifest::load(&path)?;
+            let report = manifest.verify(
+                path.parent().unwrap_or(Path::new("")),
+                &policy.load()?,
+                policy::unix_now(),
+            );
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            ensure!(
+                report.quorum,
+                "{} of {} committee members proved the claim, {} needed",
+                report.signers,
+                report.committee_size,
+                report.threshold
+            );
+            Ok(())
+        }
+        Command::Announce { bundle, config } => {
+            let config = GossipConfig::load(&config)?;
+            let encoded = std::fs::read(&bundle)
+                .with_context(|| format!("failed to read {}", bundle.display()))?;
+            let name = bundle
+                .file_name()
+                .context("bundle path has no file name")?
+                .to_string_lossy();
+            let announcement = config.announcement(&name, &encoded)?;
+            gossip::announce(&config.transport, &announcement)?;
+            println!("{}", serde_json::to_string_pretty(&announcement)?);
+            Ok(())
+        }
+        #[cfg(feature = "experimental")]
+        Command::PublishIpfs {
+            bundle,
+            api_url,
+            no_pin,
+            gossip,
+        } => {
+            let encoded = std::fs::read(&bundle)
+                .with_context(|| format!("failed to read {}", bundle.display()))?;
+            ProofBundle::decode(&encoded)?;
+            let name = bundle
+                .file_name()
+                .context("bundle path has no file name")?
+                .to_string_lossy();
+            let cid = Ipfs::new(&api_url)?.add(&name, &encoded, !no_pin)?;
+            match gossip {
+                Some(config) => {
+                    let config = GossipConfig::load(&config)?;
+                    let mut announcement = config.announcement(&name, &encoded)?;
+                    announcement.cid = Some(cid);
+                    gossip::announce(&config.transport, &announcement)?;
+                    println!("{}", serde_json::to_string_pretty(&announcement)?);
+                }
+                None => println!("{cid}"),
+            }
+            Ok(())
+        }
+        #[cfg(feature = "experimental")]
+        Command::FetchIpfs {
+            cid,
+            announcement,
+            api_url,
+            image_id,
+            pin,
+            out,
+        } => {
+            let announcement: Option<Announcement> = announcement
+                .map(|path| -> Result<_> {
+                    let contents = std::fs::read(&path)
+                        .with_context(|| format!("failed to read {}", path.display()))?;
+                    serde_json::from_slice(&contents).context("malformed announcement")
+                })
+                .transpose()?;
+            let cid = match (&announcement, cid) {
+                (Some(announcement), _) => announcement
+                    .cid
+                    .clone()
+                    .context("announcement carries no CID")?,
+                (None, Some(cid)) => cid,
+                (None, None) => unreachable!("clap requires a CID or an announcement"),
+            };
+            let ipfs = Ipfs::new(&api_url)?;
+            let encoded = ipfs.cat(&cid)?;
+            if let Some(announcement) = &announcement {
+                ensure!(
+                    encoded.len() == announcement.size
+                        && hex::encode(<sha2::Sha256 as sha2::Digest>::digest(&encoded))
+                            == announcement.sha256,
+                    "{cid} is not the announced bundle"
+                );
+            }
+            let bundle = ProofBundle::decode(&encoded)?;
+            verifier::verify_receipt(&bundle.receipt, image_id.unwrap_or(METHOD_ID.into()))?;
+            if pin {
+                ipfs.pin(&cid)?;
+            }
+            storage::write_atomic(&out, &encoded)?;
+            println!("bundle {cid} verified and written to {}", out.display());
+            Ok(())
+        }
+        Command::AuditVerify { log, signer } => {
+            let signer = signer
+                .as_deref()
+                .map(manifest::parse_public_key)
+                .transpose()?;
+            let count = audit::verify_log(&log, signer.as_ref())?;
+            println!("{count} audit entries verified");
+            Ok(())
+        }
+        Command::CosignNonce {
+            verdict,
+            key_file,
+            secret_nonce,
+        } => {
+            let message = read_statement(&verdict)?.message()?;
+            let (secret, public) = cosign::nonce(&manifest::load_signing_key(&key_file)?, &message);
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&secret_nonce)
+                .and_then(|mut file| {
+                    std::io::Write::write_all(&mut file, secret.to_hex().as_bytes())
+                })
+                .with_context(|| format!("failed to write {}", secret_nonce.display()))?;
+            println!("{}", serde_json::to_string(&public)?);
+            Ok(())
+        }
+        Command::CosignSign {
+            verdict,
+            key_file,
+            secret_nonce,
+            nonces,
+        } => {
+            let message = read_statement(&verdict)?.message()?;
+            let key = manifest::load_signing_key(&key_file)?;
+            let secret = std::fs::read_to_string(&secret_nonce)
+                .with_context(|| format!("failed to read {}", secret_nonce.display()))?;
+            // A nonce signed with twice reveals the key, so it is gone before
+            // it is used.
+            std::fs::remove_file(&secret_nonce)
+                .with_context(|| format!("failed to remove {}", secret_nonce.display()))?;
+            let partial = cosign::sign(
+                &key,
+                SecretNonce::from_hex(&secret)?,
+                &read_json_lines(&nonces)?,
+                &message,
+            )?;
+            println!("{}", serde_json::to_string(&partial)?);
+            Ok(())
+        }
+        Command::CosignAggregate {
+            verdict,
+            nonces,
+            partials,
+        } => {
+            let attested = cosign::aggregate(
+                read_statement(&verdict)?,
+                &read_json_lines(&nonces)?,
+                &read_json_lines(&partials)?,
+            )?;
+            println!("{}", serde_json::to_string_pretty(&attested)?);
+            Ok(())
+        }
+        Command::CosignCheck {
+            attested,
+            members,
+            threshold,
+        } => {
+            let encoded = std::fs::read(&attested)
+                .with_context(|| format!("failed to read {}", attested.display()))?;
+            let attested: AttestedVerdict =
+                serde_json::from_slice(&encoded).context("invalid attested verdict")?;
+            let members = members
+                .iter()
+                .map(|member| manifest::parse_public_key(member))
+                .collect::<Result<Vec<_>>>()?;
+            let committee = (!members.is_empty()).then_some(members.as_slice());
+            attested.check(committee, threshold.unwrap_or(members.len()))?;
+            println!(
+                "verdict on bundle {} attested by {} signers",
+                attested.statement.bundle_blake3,
+                attested.signers.len()
+            );
+            Ok(())
+        }
+        Command::VerifySeal {
+            seal,
+            public_input,
+            scalar_policy,
+        } => {
+            let seal = decode_hex(&seal).context("--seal is not valid hex")?;
+            let public_input =
+                decode_hex(&public_input).context("--public-input is not valid hex")?;
+            ensure!(
+                verifier::verify_seal(&seal, &public_input, scalar_policy)?,
+                "seal does not verify against the public input"
+            );
+            println!("seal verified");
+            Ok(())
+        }
+        Command::VerifyFromTx {
+            txid,
+            bitcoin,
+            confirmations,
+            input,
+            bundle,
+            image_id,
+        } => {
+            let rpc = bitcoin.config().connect()?;
+            bitcoin_rpc::require_confirmations(rpc.as_ref(), &txid, confirmations)?;
+            let tx = rpc.transaction(&txid)?;
+            let witness = AssertWitness::from_transaction(&tx, input)?;
+            if let Some(bundle) = bundle {
+                let receipt = read_bundle(&bundle)?.receipt;
+                let expected =
+                    Journal::of(&receipt).public_input(image_id.unwrap_or(METHOD_ID.into()));
+                ensure!(
+                    witness.public_input == expected,
+                    "transaction asserts public input {}, but the bundle derives {}",
+                    hex::encode(witness.public_input),
+                    hex::encode(expected)
+                );
+            }
+            witness.verify(ScalarPolicy::Reject)?;
+            println!(
+                "seal in {txid} verified against public input {}",
+                hex::encode(witness.public_input)
+            );
+            Ok(())
+        }
+        Command::AssertPsbt {
+            bundle,
+            operator,
+            funding,
+            change_address,
+            destination_address,
+            reveal_value,
+            network,
+            fee,
+            no_rbf,
+            image_id,
+            out,
+            bitcoin,
+        } => {
+            let receipt = read_bundle(&bundle)?.receipt;
+            let image_id = image_id.unwrap_or(METHOD_ID.into());
+            verifier::verify_receipt(&receipt, image_id)?;
+            let witness = AssertWitness::from_bytes(&chunks::verifier_witness(
+                &receipt,
+                image_id,
+                &BindingHash::default(),
+            )?)?;
+            let rpc = bitcoin.config().connect()?;
+            let funding = funding
+                .into_iter()
+                .map(|outpoint| {
+                    let txout = rpc
+                        .transaction(&outpoint.txid)?
+                        .output
+                        .get(outpoint.vout as usize)
+                        .with_context(|| format!("funding output {outpoint} does not exist"))?
+                        .clone();
+                    Ok(Funding { outpoint, txout })
+                })
+                .collect::<Result<_>>()?;
+            let change = change_address.require_network(network)?.script_pubkey();
+            let destination = match destination_address {
+                Some(address) => address.require_network(network)?.script_pubkey(),
+                None => change.clone(),
+            };
+            let params = AssertParams {
+                operator,
+                funding,
+                change,
+                destination,
+                reveal_value: bitcoin::Amount::from_sat(reveal_value),
+                fee_rate: fee.fee_rate(&bitcoin)?,
+                rbf: !no_rbf,
+            };
+            let package = AssertPackage::build(&params, &witness)?;
+            storage::write_atomic(&out, &serde_json::to_vec_pretty(&package)?)?;
+            print_assert_package(&package)
+        }
+        Command::FinalizeAssert {
+            package,
+            signed,
+            broadcast,
+            claim,
+            store,
+            hwi,
+            bitcoin,
+        } => {
+            let mut package = AssertPackage::load(&package)?;
+            if hwi.hwi {
+                let fingerprint = match hwi.hwi_fingerprint {
+                    Some(fingerprint) => Some(fingerprint),
+                    None => package
+                        .operator()?
+                        .origin
+                        .map(|(fingerprint, _)| fingerprint),
+                };
+                Hwi::new(&hwi.hwi_program, fingerprint, hwi.network).sign_package(&mut package)?;
+            }
+            for path in signed {
+                let encoded = std::fs::read(&path)
+                    .with_context(|| format!("failed to read PSBT {}", path.display()))?;
+                let psbt = if encoded.starts_with(b"psbt\xff") {
+                    bitcoin::Psbt::deserialize(&encoded)?
+                } else {
+                    String::from_utf8(encoded)
+                        .context("PSBT is neither binary nor base64")?
+                        .trim()
+                        .parse::<bitcoin::Psbt>()?
+                };
+                package.combine(psbt)?;
+            }
+            let (commit, reveal) = package.finalize()?;
+            if broadcast {
+                let rpc = bitcoin.config().connect()?;
+                for tx in [&commit, &reveal] {
+                    println!("broadcast {}", rpc.broadcast(tx)?);
+                }
+                if let Some(claim) = claim {
+                    let txid = reveal.compute_txid().to_string();
+                    lifecycle::record(
+                        store.open()?.as_ref(),
+                        &claim,
+                        Stage::Committed,
+                        Some(txid),
+                        policy::unix_now(),
+                    )?;
+                }
+            } else {
+                for tx in [&commit, &reveal] {
+                    println!("{}", bitcoin::consensus::encode::serialize_hex(tx));
+                }
+            }
+            Ok(())
+        }
+        Command::DetectFraud {
+            assert_tx,
+            confirmations,
+            bundle,
+            journal,
+            image_id,
+            commitments,
+            tree,
+            bitcoin,
+        } => {
+            let journal = match (bundle, journal) {
+                (Some(bundle), _) => read_bundle(&bundle)?.receipt.journal.bytes,
+                (None, Some(path)) => std::fs::read(&path)
+                    .with_context(|| format!("failed to read journal {}", path.display()))?,
+                (None, None) => unreachable!("clap requires a bundle or a journal"),
+            };
+            let rpc = bitcoin.config().connect()?;
+            bitcoin_rpc::require_confirmations(rpc.as_ref(), &assert_tx, confirmations)?;
+            let assert = AssertWitness::from_transaction(&rpc.transaction(&assert_tx)?, None)?;
+            let commitments = commitments.with(assert);
+            let image_id = image_id.unwrap_or(METHOD_ID.into());
+            let Some(finding) = fraud::detect(&commitments, image_id, &journal)? else {
+                println!("assert {assert_tx} is consistent with the claim");
+                return Ok(());
+            };
+            let mut printed = serde_json::to_value(&finding)?;
+            printed["fraud"] = finding.fraud.to_string().into();
+            if let Some(tree) = tree {
+                let witness = DisproveTree::load(&tree)?.witness(finding.fraud, &commitments)?;
+                printed["witness"] = witness
+                    .to_witness()?
+                    .iter()
+                    .map(hex::encode)
+                    .collect::<Vec<_>>()
+                    .into();
+            }
+            println!("{}", serde_json::to_string_pretty(&printed)?);
+            bail!("assert {assert_tx} is fraudulent: {}", finding.reason)
+        }
+        Command::DisproveWitness {
+            tree,
+            assert_tx,
+            confirmations,
+            fraud,
+            commitments,
+            connector,
+            payout_address,
+            network,
+            fee,
+            bitcoin,
+        } => {
+            let tree = DisproveTree::load(&tree)?;
+            let rpc = bitcoin.config().connect()?;
+            bitcoin_rpc::require_confirmations(rpc.as_ref(), &assert_tx, confirmations)?;
+            let assert = AssertWitness::from_transaction(&rpc.transaction(&assert_tx)?, None)?;
+            let witness = tree.witness(fraud, &commitments.with(assert))?;
+            let mut printed = serde_json::json!({
+                "fraud": fraud.to_string(),
+                "witness": witness
+                    .to_witness()?
+                    .iter()
+                    .map(hex::encode)
+                    .collect::<Vec<_>>(),
+            });
+            if let (Some(connector), Some(payout)) = (connector, payout_address) {
+                let txout = rpc
+                    .transaction(&connector.txid)?
+                    .output
+                    .get(connector.vout as usize)
+                    .with_context(|| format!("connector output {connector} does not exist"))?
+                    .clone();
+                ensure!(
+                    txout.script_pubkey == tree.script_pubkey()?,
+                    "connector output {connector} is not committed to the script tree"
+                );
+                let tx = witness.transaction(
+                    connector,
+                    txout.value,
+                    payout.require_network(network)?.script_pubkey(),
+                    fee.fee_rate(&bitcoin)?,
+                )?;
+                printed["txid"] = tx.compute_txid().to_string().into();
+                printed["transaction"] = bitcoin::consensus::encode::serialize_hex(&tx).into();
+            }
+            println!("{}", serde_json::to_string_pretty(&printed)?);
+            Ok(())
+        }
+        Command::BumpAssert {
+            package: path,
+            fee,
+            commit_confirmed,
+            out,
+            bitcoin,
+        } => {
+            let package = AssertPackage::load(&path)?;
+            let fee_rate = fee.fee_rate(&bitcoin)?;
+            let bumped = if commit_confirmed {
+                package.bump_reveal(fee_rate)?
+            } else {
+                package.bump(fee_rate)?
+            };
+            storage::write_atomic(
+                out.as_deref().unwrap_or(&path),
+                &serde_json::to_vec_pretty(&bumped)?,
+            )?;
+            print_assert_package(&bumped)
+        }
+        Command::ExportEvm {
+            bundle,
+            image_id,
+            normalize_seal,
+        } => {
+            let mut receipt = read_bundle(&bundle)?.receipt;
+            if normalize_seal && verifier::normalize_receipt_seal(&mut receipt)? {
+                tracing::warn!("normalized a non-canonical seal");
+            }
+            let image_id = image_id.unwrap_or(METHOD_ID.into());
+            verifier::verify_receipt(&receipt, image_id)?;
+            let input = EvmVerifierInput::new(&receipt, image_id)?;
+            println!("{}", serde_json::to_string_pretty(&input)?);
+            Ok(())
+        }
+        Command::ExportStark {
+            bundle,
+            out,
+            image_id,
+        } => {
+            let bundle = read_bundle(&bundle)?;
+            let stark = bundle
+                .stark
+                .as_ref()
+                .context("bundle keeps no STARK receipt; prove it with --keep-stark")?;
+            let image_id = image_id.unwrap_or(METHOD_ID.into());
+            verifier::verify_stark(stark, &bundle.receipt, image_id)?;
+            storage::write_atomic(&out, &bincode::serialize(stark)?)?;
+            println!("STARK receipt written to {}", out.display());
+            Ok(())
+        }
+        #[cfg(feature = "experimental")]
+        Command::ExportInscription {
+            bundle,
+            image_id,
+            key,
+            network,
+        } => {
+            let bundle = read_bundle(&bundle)?;
+            let image_id = image_id.unwrap_or(METHOD_ID.into());
+            verifier::verify_receipt(&bundle.receipt, image_id)?;
+            let inscription = Inscription::of_bundle(&bundle)?;
+            let mut printed = serde_json::json!({
+                "content_type": inscription.content_type,
+                "metaprotocol": inscription.metaprotocol,
+                "public_input": hex::encode(Journal::of(&bundle.receipt).public_input(image_id)),
+                "body_len": inscription.body.len(),
+                "body_pushes": inscription.body_pushes(),
+                "envelope": inscription.envelope()?.to_hex_string(),
+            });
+            if let Some(key) = key {
+                printed["tapscript"] = inscription.tapscript(key)?.to_hex_string().into();
+                printed["commit_address"] =
+                    inscription.commit_address(key, network)?.to_string().into();
+            }
+            println!("{}", serde_json::to_string_pretty(&printed)?);
+            Ok(())
+        }
+        Command::SplitWitness {
+            bundle,
+            max_chunk_len,
+            image_id,
+            profile,
+        } => {
+            let receipt = read_bundle(&bundle)?.receipt;
+            let (witness, max_chunk_len) = match profile.profile {
+                Some(_) => {
+                    let profile = profile.load(Vec::new(), verifier::DEFAULT_MAX_JOURNAL_LEN)?;
+                    let image_id = image_id.unwrap_or(profile.default_image_id());
+                    profile.verify_receipt(&receipt, image_id)?;
+                    let witness = chunks::verifier_witness(&receipt, image_id, &profile)?;
+                    (witness, profile.max_chunk_len)
+                }
+                None => {
+                    let image_id = image_id.unwrap_or(METHOD_ID.into());
+                    verifier::verify_receipt(&receipt, image_id)?;
+                    let witness =
+                        chunks::verifier_witness(&receipt, image_id, &BindingHash::Blake3)?;
+                    (witness, max_chunk_len)
+                }
+            };
+            let chunks = ChunkSet::split(&witness, max_chunk_len)?;
+            println!("{}", serde_json::to_string_pretty(&chunks)?);
+            Ok(())
+        }
+        Command::ShowProfile { name, profiles } => {
+            let profile = Profile::load(&name, profiles.as_deref())?;
+            println!("{}", serde_json::to_string_pretty(&profile.summary()?)?);
+            Ok(())
+        }
+        Command::FetchVk {
+            risc0_version,
+            profile: name,
+            profiles,
+            url,
+            sha256,
+        } => {
+            let env_file = std::env::var_os(profile::PROFILES_ENV).map(PathBuf::from);
+            let Some(profiles) = profiles.or(env_file) else {
+                bail!("pass --profiles <file> or set {}", profile::PROFILES_ENV);
+            };
+            let name =
+                name.unwrap_or_else(|| format!("risc0-{}", risc0_version.trim_start_matches('v')));
+            let fetched = fetch_vk::fetch(&risc0_version, &url, sha256)?;
+            Profile::store_verifying_key(&profiles, &name, &fetched.verifying_key)?;
+            println!(
+                "wrote the verifying key from {} (SHA-256 {}) to profile {name} in {}",
+                fetched.url,
+                hex::encode(fetched.sha256),
+                profiles.display()
+            );
+            if fetched.verifying_key != verifier::get_ark_verifying_key() {
+                tracing::warn!(
+                    "the key of risc0 {risc0_version} differs from the one built into this binary"
+                );
+            }
+            Ok(())
+        }
+        Command::JoinWitness { chunks } => {
+            let encoded = std::fs::read(&chunks)
+                .with_context(|| format!("failed to read chunks {}", chunks.display()))?;
+            let chunks: ChunkSet = serde_json::from_slice(&encoded).context("invalid chunks")?;
+            println!("{}", hex::encode(chunks.reassemble()?));
+            Ok(())
+        }
+        Command::ExportCircom {
+            bundle,
+            limb_bits,
+            limbs,
+            image_id,
+            profile,
+        } => {
+            let receipt = read_bundle(&bundle)?.receipt;
+            let profile = profile.load(Vec::new(), verifier::DEFAULT_MAX_JOURNAL_LEN)?;
+            let image_id = image_id.unwrap_or(profile.default_image_id());
+            profile.verify_receipt(&receipt, image_id)?;
+            let input = CircomInput::new(&receipt, image_id, &profile, limb_bits, limbs)?;
+            println!("{}", serde_json::to_string_pretty(&input)?);
+            Ok(())
+        }
+        Command::ExportGnark {
+            bundle,
+            proof,
+            public_witness,
+            image_id,
+            profile,
+        } => {
+            let receipt = read_bundle(&bundle)?.receipt;
+            let profile = profile.load(Vec::new(), verifier::DEFAULT_MAX_JOURNAL_LEN)?;
+            let image_id = image_id.unwrap_or(profile.default_image_id());
+            profile.verify_receipt(&receipt, image_id)?;
+            let export = GnarkExport::new(&receipt, image_id, &profile)?;
+            storage::write_atomic(&proof, &export.proof)?;
+            storage::write_atomic(&public_witness, &export.public_witness)?;
+            Ok(())
+        }
+        Command::BuildGuest { root, record } => {
+            let build = guest_build::docker_build(&root)?;
+            println!("image ID: {}", build.image_id);
+            println!("ELF: {}", build.elf_path.display());
+            if build.image_id != Digest::from(METHOD_ID) {
+                println!(
+                    "note: differs from the image ID embedded in this binary ({})",
+                    Digest::from(METHOD_ID)
+                );
+            }
+            if let Some(path) = record {
+                storage::write_atomic(&path, &serde_json::to_vec_pretty(&build)?)?;
+            }
+            Ok(())
+        }
+        Command::ExplainInput { receipt, image_id } => {
+            let receipt = read_receipt(&receipt)?;
+            let image_id = image_id.unwrap_or(METHOD_ID.into());
+            for step in verifier::explain_public_input(image_id, &receipt.journal.bytes) {
+                println!("{}:\n    {}", step.label, step.value);
+            }
+            Ok(())
+        }
+        Command::Journal { receipt, format } => {
+            let receipt = read_receipt(&receipt)?;
+            let journal = Journal::of(&receipt);
+            match format {
+                JournalFormat::Hex => println!("{}", hex::encode(journal.as_bytes())),
+                JournalFormat::U32 => journal.fields().for_each(|word| println!("{word}")),
+                JournalFormat::Digest => println!("{}", journal.digest()),
+                JournalFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&journal.to_json()?)?)
+                }
+            }
+            Ok(())
+        }
+        Command::LifecycleShow { claim, store } => {
+            let claim = lifecycle::load(store.open()?.as_ref(), &claim)?
+                .with_context(|| format!("no lifecycle recorded for claim {claim}"))?;
+            println!("{}", serde_json::to_string_pretty(&claim)?);
+            Ok(())
+        }
+        Command::LifecycleList { stage, store } => {
+            for claim in lifecycle::list(store.open()?.as_ref())? {
+                if stage.is_none_or(|stage| claim.stage() == stage) {
+                    let at = claim.events.last().map_or(0, |event| event.at);
+                    println!("{} {} {at}", claim.id, claim.stage());
+                }
+            }
+            Ok(())
+        }
+        Command::LifecycleRecord {
+            claim,
+            stage,
+            detail,
+            store,
+        } => {
+            let claim = lifecycle::record(
+                store.open()?.as_ref(),
+                &claim,
+                stage,
+                detail,
+                policy::unix_now(),
+            )?;
+            println!("claim {} is {}", claim.id, claim.stage());
+            Ok(())
+        }
+        Command::KvDiff { old, new } => {
+            let (old, new) = (kv::load(&old)?, kv::load(&new)?);
+            println!("old: {}", hex::encode(kv::commitment(&old)));
+            println!("new: {}", hex::encode(kv::commitment(&new)));
+            for change in kv::diff(&old, &new) {
+                match change {
+                    kv::Change::Added { key, value } => {
+                        println!("+ {} = {}", hex::encode(key), hex::encode(value))
+                    }
+                    kv::Change::Removed { key, value } => {
+                        println!("- {} = {}", hex::encode(key), hex::encode(value))
+                    }
+                    kv::Change::Changed { key, old, new } => println!(
+                        "~ {} = {} -> {}",
+                        hex::encode(key),
+                        hex::encode(old),
+                        hex::encode(new)
+                    ),
+                }
+            }
+            Ok(())
+        }
+        Command::BundleFsck { bundle } => {
+            let encoded = std::fs::read(&bundle)
+                .with_context(|| format!("failed to read {}", bundle.display()))?;
+            let report = fsck::check(&encoded)?;
+            match &report.sections {
+                Some(sections) => {
+                    for (section, status) in sections {
+                        println!(
+                            "{:<16} bytes {:>8}..{:<8} {status}",
+                            section.name,
+                            section.offset,
+                            section.offset + section.len
+                        );
+                    }
+                }
+                None => println!("bundle records no section checksums; decoding it only"),
+            }
+            if let Some(err) = &report.decode_error {
+                println!("decoding failed: {err}");
+            }
+            if !report.is_ok() {
+                let corrupt: Vec<_> = report
+                    .corrupt()
+                    .map(|(section, _)| section.name.as_str())
+                    .collect();
+                ensure!(
+                    corrupt.is_empty(),
+                    "corrupt bundle sections: {}",
+                    corrupt.join(", ")
+                );
+                bail!("bundle does not decode");
+            }
+            println!("bundle is intact");
+            Ok(())
+        }
+        Command::Timestamp {
+            bundle,
+            calendars,
+            upgrade,
+        } => {
+            let mut decoded = read_bundle(&bundle)?;
+            if upgrade {
+                let upgraded = decoded
+                    .timestamp
+                    .as_mut()
+                    .context("bundle has no timestamp to upgrade")?
+                    .upgrade()?;
+                println!("upgraded {upgraded} pending attestations");
+            } else {
+                let stamp = BundleTimestamp::submit(decoded.digest()?, &calendars)?;
+                println!("bundle timestamped by {} calendars", stamp.calendars.len());
+                decoded.timestamp = Some(stamp);
+            }
+            storage::write_atomic(&bundle, &decoded.encode()?)?;
+            Ok(())
+        }
+        Command::PublishDa {
+            bundle,
+            receipt,
+            config,
+        } => {
+            let mut decoded = read_bundle(&bundle)?;
+            ensure!(
+                decoded.timestamp.is_none(),
+                "bundle is already timestamped; publish its receipts before timestamping it"
+            );
+            let encoded = std::fs::read(&receipt)
+                .with_context(|| format!("failed to read {}", receipt.display()))?;
+            let receipt: Receipt = bincode::deserialize(&encoded).context("malformed receipt")?;
+            let layer = DaConfig::load(&config)?.connect()?;
+            let reference = da::publish_receipt(layer.as_ref(), &receipt, &decoded.receipt)?;
+            println!(
+                "{} published to {} at {}",
+                reference.content, reference.layer, reference.locator
+            );
+            decoded
+                .da
+                .retain(|published| published.content != reference.content);
+            decoded.da.push(reference);
+            storage::write_atomic(&bundle, &decoded.encode()?)?;
+            Ok(())
+        }
+        Command::FetchDa {
+            bundle,
+            config,
+            out,
+        } => {
+            let decoded = read_bundle(&bundle)?;
+            ensure!(
+                !decoded.da.is_empty(),
+                "bundle records no receipts published to a DA layer"
+            );
+            let layer = DaConfig::load(&config)?.connect()?;
+            for reference in &decoded.da {
+                let blob = da::fetch(layer.as_ref(), reference)?;
+                let receipt: Receipt = bincode::deserialize(&blob)
+                    .with_context(|| format!("malformed {}", reference.content))?;
+                ensure!(
+                    receipt.claim()?.digest() == decoded.receipt.claim()?.digest(),
+                    "{} does not prove the bundle's claim",
+                    reference.content
+                );
+                match &out {
+                    Some(dir) => {
+                        let path = dir.join(hex::encode(reference.blake3));
+                        storage::write_atomic(&path, &blob)?;
+                        println!("{} written to {}", reference.content, path.display());
+                    }
+                    None => println!("{} at {} checked", reference.content, reference.locator),
+                }
+            }
+            Ok(())
+        }
+        Command::Disclose { fields, names } => {
+            let disclosure = Disclosure::new(&fields::load(&fields)?, &names)?;
+            println!("{}", serde_json::to_string_pretty(&disclosure)?);
+            Ok(())
+        }
+        Command::CheckDisclosure {
+            receipt,
+            disclosure,
+            image_id,
+        } => {
+            let receipt = read_receipt(&receipt)?;
+            verifier::verify_receipt(&receipt, image_id.unwrap_or(METHOD_ID.into()))?;
+            let Output::Fields { root, .. } = Journal::of(&receipt).output()? else {
+                bail!("receipt does not commit to fields");
+            };
+            let encoded = std::fs::read(&disclosure)
+                .with_context(|| format!("failed to read disclosure {}", disclosure.display()))?;
+            let disclosure: Disclosure =
+                serde_json::from_slice(&encoded).context("invalid disclosure")?;
+            for (name, value) in disclosure.check(root)? {
+                println!("{name} = {}", hex::encode(value));
+            }
+            Ok(())
+        }
+        Command::Outboard {
+            input,
+            block_log,
+            context,
+            out,
+        } => {
+            let outboard = Outboard::new(&input.read()?, block_log, context.as_deref())?;
+            storage::write_atomic(&out, &outboard.pairs)?;
+            println!("root: {}", hex::encode(outboard.root));
+            println!("length: {}", outboard.len);
+            println!("outboard digest: {}", hex::encode(outboard.digest()));
+            Ok(())
+        }
+        Command::Watch {
+            dir,
+            out,
+            processed,
+            layout,
+            encryption_key_file,
+            limits,
+        } => {
+            let mut options = WatchOptions::new(dir);
+            if let Some(processed) = processed {
+                options.processed = processed;
+            }
+            options.limits = (&limits).into();
+            options.layout = layout;
+            let key = storage::load_encryption_key(encryption_key_file.as_deref())?;
+            let store = storage::with_encryption(storage::open(&out)?, key);
+            watch::watch(&options, store.as_ref())
+        }
+        Command::Consume {
+            url,
+            subject,
+            results,
+            queue_group,
+            max_journal_len,
+            limits,
+        } => nats::consume(&ConsumerOptions {
+            url,
+            subject,
+            results,
+            queue_group,
+            limits: (&limits).into(),
+            max_journal_len,
+        }),
+        Command::Serve { config } => server::serve(ServerConfig::load(&config)?),
+        Command::Monitor { config } => monitor::monitor(MonitorConfig::load(&config)?),
+        Command::Coordinator {
+            workers,
+            prove: args,
+        } => prove(&args, &workers),
+        Command::Worker { listen } => farm::serve_worker(listen),
+        Command::Loadtest {
+            url,
+            api_key,
+            workload,
+            bundle,
+            input_len,
+            concurrency,
+            requests,
+        } => {
+            let workload = match (workload, bundle) {
+                (LoadWorkload::Verify, Some(bundle)) => {
+                    Workload::Verify(std::fs::read(&bundle).with_context(|| {
+                        format!("failed to read proof bundle {}", bundle.display())
+                    })?)
+                }
+                (LoadWorkload::Verify, None) => bail!("the verify workload needs --bundle"),
+                (LoadWorkload::Prove, _) => Workload::Prove { input_len },
+            };
+            let report = loadtest::run(&LoadOptions {
+                url,
+                api_key,
+                workload,
+                concurrency,
+                requests,
+            })?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
     }
 }
 
-fn get_ark_verifying_key() -> ark_groth16::VerifyingKey<ark_bn254::Bn254> {
-    use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G2Affine};
-    use std::str::FromStr;
+/// Proves locally, or on the proving farm `workers` if any are given.
+fn prove(args: &ProveArgs, workers: &[String]) -> Result<()> {
+    // Overwritten once proven, for inputs that are secret.
+    let input = Zeroizing::new(args.input.guest_input(
+        args.input_json.as_deref(),
+        args.padding,
+        args.bind_input,
+        args.with_stats,
+        args.with_version,
+        args.nonce,
+        args.frame_len,
+    )?);
+    let cost_model = args
+        .cost_model
+        .as_deref()
+        .map(CostModel::load)
+        .transpose()?;
+    let limits = (&args.limits).into();
+    // With a store to write to, the claim's lifecycle is recorded in it too.
+    let claims = args
+        .out
+        .as_deref()
+        .map(|uri| -> Result<_> {
+            let key = storage::load_encryption_key(args.encryption_key_file.as_deref())?;
+            let store = storage::with_encryption(storage::open(uri)?, key);
+            let claim = lifecycle::claim_id(&input);
+            lifecycle::record_all(
+                store.as_ref(),
+                &claim,
+                &[Stage::InputReceived],
+                None,
+                policy::unix_now(),
+            )?;
+            Ok((store, claim))
+        })
+        .transpose()?;
+    let proven = match workers {
+        [] => prover::prove_with_stats(&input, &limits),
+        workers => prover::prove_on_farm(&input, &limits, workers),
+    };
+    let result = proven.and_then(|(receipt, stats)| {
+        let run = RunParameters::new(&limits, &stats, args.run_id);
+        println!(
+            "proved {} cycles ({} user) in {} segments on the {} prover",
+            stats.total_cycles, stats.user_cycles, stats.segments, stats.backend
+        );
+        if let Some(model) = &cost_model {
+            println!("estimated cost: ${:.4}", model.estimate(&stats).usd);
+        }
+        if let Some(fault) = prover::fault_of(&receipt)? {
+            if let Some((store, _)) = &claims {
+                let key = args.layout.put(
+                    store.as_ref(),
+                    &input,
+                    &ProofBundle::new(receipt).with_run(run),
+                )?;
+                println!("proof of the fault written to {key}");
+            }
+            bail!("{fault}");
+        }
 
-    let alpha_g1 = G1Affine::new(
-        Fq::from_str(
-            "20491192805390485299153009773594534940189261866228447918068658471970481763042",
-        )
-        .unwrap(),
-        Fq::from_str(
-            "9383485363053290200918347156157836566562967994039712273449902621266178545958",
-        )
-        .unwrap(),
-    );
+        // A short read of the input shows in its statistics, if committed,
+        // and the guest commits the BLAKE3 digest of what it read.
+        Journal::of(&receipt).check_stats(&input)?;
+        ensure!(
+            Journal::of(&receipt).as_bytes() == input.expected_journal()?,
+            "journal does not commit to the BLAKE3 digest of the input"
+        );
+
+        if let Some((store, claim)) = &claims {
+            lifecycle::record_all(
+                store.as_ref(),
+                claim,
+                &[Stage::Executed, Stage::Proven],
+                Some(format!("{} cycles", stats.total_cycles)),
+                policy::unix_now(),
+            )?;
+        }
+        verifier::verify_receipt(&receipt, METHOD_ID)?;
+        if let Some((store, claim)) = &claims {
+            let mut bundle = ProofBundle::new(receipt.clone()).with_run(run);
+            if let Some(stark) = &stats.stark {
+                bundle = bundle.with_stark(stark.clone());
+            }
+            if args.timestamp {
+                let stamp = BundleTimestamp::submit(bundle.digest()?, &args.calendars)?;
+                println!("bundle timestamped by {} calendars", stamp.calendars.len());
+                bundle.timestamp = Some(stamp);
+            }
+            let key = args.layout.put(store.as_ref(), &input, &bundle)?;
+            println!("proof bundle written to {key}");
+            // Proving a claim again leaves a later stage as it is.
+            if let Some(claim) = lifecycle::record_all(
+                store.as_ref(),
+                claim,
+                &[Stage::Wrapped],
+                Some(key),
+                policy::unix_now(),
+            )? {
+                println!("claim {} is {}", claim.id, claim.stage());
+            }
+        }
+        Ok(receipt)
+    });
 
-    let beta_g2 = G2Affine::new(
-        Fq2::new(
-            Fq::from_str(
-                "6375614351688725206403948262868962793625744043794305715222011528459656738731",
-            )
-            .unwrap(),
-            Fq::from_str(
-                "4252822878758300859123897981450591353533073413197771768651442665752259397132",
-            )
-            .unwrap(),
-        ),
-        Fq2::new(
-            Fq::from_str(
-                "10505242626370262277552901082094356697409835680220590971873171140371331206856",
-            )
-            .unwrap(),
-            Fq::from_str(
-                "21847035105528745403288232691147584728191162732299865338377159692350059136679",
-            )
-            .unwrap(),
-        ),
+    if let Some(url) = &args.callback_url {
+        let notification = match &result {
+            Ok(receipt) => ProofNotification {
+                succeeded: true,
+                proof: Some(ProofSummary::new(receipt, METHOD_ID)?),
+                ..Default::default()
+            },
+            Err(err) => ProofNotification {
+                error: Some(format!("{err:#}")),
+                ..Default::default()
+            },
+        };
+        // The proof is already made; a callback that cannot be delivered
+        // should not fail the command that made it.
+        if let Err(err) = callback::notify(url, &notification) {
+            tracing::warn!("{err:#}");
+        }
+    }
+
+    result.map(|_| ())
+}
+
+fn verify(args: &VerifyArgs) -> Result<()> {
+    let mut audit = args.audit.open()?;
+    let profile = args
+        .profile
+        .profile
+        .is_some()
+        .then(|| args.profile.load(Vec::new(), args.max_journal_len))
+        .transpose()?;
+    let mut report = VerificationReport::new(
+        &args.bundle.display().to_string(),
+        verify_parameters(args, profile.as_ref())?,
     );
+    let bundle = report
+        .time("read", || {
+            std::fs::read(&args.bundle)
+                .with_context(|| format!("failed to read proof bundle {}", args.bundle.display()))
+        })
+        .and_then(|encoded| {
+            report.digests.bundle_blake3 = Some(blake3::hash(&encoded).to_hex().to_string());
+            report.time("decode", || ProofBundle::decode(&encoded))
+        });
+    let (receipt, outcome) = match bundle {
+        Ok(mut bundle) => {
+            report.software.prover = Some(bundle.metadata.clone());
+            report.record_receipt(&bundle.receipt);
+            let outcome = verify_bundle(args, &mut bundle, profile.as_ref(), &mut report);
+            (Some(bundle.receipt), outcome)
+        }
+        Err(err) => (None, Err(err)),
+    };
+    if let Some(path) = &args.report {
+        let public_input = match (&outcome, &receipt) {
+            (Ok(image_id), Some(receipt)) => Some(match &profile {
+                Some(profile) => profile.public_input(*image_id, &receipt.journal.bytes),
+                None => BindingHash::Blake3.public_input(*image_id, &receipt.journal.bytes),
+            }),
+            _ => None,
+        };
+        report.record_outcome(&outcome, public_input.as_deref());
+        report.write(path)?;
+    }
+    if let Some(audit) = &mut audit {
+        audit.record(Check {
+            bundle: &args.bundle.display().to_string(),
+            receipt: receipt.as_ref(),
+            image_id: outcome.as_ref().ok().copied(),
+            error: outcome.as_ref().err().map(|err| format!("{err:#}")),
+        })?;
+    }
+    outcome.map(|_| ())
+}
 
-    let gamma_g2 = G2Affine::new(
-        Fq2::new(
-            Fq::from_str(
-                "10857046999023057135944570762232829481370756359578518086990519993285655852781",
-            )
-            .unwrap(),
-            Fq::from_str(
-                "11559732032986387107991004021392285783925812861821192530917403151452391805634",
-            )
-            .unwrap(),
-        ),
-        Fq2::new(
-            Fq::from_str(
-                "8495653923123431417604973247489272438418190587263600148770280649306958101930",
-            )
-            .unwrap(),
-            Fq::from_str(
-                "4082367875863433681332203403145435568316851327593401208105741076214120093531",
-            )
-            .unwrap(),
-        ),
+/// Verifies a bundle read by `verify`, returning the image ID it verified
+/// against.
+fn verify_bundle(
+    args: &VerifyArgs,
+    bundle: &mut ProofBundle,
+    profile: Option<&Profile>,
+    report: &mut VerificationReport,
+) -> Result<Digest> {
+    report.time("policy", || -> Result<()> {
+        args.policy
+            .load()?
+            .check(bundle, None, policy::unix_now())?;
+        Ok(())
+    })?;
+    if let Some(timestamp) = &bundle.timestamp {
+        let attestations = report.time("timestamp", || -> Result<_> {
+            ensure!(
+                timestamp.digest == bundle.digest()?,
+                "timestamp is not of this bundle"
+            );
+            timestamp.attestations()
+        })?;
+        let rpc = args
+            .check_timestamp
+            .then(|| args.bitcoin.config().connect())
+            .transpose()?;
+        for attested in attestations {
+            let verified = match (&rpc, &attested.attestation) {
+                (Some(rpc), Attestation::Bitcoin { .. }) => {
+                    let block =
+                        report.time("timestamp block", || attested.check_block(rpc.as_ref()))?;
+                    println!("timestamp from {attested}, found in block {block}");
+                    true
+                }
+                _ => {
+                    println!("timestamp from {attested}, unverified");
+                    false
+                }
+            };
+            report.timestamp_attestations.push(ReportedAttestation {
+                attestation: attested.to_string(),
+                verified,
+            });
+        }
+    }
+    let ProofBundle {
+        metadata,
+        receipt,
+        run,
+        escalation,
+        timestamp: _,
+        da,
+        stark,
+    } = bundle;
+    if args.normalize_seal && verifier::normalize_receipt_seal(receipt)? {
+        println!("normalized a non-canonical seal");
+    }
+    println!(
+        "guest ELF blake3 {} sha256 {}, built {} with risc0 {}",
+        metadata.elf_blake3, metadata.elf_sha256, metadata.built_at, metadata.risc0_version
     );
+    if let Some(run) = run {
+        println!("proving run: {}", serde_json::to_string(run)?);
+    }
+    if let Some(escalation) = escalation {
+        println!(
+            "deadline escalation: {}",
+            serde_json::to_string(escalation)?
+        );
+    }
+    for reference in da {
+        println!(
+            "{} published to {} at {}",
+            reference.content, reference.layer, reference.locator
+        );
+    }
+    let mode = verify_mode(args);
+    let stark = if mode.stark() {
+        Some(
+            stark
+                .as_ref()
+                .context("bundle keeps no STARK receipt; prove it with --keep-stark")?,
+        )
+    } else {
+        None
+    };
+    let image_id = match (mode.groth16(), stark) {
+        (true, stark) => {
+            let image_id = report.time("receipt", || verify_receipt(args, receipt, profile))?;
+            if let Some(stark) = stark {
+                report.time("stark", || verifier::verify_stark(stark, receipt, image_id))?;
+                println!("STARK receipt verified against image ID {image_id}");
+            }
+            image_id
+        }
+        (false, Some(stark)) => {
+            report.time("stark", || verify_stark_only(args, receipt, stark, profile))?
+        }
+        (false, None) => unreachable!("every verify mode checks a receipt"),
+    };
+    check_nonce(args, image_id, &Journal::of(receipt))?;
+    Ok(image_id)
+}
+
+/// Checks the nonce the journal of a receipt verified by `verify` commits to
+/// against `--expect-nonce`, and registers it given `--register-nonce`.
+fn check_nonce(args: &VerifyArgs, image_id: Digest, journal: &Journal) -> Result<()> {
+    if let Some(expected) = &args.expect_nonce {
+        match journal.nonce() {
+            Some(nonce) if nonce == expected => {}
+            Some(nonce) => bail!(
+                "journal commits to nonce {}, not {}",
+                hex::encode(nonce),
+                hex::encode(expected)
+            ),
+            None => bail!("journal commits to no nonce"),
+        }
+    }
+    if args.register_nonce {
+        let store = args.store.open()?;
+        nonces::register(&*store, image_id, journal, policy::unix_now())?;
+        println!(
+            "registered nonce {}",
+            hex::encode(journal.nonce().expect("registered nonces are committed"))
+        );
+    }
+    Ok(())
+}
+
+/// Verifies the receipt of a bundle read by `verify`.
+fn verify_receipt(
+    args: &VerifyArgs,
+    receipt: &Receipt,
+    profile: Option<&Profile>,
+) -> Result<Digest> {
+    if let Some(profile) = profile {
+        let image_id = profile.verify_receipt_any(receipt)?;
+        println!(
+            "receipt verified against image ID {image_id} under profile {}",
+            profile.name
+        );
+        return Ok(image_id);
+    }
+    verifier::check_journal(&receipt.journal.bytes, args.max_journal_len)?;
+    if let (Some(path), Some(height)) = (&args.allowlist, args.height) {
+        let allowlist = Allowlist::load(path)?;
+        let image = allowlist.verify(receipt, height)?;
+        if args.cross_check {
+            verifier::verify_receipt_cross_checked(receipt, image.image_id())?;
+        }
+        println!(
+            "receipt verified against image {} at height {height}",
+            image.name()
+        );
+        return Ok(image.image_id());
+    }
+    let image_ids = match args.image_ids.as_slice() {
+        [] => vec![Digest::from(METHOD_ID)],
+        image_ids => image_ids.to_vec(),
+    };
+    let image_id = verifier::verify_receipt_any(receipt, &image_ids)?;
+    if args.cross_check {
+        verifier::verify_receipt_cross_checked(receipt, image_id)?;
+    }
+    println!("receipt verified against image ID {image_id}");
+    Ok(image_id)
+}
+
+/// The receipts `verify` checks.
+fn verify_mode(args: &VerifyArgs) -> VerifyMode {
+    if args.stark {
+        VerifyMode::Both
+    } else {
+        args.verify_mode
+    }
+}
 
-    let delta_g2 = G2Affine::new(
-        Fq2::new(
-            Fq::from_str(
-                "19928663713463533589216209779412278386769407450988172849262535478593422929698",
-            )
-            .unwrap(),
-            Fq::from_str(
-                "19916519943909223643323234301580053157586699704876134064841182937085943926141",
-            )
-            .unwrap(),
-        ),
-        Fq2::new(
-            Fq::from_str(
-                "4584600978911428195337731119171761277167808711062125916470525050324985708782",
-            )
-            .unwrap(),
-            Fq::from_str(
-                "903010326261527050999816348900764705196723158942686053018929539519969664840",
-            )
-            .unwrap(),
-        ),
+/// Verifies only the STARK receipt kept in a bundle read by `verify`, against
+/// the image IDs it trusts, leaving the Groth16 receipt that wraps it
+/// unchecked.
+fn verify_stark_only(
+    args: &VerifyArgs,
+    receipt: &Receipt,
+    stark: &Receipt,
+    profile: Option<&Profile>,
+) -> Result<Digest> {
+    ensure!(
+        !args.cross_check,
+        "--cross-check checks the Groth16 seal, which --verify-mode stark skips"
     );
+    let image_ids = match (profile, &args.allowlist, args.height) {
+        (Some(profile), _, _) => profile.image_ids.clone(),
+        (None, Some(path), Some(height)) => Allowlist::load(path)?.active_at(height),
+        _ => match args.image_ids.as_slice() {
+            [] => vec![Digest::from(METHOD_ID)],
+            image_ids => image_ids.to_vec(),
+        },
+    };
+    let max_journal_len = profile.map_or(args.max_journal_len, |profile| profile.max_journal_len);
+    verifier::check_journal(&receipt.journal.bytes, max_journal_len)?;
+    let image_id = verifier::verify_stark_any(stark, receipt, &image_ids)?;
+    println!("STARK receipt verified against image ID {image_id}");
+    Ok(image_id)
+}
+
+/// The parameters `verify` runs under, for its report.
+fn verify_parameters(args: &VerifyArgs, profile: Option<&Profile>) -> Result<VerifyParameters> {
+    Ok(VerifyParameters {
+        image_ids: match (&args.allowlist, profile, args.image_ids.as_slice()) {
+            (Some(_), _, _) | (_, Some(_), _) => Vec::new(),
+            (_, _, []) => vec![Digest::from(METHOD_ID).to_string()],
+            (_, _, image_ids) => image_ids.iter().map(Digest::to_string).collect(),
+        },
+        allowlist: args
+            .allowlist
+            .as_ref()
+            .map(|path| path.display().to_string()),
+        height: args.height,
+        profile: profile.map(Profile::summary).transpose()?,
+        max_journal_len: profile.map_or(args.max_journal_len, |profile| profile.max_journal_len),
+        binding_hash: profile
+            .map_or(BindingHash::Blake3, |profile| profile.binding_hash)
+            .to_string(),
+        cross_check: args.cross_check,
+        normalize_seal: args.normalize_seal,
+        verify_mode: verify_mode(args).to_string(),
+        policy: args
+            .policy
+            .policy
+            .as_ref()
+            .map(|path| path.display().to_string()),
+    })
+}
 
-    let gamma_abc_g1 = vec![
-        G1Affine::new(
-            Fq::from_str(
-                "6698887085900109660417671413804888867145870700073340970189635830129386206569",
-            )
-            .unwrap(),
-            Fq::from_str(
-                "10431087902009508261375793061696708147989126018612269070732549055898651692604",
-            )
-            .unwrap(),
-        ),
-        G1Affine::new(
-            Fq::from_str(
-                "20225609417084538563062516991929114218412992453664808591983416996515711931386",
-            )
-            .unwrap(),
-            Fq::from_str(
-                "3236310410959095762960658876334609343091075204896196791007975095263664214628",
-            )
-            .unwrap(),
-        ),
-    ];
-
-    ark_groth16::VerifyingKey::<Bn254> {
-        alpha_g1,
-        beta_g2,
-        gamma_g2,
-        delta_g2,
-        gamma_abc_g1,
+/// Prints the ID and fee of each transaction of an assert package.
+fn print_assert_package(package: &AssertPackage) -> Result<()> {
+    for (name, psbt) in [("commit", &package.commit), ("reveal", &package.reveal)] {
+        let vsize = assert_psbt::estimated_weight(psbt)?.to_vbytes_ceil();
+        let fee = psbt.fee()?;
+        println!(
+            "{name} {} pays {fee} for {vsize} vB ({:.2} sat/vB)",
+            psbt.unsigned_tx.compute_txid(),
+            fee.to_sat() as f64 / vsize as f64
+        );
     }
+    Ok(())
 }
 
+/// `path` relative to `dir`, both taken relative to the working directory.
+fn relative_path(path: &Path, dir: &Path) -> Result<PathBuf> {
+    let path = std::path::absolute(path)?;
+    let dir = if dir.as_os_str().is_empty() {
+        std::env::current_dir()?
+    } else {
+        std::path::absolute(dir)?
+    };
+    let common = path
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative: PathBuf = dir.components().skip(common).map(|_| "..").collect();
+    relative.extend(path.components().skip(common));
+    Ok(relative)
+}
+
+/// Reads a proof bundle's receipt, or a bare bincode-encoded receipt.
+fn read_receipt(path: &Path) -> Result<risc0_zkvm::Receipt> {
+    let encoded = std::fs::read(path)
+        .with_context(|| format!("failed to read receipt {}", path.display()))?;
+    match ProofBundle::decode(&encoded) {
+        Ok(bundle) => Ok(bundle.receipt),
+        Err(_) => bincode::deserialize(&encoded).context("not a proof bundle or receipt"),
+    }
+}
+
+/// The statement of the verdict line in the file at `path`.
+fn read_statement(path: &Path) -> Result<VerdictStatement> {
+    let encoded =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let verdict: Verdict = serde_json::from_slice(&encoded)
+        .with_context(|| format!("invalid verdict {}", path.display()))?;
+    VerdictStatement::of(&verdict)
+}
+
+/// The JSON lines of the file at `path`, skipping blank lines.
+fn read_json_lines<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("invalid line {} of {}", index + 1, path.display()))
+        })
+        .collect()
+}
+
+fn read_bundle(path: &Path) -> Result<ProofBundle> {
+    let encoded = std::fs::read(path)
+        .with_context(|| format!("failed to read proof bundle {}", path.display()))?;
+    ProofBundle::decode(&encoded)
+}
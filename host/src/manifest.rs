@@ -0,0 +1,245 @@
+//! Manifests aggregating the proof bundles of several operators over the same
+//! input, for bridge committees that accept a claim once `m` of their `n`
+//! members have each proven it.
+//!
+//! A manifest names the image ID, the public input every bundle must prove,
+//! the committee's BIP340 x-only keys and the threshold. Each entry points at
+//! one operator's bundle, by a path relative to the manifest, and carries the
+//! operator's signature over the image ID, the public input and the BLAKE3
+//! digest of the bundle, so that an entry cannot be moved to another claim or
+//! another bundle.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use k256::schnorr::{Signature, SigningKey, VerifyingKey};
+use risc0_zkvm::{sha::Digest, Receipt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bundle::ProofBundle, journal::Journal, policy::VerifierPolicy, storage::write_atomic, verifier,
+};
+
+/// Key derivation context of the message operators sign.
+const SIGNING_CONTEXT: &str = "r0-bitvm-blake3 2025 multi-operator manifest entry v1";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(with = "hex::serde")]
+    pub image_id: Digest,
+    #[serde(with = "hex::serde")]
+    pub public_input: [u8; verifier::PUBLIC_INPUT_LEN],
+    /// Number of distinct committee members whose valid entries make a quorum.
+    pub threshold: usize,
+    /// Hex-encoded x-only public keys of the committee.
+    pub committee: Vec<String>,
+    pub entries: Vec<Entry>,
+}
+
+/// One operator's bundle and signature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Hex-encoded x-only public key of the operator.
+    pub operator: String,
+    /// Path of the bundle, relative to the manifest.
+    pub bundle: PathBuf,
+    #[serde(with = "hex::serde")]
+    pub bundle_digest: [u8; 32],
+    #[serde(with = "hex::serde")]
+    pub signature: Vec<u8>,
+}
+
+/// The outcome of checking one entry.
+#[derive(Debug, Serialize)]
+pub struct EntryVerdict {
+    pub operator: String,
+    pub bundle: PathBuf,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuorumReport {
+    pub entries: Vec<EntryVerdict>,
+    /// Distinct committee members with a valid entry.
+    pub signers: usize,
+    pub threshold: usize,
+    pub committee_size: usize,
+    pub quorum: bool,
+}
+
+impl Manifest {
+    pub fn new(
+        image_id: Digest,
+        public_input: [u8; verifier::PUBLIC_INPUT_LEN],
+        committee: &[VerifyingKey],
+        threshold: usize,
+    ) -> Result<Self> {
+        ensure!(threshold > 0, "threshold must be at least 1");
+        ensure!(
+            threshold <= committee.len(),
+            "threshold {threshold} exceeds the committee of {}",
+            committee.len()
+        );
+        let committee: Vec<String> = committee
+            .iter()
+            .map(|key| hex::encode(key.to_bytes()))
+            .collect();
+        let distinct: HashSet<&String> = committee.iter().collect();
+        ensure!(
+            distinct.len() == committee.len(),
+            "committee lists a key twice"
+        );
+        Ok(Self {
+            image_id,
+            public_input,
+            threshold,
+            committee,
+            entries: Vec::new(),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read(path)
+            .with_context(|| format!("failed to read manifest {}", path.display()))?;
+        serde_json::from_slice(&contents)
+            .with_context(|| format!("failed to parse manifest {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, &serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Signs `encoded`, the bundle stored at `bundle` relative to the
+    /// manifest, and adds it, replacing any earlier entry of the same
+    /// operator. The bundle is verified first, so that an operator cannot
+    /// sign a proof of some other claim by mistake.
+    pub fn sign(&mut self, bundle: &Path, encoded: &[u8], key: &SigningKey) -> Result<&Entry> {
+        let operator = hex::encode(key.verifying_key().to_bytes());
+        ensure!(
+            self.committee.contains(&operator),
+            "{operator} is not a member of the committee"
+        );
+        self.check_receipt(&ProofBundle::decode(encoded)?.receipt)?;
+        let bundle_digest: [u8; 32] = blake3::hash(encoded).into();
+        let mut aux_rand = [0; 32];
+        OsRng.fill_bytes(&mut aux_rand);
+        let signature = key
+            .sign_raw(&self.message(&bundle_digest), &aux_rand)
+            .map_err(|err| anyhow!("failed to sign manifest entry: {err}"))?;
+        self.entries.retain(|entry| entry.operator != operator);
+        self.entries.push(Entry {
+            operator,
+            bundle: bundle.to_path_buf(),
+            bundle_digest,
+            signature: signature.to_bytes().to_vec(),
+        });
+        Ok(self.entries.last().unwrap())
+    }
+
+    /// Checks every entry, reading bundles relative to `dir`, and counts the
+    /// committee members with a valid one. Bundles must satisfy `policy` at
+    /// Unix time `now`, with their entry's operator as signer.
+    pub fn verify(&self, dir: &Path, policy: &VerifierPolicy, now: u64) -> QuorumReport {
+        let mut signers = HashSet::new();
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let outcome = self.verify_entry(entry, dir, policy, now).and_then(|()| {
+                    ensure!(
+                        signers.insert(entry.operator.as_str()),
+                        "operator has an earlier valid entry"
+                    );
+                    Ok(())
+                });
+                EntryVerdict {
+                    operator: entry.operator.clone(),
+                    bundle: entry.bundle.clone(),
+                    valid: outcome.is_ok(),
+                    error: outcome.err().map(|err| format!("{err:#}")),
+                }
+            })
+            .collect();
+        QuorumReport {
+            entries,
+            signers: signers.len(),
+            threshold: self.threshold,
+            committee_size: self.committee.len(),
+            quorum: self.threshold > 0 && signers.len() >= self.threshold,
+        }
+    }
+
+    fn verify_entry(
+        &self,
+        entry: &Entry,
+        dir: &Path,
+        policy: &VerifierPolicy,
+        now: u64,
+    ) -> Result<()> {
+        ensure!(
+            self.committee.contains(&entry.operator),
+            "operator is not a member of the committee"
+        );
+        let path = dir.join(&entry.bundle);
+        let encoded = fs::read(&path)
+            .with_context(|| format!("failed to read proof bundle {}", path.display()))?;
+        ensure!(
+            blake3::hash(&encoded) == entry.bundle_digest,
+            "bundle does not match its digest"
+        );
+        let bundle = ProofBundle::decode(&encoded)?;
+        policy.check(&bundle, Some(&entry.operator), now)?;
+        let key = hex::decode(&entry.operator)
+            .ok()
+            .and_then(|key| VerifyingKey::from_bytes(&key).ok())
+            .context("operator is not a valid x-only public key")?;
+        let signature =
+            Signature::try_from(entry.signature.as_slice()).context("malformed signature")?;
+        if key
+            .verify_raw(&self.message(&entry.bundle_digest), &signature)
+            .is_err()
+        {
+            bail!("signature does not verify");
+        }
+        self.check_receipt(&bundle.receipt)
+    }
+
+    /// Verifies a receipt and checks that it proves the manifest's claim.
+    fn check_receipt(&self, receipt: &Receipt) -> Result<()> {
+        verifier::verify_receipt(receipt, self.image_id)?;
+        ensure!(
+            Journal::of(receipt).public_input(self.image_id) == self.public_input,
+            "bundle proves a different public input"
+        );
+        Ok(())
+    }
+
+    fn message(&self, bundle_digest: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new_derive_key(SIGNING_CONTEXT);
+        hasher.update(self.image_id.as_bytes());
+        hasher.update(&self.public_input);
+        hasher.update(bundle_digest);
+        hasher.finalize().into()
+    }
+}
+
+/// Reads a hex-encoded 32-byte BIP340 secret key.
+pub fn load_signing_key(path: &Path) -> Result<SigningKey> {
+    let encoded = fs::read_to_string(path)
+        .with_context(|| format!("failed to read signing key {}", path.display()))?;
+    let key = hex::decode(encoded.trim()).context("signing key is not valid hex")?;
+    SigningKey::from_bytes(&key).map_err(|_| anyhow!("invalid signing key {}", path.display()))
+}
+
+pub fn parse_public_key(encoded: &str) -> Result<VerifyingKey> {
+    let key = hex::decode(encoded.strip_prefix("0x").unwrap_or(encoded))
+        .context("public key is not valid hex")?;
+    VerifyingKey::from_bytes(&key).map_err(|_| anyhow!("invalid x-only public key {encoded}"))
+}
@@ -0,0 +1,196 @@
+//! Host side of sorted BLAKE3 Merkle trees: building a tree over a set of keys
+//! and producing the exclusion proofs the guest verifies. The tree layout is
+//! defined in [`common::merkle`].
+
+use anyhow::{bail, Result};
+use common::merkle::{self, ExclusionProof, Hash, Neighbor};
+
+/// A tree over a sorted, deduplicated set of 32-byte keys.
+#[derive(Debug, Clone)]
+pub struct SortedTree {
+    keys: Vec<Hash>,
+    leaves: Vec<Hash>,
+}
+
+impl SortedTree {
+    pub fn new(keys: impl IntoIterator<Item = Hash>) -> Self {
+        let mut keys: Vec<Hash> = keys.into_iter().collect();
+        keys.sort_unstable();
+        keys.dedup();
+        let leaves = keys.iter().map(merkle::leaf_hash).collect();
+        Self { keys, leaves }
+    }
+
+    pub fn keys(&self) -> &[Hash] {
+        &self.keys
+    }
+
+    pub fn root(&self) -> Hash {
+        let top = if self.leaves.is_empty() {
+            [0; 32]
+        } else {
+            subtree_hash(&self.leaves)
+        };
+        merkle::root_hash(self.keys.len() as u64, &top)
+    }
+
+    /// The authentication path of the leaf at `index`.
+    pub fn path(&self, index: usize) -> Vec<Hash> {
+        let mut path = Vec::new();
+        let mut leaves = self.leaves.as_slice();
+        let mut index = index;
+        // Walk down from the root, collecting siblings, then reverse so the
+        // path runs from the leaf upwards.
+        while leaves.len() > 1 {
+            let split = split_point(leaves.len());
+            let (left, right) = leaves.split_at(split);
+            if index < split {
+                path.push(subtree_hash(right));
+                leaves = left;
+            } else {
+                path.push(subtree_hash(left));
+                leaves = right;
+                index -= split;
+            }
+        }
+        path.reverse();
+        path
+    }
+
+    /// Proves that `key` is not in the tree.
+    pub fn exclusion_proof(&self, key: Hash) -> Result<ExclusionProof> {
+        let upper = match self.keys.binary_search(&key) {
+            Ok(_) => bail!("key {} is in the tree", hex::encode(key)),
+            Err(upper) => upper,
+        };
+        let neighbor = |index: usize| Neighbor {
+            index: index as u64,
+            key: self.keys[index],
+            path: self.path(index),
+        };
+        Ok(ExclusionProof {
+            key,
+            leaf_count: self.keys.len() as u64,
+            lower: upper.checked_sub(1).map(neighbor),
+            upper: (upper < self.keys.len()).then(|| neighbor(upper)),
+        })
+    }
+}
+
+/// Size of the left subtree of a tree of `n > 1` leaves: the largest power of
+/// two smaller than `n`.
+fn split_point(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+fn subtree_hash(leaves: &[Hash]) -> Hash {
+    match leaves {
+        [leaf] => *leaf,
+        _ => {
+            let (left, right) = leaves.split_at(split_point(leaves.len()));
+            merkle::node_hash(&subtree_hash(left), &subtree_hash(right))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::merkle::{top_from_path, ExclusionError};
+
+    use super::*;
+
+    /// A key whose last byte is `n`, so that keys sort as `n` does.
+    fn key(n: u8) -> Hash {
+        let mut key = [0; 32];
+        key[31] = n;
+        key
+    }
+
+    /// A tree over the even keys `2, 4, ..., 2 * count`.
+    fn tree(count: u8) -> SortedTree {
+        SortedTree::new((1..=count).map(|i| key(2 * i)))
+    }
+
+    #[test]
+    fn paths_lead_to_root() {
+        for count in 1..=17 {
+            let tree = tree(count);
+            let n = tree.keys().len() as u64;
+            for (index, key) in tree.keys().iter().enumerate() {
+                let top = top_from_path(index as u64, n, merkle::leaf_hash(key), &tree.path(index))
+                    .expect("path leads to a tree of n leaves");
+                assert_eq!(
+                    merkle::root_hash(n, &top),
+                    tree.root(),
+                    "leaf {index} of {n}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn path_of_wrong_length_or_index_is_rejected() {
+        let tree = tree(5);
+        let leaf = merkle::leaf_hash(&tree.keys()[2]);
+        let path = tree.path(2);
+        assert_eq!(top_from_path(2, 5, leaf, &path[1..]), None);
+        let mut long = path.clone();
+        long.push([0; 32]);
+        assert_eq!(top_from_path(2, 5, leaf, &long), None);
+        assert_eq!(top_from_path(5, 5, leaf, &path), None);
+    }
+
+    #[test]
+    fn exclusion_proofs_verify() {
+        for count in 0..=9 {
+            let tree = tree(count);
+            // Odd keys fall below, between and above the even ones.
+            for absent in (1..=2 * count + 1).step_by(2) {
+                let proof = tree.exclusion_proof(key(absent)).unwrap();
+                let exclusion = proof.verify().unwrap();
+                assert_eq!(exclusion.root, tree.root(), "key {absent} of {count}");
+                assert_eq!(exclusion.key, key(absent));
+                assert_eq!(ExclusionProof::decode(&proof.encode()), Ok(proof));
+            }
+        }
+    }
+
+    #[test]
+    fn present_key_has_no_exclusion_proof() {
+        assert!(tree(4).exclusion_proof(key(6)).is_err());
+    }
+
+    #[test]
+    fn non_adjacent_neighbors_are_rejected() {
+        let tree = tree(6);
+        let mut proof = tree.exclusion_proof(key(5)).unwrap();
+        proof.upper = tree.exclusion_proof(key(7)).unwrap().upper;
+        assert_eq!(proof.verify(), Err(ExclusionError::NotNeighbors));
+    }
+
+    #[test]
+    fn tampered_path_is_rejected() {
+        let tree = tree(6);
+        let mut proof = tree.exclusion_proof(key(5)).unwrap();
+        proof.lower.as_mut().unwrap().path[0][0] ^= 1;
+        assert_eq!(proof.verify(), Err(ExclusionError::BadPath));
+    }
+
+    #[test]
+    fn wrong_leaf_count_is_rejected() {
+        let tree = tree(6);
+        let mut proof = tree.exclusion_proof(key(13)).unwrap();
+        proof.leaf_count = 7;
+        assert!(proof.verify().is_err());
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let mut encoded = tree(3).exclusion_proof(key(3)).unwrap().encode();
+        encoded.push(0);
+        assert_eq!(
+            ExclusionProof::decode(&encoded),
+            Err(ExclusionError::Encoding)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! The image ID of a guest, identified in risc0 as its method ID, and the
+//! byte orders it is written in.
+//!
+//! risc0 holds the ID as eight `u32` words. Its canonical byte form, written
+//! in hex by risc0 tooling and taken by the output prefix and the on-chain
+//! `imageId`, is each word in little-endian order. Printing the words as
+//! big-endian integers gives a different, byte-swapped string, which is easy
+//! to feed to a verifier by mistake; [`MethodId::to_be_bytes`] names that form
+//! so it is only used on purpose.
+
+use std::{fmt, str::FromStr};
+
+use hex::FromHex;
+use risc0_zkvm::sha::Digest;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MethodId(Digest);
+
+impl MethodId {
+    pub fn from_words(words: [u32; 8]) -> Self {
+        Self(Digest::from(words))
+    }
+
+    pub fn to_words(self) -> [u32; 8] {
+        self.0.into()
+    }
+
+    /// From the canonical bytes: each word little-endian.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(Digest::from_bytes(bytes))
+    }
+
+    /// The canonical bytes, as hashed into the output prefix.
+    pub fn to_bytes(self) -> [u8; 32] {
+        self.0.into()
+    }
+
+    /// From the words written as big-endian integers.
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let (words, _) = bytes.as_chunks::<4>();
+        Self::from_words(std::array::from_fn(|index| {
+            u32::from_be_bytes(words[index])
+        }))
+    }
+
+    /// The words written as big-endian integers. This is not the canonical
+    /// form; verifiers expect [`MethodId::to_bytes`].
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (chunk, word) in bytes.as_chunks_mut::<4>().0.iter_mut().zip(self.to_words()) {
+            *chunk = word.to_be_bytes();
+        }
+        bytes
+    }
+
+    pub fn digest(self) -> Digest {
+        self.0
+    }
+}
+
+impl From<[u32; 8]> for MethodId {
+    fn from(words: [u32; 8]) -> Self {
+        Self::from_words(words)
+    }
+}
+
+impl From<Digest> for MethodId {
+    fn from(digest: Digest) -> Self {
+        Self(digest)
+    }
+}
+
+impl From<MethodId> for Digest {
+    fn from(id: MethodId) -> Self {
+        id.0
+    }
+}
+
+/// The canonical bytes in hex.
+impl fmt::Display for MethodId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.to_bytes()))
+    }
+}
+
+/// Parses the canonical bytes in hex, with or without a `0x` prefix.
+impl FromStr for MethodId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix("0x").unwrap_or(s);
+        <[u8; 32]>::from_hex(s)
+            .map(Self::from_bytes)
+            .map_err(|err| format!("invalid image ID: {err}"))
+    }
+}
+
+/// Serialized as the canonical bytes in hex.
+impl Serialize for MethodId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MethodId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Words whose canonical bytes count up from zero.
+    const WORDS: [u32; 8] = [
+        0x03020100, 0x07060504, 0x0b0a0908, 0x0f0e0d0c, 0x13121110, 0x17161514, 0x1b1a1918,
+        0x1f1e1d1c,
+    ];
+
+    fn counting() -> [u8; 32] {
+        std::array::from_fn(|index| index as u8)
+    }
+
+    #[test]
+    fn canonical_bytes_are_little_endian_words() {
+        let id = MethodId::from_words(WORDS);
+        assert_eq!(id.to_bytes(), counting());
+        assert_eq!(
+            id.to_string(),
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        );
+        assert_eq!(
+            hex::encode(id.to_be_bytes()),
+            "03020100070605040b0a09080f0e0d0c13121110171615141b1a19181f1e1d1c"
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let id = MethodId::from_words(WORDS);
+        assert_eq!(id.to_words(), WORDS);
+        assert_eq!(MethodId::from_bytes(id.to_bytes()), id);
+        assert_eq!(MethodId::from_be_bytes(id.to_be_bytes()), id);
+        assert_eq!(MethodId::from(id.digest()), id);
+        assert_eq!(id.to_string().parse::<MethodId>(), Ok(id));
+        assert_eq!(format!("0x{id}").parse::<MethodId>(), Ok(id));
+
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{id}\""));
+        assert_eq!(serde_json::from_str::<MethodId>(&json).unwrap(), id);
+    }
+
+    #[test]
+    fn rejects_malformed_hex() {
+        assert!("0001".parse::<MethodId>().is_err());
+        assert!(hex::encode([0; 33]).parse::<MethodId>().is_err());
+        assert!("zz".repeat(32).parse::<MethodId>().is_err());
+    }
+}
@@ -0,0 +1,612 @@
+//! `monitor` mode: watches the chain for challenges to the claims it is
+//! configured with and responds to them, so operators and challengers do not
+//! each write a chain watcher of their own.
+//!
+//! Every block is scanned, in order, for transactions that spend a watched
+//! outpoint or pay a watched address, e.g. the connector output a challenger
+//! spends to challenge an assert, or the commit output the operator's assert
+//! reveal spends. Each one found is matched to its claim and answered with
+//! the claim's [`Response`], and reported to the webhook, if configured, as
+//! a [`ChallengeAlert`]. A challenge moves a committed claim to challenged in
+//! its [`lifecycle`](crate::lifecycle).
+//!
+//! A block is only scanned once it has the configured number of
+//! confirmations. The hashes of the blocks scanned last are kept, and a block
+//! the best chain no longer has is scanned again from its replacement, after
+//! rolling back the lifecycle events its challenges recorded. Transactions a
+//! response broadcast cannot be taken back; on a reorg they are rebroadcast
+//! if the challenge is found again.
+//!
+//! The next height to scan is kept in a state file with those hashes, so a
+//! restarted monitor picks up where it stopped; a block being scanned when it
+//! stopped is scanned, and answered, again.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{ensure, Context, Result};
+use bitcoin::{
+    address::NetworkUnchecked, Address, BlockHash, Network, OutPoint, ScriptBuf, Transaction, Txid,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assert_psbt::AssertPackage,
+    assert_tx::AssertWitness,
+    bitcoin_rpc::{self, BitcoinConfig, BitcoinRpc},
+    bundle::ProofBundle,
+    callback,
+    disprove::DisproveTree,
+    fraud::{self, Commitments},
+    lifecycle::{self, Stage},
+    method_id::MethodId,
+    policy::unix_now,
+    storage::{self, write_atomic, Store},
+};
+
+/// Configuration for `monitor` mode, loaded from a TOML file.
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct MonitorConfig {
+    /// Backend blocks are read from and responses broadcast through.
+    #[serde(default)]
+    pub bitcoin: BitcoinConfig,
+    /// Network the watched and payout addresses are of.
+    #[serde(default = "default_network")]
+    pub network: Network,
+    /// Storage URI of the receipt store holding the claims' proof bundles
+    /// and lifecycles.
+    pub store: String,
+    /// File holding the hex-encoded key the store is encrypted with. If
+    /// unset, the key is taken from `R0_BITVM_STORAGE_KEY` when present.
+    #[serde(default)]
+    pub encryption_key_file: Option<PathBuf>,
+    /// File the next height to scan is kept in.
+    pub state_file: PathBuf,
+    /// Height to start scanning at when there is no state file. Defaults to
+    /// the tip, so that only blocks mined from then on are scanned.
+    #[serde(default)]
+    pub start_height: Option<u64>,
+    /// Confirmations a block needs before it is scanned.
+    #[serde(default = "default_confirmations")]
+    pub confirmations: u64,
+    /// Seconds between polls of the tip.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// URL every challenge found is POSTed to.
+    #[serde(default)]
+    pub webhook: Option<String>,
+    #[serde(default, rename = "watch")]
+    pub watches: Vec<Watch>,
+}
+
+/// A claim and the chain activity that challenges it.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Watch {
+    /// ID of the claim, as in its lifecycle.
+    pub claim: String,
+    /// Proof bundle of the claim. Defaults to the bundle its lifecycle
+    /// recorded when it was wrapped.
+    #[serde(default)]
+    pub bundle: Option<PathBuf>,
+    /// Outpoints whose spending transaction is a challenge.
+    #[serde(default)]
+    pub outpoints: Vec<OutPoint>,
+    /// Addresses a payment to which is a challenge.
+    #[serde(default)]
+    pub addresses: Vec<Address<NetworkUnchecked>>,
+    #[serde(default)]
+    pub response: Response,
+}
+
+/// What the monitor does about a challenge, besides reporting it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Response {
+    /// Only report it.
+    #[default]
+    Alert,
+    /// Broadcast the signed assert package at `package`, continuing the
+    /// claim's assert once it is challenged.
+    Assert { package: PathBuf },
+    /// Read the transaction as an operator's assert of the claim and, if it
+    /// is fraudulent, spend the disprove leaf of the connector output
+    /// `connector` refuting it, paying what is left to `payout_address`.
+    Disprove {
+        tree: PathBuf,
+        connector: OutPoint,
+        payout_address: Address<NetworkUnchecked>,
+        /// Fee rate in sat/vB. Defaults to the estimate for 6 blocks.
+        #[serde(default)]
+        fee_rate: Option<f64>,
+    },
+}
+
+impl Response {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Alert => "alert",
+            Self::Assert { .. } => "assert",
+            Self::Disprove { .. } => "disprove",
+        }
+    }
+}
+
+/// Body POSTed as JSON to the webhook for every challenge found.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChallengeAlert {
+    pub claim: String,
+    /// The challenging transaction.
+    pub txid: Txid,
+    pub height: u64,
+    pub block: BlockHash,
+    /// The watched outpoints it spends.
+    pub outpoints: Vec<OutPoint>,
+    /// The watched addresses it pays.
+    pub addresses: Vec<String>,
+    /// `alert`, `assert` or `disprove`, or `rollback` for a challenge a
+    /// reorg took out of the chain, whose lifecycle event was undone.
+    pub action: &'static str,
+    /// What the response did, e.g. the transaction it broadcast.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Blocks whose hashes are kept to detect reorgs; a deeper reorg stalls the
+/// monitor until its state is reset.
+const REORG_WINDOW: usize = 144;
+
+/// What the monitor keeps across restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MonitorState {
+    next_height: u64,
+    /// The hashes of the last blocks scanned, by height.
+    #[serde(default)]
+    scanned: BTreeMap<u64, BlockHash>,
+    /// The challenges recorded in the lifecycle from those blocks.
+    #[serde(default)]
+    recorded: Vec<Recorded>,
+}
+
+/// A challenge that moved a claim to challenged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Recorded {
+    height: u64,
+    claim: String,
+    txid: Txid,
+}
+
+impl MonitorState {
+    /// Rewinds past the scanned blocks the best chain, of height `tip`, no
+    /// longer has, returning the challenges recorded from them.
+    fn rewind(&mut self, rpc: &dyn BitcoinRpc, tip: u64) -> Result<Vec<Recorded>> {
+        let mut fork = None;
+        for (&height, &hash) in self.scanned.iter().rev() {
+            if height <= tip && rpc.block_hash(height)? == hash {
+                fork = Some(height);
+                break;
+            }
+            tracing::warn!("block {hash} at height {height} was reorged out");
+        }
+        let Some(fork) = fork else {
+            ensure!(
+                self.scanned.is_empty(),
+                "reorg deeper than the {REORG_WINDOW} blocks kept; remove the state file to \
+                 rescan from a known height"
+            );
+            return Ok(Vec::new());
+        };
+        self.scanned.retain(|&height, _| height <= fork);
+        self.next_height = self.next_height.min(fork + 1);
+        let (undone, kept) = std::mem::take(&mut self.recorded)
+            .into_iter()
+            .partition(|recorded| recorded.height > fork);
+        self.recorded = kept;
+        Ok(undone)
+    }
+
+    fn scanned(&mut self, height: u64, hash: BlockHash) {
+        self.scanned.insert(height, hash);
+        while self.scanned.len() > REORG_WINDOW {
+            self.scanned.pop_first();
+        }
+        if let Some((&oldest, _)) = self.scanned.first_key_value() {
+            self.recorded.retain(|recorded| recorded.height >= oldest);
+        }
+        self.next_height = height + 1;
+    }
+}
+
+fn default_network() -> Network {
+    Network::Bitcoin
+}
+
+fn default_confirmations() -> u64 {
+    bitcoin_rpc::DEFAULT_CONFIRMATIONS
+}
+
+fn default_interval_secs() -> u64 {
+    30
+}
+
+impl MonitorConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read monitor config {}", path.display()))?;
+        let config: Self = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse monitor config {}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(!self.watches.is_empty(), "no claims to watch");
+        ensure!(
+            self.confirmations > 0,
+            "blocks need at least one confirmation to be scanned"
+        );
+        let mut claims = HashSet::new();
+        for watch in &self.watches {
+            ensure!(
+                claims.insert(watch.claim.as_str()),
+                "claim {} is watched twice",
+                watch.claim
+            );
+            ensure!(
+                !watch.outpoints.is_empty() || !watch.addresses.is_empty(),
+                "claim {} watches no outpoint or address",
+                watch.claim
+            );
+            for address in &watch.addresses {
+                ensure!(
+                    address.is_valid_for_network(self.network),
+                    "watched address {address:?} is not a {} address",
+                    self.network
+                );
+            }
+            if let Response::Disprove { payout_address, .. } = &watch.response {
+                ensure!(
+                    payout_address.is_valid_for_network(self.network),
+                    "payout address {payout_address:?} is not a {} address",
+                    self.network
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A watch with its addresses resolved to the scripts they pay.
+struct Watched {
+    watch: Watch,
+    scripts: Vec<(ScriptBuf, String)>,
+}
+
+/// Scans the chain and answers challenges, until the process is stopped.
+pub fn monitor(config: MonitorConfig) -> Result<()> {
+    let rpc = config.bitcoin.connect()?;
+    let key = storage::load_encryption_key(config.encryption_key_file.as_deref())?;
+    let store = storage::with_encryption(storage::open(&config.store)?, key);
+    let watched: Vec<Watched> = config
+        .watches
+        .iter()
+        .map(|watch| Watched {
+            scripts: watch
+                .addresses
+                .iter()
+                .map(|address| {
+                    let address = address.clone().assume_checked();
+                    (address.script_pubkey(), address.to_string())
+                })
+                .collect(),
+            watch: watch.clone(),
+        })
+        .collect();
+
+    let mut state = match std::fs::read(&config.state_file) {
+        Ok(encoded) => serde_json::from_slice(&encoded)
+            .with_context(|| format!("corrupt monitor state {}", config.state_file.display()))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => MonitorState {
+            next_height: match config.start_height {
+                Some(height) => height,
+                None => (rpc.tip_height()? + 2).saturating_sub(config.confirmations),
+            },
+            scanned: BTreeMap::new(),
+            recorded: Vec::new(),
+        },
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to read {}", config.state_file.display()))
+        }
+    };
+    tracing::info!(
+        "monitoring {} claims from height {}",
+        watched.len(),
+        state.next_height
+    );
+
+    loop {
+        let scanned = rpc.tip_height().and_then(|tip| {
+            for undone in state.rewind(rpc.as_ref(), tip)? {
+                roll_back(&config, store.as_ref(), &undone);
+            }
+            // Blocks up to this height have the confirmations required.
+            let confirmed = (tip + 1).saturating_sub(config.confirmations);
+            while state.next_height <= confirmed {
+                let height = state.next_height;
+                let hash = rpc.block_hash(height)?;
+                for tx in rpc.block(&hash)?.txdata {
+                    for watched in &watched {
+                        let Some(alert) = challenge(watched, &tx, height, hash) else {
+                            continue;
+                        };
+                        let txid = alert.txid;
+                        if respond(&config, rpc.as_ref(), store.as_ref(), watched, &tx, alert) {
+                            state.recorded.push(Recorded {
+                                height,
+                                claim: watched.watch.claim.clone(),
+                                txid,
+                            });
+                        }
+                    }
+                }
+                state.scanned(height, hash);
+                write_atomic(&config.state_file, &serde_json::to_vec(&state)?)?;
+            }
+            Ok(())
+        });
+        if let Err(err) = scanned {
+            tracing::warn!("failed to scan the chain: {err:#}");
+        }
+        thread::sleep(Duration::from_secs(config.interval_secs));
+    }
+}
+
+/// Undoes the lifecycle event of a challenge a reorg took out of the chain,
+/// and reports it.
+fn roll_back(config: &MonitorConfig, store: &dyn Store, recorded: &Recorded) {
+    let txid = recorded.txid.to_string();
+    match lifecycle::rollback(store, &recorded.claim, Stage::Challenged, &txid) {
+        Ok(true) => tracing::warn!(
+            "claim {}: challenge {txid} was reorged out, rolled back to committed",
+            recorded.claim
+        ),
+        Ok(false) => return,
+        Err(err) => {
+            tracing::warn!("claim {}: cannot roll back: {err:#}", recorded.claim);
+            return;
+        }
+    }
+    if let Some(url) = &config.webhook {
+        let alert = serde_json::json!({
+            "claim": recorded.claim,
+            "txid": txid,
+            "height": recorded.height,
+            "action": "rollback",
+        });
+        if let Err(err) = callback::post(url, &alert) {
+            tracing::warn!("claim {}: {err:#}", recorded.claim);
+        }
+    }
+}
+
+/// The alert for `tx` if it challenges the watched claim.
+fn challenge(
+    watched: &Watched,
+    tx: &Transaction,
+    height: u64,
+    block: BlockHash,
+) -> Option<ChallengeAlert> {
+    let outpoints: Vec<OutPoint> = tx
+        .input
+        .iter()
+        .map(|input| input.previous_output)
+        .filter(|outpoint| watched.watch.outpoints.contains(outpoint))
+        .collect();
+    let addresses: Vec<String> = watched
+        .scripts
+        .iter()
+        .filter(|(script, _)| {
+            tx.output
+                .iter()
+                .any(|output| output.script_pubkey == *script)
+        })
+        .map(|(_, address)| address.clone())
+        .collect();
+    if outpoints.is_empty() && addresses.is_empty() {
+        return None;
+    }
+    Some(ChallengeAlert {
+        claim: watched.watch.claim.clone(),
+        txid: tx.compute_txid(),
+        height,
+        block,
+        outpoints,
+        addresses,
+        action: watched.watch.response.name(),
+        outcome: None,
+        error: None,
+    })
+}
+
+/// Answers a challenge and reports it, returning whether it moved the claim
+/// to challenged. Failures are reported rather than returned, so that one
+/// claim does not stop the monitor.
+fn respond(
+    config: &MonitorConfig,
+    rpc: &dyn BitcoinRpc,
+    store: &dyn Store,
+    watched: &Watched,
+    tx: &Transaction,
+    mut alert: ChallengeAlert,
+) -> bool {
+    tracing::info!(
+        "transaction {} challenges claim {}",
+        alert.txid,
+        alert.claim
+    );
+    let mut recorded = false;
+    let outcome = match &watched.watch.response {
+        Response::Alert => record_challenge(store, &alert).map(|moved| {
+            recorded = moved;
+            None
+        }),
+        Response::Assert { package } => record_challenge(store, &alert)
+            .and_then(|moved| {
+                recorded = moved;
+                continue_assert(rpc, package)
+            })
+            .map(Some),
+        Response::Disprove {
+            tree,
+            connector,
+            payout_address,
+            fee_rate,
+        } => disprove(
+            rpc,
+            store,
+            &watched.watch,
+            tx,
+            &DisproveTarget {
+                tree,
+                connector: *connector,
+                payout: payout_address.clone().assume_checked().script_pubkey(),
+                fee_rate: *fee_rate,
+            },
+        )
+        .map(Some),
+    };
+    match outcome {
+        Ok(outcome) => alert.outcome = outcome,
+        Err(err) => {
+            tracing::warn!("claim {}: response failed: {err:#}", alert.claim);
+            alert.error = Some(format!("{err:#}"));
+        }
+    }
+    if let Some(url) = &config.webhook {
+        if let Err(err) = callback::post(url, &alert) {
+            tracing::warn!("claim {}: {err:#}", alert.claim);
+        }
+    }
+    recorded
+}
+
+/// Moves a committed claim to challenged, returning whether it did.
+fn record_challenge(store: &dyn Store, alert: &ChallengeAlert) -> Result<bool> {
+    match lifecycle::load(store, &alert.claim)? {
+        Some(claim) if claim.stage() == Stage::Committed => {
+            lifecycle::record(
+                store,
+                &alert.claim,
+                Stage::Challenged,
+                Some(alert.txid.to_string()),
+                unix_now(),
+            )?;
+            return Ok(true);
+        }
+        Some(claim) => tracing::warn!(
+            "claim {} is challenged while {}",
+            alert.claim,
+            claim.stage()
+        ),
+        None => tracing::warn!("claim {} has no lifecycle", alert.claim),
+    }
+    Ok(false)
+}
+
+/// Broadcasts the transactions of a signed assert package.
+fn continue_assert(rpc: &dyn BitcoinRpc, package: &Path) -> Result<String> {
+    let (commit, reveal) = AssertPackage::load(package)?.finalize()?;
+    rpc.broadcast(&commit)?;
+    Ok(format!("broadcast assert {}", rpc.broadcast(&reveal)?))
+}
+
+struct DisproveTarget<'a> {
+    tree: &'a Path,
+    connector: OutPoint,
+    payout: ScriptBuf,
+    fee_rate: Option<f64>,
+}
+
+/// Checks `tx`, an operator's assert, against the claim's proof bundle and
+/// spends the disprove leaf refuting it if it is fraudulent.
+fn disprove(
+    rpc: &dyn BitcoinRpc,
+    store: &dyn Store,
+    watch: &Watch,
+    tx: &Transaction,
+    target: &DisproveTarget,
+) -> Result<String> {
+    let bundle = match &watch.bundle {
+        Some(path) => {
+            let encoded = std::fs::read(path)
+                .with_context(|| format!("failed to read proof bundle {}", path.display()))?;
+            ProofBundle::decode(&encoded)?
+        }
+        None => {
+            let claim = lifecycle::load(store, &watch.claim)?
+                .with_context(|| format!("claim {} has no lifecycle", watch.claim))?;
+            let key = claim
+                .events
+                .iter()
+                .find(|event| event.stage == Stage::Wrapped)
+                .and_then(|event| event.detail.as_deref())
+                .with_context(|| format!("claim {} has no stored proof bundle", watch.claim))?;
+            let encoded = store
+                .get(key)?
+                .with_context(|| format!("proof bundle {key} is missing from storage"))?;
+            ProofBundle::decode(&encoded)?
+        }
+    };
+    let image_id = MethodId::from_str(&bundle.metadata.image_id)
+        .map_err(anyhow::Error::msg)
+        .context("invalid image ID in the proof bundle")?;
+
+    let commitments = Commitments::from(AssertWitness::from_transaction(tx, None)?);
+    let Some(finding) =
+        fraud::detect(&commitments, image_id.into(), &bundle.receipt.journal.bytes)?
+    else {
+        return Ok("assert is consistent with the claim".to_string());
+    };
+
+    let tree = DisproveTree::load(target.tree)?;
+    let txout = rpc
+        .transaction(&target.connector.txid)?
+        .output
+        .get(target.connector.vout as usize)
+        .with_context(|| format!("connector output {} does not exist", target.connector))?
+        .clone();
+    ensure!(
+        txout.script_pubkey == tree.script_pubkey()?,
+        "connector output {} is not committed to the script tree",
+        target.connector
+    );
+    let fee_rate = match target.fee_rate {
+        Some(sat_per_vb) => bitcoin_rpc::sat_per_vb_rate(sat_per_vb),
+        None => rpc.fee_rate(6)?,
+    };
+    let disprove = tree.witness(finding.fraud, &commitments)?.transaction(
+        target.connector,
+        txout.value,
+        target.payout.clone(),
+        fee_rate,
+    )?;
+    let txid = rpc.broadcast(&disprove)?;
+    tracing::info!(
+        "claim {}: disproved {} in {txid}",
+        watch.claim,
+        finding.fraud
+    );
+    Ok(format!(
+        "disproved {}: {}; broadcast {txid}",
+        finding.fraud, finding.reason
+    ))
+}
@@ -0,0 +1,237 @@
+//! `consume` mode: takes prove requests from a NATS subject and publishes the
+//! resulting proof bundles, so the prover can sit behind existing operator
+//! message infrastructure.
+//!
+//! Requests have the same JSON body as `POST /jobs` of the proving service.
+//! The bincode-encoded [`ProofBundle`] of each proven request is published to
+//! the results subject, and failures as `{"error": "..."}` to
+//! `<results>.failed`. A request with a reply subject gets either answer there
+//! instead; an error starts with `{`, which a bundle never does.
+//! Workers subscribe as one queue group, so each request is proven by a single
+//! worker. Core NATS delivers at most once: a request taken by a worker that
+//! then dies is lost, so clients should time out and resubmit.
+//!
+//! Only the subset of the NATS client protocol needed for this is implemented,
+//! over plain TCP.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use methods::METHOD_ID;
+use serde::Deserialize;
+use serde_json::json;
+use zeroize::Zeroizing;
+
+use crate::{
+    bundle::ProofBundle,
+    prover::{self, ProverLimits},
+    server::SubmitRequest,
+    verifier,
+};
+
+pub struct ConsumerOptions {
+    /// `nats://[user:password@|token@]host[:port]`.
+    pub url: String,
+    pub subject: String,
+    pub results: String,
+    pub queue_group: String,
+    pub limits: ProverLimits,
+    pub max_journal_len: usize,
+}
+
+/// A message delivered on a subscription.
+pub struct Message {
+    pub subject: String,
+    pub reply: Option<String>,
+    pub payload: Vec<u8>,
+}
+
+/// A connection to a NATS server. Server pings are answered on a background
+/// thread, so the connection stays up while a long proof runs.
+pub struct Connection {
+    writer: Arc<Mutex<TcpStream>>,
+    messages: mpsc::Receiver<Result<Message>>,
+    max_payload: usize,
+}
+
+#[derive(Deserialize)]
+struct ServerInfo {
+    max_payload: usize,
+}
+
+impl Connection {
+    pub fn connect(url: &str) -> Result<Self> {
+        let location = url
+            .strip_prefix("nats://")
+            .with_context(|| format!("NATS URL {url:?} does not start with nats://"))?;
+        let (credentials, address) = match location.rsplit_once('@') {
+            Some((credentials, address)) => (Some(credentials), address),
+            None => (None, location),
+        };
+        let address = if address.contains(':') {
+            address.to_string()
+        } else {
+            format!("{address}:4222")
+        };
+        let stream = TcpStream::connect(&address)
+            .with_context(|| format!("failed to connect to NATS server {address}"))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let line = read_line(&mut reader)?;
+        let info = line
+            .strip_prefix("INFO ")
+            .with_context(|| format!("unexpected NATS greeting {line:?}"))?;
+        let info: ServerInfo = serde_json::from_str(info).context("invalid NATS server info")?;
+
+        let mut options = json!({
+            "verbose": false,
+            "pedantic": false,
+            "name": env!("CARGO_PKG_NAME"),
+            "lang": "rust",
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        match credentials.map(|credentials| credentials.split_once(':')) {
+            Some(Some((user, pass))) => {
+                options["user"] = user.into();
+                options["pass"] = pass.into();
+            }
+            Some(None) => options["auth_token"] = credentials.into(),
+            None => {}
+        }
+        let mut writer = stream;
+        write!(writer, "CONNECT {options}\r\nPING\r\n")?;
+        loop {
+            match read_line(&mut reader)?.as_str() {
+                "PONG" => break,
+                "+OK" => {}
+                line if line.starts_with("INFO ") => {}
+                line => bail!("NATS server refused the connection: {line}"),
+            }
+        }
+
+        let writer = Arc::new(Mutex::new(writer));
+        let (sender, messages) = mpsc::channel();
+        let pong_writer = writer.clone();
+        thread::spawn(move || loop {
+            let message = read_message(&mut reader, &pong_writer);
+            let failed = message.is_err();
+            if sender.send(message).is_err() || failed {
+                break;
+            }
+        });
+        Ok(Self {
+            writer,
+            messages,
+            max_payload: info.max_payload,
+        })
+    }
+
+    /// Subscribes to `subject` as a member of `queue_group`.
+    pub fn subscribe(&self, subject: &str, queue_group: &str) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        write!(writer, "SUB {subject} {queue_group} 1\r\n")?;
+        Ok(())
+    }
+
+    pub fn publish(&self, subject: &str, payload: &[u8]) -> Result<()> {
+        ensure!(
+            payload.len() <= self.max_payload,
+            "message of {} bytes exceeds the server's limit of {}",
+            payload.len(),
+            self.max_payload
+        );
+        let mut writer = self.writer.lock().unwrap();
+        write!(writer, "PUB {subject} {}\r\n", payload.len())?;
+        writer.write_all(payload)?;
+        writer.write_all(b"\r\n")?;
+        Ok(())
+    }
+
+    /// Waits for the next message on any subscription.
+    pub fn next_message(&self) -> Result<Message> {
+        self.messages
+            .recv()
+            .map_err(|_| anyhow!("NATS connection closed"))?
+    }
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = String::new();
+    ensure!(
+        reader.read_line(&mut line)? > 0,
+        "NATS server closed the connection"
+    );
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Reads up to the next `MSG`, answering pings on the way.
+fn read_message(reader: &mut impl BufRead, writer: &Mutex<TcpStream>) -> Result<Message> {
+    loop {
+        let line = read_line(reader)?;
+        let mut fields = line.split_ascii_whitespace();
+        match fields.next() {
+            Some("MSG") => {
+                let fields: Vec<&str> = fields.collect();
+                let (subject, reply, len) = match fields.as_slice() {
+                    [subject, _sid, len] => (subject, None, len),
+                    [subject, _sid, reply, len] => (subject, Some(reply.to_string()), len),
+                    _ => bail!("malformed NATS message header {line:?}"),
+                };
+                let len: usize = len.parse().context("malformed NATS message length")?;
+                let mut payload = vec![0; len + 2];
+                reader.read_exact(&mut payload)?;
+                payload.truncate(len);
+                return Ok(Message {
+                    subject: subject.to_string(),
+                    reply,
+                    payload,
+                });
+            }
+            Some("PING") => writer.lock().unwrap().write_all(b"PONG\r\n")?,
+            Some("PONG" | "+OK" | "INFO") => {}
+            Some("-ERR") => tracing::warn!("NATS server error: {line}"),
+            _ => bail!("unexpected NATS protocol line {line:?}"),
+        }
+    }
+}
+
+/// Proves requests from `options.subject` until the connection fails.
+pub fn consume(options: &ConsumerOptions) -> Result<()> {
+    let connection = Connection::connect(&options.url)?;
+    connection.subscribe(&options.subject, &options.queue_group)?;
+    tracing::info!("consuming prove requests from {}", options.subject);
+    loop {
+        let message = connection.next_message()?;
+        tracing::info!("proving request from {}", message.subject);
+        match prove_request(options, &message.payload) {
+            Ok(bundle) => {
+                let subject = message.reply.as_deref().unwrap_or(&options.results);
+                connection.publish(subject, &bundle)?;
+            }
+            Err(err) => {
+                tracing::warn!("request failed: {err:#}");
+                let error = json!({ "error": format!("{err:#}") }).to_string();
+                let subject = match &message.reply {
+                    Some(reply) => reply.clone(),
+                    None => format!("{}.failed", options.results),
+                };
+                connection.publish(&subject, error.as_bytes())?;
+            }
+        }
+    }
+}
+
+fn prove_request(options: &ConsumerOptions, payload: &[u8]) -> Result<Vec<u8>> {
+    let request: SubmitRequest =
+        serde_json::from_slice(payload).context("invalid prove request")?;
+    let input = Zeroizing::new(request.guest_input()?);
+    let receipt = prover::prove(&input, &options.limits)?;
+    verifier::check_journal(&receipt.journal.bytes, options.max_journal_len)?;
+    verifier::verify_receipt(&receipt, METHOD_ID)?;
+    ProofBundle::new(receipt).encode()
+}
@@ -0,0 +1,83 @@
+//! A registry of the nonces proofs were accepted for, so that a proof is only
+//! ever accepted once: an old proof replayed for a new bridge event commits
+//! the nonce of the old event, which is already registered.
+//!
+//! A nonce is committed by proving an input wrapped in
+//! [`GuestInput::WithNonce`](crate::input::GuestInput::WithNonce). Each
+//! accepted nonce is recorded in the receipt store under
+//! `nonces/<nonce>.json` with the image ID and journal digest of the proof
+//! that used it. The record is written with [`Store::put_new`], so of two
+//! verifiers accepting proofs for the same nonce at once, only one registers
+//! it.
+
+use anyhow::{bail, Context, Result};
+use common::journal::Nonce;
+use risc0_zkvm::sha::Digest;
+use serde::{Deserialize, Serialize};
+
+use crate::{journal::Journal, storage::Store};
+
+/// Storage key prefix of the nonce records.
+const PREFIX: &str = "nonces/";
+
+/// The proof a nonce was first accepted for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Hex-encoded image ID the proof verified against.
+    pub image_id: String,
+    /// Hex-encoded SHA-256 digest of the proof's journal.
+    pub journal_digest: String,
+    /// Unix timestamp, in seconds.
+    pub at: u64,
+}
+
+/// Reads the record of `nonce`, or `None` if it was never accepted.
+pub fn load(store: &dyn Store, nonce: &Nonce) -> Result<Option<Record>> {
+    store
+        .get(&key(nonce))?
+        .map(|encoded| {
+            serde_json::from_slice(&encoded)
+                .with_context(|| format!("corrupt record of nonce {}", hex::encode(nonce)))
+        })
+        .transpose()
+}
+
+/// Records that the proof of `journal` under `image_id` was accepted for the
+/// nonce it commits to, failing if the journal commits to none or the nonce
+/// was already accepted, by this proof or another.
+pub fn register(
+    store: &dyn Store,
+    image_id: Digest,
+    journal: &Journal,
+    now: u64,
+) -> Result<Record> {
+    let nonce = journal.nonce().context("journal commits to no nonce")?;
+    let record = Record {
+        image_id: image_id.to_string(),
+        journal_digest: journal.digest().to_string(),
+        at: now,
+    };
+    if store.put_new(&key(nonce), &serde_json::to_vec_pretty(&record)?)? {
+        return Ok(record);
+    }
+    let existing = load(store, nonce)?
+        .with_context(|| format!("nonce {} is registered but unreadable", hex::encode(nonce)))?;
+    if existing.journal_digest == record.journal_digest {
+        bail!(
+            "proof was already accepted for nonce {} at {}",
+            hex::encode(nonce),
+            existing.at
+        );
+    }
+    bail!(
+        "nonce {} was already used by the proof of journal {} under image ID {} at {}",
+        hex::encode(nonce),
+        existing.journal_digest,
+        existing.image_id,
+        existing.at
+    );
+}
+
+fn key(nonce: &Nonce) -> String {
+    format!("{PREFIX}{}.json", hex::encode(nonce))
+}
@@ -0,0 +1,135 @@
+//! The operator's Bitcoin key, given as a public key with, for keys held by
+//! an HSM or hardware wallet, the origin the signer derives it from.
+//!
+//! A key is written as the key expression of a `tr()` output descriptor, with
+//! or without the `tr()` around it and its checksum:
+//!
+//! - a hex x-only or compressed public key, e.g. `tr(79be…f817)`;
+//! - either of those after its origin, e.g. `[d34db33f/86'/0'/0']02…`;
+//! - an xpub and the unhardened path to the key below it, after an optional
+//!   origin of the xpub, e.g. `tr([d34db33f/86'/0'/0']xpub…/0/3)`.
+//!
+//! The origin goes into every PSBT input the key signs, so that an external
+//! signer can tell the key is its own and derive it. No secret key is read.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use bitcoin::{
+    bip32::{ChildNumber, DerivationPath, Fingerprint, KeySource, Xpub},
+    secp256k1::Secp256k1,
+    PublicKey, XOnlyPublicKey,
+};
+
+/// The operator's key and where it is derived from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorKey {
+    pub key: XOnlyPublicKey,
+    /// Fingerprint of the signer's master key and the path from it to the
+    /// key, if the key is given with an origin or as an xpub.
+    pub origin: Option<KeySource>,
+}
+
+impl From<XOnlyPublicKey> for OperatorKey {
+    fn from(key: XOnlyPublicKey) -> Self {
+        Self { key, origin: None }
+    }
+}
+
+impl FromStr for OperatorKey {
+    type Err = anyhow::Error;
+
+    fn from_str(descriptor: &str) -> Result<Self> {
+        let expression = descriptor
+            .split_once('#')
+            .map_or(descriptor, |(expression, _checksum)| expression)
+            .trim();
+        let expression = match expression.strip_prefix("tr(") {
+            Some(inner) => inner
+                .strip_suffix(')')
+                .context("unterminated tr() descriptor")?,
+            None => expression,
+        };
+        ensure!(
+            !expression.contains(','),
+            "only key-path tr() descriptors are supported"
+        );
+
+        let (origin, key) = match expression.strip_prefix('[') {
+            Some(rest) => {
+                let (origin, key) = rest.split_once(']').context("unterminated key origin")?;
+                (Some(parse_origin(origin)?), key)
+            }
+            None => (None, expression),
+        };
+
+        if key.starts_with("xpub") || key.starts_with("tpub") {
+            let (xpub, path) = key.split_once('/').unwrap_or((key, ""));
+            let xpub = Xpub::from_str(xpub).context("invalid xpub")?;
+            let path = parse_path(path)?;
+            ensure!(
+                (&path).into_iter().all(ChildNumber::is_normal),
+                "hardened derivation below an xpub needs its private key"
+            );
+            let derived = xpub.derive_pub(&Secp256k1::verification_only(), &path)?;
+            let (fingerprint, base) =
+                origin.unwrap_or((xpub.fingerprint(), DerivationPath::master()));
+            return Ok(Self {
+                key: derived.public_key.x_only_public_key().0,
+                origin: Some((fingerprint, base.extend(path))),
+            });
+        }
+
+        let key = match key.len() {
+            64 => XOnlyPublicKey::from_str(key).context("invalid x-only public key")?,
+            66 => {
+                PublicKey::from_str(key)
+                    .context("invalid public key")?
+                    .inner
+                    .x_only_public_key()
+                    .0
+            }
+            _ => bail!("{key} is neither a hex public key nor an xpub"),
+        };
+        Ok(Self { key, origin })
+    }
+}
+
+impl fmt::Display for OperatorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.origin {
+            Some((fingerprint, path)) => {
+                write!(f, "[{fingerprint}")?;
+                for child in path {
+                    write!(f, "/{child}")?;
+                }
+                write!(f, "]{}", self.key)
+            }
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+/// Parses `fingerprint/path`, the inside of a key origin.
+fn parse_origin(origin: &str) -> Result<KeySource> {
+    let (fingerprint, path) = origin.split_once('/').unwrap_or((origin, ""));
+    let fingerprint = Fingerprint::from_str(fingerprint)
+        .map_err(|_| anyhow!("invalid key origin fingerprint {fingerprint}"))?;
+    Ok((fingerprint, parse_path(path)?))
+}
+
+/// Parses `/`-separated child numbers, hardened ones marked by `'` or `h`.
+fn parse_path(path: &str) -> Result<DerivationPath> {
+    ensure!(
+        !path.contains('*'),
+        "ranged key; give the index of the operator's key in place of `*`"
+    );
+    Ok(path
+        .split('/')
+        .filter(|child| !child.is_empty())
+        .map(|child| {
+            ChildNumber::from_str(child).with_context(|| format!("invalid derivation step {child}"))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into())
+}
@@ -0,0 +1,171 @@
+//! Proving in which execution and segment proving overlap: each segment is
+//! handed to a segment prover as soon as the executor produces it, instead of
+//! executing the whole session before any proving begins. For long guests the
+//! end-to-end latency drops to roughly the proving time plus that of
+//! executing one segment.
+//!
+//! Segment provers run on their own threads and return lifted receipts, which
+//! are joined in segment order and wrapped in Groth16 once the last one is
+//! done, within [`ProverLimits::max_mem`] if set. The joined receipt is kept
+//! next to the wrapped one with [`ProverLimits::keep_stark`]. Local proving
+//! uses a single prover; the proving farm uses one per worker.
+
+use std::{
+    collections::BTreeMap,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use methods::METHOD_ELF;
+use risc0_zkvm::{
+    ApiClient, Asset, AssetRequest, InnerReceipt, ProverOpts, Receipt, ReceiptClaim,
+    SuccinctReceipt,
+};
+
+use crate::{
+    input::GuestInput,
+    prover::{self, Backend, ProofStats, ProverLimits},
+    wrap,
+};
+
+/// Proves and lifts one bincode-encoded segment.
+pub(crate) type SegmentProver<'a> =
+    Box<dyn Fn(&[u8]) -> Result<SuccinctReceipt<ReceiptClaim>> + Send + Sync + 'a>;
+
+/// The `r0vm` client that executes, proves and joins. Clients cannot be
+/// shared between threads, so each thread opens its own.
+pub(crate) fn r0vm() -> Result<ApiClient> {
+    ApiClient::from_env()
+        .context("failed to find r0vm, which proves segments; install it with rzup")
+}
+
+/// A segment prover running on this machine.
+pub(crate) fn local_prover() -> SegmentProver<'static> {
+    Box::new(|segment| prove_segment(&r0vm()?, segment))
+}
+
+/// Proves and lifts a bincode-encoded segment with `client`.
+pub(crate) fn prove_segment(
+    client: &ApiClient,
+    segment: &[u8],
+) -> Result<SuccinctReceipt<ReceiptClaim>> {
+    let opts = ProverOpts::succinct();
+    let receipt = client.prove_segment(
+        &opts,
+        Asset::Inline(segment.to_vec().into()),
+        AssetRequest::Inline,
+    )?;
+    tracing::debug!("proved segment {}", receipt.index);
+    client.lift(&opts, receipt.try_into()?, AssetRequest::Inline)
+}
+
+/// Executes the guest over `input`, proving segments on `provers` as they are
+/// produced, and returns the Groth16-wrapped receipt.
+pub(crate) fn prove_session(
+    input: &GuestInput,
+    limits: &ProverLimits,
+    provers: &[SegmentProver],
+) -> Result<(Receipt, ProofStats)> {
+    ensure!(!provers.is_empty(), "no segment prover is available");
+    let env = prover::executor_env(input, limits)?;
+    let client = r0vm()?;
+
+    let (segments, queue) = mpsc::channel::<(usize, Vec<u8>)>();
+    // Shared by the dispatchers, so that it is dropped and the executor
+    // stops once every prover has failed.
+    let queue = Arc::new(Mutex::new(queue));
+    let lifted = Mutex::new(BTreeMap::new());
+    let session = thread::scope(|scope| {
+        let dispatchers: Vec<_> = provers
+            .iter()
+            .map(|prove| {
+                let (queue, lifted) = (queue.clone(), &lifted);
+                scope.spawn(move || -> Result<()> {
+                    loop {
+                        let next = queue.lock().unwrap().recv();
+                        let Ok((index, segment)) = next else {
+                            return Ok(());
+                        };
+                        let receipt = prove(&segment)
+                            .with_context(|| format!("failed to prove segment {index}"))?;
+                        lifted.lock().unwrap().insert(index, receipt);
+                    }
+                })
+            })
+            .collect();
+        drop(queue);
+
+        let mut index = 0;
+        let session = client.execute(
+            &env,
+            Asset::Inline(METHOD_ELF.into()),
+            AssetRequest::Inline,
+            |_, segment| {
+                segments
+                    .send((index, segment.as_bytes()?.to_vec()))
+                    .map_err(|_| anyhow!("no segment prover is left"))?;
+                index += 1;
+                Ok(())
+            },
+        );
+        drop(segments);
+        for dispatcher in dispatchers {
+            dispatcher.join().expect("segment dispatcher panicked")?;
+        }
+        session
+    })
+    .with_context(|| format!("failed to prove {} byte input", input.data_len()))?;
+
+    let lifted = lifted.into_inner().unwrap();
+    ensure!(
+        lifted.len() == session.segments.len(),
+        "proved {} of {} segments",
+        lifted.len(),
+        session.segments.len()
+    );
+    let opts = ProverOpts::groth16();
+    let mut receipts = lifted.into_values();
+    let first = receipts.next().context("session has no segments")?;
+    let joined = receipts.try_fold(first, |left, right| {
+        client.join(
+            &opts,
+            left.try_into()?,
+            right.try_into()?,
+            AssetRequest::Inline,
+        )
+    })?;
+    let stark = limits.keep_stark.then(|| {
+        Receipt::new(
+            InnerReceipt::Succinct(joined.clone()),
+            session.journal.bytes.clone(),
+        )
+    });
+    let receipt = match limits.max_mem {
+        Some(max_mem) => Receipt::new(
+            InnerReceipt::Groth16(wrap::succinct_to_groth16(
+                &client,
+                joined,
+                limits
+                    .wrap_image
+                    .as_deref()
+                    .unwrap_or(wrap::DEFAULT_PROVER_IMAGE),
+                max_mem,
+            )?),
+            session.journal.bytes,
+        ),
+        None => {
+            let receipt = Receipt::new(InnerReceipt::Succinct(joined), session.journal.bytes);
+            client.compress(&opts, receipt.try_into()?, AssetRequest::Inline)?
+        }
+    };
+
+    let stats = ProofStats {
+        backend: Backend::Local,
+        segments: session.segments.len(),
+        total_cycles: session.segments.iter().map(|info| 1 << info.po2).sum(),
+        user_cycles: session.segments.iter().map(|info| info.cycles as u64).sum(),
+        stark,
+    };
+    Ok((receipt, stats))
+}
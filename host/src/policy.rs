@@ -0,0 +1,360 @@
+//! Rules a bundle must satisfy before it is worth verifying: image IDs and
+//! operators that are no longer trusted, how old a proof may be, and which
+//! metadata it must carry. They are cheap to check, so verifiers evaluate
+//! them before any cryptographic verification.
+
+use std::{fmt, path::Path};
+
+use anyhow::{Context, Result};
+use risc0_zkvm::sha::Digestible;
+use serde::Deserialize;
+
+use crate::{bundle::ProofBundle, method_id::MethodId};
+
+/// A verifier policy, loaded from the `[policy]` section of the server
+/// configuration or a file of the same form. The default allows everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct VerifierPolicy {
+    /// Hex-encoded image IDs whose proofs are rejected, such as guest
+    /// releases with known bugs.
+    pub denied_image_ids: Vec<MethodId>,
+    /// Hex-encoded x-only public keys of operators whose bundles are rejected.
+    pub denied_operators: Vec<String>,
+    /// Oldest proof accepted, in seconds since it was proven. Bundles that do
+    /// not record when they were proven are rejected when this is set.
+    pub max_age_secs: Option<u64>,
+    /// Metadata fields a bundle must carry with a non-empty value, named as
+    /// in its JSON form, e.g. `elf_sha256` or `run.id`.
+    pub required_metadata: Vec<String>,
+    /// Journal schemas accepted from the guest version a journal commits to.
+    /// Journals without one, proven without `--with-version`, are rejected
+    /// when this is set.
+    pub guest_schemas: Vec<u16>,
+}
+
+/// Why a bundle was rejected by a [`VerifierPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    DeniedImageId(MethodId),
+    DeniedOperator(String),
+    TooOld {
+        age_secs: u64,
+        max_age_secs: u64,
+    },
+    /// The bundle does not record when it was proven.
+    UnknownAge,
+    MissingMetadata(String),
+    /// The journal does not commit the version of its guest.
+    UnknownGuestVersion,
+    /// The journal was written by a guest with another journal schema.
+    GuestSchema(u16),
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DeniedImageId(image_id) => write!(f, "image ID {image_id} is denied"),
+            Self::DeniedOperator(operator) => write!(f, "operator {operator} is denied"),
+            Self::TooOld {
+                age_secs,
+                max_age_secs,
+            } => write!(
+                f,
+                "bundle was proven {age_secs}s ago, over the {max_age_secs}s limit"
+            ),
+            Self::UnknownAge => write!(f, "bundle does not record when it was proven"),
+            Self::MissingMetadata(field) => write!(f, "bundle metadata lacks `{field}`"),
+            Self::UnknownGuestVersion => {
+                write!(f, "journal does not commit the version of its guest")
+            }
+            Self::GuestSchema(schema) => {
+                write!(f, "journal was written with unaccepted schema {schema}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+/// The current Unix time in seconds, as policies are checked against.
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("system clock is before the Unix epoch")
+        .as_secs()
+}
+
+impl VerifierPolicy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read policy {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("failed to parse policy {}", path.display()))
+    }
+
+    /// Checks `bundle`, signed by `operator` if known, at Unix time `now`.
+    pub fn check(
+        &self,
+        bundle: &ProofBundle,
+        operator: Option<&str>,
+        now: u64,
+    ) -> Result<(), PolicyViolation> {
+        if let Some(image_id) = self.denied_image_id(bundle) {
+            return Err(PolicyViolation::DeniedImageId(image_id));
+        }
+        if let Some(operator) = operator.filter(|operator| {
+            self.denied_operators
+                .iter()
+                .any(|denied| denied.eq_ignore_ascii_case(operator))
+        }) {
+            return Err(PolicyViolation::DeniedOperator(operator.to_string()));
+        }
+        if let Some(max_age_secs) = self.max_age_secs {
+            let run = bundle.run.as_ref().ok_or(PolicyViolation::UnknownAge)?;
+            let age_secs = now.saturating_sub(run.proved_at);
+            if age_secs > max_age_secs {
+                return Err(PolicyViolation::TooOld {
+                    age_secs,
+                    max_age_secs,
+                });
+            }
+        }
+        if !self.required_metadata.is_empty() {
+            let metadata = metadata_json(bundle);
+            if let Some(field) = self
+                .required_metadata
+                .iter()
+                .find(|field| !is_present(&metadata, field))
+            {
+                return Err(PolicyViolation::MissingMetadata(field.clone()));
+            }
+        }
+        if !self.guest_schemas.is_empty() {
+            let version = common::journal::guest_version(&bundle.receipt.journal.bytes)
+                .ok_or(PolicyViolation::UnknownGuestVersion)?;
+            if !self.guest_schemas.contains(&version.schema) {
+                return Err(PolicyViolation::GuestSchema(version.schema));
+            }
+        }
+        Ok(())
+    }
+
+    /// A denied image ID the bundle claims or records, if any. The claim is
+    /// not verified yet, but a bundle lying about it fails verification
+    /// anyway.
+    fn denied_image_id(&self, bundle: &ProofBundle) -> Option<MethodId> {
+        let claimed = bundle
+            .receipt
+            .claim()
+            .ok()
+            .and_then(|claim| claim.as_value().ok().map(|claim| claim.pre.digest().into()));
+        let recorded = bundle.metadata.image_id.parse().ok();
+        [claimed, recorded]
+            .into_iter()
+            .flatten()
+            .find(|image_id| self.denied_image_ids.contains(image_id))
+    }
+}
+
+/// The bundle's build metadata with its run parameters under `run`.
+fn metadata_json(bundle: &ProofBundle) -> serde_json::Value {
+    let mut metadata = serde_json::to_value(&bundle.metadata).unwrap_or_default();
+    if let (Some(object), Some(run)) = (metadata.as_object_mut(), &bundle.run) {
+        object.insert("run".into(), serde_json::to_value(run).unwrap_or_default());
+    }
+    if let (Some(object), Some(escalation)) = (metadata.as_object_mut(), &bundle.escalation) {
+        object.insert(
+            "escalation".into(),
+            serde_json::to_value(escalation).unwrap_or_default(),
+        );
+    }
+    if let (Some(object), Some(timestamp)) = (metadata.as_object_mut(), &bundle.timestamp) {
+        object.insert(
+            "timestamp".into(),
+            serde_json::to_value(timestamp).unwrap_or_default(),
+        );
+    }
+    metadata
+}
+
+fn is_present(metadata: &serde_json::Value, field: &str) -> bool {
+    let value = field
+        .split('.')
+        .try_fold(metadata, |value, key| value.get(key));
+    match value {
+        None | Some(serde_json::Value::Null) => false,
+        Some(serde_json::Value::String(value)) => !value.is_empty(),
+        Some(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::journal::{self, GuestVersion};
+    use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+    use super::*;
+    use crate::{
+        bundle::{BuildMetadata, RunParameters},
+        prover::Backend,
+    };
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn claimed() -> MethodId {
+        MethodId::from_words([1; 8])
+    }
+
+    fn recorded() -> MethodId {
+        MethodId::from_words([2; 8])
+    }
+
+    fn bundle(journal: Vec<u8>) -> ProofBundle {
+        let claim = ReceiptClaim::ok(claimed().digest(), journal.clone());
+        ProofBundle {
+            metadata: BuildMetadata {
+                image_id: recorded().to_string(),
+                elf_blake3: "ab".repeat(32),
+                elf_sha256: "cd".repeat(32),
+                risc0_version: "2.0.2".to_string(),
+                built_at: "2025-01-01T00:00:00+00:00".to_string(),
+            },
+            receipt: Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal),
+            run: Some(RunParameters {
+                proved_at: NOW - 60,
+                id: Some(7),
+                backend: Backend::Local,
+                segment_limit_po2: None,
+                session_limit: None,
+                risc0_prover: None,
+                dev_mode: true,
+                host: "x86_64-linux".to_string(),
+                segments: 1,
+                total_cycles: 1 << 16,
+                user_cycles: 1 << 15,
+            }),
+            escalation: None,
+            timestamp: None,
+            da: Vec::new(),
+            stark: None,
+        }
+    }
+
+    fn versioned(schema: u16) -> ProofBundle {
+        let mut journal = vec![0, 1, 2, 3];
+        journal::bind_version(&mut journal, &GuestVersion { schema, modes: 1 });
+        bundle(journal)
+    }
+
+    #[test]
+    fn default_allows_everything() {
+        let policy = VerifierPolicy::default();
+        assert_eq!(policy.check(&bundle(vec![0]), Some("aa"), NOW), Ok(()));
+    }
+
+    #[test]
+    fn denies_claimed_and_recorded_image_ids() {
+        for image_id in [claimed(), recorded()] {
+            let policy = VerifierPolicy {
+                denied_image_ids: vec![image_id],
+                ..Default::default()
+            };
+            assert_eq!(
+                policy.check(&bundle(vec![0]), None, NOW),
+                Err(PolicyViolation::DeniedImageId(image_id))
+            );
+        }
+        let policy = VerifierPolicy {
+            denied_image_ids: vec![MethodId::from_words([3; 8])],
+            ..Default::default()
+        };
+        assert_eq!(policy.check(&bundle(vec![0]), None, NOW), Ok(()));
+    }
+
+    #[test]
+    fn denies_operators_in_any_case() {
+        let operator = "ab".repeat(32);
+        let policy = VerifierPolicy {
+            denied_operators: vec![operator.to_uppercase()],
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.check(&bundle(vec![0]), Some(&operator), NOW),
+            Err(PolicyViolation::DeniedOperator(operator.clone()))
+        );
+        assert_eq!(
+            policy.check(&bundle(vec![0]), Some(&"cd".repeat(32)), NOW),
+            Ok(())
+        );
+        assert_eq!(policy.check(&bundle(vec![0]), None, NOW), Ok(()));
+    }
+
+    #[test]
+    fn rejects_old_bundles_and_bundles_of_unknown_age() {
+        let policy = VerifierPolicy {
+            max_age_secs: Some(60),
+            ..Default::default()
+        };
+        let mut bundle = bundle(vec![0]);
+        assert_eq!(policy.check(&bundle, None, NOW), Ok(()));
+        assert_eq!(
+            policy.check(&bundle, None, NOW + 1),
+            Err(PolicyViolation::TooOld {
+                age_secs: 61,
+                max_age_secs: 60
+            })
+        );
+        bundle.run = None;
+        assert_eq!(
+            policy.check(&bundle, None, NOW),
+            Err(PolicyViolation::UnknownAge)
+        );
+    }
+
+    #[test]
+    fn requires_metadata_by_dotted_path() {
+        let policy = VerifierPolicy {
+            required_metadata: vec!["elf_sha256".to_string(), "run.id".to_string()],
+            ..Default::default()
+        };
+        let mut bundle = bundle(vec![0]);
+        assert_eq!(policy.check(&bundle, None, NOW), Ok(()));
+
+        bundle.run.as_mut().unwrap().id = None;
+        assert_eq!(
+            policy.check(&bundle, None, NOW),
+            Err(PolicyViolation::MissingMetadata("run.id".to_string()))
+        );
+        bundle.run = None;
+        assert_eq!(
+            policy.check(&bundle, None, NOW),
+            Err(PolicyViolation::MissingMetadata("run.id".to_string()))
+        );
+
+        // An empty string counts as missing.
+        bundle.metadata.elf_sha256 = String::new();
+        assert_eq!(
+            policy.check(&bundle, None, NOW),
+            Err(PolicyViolation::MissingMetadata("elf_sha256".to_string()))
+        );
+    }
+
+    #[test]
+    fn accepts_listed_guest_schemas() {
+        let policy = VerifierPolicy {
+            guest_schemas: vec![1],
+            ..Default::default()
+        };
+        assert_eq!(policy.check(&versioned(1), None, NOW), Ok(()));
+        assert_eq!(
+            policy.check(&versioned(2), None, NOW),
+            Err(PolicyViolation::GuestSchema(2))
+        );
+        assert_eq!(
+            policy.check(&bundle(vec![0, 1, 2, 3]), None, NOW),
+            Err(PolicyViolation::UnknownGuestVersion)
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Pre-commitments for bridge flows in which the claim reaches Bitcoin before
+//! its proof exists: the operator first commits to the image ID and the
+//! BLAKE3 digest of the guest input, then proves the input with
+//! `--bind-input`, and anyone can later check that the proof is of the
+//! committed claim.
+//!
+//! The commitment is the payload `MAGIC || image_id || input_digest`, carried
+//! either in an `OP_RETURN` output, which stays within the 80-byte standard
+//! relay limit, or in a tapleaf `<payload> OP_DROP <operator> OP_CHECKSIG`,
+//! which only the operator can spend and which reveals the payload when it
+//! is. The input digest is the one [`GuestInput::input_digest`] gives and
+//! the guest commits to the journal, so the input itself stays private.
+
+use anyhow::{bail, ensure, Context, Result};
+use bitcoin::{
+    opcodes::all::{OP_CHECKSIG, OP_DROP},
+    script::{Builder, Instruction, PushBytesBuf},
+    taproot::{LeafVersion, TapLeafHash},
+    ScriptBuf, XOnlyPublicKey,
+};
+use k256::schnorr::VerifyingKey;
+use risc0_zkvm::{sha::Digest, Receipt};
+use serde::{Deserialize, Serialize};
+
+use crate::{input::GuestInput, journal::Journal, verifier};
+
+/// Marks a pre-commitment payload, so that it is not mistaken for another
+/// protocol's `OP_RETURN` data.
+pub const MAGIC: [u8; 4] = *b"R0BP";
+
+/// Length of the payload: the magic, the image ID and the input digest.
+pub const PAYLOAD_LEN: usize = MAGIC.len() + 32 + 32;
+
+/// A claim committed to before it is proven.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreCommitment {
+    #[serde(with = "hex::serde")]
+    pub image_id: Digest,
+    /// BLAKE3 digest of the bound guest input, as committed to the journal.
+    #[serde(with = "hex::serde")]
+    pub input_digest: [u8; 32],
+}
+
+impl PreCommitment {
+    /// The pre-commitment to proving `input` with the guest `image_id`. The
+    /// input must then be proven bound, with [`GuestInput::BindInput`].
+    pub fn new(image_id: impl Into<Digest>, input: &GuestInput) -> Self {
+        Self {
+            image_id: image_id.into(),
+            input_digest: input.input_digest(),
+        }
+    }
+
+    pub fn payload(&self) -> [u8; PAYLOAD_LEN] {
+        let mut payload = [0; PAYLOAD_LEN];
+        payload[..MAGIC.len()].copy_from_slice(&MAGIC);
+        payload[MAGIC.len()..MAGIC.len() + 32].copy_from_slice(self.image_id.as_bytes());
+        payload[MAGIC.len() + 32..].copy_from_slice(&self.input_digest);
+        payload
+    }
+
+    pub fn from_payload(payload: &[u8]) -> Result<Self> {
+        ensure!(
+            payload.len() == PAYLOAD_LEN,
+            "pre-commitment payload is {} bytes, expected {PAYLOAD_LEN}",
+            payload.len()
+        );
+        let (magic, rest) = payload.split_at(MAGIC.len());
+        ensure!(magic == MAGIC, "payload is not a pre-commitment");
+        let (image_id, input_digest) = rest.split_at(32);
+        Ok(Self {
+            image_id: Digest::try_from(image_id).unwrap(),
+            input_digest: input_digest.try_into().unwrap(),
+        })
+    }
+
+    /// The `OP_RETURN` output script carrying the payload.
+    pub fn op_return_script(&self) -> ScriptBuf {
+        ScriptBuf::new_op_return(push_bytes(&self.payload()))
+    }
+
+    /// The tapleaf script carrying the payload, spendable by `operator`.
+    pub fn tapleaf_script(&self, operator: &VerifyingKey) -> Result<ScriptBuf> {
+        let operator = XOnlyPublicKey::from_slice(&operator.to_bytes())
+            .context("operator is not a valid x-only public key")?;
+        Ok(Builder::new()
+            .push_slice(push_bytes(&self.payload()))
+            .push_opcode(OP_DROP)
+            .push_x_only_key(&operator)
+            .push_opcode(OP_CHECKSIG)
+            .into_script())
+    }
+
+    /// The leaf hash of [`PreCommitment::tapleaf_script`], to build the
+    /// taproot output committing to it.
+    pub fn tapleaf_hash(&self, operator: &VerifyingKey) -> Result<TapLeafHash> {
+        Ok(TapLeafHash::from_script(
+            &self.tapleaf_script(operator)?,
+            LeafVersion::TapScript,
+        ))
+    }
+
+    /// Reads the pre-commitment back from an `OP_RETURN` output script or a
+    /// tapleaf script of the forms above.
+    pub fn from_script(script: &bitcoin::Script) -> Result<Self> {
+        let mut pushes = script
+            .instructions()
+            .filter_map(|instruction| match instruction {
+                Ok(Instruction::PushBytes(bytes)) => Some(bytes.as_bytes()),
+                _ => None,
+            });
+        let payload = pushes
+            .next()
+            .context("script does not push a pre-commitment")?;
+        let commitment = Self::from_payload(payload)?;
+        let expected_prefix = if script.is_op_return() {
+            commitment.op_return_script()
+        } else {
+            Builder::new()
+                .push_slice(push_bytes(&commitment.payload()))
+                .push_opcode(OP_DROP)
+                .into_script()
+        };
+        if !script.as_bytes().starts_with(expected_prefix.as_bytes()) {
+            bail!("script is not a pre-commitment output or tapleaf");
+        }
+        Ok(commitment)
+    }
+
+    /// Checks that `receipt` proves the committed claim: that it verifies
+    /// against the committed image ID, and that its journal is bound to the
+    /// committed input digest. The public input the seal verifies against is
+    /// then that of the claim, which is returned.
+    pub fn check(&self, receipt: &Receipt) -> Result<[u8; verifier::PUBLIC_INPUT_LEN]> {
+        verifier::verify_receipt(receipt, self.image_id)?;
+        let journal = Journal::of(receipt);
+        let input_digest = journal
+            .input_digest()
+            .context("journal is not bound to its input; prove with --bind-input")?;
+        ensure!(
+            *input_digest == self.input_digest,
+            "journal is bound to input digest {}, but {} was committed",
+            hex::encode(input_digest),
+            hex::encode(self.input_digest)
+        );
+        Ok(journal.public_input(self.image_id))
+    }
+}
+
+fn push_bytes(payload: &[u8; PAYLOAD_LEN]) -> PushBytesBuf {
+    PushBytesBuf::try_from(payload.to_vec()).expect("the payload fits in one push")
+}
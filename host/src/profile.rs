@@ -0,0 +1,330 @@
+//! Verifier profiles: every parameter one deployment verifies proofs under,
+//! named so that one binary can serve several deployments with `--profile`
+//! instead of a combination of flags per command.
+//!
+//! `local-dev` is built in and holds the parameters of this build: the image
+//! ID of its guest, the control root of its risc0 and the verifying key of
+//! the circuit risc0 wraps receipts with. Other profiles are read from a TOML
+//! file, and default to the same values for the fields they leave out:
+//!
+//! ```toml
+//! [profiles.fiamma-testnet]
+//! image_ids = ["<hex image ID>"]
+//! control_root = "<hex succinct control root>"
+//! verifying_key = "<hex arkworks-compressed Groth16 verifying key>"
+//! binding_hash = "blake3"   # or "sha256"
+//! scalar_policy = "reject"  # or "reduce"
+//! max_journal_len = 4_194_304
+//! max_chunk_len = 520       # largest chunk of a split witness
+//! ```
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use ark_bn254::Bn254;
+use ark_groth16::VerifyingKey;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use hex::FromHex;
+use methods::METHOD_ID;
+use risc0_zkvm::{sha::Digest, Groth16ReceiptVerifierParameters, Receipt, VerifierContext};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunks,
+    method_id::MethodId,
+    storage,
+    transform::{BindingHash, OutputTransform},
+    verifier::{self, ScalarPolicy},
+};
+
+/// Name of the profile built into this binary.
+pub const BUILTIN: &str = "local-dev";
+
+/// Environment variable naming the profile file when `--profiles` is not
+/// given.
+pub const PROFILES_ENV: &str = "R0_BITVM_PROFILES";
+
+/// The parameters a deployment verifies proofs under.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    /// Image IDs a receipt may verify against.
+    pub image_ids: Vec<Digest>,
+    /// Succinct control root the receipts are lifted under, bound into the
+    /// public input.
+    pub control_root: Digest,
+    pub verifying_key: VerifyingKey<Bn254>,
+    pub binding_hash: BindingHash,
+    pub scalar_policy: ScalarPolicy,
+    pub max_journal_len: usize,
+    /// Largest chunk a verifier witness is split into for a script.
+    pub max_chunk_len: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, ProfileEntry>,
+}
+
+/// A profile as written in the file, every field defaulting to the built-in
+/// profile's.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileEntry {
+    #[serde(default)]
+    image_ids: Vec<MethodId>,
+    control_root: Option<String>,
+    verifying_key: Option<String>,
+    binding_hash: Option<String>,
+    scalar_policy: Option<String>,
+    max_journal_len: Option<usize>,
+    max_chunk_len: Option<usize>,
+}
+
+/// A profile as printed by `profile-show`, in the form of the file.
+#[derive(Debug, Serialize)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub image_ids: Vec<String>,
+    pub control_root: String,
+    pub verifying_key: String,
+    pub binding_hash: String,
+    pub scalar_policy: String,
+    pub max_journal_len: usize,
+    pub max_chunk_len: usize,
+}
+
+impl Profile {
+    /// The parameters of this build.
+    pub fn builtin() -> Self {
+        Self {
+            name: BUILTIN.to_string(),
+            image_ids: vec![Digest::from(METHOD_ID)],
+            control_root: verifier::default_control_root(),
+            verifying_key: verifier::get_ark_verifying_key(),
+            binding_hash: BindingHash::default(),
+            scalar_policy: ScalarPolicy::Reject,
+            max_journal_len: verifier::DEFAULT_MAX_JOURNAL_LEN,
+            max_chunk_len: chunks::MAX_SCRIPT_ELEMENT_LEN,
+        }
+    }
+
+    /// Loads the profile `name`, from `file` if given and otherwise from the
+    /// file named by [`PROFILES_ENV`]. The built-in profile needs no file.
+    pub fn load(name: &str, file: Option<&Path>) -> Result<Self> {
+        let env_file = std::env::var_os(PROFILES_ENV);
+        let file = file.or(env_file.as_deref().map(Path::new));
+        if name == BUILTIN {
+            return Ok(Self::builtin());
+        }
+        let Some(file) = file else {
+            bail!("profile {name:?} is not built in; pass --profiles <file> or set {PROFILES_ENV}");
+        };
+        let contents = std::fs::read_to_string(file)
+            .with_context(|| format!("failed to read profiles {}", file.display()))?;
+        Self::parse(name, &contents, file)
+    }
+
+    /// Parses the profile `name` out of `contents`, the profile file `file`.
+    fn parse(name: &str, contents: &str, file: &Path) -> Result<Self> {
+        let mut profiles: ProfileFile = toml::from_str(contents)
+            .with_context(|| format!("failed to parse profiles {}", file.display()))?;
+        ensure!(
+            !profiles.profiles.contains_key(BUILTIN),
+            "profile {BUILTIN:?} is built in and cannot be redefined in {}",
+            file.display()
+        );
+        let entry = profiles.profiles.remove(name).with_context(|| {
+            let names: Vec<_> = profiles.profiles.keys().map(String::as_str).collect();
+            format!(
+                "no profile {name:?} in {}; it defines {}",
+                file.display(),
+                names.join(", ")
+            )
+        })?;
+        entry
+            .resolve(name)
+            .with_context(|| format!("invalid profile {name:?} in {}", file.display()))
+    }
+
+    /// Sets the verifying key of profile `name` in the profile file `file`,
+    /// adding the profile, and the file, if missing. The profile's other
+    /// fields are kept; comments in the file are not.
+    pub fn store_verifying_key(
+        file: &Path,
+        name: &str,
+        verifying_key: &VerifyingKey<Bn254>,
+    ) -> Result<()> {
+        ensure!(
+            name != BUILTIN,
+            "profile {BUILTIN:?} is built in and cannot be redefined"
+        );
+        let mut contents = match std::fs::read_to_string(file) {
+            Ok(contents) => contents
+                .parse::<toml::Table>()
+                .with_context(|| format!("failed to parse profiles {}", file.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to read profiles {}", file.display()))
+            }
+        };
+        let mut encoded = Vec::new();
+        verifying_key.serialize_compressed(&mut encoded)?;
+        let profile = contents
+            .entry("profiles")
+            .or_insert_with(|| toml::Table::new().into())
+            .as_table_mut()
+            .context("`profiles` is not a table")?
+            .entry(name)
+            .or_insert_with(|| toml::Table::new().into())
+            .as_table_mut()
+            .with_context(|| format!("profile {name:?} is not a table"))?;
+        profile.insert("verifying_key".to_string(), hex::encode(encoded).into());
+        let contents = toml::to_string(&contents)?;
+        // Refuses to replace the file with one that does not load, e.g. over
+        // a field of the profile that was invalid before.
+        Self::parse(name, &contents, file)?;
+        storage::write_atomic(file, contents.as_bytes())
+    }
+
+    /// The profile in the form of the profile file.
+    pub fn summary(&self) -> Result<ProfileSummary> {
+        let mut verifying_key = Vec::new();
+        self.verifying_key
+            .serialize_compressed(&mut verifying_key)?;
+        Ok(ProfileSummary {
+            name: self.name.clone(),
+            image_ids: self.image_ids.iter().map(hex::encode).collect(),
+            control_root: hex::encode(self.control_root),
+            verifying_key: hex::encode(verifying_key),
+            binding_hash: self.binding_hash.to_string(),
+            scalar_policy: self.scalar_policy.to_string(),
+            max_journal_len: self.max_journal_len,
+            max_chunk_len: self.max_chunk_len,
+        })
+    }
+
+    /// Verifies `receipt` against `image_id` under this profile, with the
+    /// risc0 verifier and then with the arkworks Groth16 verifier.
+    pub fn verify_receipt(&self, receipt: &Receipt, image_id: Digest) -> Result<()> {
+        verifier::check_journal(&receipt.journal.bytes, self.max_journal_len)?;
+        receipt
+            .verify_with_context(&self.context()?, image_id)
+            .context("risc0 receipt verification failed")?;
+        let groth_proof = receipt.inner.groth16().context("Not a groth16 proof!!!")?;
+        let public_input = self.public_input(image_id, &receipt.journal.bytes);
+        // The key of this build is prepared for pairings at build time.
+        let verified = if self.verifying_key == verifier::get_ark_verifying_key() {
+            verifier::verify_seal(&groth_proof.seal, &public_input, self.scalar_policy)?
+        } else {
+            verifier::verify_seal_with_key(
+                &groth_proof.seal,
+                &public_input,
+                self.scalar_policy,
+                &self.verifying_key,
+            )?
+        };
+        ensure!(verified, "proof verification failed for receipt");
+        Ok(())
+    }
+
+    /// Verifies `receipt` against the profile's image ID it claims to be
+    /// produced for, and returns that image ID.
+    pub fn verify_receipt_any(&self, receipt: &Receipt) -> Result<Digest> {
+        let image_id = verifier::claimed_image_id(receipt)?;
+        ensure!(
+            self.image_ids.contains(&image_id),
+            "receipt claims image ID {image_id}, not one of the {} image IDs of profile {}",
+            self.image_ids.len(),
+            self.name
+        );
+        self.verify_receipt(receipt, image_id)?;
+        Ok(image_id)
+    }
+
+    /// The image ID commands taking a single one default to.
+    pub fn default_image_id(&self) -> Digest {
+        self.image_ids[0]
+    }
+
+    /// The risc0 verifier context accepting Groth16 receipts under this
+    /// profile's control root and verifying key.
+    fn context(&self) -> Result<VerifierContext> {
+        // The parameters of this build are left to risc0, so that the
+        // built-in profile verifies exactly as the commands without one do.
+        let builtin = Profile::builtin();
+        if self.control_root == builtin.control_root && self.verifying_key == builtin.verifying_key
+        {
+            return Ok(VerifierContext::default());
+        }
+        // risc0 only builds its verifying key from its own constants, so this
+        // one is passed through its serde form: the uncompressed key bytes.
+        let mut key = Vec::new();
+        self.verifying_key.serialize_uncompressed(&mut key)?;
+        let verifying_key = bincode::deserialize(&bincode::serialize(&key)?)
+            .context("verifying key is not accepted by risc0")?;
+        let params = Groth16ReceiptVerifierParameters {
+            control_root: self.control_root,
+            verifying_key,
+            ..Default::default()
+        };
+        Ok(VerifierContext::default().with_groth16_verifier_parameters(params))
+    }
+}
+
+impl OutputTransform for Profile {
+    fn public_input(&self, image_id: Digest, journal: &[u8]) -> Vec<u8> {
+        let prefix = verifier::succinct_output_prefix_with(image_id, self.control_root);
+        self.binding_hash.bind(&prefix, journal)
+    }
+}
+
+impl ProfileEntry {
+    fn resolve(self, name: &str) -> Result<Profile> {
+        let builtin = Profile::builtin();
+        let control_root = self
+            .control_root
+            .map(|encoded| {
+                <[u8; 32]>::from_hex(encoded).context("control_root must be 32 hex-encoded bytes")
+            })
+            .transpose()?
+            .map_or(builtin.control_root, Digest::from_bytes);
+        let verifying_key = self
+            .verifying_key
+            .map(|encoded| -> Result<_> {
+                let bytes = hex::decode(encoded).context("verifying_key is not hex")?;
+                VerifyingKey::deserialize_compressed(bytes.as_slice())
+                    .context("verifying_key is not a compressed BN254 Groth16 verifying key")
+            })
+            .transpose()?
+            .unwrap_or(builtin.verifying_key);
+        ensure!(
+            verifying_key.gamma_abc_g1.len() == 2,
+            "verifying_key must be for a circuit with one public input"
+        );
+        let max_chunk_len = self.max_chunk_len.unwrap_or(builtin.max_chunk_len);
+        ensure!(max_chunk_len > 0, "max_chunk_len must be positive");
+        Ok(Profile {
+            name: name.to_string(),
+            image_ids: match self.image_ids.as_slice() {
+                [] => builtin.image_ids,
+                image_ids => image_ids.iter().map(|image_id| image_id.digest()).collect(),
+            },
+            control_root,
+            verifying_key,
+            binding_hash: self
+                .binding_hash
+                .map_or(Ok(builtin.binding_hash), |hash| hash.parse())
+                .map_err(anyhow::Error::msg)?,
+            scalar_policy: self
+                .scalar_policy
+                .map_or(Ok(builtin.scalar_policy), |policy| policy.parse())
+                .map_err(anyhow::Error::msg)?,
+            max_journal_len: self.max_journal_len.unwrap_or(builtin.max_journal_len),
+            max_chunk_len,
+        })
+    }
+}
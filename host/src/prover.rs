@@ -0,0 +1,491 @@
+use std::fmt;
+
+use anyhow::{bail, ensure, Context, Result};
+use methods::METHOD_ELF;
+use risc0_zkvm::{default_executor, default_prover, ExecutorEnv, ExitCode, ProverOpts, Receipt};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{bonsai, farm, input::GuestInput, pipeline};
+
+/// Size of the guest address space. Inputs are streamed to the guest in
+/// frames, but anything larger than this is certainly a mistake.
+pub const GUEST_ADDRESS_SPACE: u64 = 0xC000_0000;
+
+/// Executor limits applied when proving.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct ProverLimits {
+    /// Segment size as a power of two cycles. Prover memory use is roughly
+    /// linear in the segment size, so lowering this by one halves it.
+    pub segment_limit_po2: Option<u32>,
+    /// Maximum number of cycles the whole session may take.
+    pub session_limit: Option<u64>,
+    /// Largest input accepted, in bytes. Zero padding counts towards it, so
+    /// that a short input cannot have the guest hash gigabytes of zeros.
+    pub max_input_len: u64,
+    /// Return the receipt of a session in which the guest exits with a
+    /// non-zero code, as proof of the fault, instead of failing with
+    /// [`GuestFault::Exit`]. Such a receipt never verifies against the image
+    /// ID. Guest panics cannot be proven by risc0 and always fail.
+    pub prove_faults: bool,
+    /// Memory the Groth16 wrap may use, in bytes, beyond which it spills to
+    /// swap. Applies to local and farm proving outside dev mode.
+    pub max_mem: Option<u64>,
+    /// Docker image of the gnark prover the wrap runs under [`Self::max_mem`].
+    /// Defaults to the image of the risc0-groth16 release this is built
+    /// against; set it to the image of the risc0 release actually deployed.
+    pub wrap_image: Option<String>,
+    /// Also return the succinct STARK receipt the Groth16 receipt wraps, in
+    /// [`ProofStats::stark`], for verifiers who do not trust the Groth16
+    /// setup. Only local and farm proving outside dev mode keep it.
+    pub keep_stark: bool,
+}
+
+impl Default for ProverLimits {
+    fn default() -> Self {
+        Self {
+            segment_limit_po2: None,
+            session_limit: None,
+            max_input_len: GUEST_ADDRESS_SPACE,
+            prove_faults: false,
+            max_mem: None,
+            wrap_image: None,
+            keep_stark: false,
+        }
+    }
+}
+
+impl ProverLimits {
+    /// Checks that `input` is valid and fits within these limits, before any
+    /// proving work is spent on it.
+    pub fn check_input(&self, input: &GuestInput) -> Result<()> {
+        input.validate()?;
+        input.decompressed_len(self.max_input_len)?;
+        let len = input.data_len() as u64;
+        if len > self.max_input_len {
+            bail!(
+                "input of {len} bytes exceeds the {} byte limit for a single proof; \
+                 split it into chunks and prove each one, or commit to the chunks \
+                 with a Merkle tree and prove the root",
+                self.max_input_len
+            );
+        }
+        let hashed = input.hashed_len();
+        if hashed > self.max_input_len {
+            bail!(
+                "input padded to {hashed} bytes exceeds the {} byte limit for a single \
+                 proof; choose a smaller padded size",
+                self.max_input_len
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Where a proof was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Local,
+    Bonsai,
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Local => write!(f, "local"),
+            Self::Bonsai => write!(f, "Bonsai"),
+        }
+    }
+}
+
+/// Execution statistics of a proven session.
+#[derive(Debug, Clone)]
+pub struct ProofStats {
+    pub backend: Backend,
+    pub segments: usize,
+    /// Cycles proven, including padding of each segment to a power of two.
+    pub total_cycles: u64,
+    /// Cycles spent running the guest itself.
+    pub user_cycles: u64,
+    /// The succinct STARK receipt of the session, before it was wrapped in
+    /// Groth16, if kept with [`ProverLimits::keep_stark`].
+    pub stark: Option<Receipt>,
+}
+
+/// The size of a session, measured by executing the guest without proving
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CycleEstimate {
+    /// Cycles spent running the guest itself.
+    pub user_cycles: u64,
+    /// Cycles that would be proven, including padding of each segment to a
+    /// power of two.
+    pub total_cycles: u64,
+    /// Segments the session would be proven in at the configured segment size.
+    pub segments: usize,
+}
+
+/// Why the guest did not finish successfully, recovered from the prover's
+/// error so that callers can tell bad inputs from prover problems.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GuestFault {
+    /// The guest panicked, typically on an input it could not decode.
+    Panic {
+        /// Source location of the panic in the guest, if reported.
+        location: Option<String>,
+        message: String,
+    },
+    /// The guest ran out of memory.
+    OutOfMemory,
+    /// The session ran past the configured cycle limit.
+    SessionLimit,
+    /// The guest halted with a non-zero exit code.
+    Exit { code: u32 },
+}
+
+impl fmt::Display for GuestFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panic {
+                location: Some(location),
+                message,
+            } => write!(f, "guest panicked at {location}: {message}"),
+            Self::Panic {
+                location: None,
+                message,
+            } => write!(f, "guest panicked: {message}"),
+            Self::OutOfMemory => write!(f, "guest ran out of memory"),
+            Self::SessionLimit => write!(f, "guest exceeded the session cycle limit"),
+            Self::Exit { code } => write!(f, "guest exited with code {code}"),
+        }
+    }
+}
+
+impl std::error::Error for GuestFault {}
+
+impl GuestFault {
+    /// Recognizes a guest fault in a prover error, from the messages risc0's
+    /// executor and Bonsai report it with.
+    pub fn from_error(err: &anyhow::Error) -> Option<Self> {
+        if let Some(fault) = err.downcast_ref::<Self>() {
+            return Some(fault.clone());
+        }
+        err.chain().find_map(|cause| {
+            let cause = cause.to_string();
+            if cause.contains("Session limit exceeded") {
+                return Some(Self::SessionLimit);
+            }
+            let (_, panic) = cause.split_once("Guest panicked: ")?;
+            if panic.starts_with("Out of memory") {
+                return Some(Self::OutOfMemory);
+            }
+            // The guest reports `panicked at <file>:<line>:<column>:\n<message>`.
+            let panic = panic.trim_end();
+            Some(match panic.strip_prefix("panicked at ") {
+                Some(rest) => match rest.split_once(":\n") {
+                    Some((location, message)) => Self::Panic {
+                        location: Some(location.to_string()),
+                        message: message.to_string(),
+                    },
+                    None => Self::Panic {
+                        location: None,
+                        message: rest.to_string(),
+                    },
+                },
+                None => Self::Panic {
+                    location: None,
+                    message: panic.to_string(),
+                },
+            })
+        })
+    }
+}
+
+/// Proves the guest over `input` and returns the Groth16-wrapped receipt.
+pub fn prove(input: &GuestInput, limits: &ProverLimits) -> Result<Receipt> {
+    prove_with_stats(input, limits).map(|(receipt, _)| receipt)
+}
+
+/// Like [`prove`], also returning the statistics of the session. A guest
+/// failure is reported as a [`GuestFault`] within the error.
+pub fn prove_with_stats(
+    input: &GuestInput,
+    limits: &ProverLimits,
+) -> Result<(Receipt, ProofStats)> {
+    prove_checked(input, limits, prove_session)
+}
+
+/// Like [`prove_with_stats`], proving here even when Bonsai is configured.
+pub fn prove_locally(input: &GuestInput, limits: &ProverLimits) -> Result<(Receipt, ProofStats)> {
+    prove_checked(input, limits, prove_local_session)
+}
+
+/// Like [`prove_with_stats`], proving on Bonsai, which must be configured.
+pub fn prove_on_bonsai(input: &GuestInput, limits: &ProverLimits) -> Result<(Receipt, ProofStats)> {
+    ensure!(
+        bonsai::is_configured(),
+        "Bonsai is not configured; set BONSAI_API_URL and BONSAI_API_KEY outside dev mode"
+    );
+    prove_checked(input, limits, prove_bonsai_session)
+}
+
+/// Like [`prove_with_stats`], sharding the segments of the session across the
+/// proving farm `workers` instead of proving them here.
+pub fn prove_on_farm(
+    input: &GuestInput,
+    limits: &ProverLimits,
+    workers: &[String],
+) -> Result<(Receipt, ProofStats)> {
+    prove_checked(input, limits, |input, limits| {
+        farm::prove_session(input, limits, workers)
+    })
+}
+
+/// Where a [`ProveRequest`] is proven.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProverTarget {
+    /// On Bonsai if it is configured, here otherwise, as [`prove_with_stats`].
+    #[default]
+    Default,
+    Local,
+    Bonsai,
+    /// Sharded across the workers of a proving farm.
+    Farm(Vec<String>),
+}
+
+/// A proof to make: the input, the limits it is proven under and where.
+/// Built with [`ProveRequest::builder`], so that options added later do not
+/// break callers.
+#[derive(Debug, Clone)]
+pub struct ProveRequest {
+    input: GuestInput,
+    limits: ProverLimits,
+    target: ProverTarget,
+}
+
+impl ProveRequest {
+    pub fn builder() -> ProveRequestBuilder {
+        ProveRequestBuilder::default()
+    }
+
+    pub fn input(&self) -> &GuestInput {
+        &self.input
+    }
+
+    pub fn limits(&self) -> &ProverLimits {
+        &self.limits
+    }
+
+    pub fn target(&self) -> &ProverTarget {
+        &self.target
+    }
+
+    /// Proves the request, returning the Groth16-wrapped receipt and the
+    /// statistics of the session.
+    pub fn prove(&self) -> Result<(Receipt, ProofStats)> {
+        match &self.target {
+            ProverTarget::Default => prove_with_stats(&self.input, &self.limits),
+            ProverTarget::Local => prove_locally(&self.input, &self.limits),
+            ProverTarget::Bonsai => prove_on_bonsai(&self.input, &self.limits),
+            ProverTarget::Farm(workers) => prove_on_farm(&self.input, &self.limits, workers),
+        }
+    }
+}
+
+/// Builds a [`ProveRequest`]; only the input is required.
+#[derive(Debug, Clone, Default)]
+pub struct ProveRequestBuilder {
+    input: Option<GuestInput>,
+    limits: ProverLimits,
+    target: ProverTarget,
+}
+
+impl ProveRequestBuilder {
+    pub fn input(mut self, input: GuestInput) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// Replaces all the limits at once.
+    pub fn limits(mut self, limits: ProverLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn segment_limit_po2(mut self, po2: u32) -> Self {
+        self.limits.segment_limit_po2 = Some(po2);
+        self
+    }
+
+    pub fn session_limit(mut self, cycles: u64) -> Self {
+        self.limits.session_limit = Some(cycles);
+        self
+    }
+
+    pub fn max_input_len(mut self, len: u64) -> Self {
+        self.limits.max_input_len = len;
+        self
+    }
+
+    pub fn prove_faults(mut self, prove_faults: bool) -> Self {
+        self.limits.prove_faults = prove_faults;
+        self
+    }
+
+    pub fn max_mem(mut self, bytes: u64) -> Self {
+        self.limits.max_mem = Some(bytes);
+        self
+    }
+
+    pub fn wrap_image(mut self, image: impl Into<String>) -> Self {
+        self.limits.wrap_image = Some(image.into());
+        self
+    }
+
+    pub fn keep_stark(mut self, keep_stark: bool) -> Self {
+        self.limits.keep_stark = keep_stark;
+        self
+    }
+
+    pub fn target(mut self, target: ProverTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn build(self) -> Result<ProveRequest> {
+        Ok(ProveRequest {
+            input: self.input.context("a prove request needs an input")?,
+            limits: self.limits,
+            target: self.target,
+        })
+    }
+}
+
+fn prove_checked(
+    input: &GuestInput,
+    limits: &ProverLimits,
+    prove_session: impl FnOnce(&GuestInput, &ProverLimits) -> Result<(Receipt, ProofStats)>,
+) -> Result<(Receipt, ProofStats)> {
+    limits.check_input(input)?;
+    let (receipt, stats) =
+        prove_session(input, limits).map_err(|err| match GuestFault::from_error(&err) {
+            Some(fault) => anyhow::Error::new(fault)
+                .context(format!("failed to prove {} byte input", input.data_len())),
+            None => err,
+        })?;
+
+    match fault_of(&receipt)? {
+        None => {}
+        Some(fault) if limits.prove_faults => {
+            tracing::warn!("{fault}; returning the proof of the fault");
+        }
+        Some(fault) => return Err(fault.into()),
+    }
+    Ok((receipt, stats))
+}
+
+/// Executes the guest over `input` under `limits` without proving it, which
+/// takes a small fraction of the time and memory of a proof, so that a job
+/// can be routed to a machine that fits it before it is proven. A guest that
+/// exits with a non-zero code is still measured.
+pub fn estimate_cycles(input: &GuestInput, limits: &ProverLimits) -> Result<CycleEstimate> {
+    limits.check_input(input)?;
+    let session = default_executor()
+        .execute(executor_env(input, limits)?, METHOD_ELF)
+        .map_err(|err| match GuestFault::from_error(&err) {
+            Some(fault) => anyhow::Error::new(fault)
+                .context(format!("failed to execute {} byte input", input.data_len())),
+            None => err,
+        })?;
+    Ok(CycleEstimate {
+        user_cycles: session.cycles(),
+        total_cycles: session
+            .segments
+            .iter()
+            .map(|segment| 1 << segment.po2)
+            .sum(),
+        segments: session.segments.len(),
+    })
+}
+
+/// The fault a receipt proves, if its guest exited with a non-zero code.
+pub fn fault_of(receipt: &Receipt) -> Result<Option<GuestFault>> {
+    let Ok(claim) = receipt.claim()?.as_value().cloned() else {
+        return Ok(None);
+    };
+    match claim.exit_code {
+        ExitCode::Halted(0) => Ok(None),
+        ExitCode::Halted(code) => Ok(Some(GuestFault::Exit { code })),
+        other => bail!("guest session ended with unexpected exit code {other:?}"),
+    }
+}
+
+/// An executor environment describes the configurations for the zkVM
+/// including program inputs. The input is split into frames so that the
+/// guest never has to hold all of it in memory. The encoded frames are
+/// zeroized once copied into the environment, which holds its own copy until
+/// it is dropped.
+pub(crate) fn executor_env(
+    input: &GuestInput,
+    limits: &ProverLimits,
+) -> Result<ExecutorEnv<'static>> {
+    let encoded = Zeroizing::new(input.encode());
+    let mut builder = ExecutorEnv::builder();
+    builder
+        .write_slice(&encoded)
+        .session_limit(limits.session_limit);
+    if let Some(po2) = limits.segment_limit_po2 {
+        builder.segment_limit_po2(po2);
+    }
+    builder.build()
+}
+
+fn prove_session(input: &GuestInput, limits: &ProverLimits) -> Result<(Receipt, ProofStats)> {
+    if bonsai::is_configured() {
+        return prove_bonsai_session(input, limits);
+    }
+    prove_local_session(input, limits)
+}
+
+/// Remote proofs go through our own Bonsai client, which retries failed
+/// requests and resumes sessions started before a restart.
+fn prove_bonsai_session(
+    input: &GuestInput,
+    limits: &ProverLimits,
+) -> Result<(Receipt, ProofStats)> {
+    bonsai::BonsaiProver::from_env()?
+        .prove(&Zeroizing::new(input.encode()), limits.session_limit)
+        .with_context(|| format!("failed to prove {} byte input", input.data_len()))
+}
+
+fn prove_local_session(input: &GuestInput, limits: &ProverLimits) -> Result<(Receipt, ProofStats)> {
+    // Segments are proven as soon as they are executed. Dev mode keeps
+    // risc0's own prover, whose fake receipts have no segments to prove.
+    if !ProverOpts::default().dev_mode() {
+        return pipeline::prove_session(input, limits, &[pipeline::local_prover()]);
+    }
+    let env = executor_env(input, limits)?;
+
+    // Obtain the default prover.
+    let prover = default_prover();
+
+    // Proof information by proving the specified ELF binary.
+    // This struct contains the receipt along with statistics about execution of the guest
+    let prove_info = prover
+        .prove_with_opts(env, METHOD_ELF, &ProverOpts::groth16())
+        .with_context(|| format!("failed to prove {} byte input", input.data_len()))?;
+
+    let stats = ProofStats {
+        backend: Backend::Local,
+        segments: prove_info.stats.segments,
+        total_cycles: prove_info.stats.total_cycles,
+        user_cycles: prove_info.stats.user_cycles,
+        stark: None,
+    };
+    Ok((prove_info.receipt, stats))
+}
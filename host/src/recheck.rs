@@ -0,0 +1,77 @@
+//! `recheck` mode: re-verifies every bundle archived in a store against the
+//! verifier parameters of this build, and reports those that no longer
+//! verify, so that a misconfigured image ID, a policy change or a corrupted
+//! object is noticed before a challenger relies on the archive.
+//!
+//! Each pass lists the store and verifies its objects as `verify-stream`
+//! does, writing a verdict line only for the bundles that fail. Run with an
+//! interval, passes repeat until the process is stopped.
+
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::Result;
+use rayon::prelude::*;
+
+use crate::{
+    storage::Store,
+    verify_stream::{self, StreamOptions, Summary, Verdict},
+};
+
+/// Re-verifies every object of `store` once, writing a verdict line for each
+/// that fails to `out`. Fails only if the store cannot be listed or verdicts
+/// written; unreadable objects are reported in their verdicts.
+pub fn recheck(
+    store: &dyn Store,
+    options: &StreamOptions,
+    out: impl Write + Send,
+) -> Result<Summary> {
+    let pool = verify_stream::thread_pool(options)?;
+    let mut keys = store.list()?;
+    keys.sort();
+    tracing::info!("rechecking {} archived bundles", keys.len());
+
+    let out = Mutex::new(out);
+    let valid = AtomicUsize::new(0);
+    let invalid = AtomicUsize::new(0);
+    pool.install(|| {
+        keys.par_iter().try_for_each(|key| -> Result<()> {
+            let verdict = match store.get(key) {
+                Ok(Some(encoded)) => verify_stream::verify_one(key.clone(), &encoded, options)?,
+                // Removed since the store was listed.
+                Ok(None) => return Ok(()),
+                Err(err) => Verdict {
+                    bundle: key.clone(),
+                    bundle_blake3: None,
+                    valid: false,
+                    image_id: None,
+                    journal_digest: None,
+                    error: Some(format!("{err:#}")),
+                },
+            };
+            if verdict.valid {
+                valid.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
+            }
+            invalid.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                "{key} no longer verifies: {}",
+                verdict.error.as_deref().unwrap_or_default()
+            );
+            let mut out = out.lock().unwrap();
+            serde_json::to_writer(&mut *out, &verdict)?;
+            writeln!(out)?;
+            out.flush()?;
+            Ok(())
+        })
+    })?;
+    Ok(Summary {
+        valid: valid.into_inner(),
+        invalid: invalid.into_inner(),
+    })
+}
@@ -0,0 +1,214 @@
+//! An end-to-end harness over a local bitcoind in regtest mode, so that
+//! integrators can exercise the whole assert flow programmatically: fund the
+//! operator, build, sign and broadcast the assert package, mine it, and read
+//! the proof back from the chain as `verify-from-tx` does.
+//!
+//! The node must run with `-regtest -txindex`. The harness keeps its coins in
+//! a wallet of its own, created on the node if needed, and mines to it
+//! whenever it runs short.
+
+use anyhow::{ensure, Context, Result};
+use bitcoin::{
+    address::NetworkUnchecked, key::Keypair, secp256k1::Secp256k1, Address, Amount, BlockHash,
+    FeeRate, Network, OutPoint, Transaction, Txid, XOnlyPublicKey,
+};
+use risc0_zkvm::{sha::Digest, Receipt};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    assert_psbt::{AssertPackage, AssertParams, Funding, DEFAULT_REVEAL_VALUE},
+    assert_tx::AssertWitness,
+    bitcoin_rpc::{BitcoinRpc, Bitcoind},
+    chunks,
+    transform::BindingHash,
+    verifier::{self, ScalarPolicy},
+};
+
+/// Wallet the harness keeps its coins in.
+pub const WALLET: &str = "r0-bitvm-blake3";
+
+/// Blocks after which a coinbase output can be spent.
+const COINBASE_MATURITY: u64 = 100;
+
+/// Fee rate paid on regtest, whose fee estimates stay empty until the node
+/// has seen enough transactions.
+pub const FEE_RATE: FeeRate = FeeRate::from_sat_per_vb_unchecked(2);
+
+/// What one run of the harness put on chain.
+#[derive(Debug, Clone)]
+pub struct RegtestRun {
+    /// The funding output of the operator's P2TR address the assert spent.
+    pub funding: OutPoint,
+    pub commit: Txid,
+    pub reveal: Txid,
+    /// The block the assert package was mined in.
+    pub block: BlockHash,
+    /// The verifier witness read back from the reveal transaction.
+    pub witness: AssertWitness,
+}
+
+/// A bitcoind regtest node.
+pub struct Regtest {
+    node: Bitcoind,
+    wallet: Bitcoind,
+}
+
+impl Regtest {
+    /// Connects to the node at `url`, checking that it runs regtest with a
+    /// transaction index, and loads or creates the harness's wallet.
+    pub fn connect(url: &str, auth: Option<(String, String)>) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct ChainInfo {
+            chain: String,
+        }
+        let node = Bitcoind::new(url, auth.clone())?;
+        let info: ChainInfo = node.call("getblockchaininfo", json!([]))?;
+        ensure!(
+            info.chain == "regtest",
+            "node at {url} runs {}, not regtest",
+            info.chain
+        );
+        let indexes: serde_json::Value = node.call("getindexinfo", json!(["txindex"]))?;
+        ensure!(
+            indexes.get("txindex").is_some(),
+            "node at {url} runs without -txindex"
+        );
+
+        let loaded: Vec<String> = node.call("listwallets", json!([]))?;
+        if !loaded.iter().any(|wallet| wallet == WALLET) {
+            let created: Result<serde_json::Value> = node.call("loadwallet", json!([WALLET]));
+            if created.is_err() {
+                node.call::<serde_json::Value>("createwallet", json!([WALLET]))
+                    .context("failed to create the harness wallet")?;
+            }
+        }
+        let wallet = Bitcoind::new(
+            &format!("{}/wallet/{WALLET}", url.trim_end_matches('/')),
+            auth,
+        )?;
+        Ok(Self { node, wallet })
+    }
+
+    /// The node, as a [`BitcoinRpc`] backend.
+    pub fn rpc(&self) -> &dyn BitcoinRpc {
+        &self.node
+    }
+
+    /// A new address of the harness's wallet.
+    pub fn new_address(&self) -> Result<Address> {
+        let address: Address<NetworkUnchecked> =
+            self.wallet.call("getnewaddress", json!(["", "bech32m"]))?;
+        Ok(address.require_network(Network::Regtest)?)
+    }
+
+    /// Mines `blocks` blocks to the harness's wallet.
+    pub fn mine(&self, blocks: u64) -> Result<Vec<BlockHash>> {
+        let address = self.new_address()?;
+        self.node
+            .call("generatetoaddress", json!([blocks, address.to_string()]))
+    }
+
+    /// Pays `amount` to the P2TR address of `operator` and mines the payment,
+    /// first mining spendable coins if the wallet lacks them.
+    pub fn fund(&self, operator: XOnlyPublicKey, amount: Amount) -> Result<Funding> {
+        let balance: f64 = self.wallet.call("getbalance", json!([]))?;
+        if Amount::from_btc(balance)? < amount {
+            self.mine(COINBASE_MATURITY + 1)?;
+        }
+        let address = Address::p2tr(
+            &Secp256k1::verification_only(),
+            operator,
+            None,
+            Network::Regtest,
+        );
+        let script = address.script_pubkey();
+        let txid: Txid = self.wallet.call(
+            "sendtoaddress",
+            json!({
+                "address": address.to_string(),
+                "amount": amount.to_btc(),
+                "fee_rate": FEE_RATE.to_sat_per_vb_ceil(),
+            }),
+        )?;
+        self.mine(1)?;
+        let tx = self.node.transaction(&txid)?;
+        let vout = tx
+            .output
+            .iter()
+            .position(|output| output.script_pubkey == script)
+            .context("funding transaction does not pay the operator")?;
+        Ok(Funding {
+            outpoint: OutPoint {
+                txid,
+                vout: vout as u32,
+            },
+            txout: tx.output[vout].clone(),
+        })
+    }
+
+    /// Broadcasts `txs` in order and mines them into one block.
+    pub fn confirm(&self, txs: &[Transaction]) -> Result<BlockHash> {
+        for tx in txs {
+            self.node.broadcast(tx)?;
+        }
+        self.mine(1)?.pop().context("node mined no block")
+    }
+
+    /// Reads the verifier witness back from the assert transaction `txid` and
+    /// verifies its seal, as `verify-from-tx` does.
+    pub fn verify_from_tx(&self, txid: &Txid) -> Result<AssertWitness> {
+        let witness = AssertWitness::from_transaction(&self.node.transaction(txid)?, None)?;
+        witness.verify(ScalarPolicy::Reject)?;
+        Ok(witness)
+    }
+
+    /// Runs the whole flow for `receipt`: funds `operator` with `amount`,
+    /// asserts the receipt's verifier witness with a package signed by the
+    /// operator, and checks that the witness read back from the chain is the
+    /// receipt's.
+    pub fn run(
+        &self,
+        receipt: &Receipt,
+        image_id: impl Into<Digest>,
+        operator: &Keypair,
+        amount: Amount,
+    ) -> Result<RegtestRun> {
+        let image_id = image_id.into();
+        verifier::verify_receipt(receipt, image_id)?;
+        let witness = AssertWitness::from_bytes(&chunks::verifier_witness(
+            receipt,
+            image_id,
+            &BindingHash::default(),
+        )?)?;
+
+        let (key, _) = operator.x_only_public_key();
+        let funding = self.fund(key, amount)?;
+        let params = AssertParams {
+            operator: key.into(),
+            funding: vec![funding.clone()],
+            change: self.new_address()?.script_pubkey(),
+            destination: self.new_address()?.script_pubkey(),
+            reveal_value: DEFAULT_REVEAL_VALUE,
+            fee_rate: FEE_RATE,
+            rbf: true,
+        };
+        let mut package = AssertPackage::build(&params, &witness)?;
+        package.sign(operator)?;
+        let (commit, reveal) = package.finalize()?;
+        let block = self.confirm(&[commit.clone(), reveal.clone()])?;
+
+        let read_back = self.verify_from_tx(&reveal.compute_txid())?;
+        ensure!(
+            read_back == witness,
+            "the reveal transaction carries another witness than the receipt's"
+        );
+        Ok(RegtestRun {
+            funding: funding.outpoint,
+            commit: commit.compute_txid(),
+            reveal: reveal.compute_txid(),
+            block,
+            witness: read_back,
+        })
+    }
+}
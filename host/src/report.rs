@@ -0,0 +1,172 @@
+//! Verification reports: a machine-readable record of one `verify` run, with
+//! the parameters the bundle was verified under, the digests checked, how
+//! long each stage took, the verdict and the software that reached it, for
+//! attaching to a governance vote or a dispute.
+//!
+//! A report is written whether or not the bundle verifies; a rejection is as
+//! much worth recording as an acceptance.
+
+use std::{path::Path, time::Instant};
+
+use anyhow::Result;
+use methods::METHOD_ID;
+use risc0_zkvm::{
+    sha::{Digest, Digestible},
+    Receipt,
+};
+use serde::Serialize;
+
+use crate::{
+    bundle::BuildMetadata, journal::Journal, policy, profile::ProfileSummary,
+    storage::write_atomic, verifier,
+};
+
+/// Version of the report layout, raised when a field changes meaning.
+pub const REPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    pub report_version: u32,
+    /// Unix timestamp the verification started at, in seconds.
+    pub verified_at: u64,
+    /// The bundle as named on the command line.
+    pub bundle: String,
+    pub software: Software,
+    pub parameters: VerifyParameters,
+    pub digests: ReportDigests,
+    /// Stages of the verification in the order they ran. A stage that
+    /// failed is the last one.
+    pub timings: Vec<Timing>,
+    /// Attestations of the bundle's timestamp, if it has one.
+    pub timestamp_attestations: Vec<ReportedAttestation>,
+    pub valid: bool,
+    /// Image ID the receipt verified against.
+    pub image_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// The software that verified the bundle, and that the bundle says proved it.
+#[derive(Debug, Serialize)]
+pub struct Software {
+    pub host_version: String,
+    pub risc0_version: String,
+    /// Image ID of the guest built into this binary.
+    pub builtin_image_id: String,
+    /// How the arkworks verifier was built.
+    pub arithmetic_backend: String,
+    /// The guest the bundle records it was proven with.
+    pub prover: Option<BuildMetadata>,
+}
+
+/// The parameters a bundle was verified under.
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyParameters {
+    /// Hex-encoded image IDs accepted, unless an allowlist or profile chose
+    /// them.
+    pub image_ids: Vec<String>,
+    pub allowlist: Option<String>,
+    pub height: Option<u64>,
+    /// The profile verified under, in the form of the profile file.
+    pub profile: Option<ProfileSummary>,
+    pub max_journal_len: usize,
+    pub binding_hash: String,
+    pub cross_check: bool,
+    pub normalize_seal: bool,
+    /// Which receipts were verified: `groth16`, `stark` or `both`.
+    pub verify_mode: String,
+    pub policy: Option<String>,
+}
+
+/// Digests of what was checked, each present once the stage computing it
+/// was reached.
+#[derive(Debug, Default, Serialize)]
+pub struct ReportDigests {
+    /// BLAKE3 digest of the bundle file.
+    pub bundle_blake3: Option<String>,
+    /// BLAKE3 digest of the Groth16 seal.
+    pub seal_blake3: Option<String>,
+    /// SHA-256 journal digest committed to by the receipt claim.
+    pub journal_digest: Option<String>,
+    pub claim_digest: Option<String>,
+    /// Hex-encoded public input the seal verified against.
+    pub public_input: Option<String>,
+}
+
+/// One attestation of a bundle's timestamp.
+#[derive(Debug, Serialize)]
+pub struct ReportedAttestation {
+    /// The attestation as printed, with its calendar.
+    pub attestation: String,
+    /// Whether the attested block was found in the chain; pending
+    /// attestations, and Bitcoin ones not checked, are not verified.
+    pub verified: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Timing {
+    pub stage: &'static str,
+    pub elapsed_micros: u64,
+}
+
+impl VerificationReport {
+    pub fn new(bundle: &str, parameters: VerifyParameters) -> Self {
+        Self {
+            report_version: REPORT_VERSION,
+            verified_at: policy::unix_now(),
+            bundle: bundle.to_string(),
+            software: Software {
+                host_version: env!("CARGO_PKG_VERSION").to_string(),
+                risc0_version: risc0_zkvm::VERSION.to_string(),
+                builtin_image_id: Digest::from(METHOD_ID).to_string(),
+                arithmetic_backend: verifier::arithmetic_backend().to_string(),
+                prover: None,
+            },
+            parameters,
+            digests: ReportDigests::default(),
+            timings: Vec::new(),
+            timestamp_attestations: Vec::new(),
+            valid: false,
+            image_id: None,
+            error: None,
+        }
+    }
+
+    /// Runs `stage` and records how long it took.
+    pub fn time<T>(&mut self, stage: &'static str, run: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = run();
+        self.timings.push(Timing {
+            stage,
+            elapsed_micros: started.elapsed().as_micros() as u64,
+        });
+        result
+    }
+
+    /// Records the digests of `receipt`, the one the bundle carries.
+    pub fn record_receipt(&mut self, receipt: &Receipt) {
+        self.digests.seal_blake3 = receipt
+            .inner
+            .groth16()
+            .ok()
+            .map(|groth_proof| blake3::hash(&groth_proof.seal).to_hex().to_string());
+        self.digests.journal_digest = Some(Journal::of(receipt).digest().to_string());
+        self.digests.claim_digest = receipt.claim().ok().map(|claim| claim.digest().to_string());
+    }
+
+    /// Records the verdict, with the public input the seal verified against
+    /// if it did.
+    pub fn record_outcome(&mut self, outcome: &Result<Digest>, public_input: Option<&[u8]>) {
+        match outcome {
+            Ok(image_id) => {
+                self.valid = true;
+                self.image_id = Some(image_id.to_string());
+                self.digests.public_input = public_input.map(hex::encode);
+            }
+            Err(err) => self.error = Some(format!("{err:#}")),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        write_atomic(path, &serde_json::to_vec_pretty(self)?)
+    }
+}
@@ -0,0 +1,145 @@
+//! `selftest` mode: proves a guest that does nothing but hash its input with
+//! BLAKE3 over one of the official BLAKE3 test vectors, and checks the
+//! journal against the known answer. A toolchain that miscompiles the guest,
+//! a broken `r0vm` or a misconfigured Groth16 prover shows up here, on a
+//! proof whose expected result is published, rather than in production.
+//!
+//! The official vectors hash the first `len` bytes of the repeating sequence
+//! `0, 1, ..., 250`.
+
+use anyhow::{ensure, Context, Result};
+use methods::{SELFTEST_ELF, SELFTEST_ID};
+use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts, Receipt, VerifierContext};
+
+use crate::verifier;
+
+/// One known-answer vector: the BLAKE3 hash of `len` input bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Vector {
+    pub len: usize,
+    /// Hex-encoded, as published.
+    pub hash: &'static str,
+}
+
+/// The vectors selftests can be run over, covering inputs within one block,
+/// at the block and chunk boundaries, and spanning many chunks.
+pub const VECTORS: &[Vector] = &[
+    Vector {
+        len: 0,
+        hash: "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+    },
+    Vector {
+        len: 1,
+        hash: "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213",
+    },
+    Vector {
+        len: 63,
+        hash: "e9bc37a594daad83be9470df7f7b3798297c3d834ce80ba85d6e207627b7db7b",
+    },
+    Vector {
+        len: 64,
+        hash: "4eed7141ea4a5cd4b788606bd23f46e212af9cacebacdc7d1f4c6dc7f2511b98",
+    },
+    Vector {
+        len: 65,
+        hash: "de1e5fa0be70df6d2be8fffd0e99ceaa8eb6e8c93a63f2d8d1c30ecb6b263dee",
+    },
+    Vector {
+        len: 1023,
+        hash: "10108970eeda3eb932baac1428c7a2163b0e924c9a9e25b35bba72b28f70bd11",
+    },
+    Vector {
+        len: 1024,
+        hash: "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7",
+    },
+    Vector {
+        len: 1025,
+        hash: "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444",
+    },
+    Vector {
+        len: 2048,
+        hash: "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a",
+    },
+    Vector {
+        len: 2049,
+        hash: "5f4d72f40d7a5f82b15ca2b2e44b1de3c2ef86c426c95c1af0b6879522563030",
+    },
+    Vector {
+        len: 8192,
+        hash: "aae792484c8efe4f19e2ca7d371d8c467ffb10748d8a5a1ae579948f718a2a63",
+    },
+    Vector {
+        len: 8193,
+        hash: "bab6c09cb8ce8cf459261398d2e7aef35700bf488116ceb94a36d0f5f1b7bc3b",
+    },
+    Vector {
+        len: 102400,
+        hash: "bc3e3d41a1146b069abffad3c0d44860cf664390afce4d9661f7902e7943e085",
+    },
+];
+
+impl Vector {
+    /// The vector of `len` input bytes.
+    pub fn find(len: usize) -> Result<Self> {
+        VECTORS
+            .iter()
+            .copied()
+            .find(|vector| vector.len == len)
+            .with_context(|| {
+                let lens: Vec<_> = VECTORS
+                    .iter()
+                    .map(|vector| vector.len.to_string())
+                    .collect();
+                format!(
+                    "no test vector of {len} bytes; vectors are of {} bytes",
+                    lens.join(", ")
+                )
+            })
+    }
+
+    pub fn input(&self) -> Vec<u8> {
+        (0..self.len).map(|i| (i % 251) as u8).collect()
+    }
+}
+
+/// Proves the selftest guest over `vector`, verifies the receipt and checks
+/// that its journal is the known answer. A dev-mode receipt is only checked
+/// against the claim; a real one is verified as a Groth16 proof bound to its
+/// journal, as bundles are.
+pub fn run(vector: &Vector, dev_mode: bool) -> Result<Receipt> {
+    let input = vector.input();
+    let expected = hex::decode(vector.hash)?;
+    ensure!(
+        blake3::hash(&input).as_bytes()[..] == expected[..],
+        "the BLAKE3 of this host disagrees with the {} byte test vector",
+        vector.len
+    );
+
+    let env = ExecutorEnv::builder()
+        .write_slice(&(input.len() as u32).to_le_bytes())
+        .write_slice(&input)
+        .build()?;
+    let receipt = default_prover()
+        .prove_with_opts(
+            env,
+            SELFTEST_ELF,
+            &ProverOpts::groth16().with_dev_mode(dev_mode),
+        )
+        .context("failed to prove the selftest guest")?
+        .receipt;
+
+    if dev_mode {
+        receipt
+            .verify_with_context(&VerifierContext::default().with_dev_mode(true), SELFTEST_ID)?;
+    } else {
+        verifier::verify_receipt_any(&receipt, &[SELFTEST_ID.into()])?;
+    }
+    ensure!(
+        receipt.journal.bytes == expected,
+        "the guest hashed the {} byte test vector to {}, expected {}",
+        vector.len,
+        hex::encode(&receipt.journal.bytes),
+        vector.hash
+    );
+    Ok(receipt)
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+
+use super::{queue::Priority, verify_cache::VerifyCacheConfig};
+use crate::{
+    callback::CallbackPolicy, cost::CostModel, deadline::EscalationConfig, gossip::GossipConfig,
+    policy::VerifierPolicy, prover::ProverLimits, storage::Layout, verifier,
+};
+
+/// Configuration for `serve` mode, loaded from a TOML file.
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct ServerConfig {
+    /// Address the HTTP API listens on.
+    #[serde(default = "default_listen")]
+    pub listen: SocketAddr,
+    /// Directory holding the persistent job queue, and by default the receipts
+    /// of finished jobs.
+    pub data_dir: PathBuf,
+    /// Storage URI receipts are written to instead, e.g. an `s3://` bucket
+    /// shared with other workers.
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Keys bundles are stored under, see [`Layout`].
+    #[serde(default)]
+    pub layout: Layout,
+    /// File holding a hex-encoded key used to encrypt stored receipts. If
+    /// unset, the key is taken from `R0_BITVM_STORAGE_KEY` when present.
+    #[serde(default)]
+    pub encryption_key_file: Option<PathBuf>,
+    /// Executor limits applied to every job. Unless set, `max_input_len` is
+    /// [`DEFAULT_MAX_INPUT_LEN`] rather than the whole guest address space,
+    /// since every input is held in memory and sent as hex.
+    #[serde(default = "default_limits", deserialize_with = "deserialize_limits")]
+    pub limits: ProverLimits,
+    /// Largest journal a job may produce, in bytes. Many tiny inputs in one
+    /// multi-input job would otherwise yield an arbitrarily large journal.
+    #[serde(default = "default_max_journal_len")]
+    pub max_journal_len: usize,
+    /// How long the records of finished jobs are kept, in seconds. Older ones
+    /// are deleted, so the queue does not grow without bound; their proof
+    /// bundles stay in storage.
+    #[serde(default = "default_job_retention_secs")]
+    pub job_retention_secs: u64,
+    /// Prices the estimated cost recorded with each finished job is based on.
+    #[serde(default)]
+    pub cost: CostModel,
+    /// Policy every proof must satisfy before it is verified and stored.
+    #[serde(default)]
+    pub policy: VerifierPolicy,
+    /// How long the verdicts of `POST /verify` are kept.
+    #[serde(default)]
+    pub verify_cache: VerifyCacheConfig,
+    /// Backends a job with a deadline may be escalated to, and how fast each
+    /// proves. Without it, deadlines are recorded but not acted on.
+    #[serde(default)]
+    pub escalation: Option<EscalationConfig>,
+    /// Where finished proofs are announced, if anywhere.
+    #[serde(default)]
+    pub gossip: Option<GossipConfig>,
+    /// Where jobs may have their completion notifications sent.
+    #[serde(default)]
+    pub callbacks: CallbackPolicy,
+    /// Operators allowed to submit jobs, each identified by its API key.
+    #[serde(default)]
+    pub tenants: Vec<Tenant>,
+}
+
+/// A bridge operator and the quotas applied to its API key.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Tenant {
+    pub name: String,
+    pub api_key: String,
+    /// Maximum number of jobs the tenant may have queued or running at once.
+    #[serde(default = "default_max_pending")]
+    pub max_pending: usize,
+    /// Maximum number of jobs the tenant may submit within any rolling hour.
+    #[serde(default = "default_jobs_per_hour")]
+    pub jobs_per_hour: usize,
+    /// Highest priority the tenant may submit jobs at. Routine unless set, so
+    /// the dispute lane is only open to tenants it is granted to.
+    #[serde(default = "default_max_priority")]
+    pub max_priority: Priority,
+    /// Maximum number of proofs the tenant may have verified within any
+    /// rolling minute, not counting those answered from the verify cache.
+    #[serde(default = "default_verifies_per_minute")]
+    pub verifies_per_minute: usize,
+}
+
+/// Largest job input accepted unless `max_input_len` is set under `[limits]`,
+/// in bytes.
+pub const DEFAULT_MAX_INPUT_LEN: u64 = 32 * 1024 * 1024;
+
+fn default_limits() -> ProverLimits {
+    let mut limits = ProverLimits::default();
+    limits.max_input_len = DEFAULT_MAX_INPUT_LEN;
+    limits
+}
+
+/// [`ProverLimits`] with the server's default `max_input_len`.
+fn deserialize_limits<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<ProverLimits, D::Error> {
+    let mut table = toml::Table::deserialize(deserializer)?;
+    table
+        .entry("max_input_len")
+        .or_insert(toml::Value::Integer(DEFAULT_MAX_INPUT_LEN as i64));
+    toml::Value::Table(table)
+        .try_into()
+        .map_err(serde::de::Error::custom)
+}
+
+fn default_listen() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 8080))
+}
+
+fn default_max_journal_len() -> usize {
+    verifier::DEFAULT_MAX_JOURNAL_LEN
+}
+
+fn default_job_retention_secs() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_max_pending() -> usize {
+    4
+}
+
+fn default_jobs_per_hour() -> usize {
+    60
+}
+
+fn default_max_priority() -> Priority {
+    Priority::Routine
+}
+
+fn default_verifies_per_minute() -> usize {
+    60
+}
+
+impl ServerConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read server config {}", path.display()))?;
+        let config: Self = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse server config {}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        // Hourly quotas count finished jobs too.
+        ensure!(
+            self.job_retention_secs >= 60 * 60,
+            "job_retention_secs must be at least an hour"
+        );
+        let mut names = HashSet::new();
+        let mut keys = HashSet::new();
+        for tenant in &self.tenants {
+            ensure!(
+                names.insert(tenant.name.as_str()),
+                "duplicate tenant name {:?}",
+                tenant.name
+            );
+            ensure!(
+                keys.insert(tenant.api_key.as_str()),
+                "tenant {:?} reuses an API key",
+                tenant.name
+            );
+            ensure!(
+                !tenant.api_key.is_empty(),
+                "tenant {:?} has an empty API key",
+                tenant.name
+            );
+        }
+        Ok(())
+    }
+
+    /// The tenant of `api_key`. Every key is compared in full, in time that
+    /// does not depend on how much of it matches.
+    pub fn tenant_for_key(&self, api_key: &str) -> Option<&Tenant> {
+        self.tenants
+            .iter()
+            .filter(|tenant| keys_equal(&tenant.api_key, api_key))
+            .fold(None, |found, tenant| found.or(Some(tenant)))
+    }
+}
+
+/// Compares the SHA-256 digests of `a` and `b` without stopping at the first
+/// differing byte, so neither their lengths nor a matching prefix show in the
+/// timing.
+fn keys_equal(a: &str, b: &str) -> bool {
+    let (a, b) = (Sha256::digest(a.as_bytes()), Sha256::digest(b.as_bytes()));
+    a.iter()
+        .zip(b.iter())
+        .fold(0, |diff, (x, y)| diff | (x ^ y))
+        == 0
+}
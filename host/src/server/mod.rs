@@ -0,0 +1,650 @@
+//! `serve` mode: a small HTTP proving service shared by several operators.
+//!
+//! Jobs are submitted with `POST /jobs` and an `X-Api-Key` header, and are
+//! proven one at a time, by priority and then in submission order. Their state is available from
+//! `GET /jobs/<id>`, and the bincode-encoded
+//! [`ProofBundle`](crate::bundle::ProofBundle) of a finished job from
+//! `GET /jobs/<id>/result`, and the [`lifecycle`](crate::lifecycle) of the
+//! claim it proves from `GET /jobs/<id>/lifecycle`. A job may also name a `callback_url`, which is
+//! sent a [`ProofNotification`] referencing the result once the job finishes,
+//! if the `[callbacks]` [`CallbackPolicy`] allows it.
+//! `POST /verify` verifies a bincode-encoded bundle sent as the body against
+//! the built-in image ID, without queueing anything, answering a proof it
+//! verified recently from a cache of verdicts. With a `[gossip]` table
+//! configured, every stored proof is also announced on it.
+
+mod config;
+mod queue;
+mod verify_cache;
+
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use common::padding::Padding;
+use hex::FromHex;
+use methods::METHOD_ID;
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+pub use config::{ServerConfig, Tenant};
+pub use queue::{Job, JobQueue, JobStatus, Priority};
+pub use verify_cache::VerifyCacheConfig;
+
+use crate::{
+    bundle::{ProofBundle, RunParameters},
+    callback::{self, CallbackPolicy, ProofNotification},
+    deadline, gossip,
+    input::GuestInput,
+    journal::Journal,
+    merkle::SortedTree,
+    prover,
+    smt::SparseTree,
+    storage::{self, LocalStore},
+    verifier,
+};
+use verify_cache::VerifyCache;
+
+/// Maximum accepted size of a bundle sent to `POST /verify`.
+const MAX_BUNDLE_LEN: u64 = 64 * 1024 * 1024;
+
+/// Room in a job request for everything but its hex-encoded input data.
+const JOB_REQUEST_OVERHEAD: u64 = 1024 * 1024;
+
+type HttpResponse = Response<std::io::Cursor<Vec<u8>>>;
+
+struct State {
+    config: ServerConfig,
+    queue: Mutex<JobQueue>,
+    ready: Condvar,
+    verify_cache: Mutex<VerifyCache>,
+}
+
+/// A job submission. Exactly one of `input` (hashed into one digest), `inputs`
+/// (each hashed into its own digest), `entries`, `absent` or `sparse` must be
+/// given, hex-encoded.
+#[derive(Deserialize)]
+pub(crate) struct SubmitRequest {
+    #[serde(default)]
+    input: Option<String>,
+    #[serde(default)]
+    inputs: Option<Vec<String>>,
+    /// A key-value map of hex-encoded keys and values, committed to as a whole.
+    #[serde(default)]
+    entries: Option<BTreeMap<String, String>>,
+    /// Named fields with hex-encoded values, committed to so that they can be
+    /// disclosed one at a time later.
+    #[serde(default)]
+    fields: Option<BTreeMap<String, String>>,
+    /// Prove that a key is absent from the sorted Merkle tree over `leaves`.
+    #[serde(default)]
+    absent: Option<AbsentRequest>,
+    /// Prove the value, or absence, of a key in the sparse Merkle tree over
+    /// `entries`.
+    #[serde(default)]
+    sparse: Option<SparseRequest>,
+    /// Commit to the BLAKE3 outboard of `input` rather than only its hash.
+    #[serde(default)]
+    outboard: Option<OutboardOptions>,
+    /// Prove only the digest of a byte range of `input`, and that it belongs
+    /// to the input's BLAKE3 tree.
+    #[serde(default)]
+    slice: Option<SliceOptions>,
+    /// Set to `"zstd"` if `input` is zstd-compressed.
+    #[serde(default)]
+    compression: Option<Compression>,
+    /// Padding applied to an uncompressed `input` before hashing, e.g.
+    /// `"zero-pad:10"`.
+    #[serde(default)]
+    padding: Option<String>,
+    /// Also commit the BLAKE3 digest of the whole guest input, binding the
+    /// proof to it without revealing it.
+    #[serde(default)]
+    bind_input: bool,
+    /// Also commit the number of bytes and frames the guest read.
+    #[serde(default)]
+    with_stats: bool,
+    /// Also commit the journal schema and modes of the guest.
+    #[serde(default)]
+    with_version: bool,
+    /// Hex-encoded 32-byte nonce to send with the input and commit, binding
+    /// the proof to the request it answers.
+    #[serde(default)]
+    nonce: Option<String>,
+    /// Largest frame the input is sent to the guest in, a power of two from
+    /// 1024 to 65536 bytes.
+    #[serde(default)]
+    frame_len: Option<usize>,
+    /// `"dispute"` to prove the job ahead of every queued `"routine"` job.
+    #[serde(default)]
+    priority: Priority,
+    /// Unix timestamp, in seconds, the proof is due by. With `[escalation]`
+    /// configured, the job is proven on a faster backend if proving it here
+    /// is projected to miss it.
+    #[serde(default)]
+    deadline: Option<u64>,
+    #[serde(default)]
+    callback_url: Option<String>,
+}
+
+/// Hex-encoded 32-byte keys.
+#[derive(Deserialize)]
+struct AbsentRequest {
+    leaves: Vec<String>,
+    key: String,
+}
+
+/// Hex-encoded 32-byte keys and values of any length.
+#[derive(Deserialize)]
+struct SparseRequest {
+    entries: BTreeMap<String, String>,
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct OutboardOptions {
+    /// Log2 of the block size in KiB: 0 for bao, 4 for Iroh.
+    #[serde(default)]
+    block_log: u8,
+    /// Derive-key context string personalizing the hash.
+    #[serde(default)]
+    context: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SliceOptions {
+    offset: u64,
+    length: u64,
+    #[serde(flatten)]
+    tree: OutboardOptions,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Compression {
+    Zstd,
+}
+
+impl SubmitRequest {
+    pub(crate) fn guest_input(&self) -> Result<GuestInput> {
+        let input = self.unbound_input()?;
+        let input = if self.bind_input {
+            GuestInput::BindInput(Box::new(input))
+        } else {
+            input
+        };
+        let input = if self.with_stats {
+            GuestInput::WithStats(Box::new(input))
+        } else {
+            input
+        };
+        let input = if self.with_version {
+            GuestInput::WithVersion(Box::new(input))
+        } else {
+            input
+        };
+        let input = match &self.nonce {
+            Some(nonce) => GuestInput::WithNonce {
+                nonce: <[u8; 32]>::from_hex(nonce)
+                    .context("`nonce` must be 32 hex-encoded bytes")?,
+                input: Box::new(input),
+            },
+            None => input,
+        };
+        Ok(match self.frame_len {
+            Some(frame_len) => GuestInput::Framed {
+                frame_len,
+                input: Box::new(input),
+            },
+            None => input,
+        })
+    }
+
+    fn unbound_input(&self) -> Result<GuestInput> {
+        if self.padding.is_some() && (self.input.is_none() || self.compression.is_some()) {
+            bail!("`padding` only applies to an uncompressed `input`");
+        }
+        let given = [
+            self.input.is_some(),
+            self.inputs.is_some(),
+            self.entries.is_some(),
+            self.fields.is_some(),
+            self.absent.is_some(),
+            self.sparse.is_some(),
+        ];
+        if given.iter().filter(|&&given| given).count() != 1 {
+            bail!(
+                "exactly one of `input`, `inputs`, `entries`, `fields`, `absent` and `sparse` must \
+                 be given"
+            );
+        }
+        if self.compression.is_some() && self.input.is_none() {
+            bail!("`compression` only applies to `input`");
+        }
+        if self.outboard.is_some() || self.slice.is_some() {
+            let (Some(input), None, None) = (&self.input, &self.compression, &self.padding) else {
+                bail!("`outboard` and `slice` only apply to an uncompressed, unpadded `input`");
+            };
+            let data = hex::decode(input)?;
+            let input = match (&self.outboard, &self.slice) {
+                (Some(outboard), None) => GuestInput::Outboard {
+                    data,
+                    block_log: outboard.block_log,
+                    context: outboard.context.clone(),
+                },
+                (None, Some(slice)) => GuestInput::Slice {
+                    data,
+                    block_log: slice.tree.block_log,
+                    context: slice.tree.context.clone(),
+                    offset: slice.offset,
+                    length: slice.length,
+                },
+                _ => bail!("`outboard` and `slice` cannot be combined"),
+            };
+            input.validate()?;
+            return Ok(input);
+        }
+        let key = |encoded: &String| -> Result<[u8; 32]> {
+            <[u8; 32]>::from_hex(encoded).context("keys must be 32 hex-encoded bytes")
+        };
+        if let Some(sparse) = &self.sparse {
+            let mut tree = SparseTree::new();
+            for (entry, value) in &sparse.entries {
+                tree.insert(key(entry)?, &hex::decode(value)?);
+            }
+            return Ok(GuestInput::SparseMerkle(tree.proof(key(&sparse.key)?)));
+        }
+        if let Some(absent) = &self.absent {
+            let tree = SortedTree::new(absent.leaves.iter().map(key).collect::<Result<Vec<_>>>()?);
+            return Ok(GuestInput::MerkleExclusion(
+                tree.exclusion_proof(key(&absent.key)?)?,
+            ));
+        }
+        if let Some(entries) = &self.entries {
+            let input = GuestInput::KeyValue(
+                entries
+                    .iter()
+                    .map(|(key, value)| Ok((hex::decode(key)?, hex::decode(value)?)))
+                    .collect::<Result<_>>()?,
+            );
+            input.validate()?;
+            return Ok(input);
+        }
+        if let Some(fields) = &self.fields {
+            let input = GuestInput::Fields(
+                fields
+                    .iter()
+                    .map(|(name, value)| Ok((name.clone(), hex::decode(value)?)))
+                    .collect::<Result<_>>()?,
+            );
+            input.validate()?;
+            return Ok(input);
+        }
+        let input = match (&self.input, &self.inputs, &self.compression) {
+            (Some(input), None, None) => GuestInput::Single {
+                data: hex::decode(input)?,
+                padding: match &self.padding {
+                    Some(padding) => padding.parse().map_err(|err: String| anyhow!(err))?,
+                    None => Padding::None,
+                },
+            },
+            (Some(input), None, Some(Compression::Zstd)) => GuestInput::Zstd(hex::decode(input)?),
+            (None, Some(inputs), None) => {
+                GuestInput::Multi(inputs.iter().map(hex::decode).collect::<Result<_, _>>()?)
+            }
+            _ => unreachable!("exactly one input was given"),
+        };
+        input.validate()?;
+        Ok(input)
+    }
+}
+
+#[derive(Serialize)]
+struct SubmitResponse<'a> {
+    id: &'a str,
+}
+
+#[derive(Serialize)]
+struct VerifyResponse {
+    journal_digest: String,
+    /// Whether the verdict was answered from the verify cache.
+    cached: bool,
+}
+
+/// Runs the proving service until the process is stopped.
+pub fn serve(config: ServerConfig) -> Result<()> {
+    let store = match &config.output {
+        Some(uri) => storage::open(uri)?,
+        None => Box::new(LocalStore::new(config.data_dir.join("results"))),
+    };
+    let key = storage::load_encryption_key(config.encryption_key_file.as_deref())?;
+    let store = storage::with_encryption(store, key);
+    let mut queue = JobQueue::open(&config.data_dir, store, config.layout.clone())?;
+    queue.prune(config.job_retention_secs, now());
+    let server = Server::http(config.listen).map_err(|err| anyhow!(err))?;
+    tracing::info!("listening on {}", config.listen);
+
+    let state = Arc::new(State {
+        verify_cache: Mutex::new(VerifyCache::new(config.verify_cache.clone())),
+        config,
+        queue: Mutex::new(queue),
+        ready: Condvar::new(),
+    });
+
+    let worker_state = state.clone();
+    thread::spawn(move || run_worker(&worker_state));
+
+    for mut request in server.incoming_requests() {
+        let response = handle(&state, &mut request);
+        if let Err(err) = request.respond(response) {
+            tracing::warn!("failed to send response: {err}");
+        }
+    }
+    Ok(())
+}
+
+/// Proves queued jobs one at a time. Errors end up in the job they occur in,
+/// so one job's failure never stops the others.
+fn run_worker(state: &State) -> ! {
+    loop {
+        let (job, input) = {
+            let mut queue = state.queue.lock().unwrap();
+            loop {
+                match queue.claim_next() {
+                    Some(claimed) => break claimed,
+                    None => queue = state.ready.wait(queue).unwrap(),
+                }
+            }
+        };
+
+        tracing::info!("proving job {} for tenant {}", job.id, job.tenant);
+        let limits = &state.config.limits;
+        let proven = match (job.deadline, &state.config.escalation) {
+            (Some(deadline), Some(escalation)) => {
+                deadline::prove_by(&input, limits, deadline, escalation)
+                    .map(|(receipt, stats, escalation)| (receipt, stats, Some(escalation)))
+            }
+            _ => prover::prove_with_stats(&input, limits)
+                .map(|(receipt, stats)| (receipt, stats, None)),
+        };
+        let outcome = proven.and_then(|(receipt, stats, escalation)| {
+            let run = RunParameters::new(limits, &stats, None);
+            let mut bundle = ProofBundle::new(receipt).with_run(run);
+            if let Some(escalation) = escalation {
+                bundle = bundle.with_escalation(escalation);
+            }
+            if let Some(stark) = stats.stark.clone() {
+                bundle = bundle.with_stark(stark);
+            }
+            state.config.policy.check(&bundle, None, now())?;
+            verifier::check_journal(&bundle.receipt.journal.bytes, state.config.max_journal_len)?;
+            verifier::verify_receipt(&bundle.receipt, METHOD_ID)?;
+            Ok((bundle, state.config.cost.estimate(&stats)))
+        });
+        if let Err(err) = &outcome {
+            tracing::warn!("job {} failed: {err:#}", job.id);
+        }
+        let announcement = match (&state.config.gossip, &outcome) {
+            (Some(gossip), Ok((bundle, _))) => bundle
+                .encode()
+                .and_then(|encoded| gossip.announcement(&job.id, &encoded))
+                .map_err(|err| tracing::warn!("job {}: cannot announce: {err:#}", job.id))
+                .ok()
+                .map(|announcement| (gossip.transport.clone(), announcement)),
+            _ => None,
+        };
+        let job = {
+            let mut queue = state.queue.lock().unwrap();
+            queue.prune(state.config.job_retention_secs, now());
+            match queue.complete(&job.id, outcome, now()) {
+                Ok(job) => job,
+                Err(err) => {
+                    tracing::error!("job {}: {err:#}", job.id);
+                    continue;
+                }
+            }
+        };
+        if let Some((transport, announcement)) = announcement {
+            let id = job.id.clone();
+            thread::spawn(move || {
+                if let Err(err) = gossip::announce(&transport, &announcement) {
+                    tracing::warn!("job {id}: {err:#}");
+                }
+            });
+        }
+        if let Some(url) = job.callback_url.clone() {
+            let policy = state.config.callbacks.clone();
+            thread::spawn(move || notify_finished(&policy, &url, &job));
+        }
+    }
+}
+
+fn notify_finished(policy: &CallbackPolicy, url: &str, job: &Job) {
+    let succeeded = job.status == JobStatus::Succeeded;
+    let notification = ProofNotification {
+        job_id: Some(job.id.clone()),
+        succeeded,
+        error: job.error.clone(),
+        result_path: succeeded.then(|| format!("/jobs/{}/result", job.id)),
+        ..Default::default()
+    };
+    if let Err(err) = callback::notify_checked(policy, url, &notification) {
+        tracing::warn!("job {}: {err:#}", job.id);
+    }
+}
+
+fn handle(state: &State, request: &mut Request) -> HttpResponse {
+    let Some(tenant) = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("X-Api-Key"))
+        .and_then(|header| state.config.tenant_for_key(header.value.as_str()))
+    else {
+        return error_response(401, "missing or unknown API key");
+    };
+
+    let path: Vec<&str> = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match (request.method(), path.as_slice()) {
+        (Method::Post, ["jobs"]) => submit_job(state, tenant, request),
+        (Method::Get, ["jobs", id]) => job_status(state, tenant, id),
+        (Method::Get, ["jobs", id, "result"]) => job_result(state, tenant, id),
+        (Method::Get, ["jobs", id, "lifecycle"]) => job_lifecycle(state, tenant, id),
+        (Method::Post, ["verify"]) => verify_bundle(state, tenant, request),
+        _ => error_response(404, "not found"),
+    }
+}
+
+fn submit_job(state: &State, tenant: &Tenant, request: &mut Request) -> HttpResponse {
+    // Input data is sent hex-encoded, at two characters a byte.
+    let max_len = (state.config.limits.max_input_len)
+        .saturating_mul(2)
+        .saturating_add(JOB_REQUEST_OVERHEAD);
+    let body = match read_body(request, max_len) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let submit: SubmitRequest = match serde_json::from_slice(&body) {
+        Ok(submit) => submit,
+        Err(err) => return error_response(400, &format!("invalid job request: {err}")),
+    };
+    let input = match submit.guest_input() {
+        Ok(input) => input,
+        Err(err) => return error_response(400, &format!("invalid job input: {err:#}")),
+    };
+    if let Err(err) = state.config.limits.check_input(&input) {
+        return error_response(400, &format!("invalid job input: {err:#}"));
+    }
+    if let Some(url) = &submit.callback_url {
+        if let Err(err) = state.config.callbacks.check(url) {
+            return error_response(400, &format!("callback_url refused: {err:#}"));
+        }
+    }
+
+    let now = now();
+    let mut queue = state.queue.lock().unwrap();
+    if let Some(reason) = queue.check_quota(tenant, submit.priority, now) {
+        return error_response(429, &reason);
+    }
+    match queue.submit(
+        tenant,
+        input,
+        submit.priority,
+        submit.deadline,
+        submit.callback_url,
+        now,
+    ) {
+        Ok(job) => {
+            state.ready.notify_one();
+            tracing::info!(
+                "queued {} job {} for tenant {}",
+                job.priority,
+                job.id,
+                tenant.name
+            );
+            json_response(202, &SubmitResponse { id: &job.id })
+        }
+        Err(err) => error_response(500, &format!("{err:#}")),
+    }
+}
+
+/// Reads the request body, or the response refusing it if it is longer than
+/// `max_len` bytes.
+fn read_body(request: &mut Request, max_len: u64) -> Result<Vec<u8>, HttpResponse> {
+    let mut body = Vec::new();
+    if let Err(err) = request
+        .as_reader()
+        .take(max_len.saturating_add(1))
+        .read_to_end(&mut body)
+    {
+        return Err(error_response(
+            400,
+            &format!("failed to read request body: {err}"),
+        ));
+    }
+    if body.len() as u64 > max_len {
+        return Err(error_response(
+            413,
+            &format!("request body exceeds {max_len} bytes"),
+        ));
+    }
+    Ok(body)
+}
+
+fn job_status(state: &State, tenant: &Tenant, id: &str) -> HttpResponse {
+    let queue = state.queue.lock().unwrap();
+    match queue.get(id).filter(|job| job.tenant == tenant.name) {
+        Some(job) => json_response(200, job),
+        None => error_response(404, "unknown job"),
+    }
+}
+
+fn job_result(state: &State, tenant: &Tenant, id: &str) -> HttpResponse {
+    let queue = state.queue.lock().unwrap();
+    let Some(job) = queue.get(id).filter(|job| job.tenant == tenant.name) else {
+        return error_response(404, "unknown job");
+    };
+    match job.status {
+        JobStatus::Succeeded => match queue.result(job) {
+            Ok(receipt) => {
+                Response::from_data(receipt).with_header(content_type("application/octet-stream"))
+            }
+            Err(err) => error_response(500, &format!("failed to read result: {err:#}")),
+        },
+        JobStatus::Failed => error_response(409, job.error.as_deref().unwrap_or("job failed")),
+        JobStatus::Queued | JobStatus::Running => error_response(409, "job has not finished"),
+    }
+}
+
+fn job_lifecycle(state: &State, tenant: &Tenant, id: &str) -> HttpResponse {
+    let queue = state.queue.lock().unwrap();
+    let Some(job) = queue.get(id).filter(|job| job.tenant == tenant.name) else {
+        return error_response(404, "unknown job");
+    };
+    match queue.lifecycle(job) {
+        Ok(Some(claim)) => json_response(200, &claim),
+        Ok(None) => error_response(404, "no lifecycle recorded for the job's claim"),
+        Err(err) => error_response(500, &format!("failed to read lifecycle: {err:#}")),
+    }
+}
+
+fn verify_bundle(state: &State, tenant: &Tenant, request: &mut Request) -> HttpResponse {
+    let body = match read_body(request, MAX_BUNDLE_LEN) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let now = now();
+    // The policy depends on the time and the bundle's metadata, so it is
+    // checked every time; only the pairing check is cached.
+    let checked = ProofBundle::decode(&body).and_then(|bundle| {
+        state.config.policy.check(&bundle, None, now)?;
+        verifier::check_journal(&bundle.receipt.journal.bytes, state.config.max_journal_len)?;
+        Ok(bundle)
+    });
+    let receipt = match checked {
+        Ok(bundle) => bundle.receipt,
+        Err(err) => return error_response(422, &format!("{err:#}")),
+    };
+    let key = VerifyCache::key(&receipt, METHOD_ID.into());
+    let cached = key
+        .as_ref()
+        .is_some_and(|key| state.verify_cache.lock().unwrap().contains(key, now));
+    let verdict = if cached {
+        Ok(())
+    } else {
+        if let Some(reason) = state.verify_cache.lock().unwrap().check_rate(tenant, now) {
+            return error_response(429, &reason);
+        }
+        let verdict =
+            verifier::verify_receipt(&receipt, METHOD_ID).map_err(|err| format!("{err:#}"));
+        // Only proofs that verified are cached: a rejection is not kept.
+        if let (Ok(()), Some(key)) = (&verdict, key) {
+            state.verify_cache.lock().unwrap().insert(key, now);
+        }
+        verdict
+    };
+    match verdict {
+        Ok(()) => json_response(
+            200,
+            &VerifyResponse {
+                journal_digest: Journal::of(&receipt).digest().to_string(),
+                cached,
+            },
+        ),
+        Err(err) => error_response(422, &err),
+    }
+}
+
+fn json_response(status: u16, body: &impl Serialize) -> HttpResponse {
+    let body = serde_json::to_vec(body).expect("response serialization cannot fail");
+    Response::from_data(body)
+        .with_status_code(status)
+        .with_header(content_type("application/json"))
+}
+
+fn error_response(status: u16, message: &str) -> HttpResponse {
+    json_response(status, &serde_json::json!({ "error": message }))
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).unwrap()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before the Unix epoch")
+        .as_secs()
+}
@@ -0,0 +1,448 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::config::Tenant;
+use crate::{
+    bundle::ProofBundle,
+    cost::CostEstimate,
+    input::GuestInput,
+    lifecycle::{self, Claim, Stage},
+    prover::GuestFault,
+    storage::{write_atomic, Layout, Store},
+};
+
+const ONE_HOUR_SECS: u64 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// Order in which queued jobs are proven: every queued job of a higher
+/// priority goes before any of a lower one. A challenge window on Bitcoin is
+/// time-boxed, so proofs answering a dispute must not wait behind routine
+/// peg-in proofs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    #[default]
+    Routine,
+    Dispute,
+}
+
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Routine => write!(f, "routine"),
+            Self::Dispute => write!(f, "dispute"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    /// Submission order, used to serve queued jobs of the same priority
+    /// first-in first-out.
+    pub seq: u64,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Unix timestamp, in seconds, the proof is due by.
+    #[serde(default)]
+    pub deadline: Option<u64>,
+    pub tenant: String,
+    /// URL notified once the job finishes.
+    #[serde(default)]
+    pub callback_url: Option<String>,
+    pub status: JobStatus,
+    /// Unix timestamps, in seconds.
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    pub error: Option<String>,
+    /// Why the guest failed, when a failed job got as far as running it.
+    #[serde(default)]
+    pub fault: Option<GuestFault>,
+    /// Storage key of the proof bundle of a succeeded job.
+    #[serde(default)]
+    pub result_key: Option<String>,
+    /// Cycles proven and estimated cost of a succeeded job, for billing.
+    #[serde(default)]
+    pub cost: Option<CostEstimate>,
+}
+
+impl Job {
+    pub fn is_pending(&self) -> bool {
+        matches!(self.status, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+/// The layout of job files written before inputs were kept apart from them,
+/// as hex within the job.
+#[derive(Deserialize)]
+struct LegacyJob {
+    input: GuestInput,
+}
+
+/// Job queue mirrored to `<root>/jobs/<id>.json`, so that queued work
+/// survives a restart of the server. Each job's input is written once, in
+/// its bincode encoding, to `<root>/jobs/<id>.input` beside it, rather than
+/// being rewritten as hex with every change of status. Proof bundles of
+/// finished jobs are written to a separate [`Store`], under keys given by a
+/// [`Layout`].
+pub struct JobQueue {
+    root: PathBuf,
+    store: Box<dyn Store>,
+    layout: Layout,
+    jobs: HashMap<String, Job>,
+    inputs: HashMap<String, GuestInput>,
+    next_seq: u64,
+}
+
+impl JobQueue {
+    /// Opens the queue stored under `root`, creating it if needed. Jobs that
+    /// were running when the server stopped are queued again.
+    pub fn open(root: &Path, store: Box<dyn Store>, layout: Layout) -> Result<Self> {
+        let mut queue = Self {
+            root: root.to_path_buf(),
+            store,
+            layout,
+            jobs: HashMap::new(),
+            inputs: HashMap::new(),
+            next_seq: 0,
+        };
+        fs::create_dir_all(queue.jobs_dir())?;
+
+        for entry in fs::read_dir(queue.jobs_dir())? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let contents = fs::read(&path)?;
+            let job: Job = serde_json::from_slice(&contents)
+                .with_context(|| format!("corrupt job file {}", path.display()))?;
+            let input = match fs::read(queue.input_path(&job.id)) {
+                Ok(encoded) => bincode::deserialize(&encoded)
+                    .with_context(|| format!("corrupt input of job {}", job.id))?,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    let legacy: LegacyJob = serde_json::from_slice(&contents)
+                        .with_context(|| format!("job file {} has no input", path.display()))?;
+                    queue.persist_input(&job.id, &legacy.input)?;
+                    queue.persist(&job)?;
+                    legacy.input
+                }
+                Err(err) => return Err(err).context("failed to read job input"),
+            };
+            queue.next_seq = queue.next_seq.max(job.seq + 1);
+            queue.inputs.insert(job.id.clone(), input);
+            queue.jobs.insert(job.id.clone(), job);
+        }
+
+        let interrupted: Vec<String> = queue
+            .jobs
+            .values()
+            .filter(|job| job.status == JobStatus::Running)
+            .map(|job| job.id.clone())
+            .collect();
+        for id in interrupted {
+            tracing::info!("requeueing job {id} interrupted by restart");
+            let job = queue.jobs.get_mut(&id).unwrap();
+            job.status = JobStatus::Queued;
+            let job = job.clone();
+            queue.persist(&job)?;
+        }
+
+        Ok(queue)
+    }
+
+    /// Returns why `tenant` may not submit another job of `priority` right
+    /// now, if anything. Only pending jobs of at least `priority` count
+    /// towards `max_pending`, so a backlog of routine jobs never keeps a
+    /// dispute proof from being queued.
+    pub fn check_quota(&self, tenant: &Tenant, priority: Priority, now: u64) -> Option<String> {
+        if priority > tenant.max_priority {
+            return Some(format!(
+                "tenant may not submit {priority} jobs (limit {})",
+                tenant.max_priority
+            ));
+        }
+        let owned = || self.jobs.values().filter(|job| job.tenant == tenant.name);
+        let pending = owned()
+            .filter(|job| job.is_pending() && job.priority >= priority)
+            .count();
+        if pending >= tenant.max_pending {
+            return Some(format!(
+                "tenant has {pending} pending jobs (limit {})",
+                tenant.max_pending
+            ));
+        }
+        let recent = owned()
+            .filter(|job| now.saturating_sub(job.created_at) < ONE_HOUR_SECS)
+            .count();
+        if recent >= tenant.jobs_per_hour {
+            return Some(format!(
+                "tenant submitted {recent} jobs in the last hour (limit {})",
+                tenant.jobs_per_hour
+            ));
+        }
+        None
+    }
+
+    pub fn submit(
+        &mut self,
+        tenant: &Tenant,
+        input: GuestInput,
+        priority: Priority,
+        deadline: Option<u64>,
+        callback_url: Option<String>,
+        now: u64,
+    ) -> Result<Job> {
+        let seq = self.next_seq;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(tenant.name.as_bytes());
+        hasher.update(&seq.to_le_bytes());
+        hasher.update(&now.to_le_bytes());
+        let id = hasher.finalize().to_hex()[..32].to_string();
+
+        let job = Job {
+            id,
+            seq,
+            priority,
+            deadline,
+            tenant: tenant.name.clone(),
+            callback_url,
+            status: JobStatus::Queued,
+            created_at: now,
+            finished_at: None,
+            error: None,
+            fault: None,
+            result_key: None,
+            cost: None,
+        };
+        self.persist_input(&job.id, &input)?;
+        self.persist(&job)?;
+        self.next_seq += 1;
+        self.record(
+            &job,
+            &input,
+            &[Stage::InputReceived],
+            Some(job.id.clone()),
+            now,
+        );
+        self.jobs.insert(job.id.clone(), job.clone());
+        self.inputs.insert(job.id.clone(), input);
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Job> {
+        self.jobs.get(id)
+    }
+
+    /// Marks the oldest queued job of the highest priority as running and
+    /// returns it with its input. A running job is never interrupted; one
+    /// interrupted by a restart is queued again in its place.
+    pub fn claim_next(&mut self) -> Option<(Job, GuestInput)> {
+        let job = self
+            .jobs
+            .values_mut()
+            .filter(|job| job.status == JobStatus::Queued)
+            .min_by_key(|job| (Reverse(job.priority), job.seq))?;
+        job.status = JobStatus::Running;
+        let job = job.clone();
+        // Still queued on disk, so a restart only proves it again.
+        if let Err(err) = self.persist(&job) {
+            tracing::warn!("job {}: cannot persist that it is running: {err:#}", job.id);
+        }
+        let input = self.inputs.get(&job.id)?.clone();
+        Some((job, input))
+    }
+
+    /// Records the outcome of a running job, storing its proof bundle on
+    /// success. A bundle that cannot be stored fails the job; an outcome that
+    /// cannot be persisted is still served until the server restarts, when
+    /// the job is proven again.
+    pub fn complete(
+        &mut self,
+        id: &str,
+        outcome: Result<(ProofBundle, CostEstimate)>,
+        now: u64,
+    ) -> Result<Job> {
+        let mut job = self.jobs.get(id).context("unknown job")?.clone();
+        let input = self.inputs.get(id).context("job has no input")?;
+        let outcome = outcome.and_then(|(bundle, cost)| {
+            let key = self
+                .layout
+                .put(self.store.as_ref(), input, &bundle)
+                .context("failed to store the proof bundle")?;
+            Ok((key, cost))
+        });
+        match outcome {
+            Ok((key, cost)) => {
+                let stages = [Stage::Executed, Stage::Proven, Stage::Wrapped];
+                self.record(&job, input, &stages, Some(key.clone()), now);
+                job.result_key = Some(key);
+                job.cost = Some(cost);
+                job.status = JobStatus::Succeeded;
+            }
+            Err(err) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(format!("{err:#}"));
+                job.fault = GuestFault::from_error(&err);
+            }
+        }
+        job.finished_at = Some(now);
+        if let Err(err) = self.persist(&job) {
+            tracing::error!("job {}: cannot persist its outcome: {err:#}", job.id);
+        }
+        self.jobs.insert(job.id.clone(), job.clone());
+        Ok(job)
+    }
+
+    /// Deletes the records of jobs that finished more than `retention_secs`
+    /// before `now`. Their proof bundles are left in storage.
+    pub fn prune(&mut self, retention_secs: u64, now: u64) {
+        let expired: Vec<String> = self
+            .jobs
+            .values()
+            .filter(|job| {
+                job.finished_at
+                    .is_some_and(|at| now.saturating_sub(at) > retention_secs)
+            })
+            .map(|job| job.id.clone())
+            .collect();
+        for id in expired {
+            // The job file goes first, so a job is never left without input.
+            let removed = [self.job_path(&id), self.input_path(&id)]
+                .iter()
+                .try_for_each(|path| match fs::remove_file(path) {
+                    Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                        Err(anyhow::Error::new(err)
+                            .context(format!("cannot delete {}", path.display())))
+                    }
+                    _ => Ok(()),
+                });
+            if let Err(err) = removed {
+                tracing::warn!("job {id}: {err:#}");
+                continue;
+            }
+            self.jobs.remove(&id);
+            self.inputs.remove(&id);
+        }
+    }
+
+    /// Reads the bincode-encoded [`ProofBundle`] of a succeeded job.
+    pub fn result(&self, job: &Job) -> Result<Vec<u8>> {
+        let key = job.result_key.as_deref().context("job has no result")?;
+        self.store
+            .get(key)?
+            .with_context(|| format!("result {key} is missing from storage"))
+    }
+
+    /// The lifecycle of the claim `job` proves.
+    pub fn lifecycle(&self, job: &Job) -> Result<Option<Claim>> {
+        let input = self.inputs.get(&job.id).context("job has no input")?;
+        lifecycle::load(self.store.as_ref(), &lifecycle::claim_id(input))
+    }
+
+    /// Moves the claim `job` proves on. The job's own state is authoritative
+    /// here, so a log that cannot be written is only reported.
+    fn record(
+        &self,
+        job: &Job,
+        input: &GuestInput,
+        stages: &[Stage],
+        detail: Option<String>,
+        now: u64,
+    ) {
+        let claim = lifecycle::claim_id(input);
+        if let Err(err) = lifecycle::record_all(self.store.as_ref(), &claim, stages, detail, now) {
+            tracing::warn!(
+                "job {}: cannot record lifecycle of claim {claim}: {err:#}",
+                job.id
+            );
+        }
+    }
+
+    fn jobs_dir(&self) -> PathBuf {
+        self.root.join("jobs")
+    }
+
+    fn job_path(&self, id: &str) -> PathBuf {
+        self.jobs_dir().join(format!("{id}.json"))
+    }
+
+    fn input_path(&self, id: &str) -> PathBuf {
+        self.jobs_dir().join(format!("{id}.input"))
+    }
+
+    fn persist_input(&self, id: &str, input: &GuestInput) -> Result<()> {
+        write_atomic(&self.input_path(id), &bincode::serialize(input)?)
+    }
+
+    fn persist(&self, job: &Job) -> Result<()> {
+        write_atomic(&self.job_path(&job.id), &serde_json::to_vec_pretty(job)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStore;
+
+    #[test]
+    fn prune_forgets_only_expired_finished_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Box::new(LocalStore::new(dir.path().join("results")));
+        let mut queue = JobQueue::open(dir.path(), store, Layout::default()).unwrap();
+        let tenant = Tenant {
+            name: "operator".into(),
+            api_key: "key".into(),
+            max_pending: 4,
+            jobs_per_hour: 60,
+            max_priority: Priority::Routine,
+            verifies_per_minute: 60,
+        };
+        let submit = |queue: &mut JobQueue, data: &[u8]| {
+            let input = GuestInput::Multi(vec![data.to_vec()]);
+            queue
+                .submit(&tenant, input, Priority::Routine, None, None, 0)
+                .unwrap()
+                .id
+        };
+        let finished = submit(&mut queue, b"finished");
+        let pending = submit(&mut queue, b"pending");
+        assert_eq!(queue.claim_next().unwrap().0.id, finished);
+        queue
+            .complete(&finished, Err(anyhow::anyhow!("failed")), 100)
+            .unwrap();
+
+        queue.prune(50, 150);
+        assert!(queue.get(&finished).is_some());
+        queue.prune(50, 151);
+        assert!(queue.get(&finished).is_none());
+        assert!(queue.get(&pending).is_some());
+        assert!(!queue.job_path(&finished).exists());
+        assert!(!queue.input_path(&finished).exists());
+
+        let reopened = JobQueue::open(
+            dir.path(),
+            Box::new(LocalStore::new(dir.path().join("results"))),
+            Layout::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            reopened.inputs[&pending],
+            GuestInput::Multi(vec![b"pending".to_vec()])
+        );
+    }
+}
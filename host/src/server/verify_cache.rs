@@ -0,0 +1,126 @@
+//! Proofs `POST /verify` accepted, kept for a while so that the same proof
+//! submitted again, as happens when a dispute is spammed, is answered without
+//! running the pairings again, and a limit on how many proofs each tenant may
+//! have verified from scratch.
+//!
+//! A proof is keyed by the BLAKE3 digest of the whole bincode-encoded receipt
+//! and the image ID it was verified against, so that a cache hit is exactly
+//! the receipt that verified. Only receipts that verified are kept: a
+//! rejected one is verified again each time, so a receipt sharing a genuine
+//! proof's seal and journal but not its claim can neither be accepted from
+//! the cache nor get the genuine proof rejected. Receipts other than Groth16
+//! are never cached.
+
+use std::collections::{HashMap, VecDeque};
+
+use risc0_zkvm::{sha::Digest, Receipt};
+use serde::Deserialize;
+
+use super::Tenant;
+
+const ONE_MINUTE_SECS: u64 = 60;
+
+/// The `[verify_cache]` table of the server configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct VerifyCacheConfig {
+    /// How long a verdict is kept, in seconds; 0 disables the cache.
+    pub ttl_secs: u64,
+    /// Most verdicts kept at once; the ones expiring first make room.
+    pub max_entries: usize,
+}
+
+impl Default for VerifyCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 600,
+            max_entries: 10_000,
+        }
+    }
+}
+
+pub(crate) struct VerifyCache {
+    config: VerifyCacheConfig,
+    /// When each accepted proof expires from the cache.
+    entries: HashMap<[u8; 32], u64>,
+    /// When each tenant last had a proof verified from scratch, oldest first,
+    /// within the last minute.
+    verified: HashMap<String, VecDeque<u64>>,
+}
+
+impl VerifyCache {
+    pub(crate) fn new(config: VerifyCacheConfig) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+            verified: HashMap::new(),
+        }
+    }
+
+    /// The key of `receipt` verified against `image_id`, if it is a Groth16
+    /// receipt.
+    pub(crate) fn key(receipt: &Receipt, image_id: Digest) -> Option<[u8; 32]> {
+        receipt.inner.groth16().ok()?;
+        let encoded = bincode::serialize(receipt).ok()?;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&encoded);
+        hasher.update(image_id.as_bytes());
+        Some(hasher.finalize().into())
+    }
+
+    /// Whether the proof under `key` was accepted and has not expired.
+    pub(crate) fn contains(&mut self, key: &[u8; 32], now: u64) -> bool {
+        match self.entries.get(key) {
+            Some(&expires_at) if expires_at > now => true,
+            Some(_) => {
+                self.entries.remove(key);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Keeps the proof under `key`, which verified.
+    pub(crate) fn insert(&mut self, key: [u8; 32], now: u64) {
+        if self.config.ttl_secs == 0 || self.config.max_entries == 0 {
+            return;
+        }
+        if self.entries.len() >= self.config.max_entries {
+            self.entries.retain(|_, &mut expires_at| expires_at > now);
+        }
+        if self.entries.len() >= self.config.max_entries {
+            if let Some(&oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, &expires_at)| expires_at)
+                .map(|(key, _)| key)
+            {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries
+            .insert(key, now.saturating_add(self.config.ttl_secs));
+    }
+
+    /// Counts a verification from scratch against the quota of `tenant`, or
+    /// returns why the tenant is over it.
+    pub(crate) fn check_rate(&mut self, tenant: &Tenant, now: u64) -> Option<String> {
+        let verified = self.verified.entry(tenant.name.clone()).or_default();
+        while verified
+            .front()
+            .is_some_and(|&at| now.saturating_sub(at) >= ONE_MINUTE_SECS)
+        {
+            verified.pop_front();
+        }
+        if verified.len() >= tenant.verifies_per_minute {
+            return Some(format!(
+                "tenant had {} proofs verified in the last minute (limit {})",
+                verified.len(),
+                tenant.verifies_per_minute
+            ));
+        }
+        verified.push_back(now);
+        None
+    }
+}
@@ -0,0 +1,164 @@
+//! Host side of sparse BLAKE3 Merkle trees: a keyed state map that can prove
+//! the value, or absence, of any key. The tree layout is defined in
+//! [`common::smt`].
+
+use std::collections::BTreeMap;
+
+use common::{
+    merkle::Hash,
+    smt::{self, Defaults, SparseProof, DEPTH},
+};
+
+/// A sparse tree over the keys set so far. Only the leaves are stored; inner
+/// nodes are recomputed when a root or proof is requested.
+#[derive(Debug, Clone, Default)]
+pub struct SparseTree {
+    /// Value digests by key.
+    leaves: BTreeMap<Hash, Hash>,
+    defaults: Defaults,
+}
+
+impl SparseTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets `key` to `value`, returning the digest of the value it replaces.
+    pub fn insert(&mut self, key: Hash, value: &[u8]) -> Option<Hash> {
+        self.leaves.insert(key, blake3::hash(value).into())
+    }
+
+    /// Clears `key`, returning the digest of its value.
+    pub fn remove(&mut self, key: &Hash) -> Option<Hash> {
+        self.leaves.remove(key)
+    }
+
+    /// The digest of the value of `key`.
+    pub fn get(&self, key: &Hash) -> Option<&Hash> {
+        self.leaves.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn root(&self) -> Hash {
+        let leaves: Vec<_> = self.leaves.iter().collect();
+        self.subtree_hash(&leaves, 0)
+    }
+
+    /// Proves the value of `key`, or that it is absent.
+    pub fn proof(&self, key: Hash) -> SparseProof {
+        let mut leaves: &[_] = &self.leaves.iter().collect::<Vec<_>>();
+        let mut bitmap = [0; DEPTH / 8];
+        let mut siblings = Vec::new();
+        for depth in 0..DEPTH {
+            let split = leaves.partition_point(|(leaf, _)| !smt::key_bit(leaf, depth));
+            let (left, right) = leaves.split_at(split);
+            let (path, sibling) = if smt::key_bit(&key, depth) {
+                (right, left)
+            } else {
+                (left, right)
+            };
+            if !sibling.is_empty() {
+                let height = DEPTH - 1 - depth;
+                bitmap[height / 8] |= 1 << (height % 8);
+                siblings.push(self.subtree_hash(sibling, depth + 1));
+            }
+            leaves = path;
+        }
+        // Collected from the root down; proofs list siblings from the leaf up.
+        siblings.reverse();
+        SparseProof {
+            key,
+            value: self.leaves.get(&key).copied(),
+            bitmap,
+            siblings,
+        }
+    }
+
+    fn subtree_hash(&self, leaves: &[(&Hash, &Hash)], depth: usize) -> Hash {
+        smt::subtree_hash(leaves, depth, &self.defaults)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(first: u8, last: u8) -> Hash {
+        let mut key = [0; 32];
+        key[0] = first;
+        key[31] = last;
+        key
+    }
+
+    fn tree() -> SparseTree {
+        let mut tree = SparseTree::new();
+        // Keys sharing long prefixes, and ones splitting at the root.
+        for (first, last) in [(0x00, 1), (0x00, 2), (0x00, 3), (0x80, 0), (0xff, 0xff)] {
+            tree.insert(key(first, last), &[first, last]);
+        }
+        tree
+    }
+
+    #[test]
+    fn empty_tree_has_default_root() {
+        let tree = SparseTree::new();
+        assert_eq!(&tree.root(), Defaults::new().get(DEPTH));
+        let proof = tree.proof(key(0x12, 0x34));
+        assert_eq!(proof.value, None);
+        assert!(proof.siblings.is_empty());
+        assert_eq!(proof.root(&Defaults::new()), tree.root());
+    }
+
+    #[test]
+    fn proofs_lead_to_root() {
+        let tree = tree();
+        let defaults = Defaults::new();
+        let present = [(0x00, 1), (0x00, 3), (0x80, 0), (0xff, 0xff)];
+        let absent = [(0x00, 0), (0x00, 4), (0x40, 0), (0xff, 0xfe)];
+        for (first, last) in present.into_iter().chain(absent) {
+            let key = key(first, last);
+            let proof = tree.proof(key);
+            assert_eq!(proof.value.as_ref(), tree.get(&key));
+            assert_eq!(proof.root(&defaults), tree.root(), "key {key:02x?}");
+            assert_eq!(SparseProof::decode(&proof.encode()), Some(proof));
+        }
+    }
+
+    #[test]
+    fn remove_restores_root() {
+        let mut tree = tree();
+        let root = tree.root();
+        tree.insert(key(0x01, 0), b"new");
+        assert_ne!(tree.root(), root);
+        tree.remove(&key(0x01, 0));
+        assert_eq!(tree.root(), root);
+    }
+
+    #[test]
+    fn proof_of_other_value_is_rejected() {
+        let tree = tree();
+        let mut proof = tree.proof(key(0x80, 0));
+        proof.value = Some(blake3::hash(b"other").into());
+        assert_ne!(proof.root(&Defaults::new()), tree.root());
+    }
+
+    #[test]
+    fn malformed_proofs_are_rejected() {
+        let encoded = tree().proof(key(0x00, 2)).encode();
+        let mut trailing = encoded.clone();
+        trailing.extend_from_slice(&[0; 32]);
+        assert_eq!(SparseProof::decode(&trailing), None);
+        assert_eq!(SparseProof::decode(&encoded[..encoded.len() - 32]), None);
+        assert_eq!(SparseProof::decode(&encoded[..encoded.len() - 1]), None);
+        let mut presence = encoded;
+        presence[32] = 2;
+        assert_eq!(SparseProof::decode(&presence), None);
+    }
+}
@@ -0,0 +1,641 @@
+//! Where receipts are written: a local directory, or an S3-compatible bucket
+//! shared by several proving workers.
+//!
+//! Proof bundles are stored under keys derived from what they prove, given by
+//! a [`Layout`] and by default `<image-id>/<input-hash>/bundle.r0bvm`, so
+//! workers never need to coordinate names and rewriting an existing object
+//! with a new proof of the same input is harmless. Local files are written to
+//! a hidden temporary file and renamed into place, so watchers of the
+//! directory never pick up a partially written bundle, even after a crash.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use hmac::{Hmac, Mac, NewMac};
+use reqwest::{blocking::Client, StatusCode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{bundle::ProofBundle, input::GuestInput};
+
+/// A flat key-value object store.
+pub trait Store: Send + Sync {
+    fn put(&self, key: &str, data: &[u8]) -> Result<()>;
+
+    /// Stores `data` under `key` unless an object is already stored there,
+    /// checked and written as one step so that of two writers racing for the
+    /// key exactly one succeeds. Returns whether `data` was stored.
+    fn put_new(&self, key: &str, data: &[u8]) -> Result<bool>;
+
+    /// Returns `None` if no object is stored under `key`.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Every key in the store, in no particular order.
+    fn list(&self) -> Result<Vec<String>>;
+}
+
+/// A template for the keys proof bundles are stored under, of `/`-separated
+/// segments in which `{image_id}` is replaced by the hex image ID of the
+/// bundle, `{input_hash}` by the BLAKE3 digest of the encoded guest input and
+/// `{bundle_hash}` by that of the encoded bundle. It must contain one of the
+/// hashes, so that different proofs are not stored under the same key;
+/// `bundles/{bundle_hash}` is the content-addressed layout of older releases.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Layout(String);
+
+const LAYOUT_PLACEHOLDERS: [&str; 3] = ["image_id", "input_hash", "bundle_hash"];
+
+impl Layout {
+    /// The key of `bundle`, proven for `input`.
+    pub fn key(&self, input: &GuestInput, bundle: &ProofBundle, encoded: &[u8]) -> String {
+        self.0
+            .replace("{image_id}", &bundle.metadata.image_id)
+            .replace(
+                "{input_hash}",
+                blake3::hash(&input.encode()).to_hex().as_str(),
+            )
+            .replace("{bundle_hash}", blake3::hash(encoded).to_hex().as_str())
+    }
+
+    /// Encodes `bundle` and stores it in `store`, returning its key.
+    pub fn put(
+        &self,
+        store: &dyn Store,
+        input: &GuestInput,
+        bundle: &ProofBundle,
+    ) -> Result<String> {
+        let encoded = bundle.encode()?;
+        let key = self.key(input, bundle, &encoded);
+        store.put(&key, &encoded)?;
+        Ok(key)
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self("{image_id}/{input_hash}/bundle.r0bvm".to_string())
+    }
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed placeholder in layout {s:?}"))?;
+            let name = &rest[start + 1..start + end];
+            if !LAYOUT_PLACEHOLDERS.contains(&name) {
+                return Err(format!(
+                    "unknown placeholder {{{name}}} in layout {s:?}: expected one of \
+                     {{image_id}}, {{input_hash}} and {{bundle_hash}}"
+                ));
+            }
+            rest = &rest[start + end + 1..];
+        }
+        if !s.contains("{input_hash}") && !s.contains("{bundle_hash}") {
+            return Err(format!(
+                "layout {s:?} contains neither {{input_hash}} nor {{bundle_hash}}"
+            ));
+        }
+        if s.split('/')
+            .any(|segment| segment.is_empty() || segment == "..")
+        {
+            return Err(format!("layout {s:?} has an empty or `..` segment"));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for Layout {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Opens the store described by `uri`: either `s3://<bucket>[/<prefix>]` or a
+/// local directory path (optionally written as `file://<path>`).
+///
+/// S3 stores take their endpoint, region and credentials from the usual
+/// `AWS_ENDPOINT_URL`, `AWS_REGION`, `AWS_ACCESS_KEY_ID` and
+/// `AWS_SECRET_ACCESS_KEY` environment variables.
+pub fn open(uri: &str) -> Result<Box<dyn Store>> {
+    if let Some(location) = uri.strip_prefix("s3://") {
+        let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+        return Ok(Box::new(S3Store::from_env(bucket, prefix)?));
+    }
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    Ok(Box::new(LocalStore::new(path)))
+}
+
+/// Stores objects as files below a root directory.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        ensure!(
+            key.split('/')
+                .all(|segment| !segment.is_empty() && segment != ".."),
+            "invalid object key {key:?}"
+        );
+        Ok(self.root.join(key))
+    }
+}
+
+impl Store for LocalStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&path, data)
+    }
+
+    /// Creates the file with `O_EXCL`, so it is not renamed into place: a
+    /// crash while writing leaves a truncated object that fails to decode
+    /// rather than none.
+    fn put_new(&self, key: &str, data: &[u8]) -> Result<bool> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = match File::options().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to write {}", path.display()))
+            }
+        };
+        file.write_all(data)
+            .and_then(|()| file.sync_all())
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(true)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.path(key)?) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("failed to read object {key}")),
+        }
+    }
+
+    /// Skips hidden files, which include those still being written.
+    fn list(&self) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut dirs = vec![(self.root.clone(), String::new())];
+        while let Some((dir, prefix)) = dirs.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound && prefix.is_empty() => {
+                    continue
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| format!("failed to read {}", dir.display()))
+                }
+            };
+            for entry in entries {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name.starts_with('.') {
+                    continue;
+                }
+                let key = format!("{prefix}{name}");
+                if entry.file_type()?.is_dir() {
+                    dirs.push((entry.path(), format!("{key}/")));
+                } else {
+                    keys.push(key);
+                }
+            }
+        }
+        Ok(keys)
+    }
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into
+/// place, so readers never observe a partially written file. The temporary
+/// file is hidden and unique to this call, so concurrent writers of the same
+/// path never share one, and is synced to disk before the rename, so that a
+/// crash leaves either the old file or the new one.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let name = path
+        .file_name()
+        .with_context(|| format!("{} has no file name", path.display()))?;
+    let tmp = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .with_context(|| format!("failed to write {}", tmp.display()));
+    if let Err(err) = written.and_then(|()| {
+        fs::rename(&tmp, path).with_context(|| format!("failed to write {}", path.display()))
+    }) {
+        let _ = fs::remove_file(&tmp);
+        return Err(err);
+    }
+    // Persist the rename itself; not every platform can sync a directory.
+    if let Some(dir) = path.parent().and_then(|dir| File::open(dir).ok()) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Stores objects in an S3-compatible bucket, addressed path-style so that
+/// MinIO and other self-hosted services work as well as AWS.
+pub struct S3Store {
+    client: Client,
+    endpoint: String,
+    region: String,
+    bucket: String,
+    prefix: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3Store {
+    pub fn from_env(bucket: &str, prefix: &str) -> Result<Self> {
+        let var = |name: &str| std::env::var(name).with_context(|| format!("{name} is not set"));
+        let region = std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        let endpoint = std::env::var("AWS_ENDPOINT_URL")
+            .unwrap_or_else(|_| format!("https://s3.{region}.amazonaws.com"));
+        ensure!(!bucket.is_empty(), "S3 URI is missing a bucket name");
+        Ok(Self {
+            client: Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            region,
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+            access_key: var("AWS_ACCESS_KEY_ID")?,
+            secret_key: var("AWS_SECRET_ACCESS_KEY")?,
+        })
+    }
+
+    fn object_path(&self, key: &str) -> String {
+        let key = if self.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{key}", self.prefix)
+        };
+        format!("/{}/{}", self.bucket, uri_encode(&key))
+    }
+
+    fn request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<reqwest::blocking::Response> {
+        self.signed_request(method, &self.object_path(key), "", headers, body)
+    }
+
+    /// Sends a request for `path` with the canonical, sorted and encoded
+    /// query string `query`, and `headers` besides the signed ones.
+    fn signed_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        query: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<reqwest::blocking::Response> {
+        let url = match query {
+            "" => format!("{}{path}", self.endpoint),
+            query => format!("{}{path}?{query}", self.endpoint),
+        };
+        let url = reqwest::Url::parse(&url)?;
+        let host = url.host_str().context("S3 endpoint has no host")?;
+        let host = match url.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(body));
+
+        // AWS Signature Version 4, restricted to the headers we always send.
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{method}\n{path}\n{query}\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{payload_hash}"
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = hmac_sha256(
+            format!("AWS4{}", self.secret_key).as_bytes(),
+            date.as_bytes(),
+        );
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac_sha256(&key, part.as_bytes());
+        }
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key
+        );
+
+        let request = headers.iter().fold(
+            self.client
+                .request(method, url)
+                .header("x-amz-content-sha256", payload_hash)
+                .header("x-amz-date", amz_date)
+                .header("authorization", authorization),
+            |request, (name, value)| request.header(*name, *value),
+        );
+        Ok(request.body(body.to_vec()).send()?)
+    }
+}
+
+impl Store for S3Store {
+    fn put(&self, key: &str, data: &[u8]) -> Result<()> {
+        let response = self.request(reqwest::Method::PUT, key, &[], data)?;
+        if !response.status().is_success() {
+            bail!(
+                "S3 PUT {key} failed with {}: {}",
+                response.status(),
+                response.text()?
+            );
+        }
+        Ok(())
+    }
+
+    /// A conditional write, which AWS and MinIO support; services that
+    /// ignore `If-None-Match` would overwrite the object instead.
+    fn put_new(&self, key: &str, data: &[u8]) -> Result<bool> {
+        let response = self.request(reqwest::Method::PUT, key, &[("if-none-match", "*")], data)?;
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => bail!("S3 PUT {key} failed with {status}: {}", response.text()?),
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let response = self.request(reqwest::Method::GET, key, &[], &[])?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.bytes()?.to_vec())),
+            status => bail!("S3 GET {key} failed with {status}: {}", response.text()?),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let prefix = match self.prefix.as_str() {
+            "" => String::new(),
+            prefix => format!("{prefix}/"),
+        };
+        let mut keys = Vec::new();
+        let mut continuation: Option<String> = None;
+        loop {
+            // Parameters in the sorted order the signature requires.
+            let mut query = String::new();
+            if let Some(token) = &continuation {
+                query.push_str(&format!("continuation-token={}&", query_encode(token)));
+            }
+            query.push_str(&format!("list-type=2&prefix={}", query_encode(&prefix)));
+            let response = self.signed_request(
+                reqwest::Method::GET,
+                &format!("/{}", self.bucket),
+                &query,
+                &[],
+                &[],
+            )?;
+            let status = response.status();
+            let body = response.text()?;
+            ensure!(
+                status.is_success(),
+                "S3 list of {} failed with {status}: {body}",
+                self.bucket
+            );
+            for key in xml_elements(&body, "Key") {
+                let key = xml_unescape(key);
+                keys.push(key.strip_prefix(&prefix).unwrap_or(&key).to_string());
+            }
+            if xml_elements(&body, "IsTruncated").next() != Some("true") {
+                return Ok(keys);
+            }
+            continuation = Some(xml_unescape(
+                xml_elements(&body, "NextContinuationToken")
+                    .next()
+                    .context("truncated S3 listing has no continuation token")?,
+            ));
+        }
+    }
+}
+
+/// The text of every `<name>` element of `xml`, which S3 listings never nest.
+fn xml_elements<'a>(xml: &'a str, name: &str) -> impl Iterator<Item = &'a str> {
+    let open = format!("<{name}>");
+    let close = format!("</{name}>");
+    let mut rest = xml;
+    std::iter::from_fn(move || {
+        let start = rest.find(&open)? + open.len();
+        let end = start + rest[start..].find(&close)?;
+        let text = &rest[start..end];
+        rest = &rest[end + close.len()..];
+        Some(text)
+    })
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes everything but unreserved characters and `/`, as S3 expects
+/// for object paths.
+fn uri_encode(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Percent-encodes everything but unreserved characters, as S3 expects for
+/// query parameters.
+fn query_encode(value: &str) -> String {
+    uri_encode(value).replace('/', "%2F")
+}
+
+/// Environment variable holding the hex-encoded 32-byte key used to encrypt
+/// stored objects, when no key file is given.
+pub const ENCRYPTION_KEY_ENV: &str = "R0_BITVM_STORAGE_KEY";
+
+/// Loads the storage encryption key from `path`, or else from
+/// [`ENCRYPTION_KEY_ENV`]. Returns `None` if neither is set.
+pub fn load_encryption_key(path: Option<&Path>) -> Result<Option<[u8; 32]>> {
+    let encoded = match path {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("failed to read encryption key {}", path.display()))?,
+        None => match std::env::var(ENCRYPTION_KEY_ENV) {
+            Ok(encoded) => encoded,
+            Err(_) => return Ok(None),
+        },
+    };
+    let key = hex::decode(encoded.trim()).context("encryption key is not valid hex")?;
+    let key = key
+        .try_into()
+        .map_err(|key: Vec<u8>| anyhow!("encryption key must be 32 bytes, got {}", key.len()))?;
+    Ok(Some(key))
+}
+
+/// Wraps `store` in an [`EncryptedStore`] if a key is given.
+pub fn with_encryption(store: Box<dyn Store>, key: Option<[u8; 32]>) -> Box<dyn Store> {
+    match key {
+        Some(key) => Box::new(EncryptedStore::new(store, &key)),
+        None => store,
+    }
+}
+
+/// Encrypts objects with ChaCha20-Poly1305 before handing them to the inner
+/// store, for operators whose journals carry commercially sensitive data.
+///
+/// Each object is stored as a random 12-byte nonce followed by the ciphertext.
+/// The object key is authenticated as associated data, so ciphertexts cannot be
+/// swapped between keys. Content-addressed keys are still derived from the
+/// plaintext, so deduplication keeps working.
+pub struct EncryptedStore {
+    inner: Box<dyn Store>,
+    cipher: ChaCha20Poly1305,
+}
+
+impl EncryptedStore {
+    const NONCE_LEN: usize = 12;
+
+    pub fn new(inner: Box<dyn Store>, key: &[u8; 32]) -> Self {
+        Self {
+            inner,
+            cipher: ChaCha20Poly1305::new(key.into()),
+        }
+    }
+
+    /// Encrypts `data` for storage under `key`.
+    fn seal(&self, key: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: data,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("failed to encrypt object {key}"))?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+}
+
+impl Store for EncryptedStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<()> {
+        self.inner.put(key, &self.seal(key, data)?)
+    }
+
+    fn put_new(&self, key: &str, data: &[u8]) -> Result<bool> {
+        self.inner.put_new(key, &self.seal(key, data)?)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let Some(sealed) = self.inner.get(key)? else {
+            return Ok(None);
+        };
+        ensure!(
+            sealed.len() >= Self::NONCE_LEN,
+            "encrypted object {key} is truncated"
+        );
+        let (nonce, ciphertext) = sealed.split_at(Self::NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("failed to decrypt object {key}: wrong key or corrupted data"))?;
+        Ok(Some(plaintext))
+    }
+    fn list(&self) -> Result<Vec<String>> {
+        self.inner.list()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_atomic_writes_of_one_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("object");
+        std::thread::scope(|scope| {
+            for i in 0..8u8 {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..50 {
+                        write_atomic(path, &[i; 4096]).unwrap();
+                    }
+                });
+            }
+        });
+        let written = fs::read(&path).unwrap();
+        assert_eq!(written.len(), 4096);
+        assert!(written.iter().all(|&byte| byte == written[0]));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,487 @@
+//! OpenTimestamps anchoring of proof bundles, so that an operator can show a
+//! proof existed by a given Bitcoin block.
+//!
+//! The BLAKE3 digest of a bundle, encoded without its timestamp, is submitted
+//! to OpenTimestamps calendars, each of which answers with a timestamp: the
+//! operations taking the digest to a commitment the calendar promises to
+//! anchor in Bitcoin, ending in a pending attestation. Once the calendar's
+//! transaction confirms, [`BundleTimestamp::upgrade`] replaces the pending
+//! attestations with the path on to the block's Merkle root.
+//!
+//! A Bitcoin attestation is only a claim about a block until
+//! [`Attested::check_block`] finds its message as the Merkle root of the block
+//! at its height.
+//!
+//! Timestamps are kept in the OpenTimestamps binary serialization, so they can
+//! also be checked with the reference client. Of its operations, RIPEMD-160
+//! and Keccak-256 are not supported; calendars do not use them.
+
+use std::{fmt, time::Duration};
+
+use anyhow::{bail, ensure, Context, Result};
+use bitcoin::{hashes::Hash, BlockHash};
+use serde::{Deserialize, Serialize};
+
+use crate::bitcoin_rpc::BitcoinRpc;
+
+/// Calendars submitted to when none are configured, the defaults of the
+/// reference client.
+pub const DEFAULT_CALENDARS: [&str; 3] = [
+    "https://a.pool.opentimestamps.org",
+    "https://b.pool.opentimestamps.org",
+    "https://a.pool.eternitywall.com",
+];
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest message an operation may produce, as in the reference client.
+const MAX_MESSAGE_LEN: usize = 4096;
+/// Longest operation argument or attestation payload.
+const MAX_PAYLOAD_LEN: usize = 8192;
+/// Deepest nesting of operations accepted.
+const MAX_DEPTH: usize = 256;
+
+const PENDING_TAG: [u8; 8] = [0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e];
+const BITCOIN_TAG: [u8; 8] = [0x05, 0x88, 0x96, 0x0d, 0x73, 0xd7, 0x19, 0x01];
+
+/// The timestamps of a bundle, as recorded in it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleTimestamp {
+    /// BLAKE3 digest of the bundle without its timestamp, as submitted.
+    pub digest: [u8; 32],
+    /// Unix timestamp of the submission, in seconds.
+    pub submitted_at: u64,
+    pub calendars: Vec<CalendarTimestamp>,
+}
+
+/// The timestamp of the bundle digest returned by one calendar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarTimestamp {
+    pub calendar: String,
+    /// OpenTimestamps serialization of the timestamp.
+    pub timestamp: Vec<u8>,
+}
+
+/// A timestamp: the attestations of a message, and the operations deriving
+/// other messages from it with their own timestamps.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Timestamp {
+    pub attestations: Vec<Attestation>,
+    pub ops: Vec<(Op, Timestamp)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Sha1,
+    Sha256,
+    Append(Vec<u8>),
+    Prepend(Vec<u8>),
+    Reverse,
+    Hexlify,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attestation {
+    /// The calendar at `uri` promises to anchor the message.
+    Pending {
+        uri: String,
+    },
+    /// The message is the Merkle root of the Bitcoin block at `height`, in
+    /// internal byte order.
+    Bitcoin {
+        height: u64,
+    },
+    Unknown {
+        tag: [u8; 8],
+        payload: Vec<u8>,
+    },
+}
+
+/// Fetches the timestamp of a commitment from the calendar at a URI, or
+/// `None` if it has not anchored the commitment yet.
+type Fetch<'a> = dyn FnMut(&str, &[u8]) -> Result<Option<Timestamp>> + 'a;
+
+/// An attestation reached from the bundle digest, with the message it
+/// attests to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attested {
+    pub calendar: String,
+    pub message: Vec<u8>,
+    pub attestation: Attestation,
+}
+
+impl fmt::Display for Attested {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.calendar)?;
+        match &self.attestation {
+            Attestation::Pending { uri } => write!(f, "pending at {uri}"),
+            // Block explorers show Merkle roots in reverse byte order.
+            Attestation::Bitcoin { height } => {
+                let root: Vec<u8> = self.message.iter().rev().copied().collect();
+                write!(
+                    f,
+                    "Bitcoin block {height} with Merkle root {}",
+                    hex::encode(root)
+                )
+            }
+            Attestation::Unknown { tag, .. } => {
+                write!(f, "unknown attestation {}", hex::encode(tag))
+            }
+        }
+    }
+}
+
+impl Attested {
+    /// Checks a Bitcoin attestation against the chain as `rpc` sees it: the
+    /// block at the attested height must have the attested message as its
+    /// Merkle root. Returns the hash of that block.
+    pub fn check_block(&self, rpc: &dyn BitcoinRpc) -> Result<BlockHash> {
+        let Attestation::Bitcoin { height } = self.attestation else {
+            bail!("{self} is not a Bitcoin attestation");
+        };
+        let hash = rpc.block_hash(height)?;
+        let header = rpc.block_header(&hash)?;
+        ensure!(
+            header.block_hash() == hash,
+            "asked for block {hash}, got {}",
+            header.block_hash()
+        );
+        ensure!(
+            self.message == header.merkle_root.to_byte_array(),
+            "{self}, but block {hash} at that height has Merkle root {}",
+            header.merkle_root
+        );
+        Ok(hash)
+    }
+}
+
+impl BundleTimestamp {
+    /// Submits `digest` to each of `calendars`. Fails only if no calendar
+    /// returns a timestamp.
+    pub fn submit(digest: [u8; 32], calendars: &[String]) -> Result<Self> {
+        let client = client()?;
+        let mut timestamps = Vec::new();
+        for calendar in calendars {
+            let result = client
+                .post(format!("{}/digest", calendar.trim_end_matches('/')))
+                .body(digest.to_vec())
+                .send()
+                .and_then(|response| response.error_for_status())
+                .and_then(|response| response.bytes())
+                .map_err(anyhow::Error::from)
+                .and_then(|body| {
+                    Timestamp::decode(&body)?;
+                    Ok(body.to_vec())
+                });
+            match result {
+                Ok(timestamp) => timestamps.push(CalendarTimestamp {
+                    calendar: calendar.clone(),
+                    timestamp,
+                }),
+                Err(err) => tracing::warn!("calendar {calendar} failed: {err:#}"),
+            }
+        }
+        ensure!(
+            !timestamps.is_empty(),
+            "none of the {} calendars returned a timestamp",
+            calendars.len()
+        );
+        Ok(Self {
+            digest,
+            submitted_at: crate::policy::unix_now(),
+            calendars: timestamps,
+        })
+    }
+
+    /// Every attestation of the digest, checking that each timestamp is well
+    /// formed.
+    pub fn attestations(&self) -> Result<Vec<Attested>> {
+        let mut attested = Vec::new();
+        for calendar in &self.calendars {
+            let timestamp = Timestamp::decode(&calendar.timestamp)
+                .with_context(|| format!("malformed timestamp from {}", calendar.calendar))?;
+            for (message, attestation) in timestamp.attestations(&self.digest)? {
+                attested.push(Attested {
+                    calendar: calendar.calendar.clone(),
+                    message,
+                    attestation: attestation.clone(),
+                });
+            }
+        }
+        Ok(attested)
+    }
+
+    /// Asks the calendars of pending attestations for the rest of their
+    /// timestamps, and returns how many attestations were upgraded. Those
+    /// whose calendar has not anchored them yet are left pending.
+    pub fn upgrade(&mut self) -> Result<usize> {
+        let client = client()?;
+        let mut upgraded = 0;
+        for calendar in &mut self.calendars {
+            let mut timestamp = Timestamp::decode(&calendar.timestamp)
+                .with_context(|| format!("malformed timestamp from {}", calendar.calendar))?;
+            let count = timestamp.upgrade(&self.digest, &mut |uri, commitment| {
+                let response = client
+                    .get(format!(
+                        "{}/timestamp/{}",
+                        uri.trim_end_matches('/'),
+                        hex::encode(commitment)
+                    ))
+                    .send()?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                let body = response.error_for_status()?.bytes()?;
+                Ok(Some(Timestamp::decode(&body)?))
+            })?;
+            if count > 0 {
+                calendar.timestamp = timestamp.encode();
+                upgraded += count;
+            }
+        }
+        Ok(upgraded)
+    }
+}
+
+fn client() -> Result<reqwest::blocking::Client> {
+    Ok(reqwest::blocking::Client::builder()
+        .timeout(TIMEOUT)
+        .default_headers(
+            [(
+                reqwest::header::ACCEPT,
+                reqwest::header::HeaderValue::from_static("application/vnd.opentimestamps.v1"),
+            )]
+            .into_iter()
+            .collect(),
+        )
+        .build()?)
+}
+
+impl Op {
+    pub fn apply(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let result = match self {
+            Self::Sha1 => <sha1::Sha1 as sha1::Digest>::digest(message).to_vec(),
+            Self::Sha256 => <sha2::Sha256 as sha2::Digest>::digest(message).to_vec(),
+            Self::Append(suffix) => [message, suffix].concat(),
+            Self::Prepend(prefix) => [prefix, message].concat(),
+            Self::Reverse => message.iter().rev().copied().collect(),
+            Self::Hexlify => hex::encode(message).into_bytes(),
+        };
+        ensure!(
+            result.len() <= MAX_MESSAGE_LEN,
+            "operation result of {} bytes exceeds {MAX_MESSAGE_LEN}",
+            result.len()
+        );
+        Ok(result)
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            Self::Sha1 => 0x02,
+            Self::Sha256 => 0x08,
+            Self::Append(_) => 0xf0,
+            Self::Prepend(_) => 0xf1,
+            Self::Reverse => 0xf2,
+            Self::Hexlify => 0xf3,
+        }
+    }
+}
+
+impl Timestamp {
+    /// Decodes a timestamp, rejecting trailing bytes.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = bytes;
+        let timestamp = Self::read(&mut reader, 0)?;
+        ensure!(
+            reader.is_empty(),
+            "{} trailing bytes after timestamp",
+            reader.len()
+        );
+        Ok(timestamp)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        self.write(&mut encoded);
+        encoded
+    }
+
+    /// The attestations of the timestamp of `message`, each with the message
+    /// it attests to.
+    pub fn attestations(&self, message: &[u8]) -> Result<Vec<(Vec<u8>, &Attestation)>> {
+        let mut attested: Vec<_> = self
+            .attestations
+            .iter()
+            .map(|attestation| (message.to_vec(), attestation))
+            .collect();
+        for (op, timestamp) in &self.ops {
+            attested.extend(timestamp.attestations(&op.apply(message)?)?);
+        }
+        Ok(attested)
+    }
+
+    /// Replaces pending attestations with the timestamps `fetch` returns for
+    /// them from their calendar, returning how many were replaced.
+    fn upgrade(&mut self, message: &[u8], fetch: &mut Fetch<'_>) -> Result<usize> {
+        let mut upgraded = 0;
+        let mut attestations = Vec::new();
+        for attestation in std::mem::take(&mut self.attestations) {
+            let Attestation::Pending { uri } = &attestation else {
+                attestations.push(attestation);
+                continue;
+            };
+            match fetch(uri, message)? {
+                Some(timestamp) => {
+                    attestations.extend(timestamp.attestations);
+                    self.ops.extend(timestamp.ops);
+                    upgraded += 1;
+                }
+                None => attestations.push(attestation),
+            }
+        }
+        self.attestations = attestations;
+        for (op, timestamp) in &mut self.ops {
+            upgraded += timestamp.upgrade(&op.apply(message)?, fetch)?;
+        }
+        Ok(upgraded)
+    }
+
+    fn read(reader: &mut &[u8], depth: usize) -> Result<Self> {
+        ensure!(depth <= MAX_DEPTH, "timestamp is nested too deeply");
+        let mut timestamp = Self::default();
+        // Every item but the last of a timestamp is preceded by 0xff.
+        loop {
+            let tag = read_byte(reader)?;
+            if tag == 0xff {
+                let tag = read_byte(reader)?;
+                timestamp.read_item(tag, reader, depth)?;
+            } else {
+                timestamp.read_item(tag, reader, depth)?;
+                return Ok(timestamp);
+            }
+        }
+    }
+
+    fn read_item(&mut self, tag: u8, reader: &mut &[u8], depth: usize) -> Result<()> {
+        let op = match tag {
+            0x00 => {
+                let tag = read_array::<8>(reader)?;
+                let mut payload: &[u8] = &read_varbytes(reader)?;
+                let attestation = match tag {
+                    PENDING_TAG => Attestation::Pending {
+                        uri: String::from_utf8(read_varbytes(&mut payload)?)
+                            .context("calendar URI is not UTF-8")?,
+                    },
+                    BITCOIN_TAG => Attestation::Bitcoin {
+                        height: read_varuint(&mut payload)?,
+                    },
+                    tag => Attestation::Unknown {
+                        tag,
+                        payload: std::mem::take(&mut payload).to_vec(),
+                    },
+                };
+                ensure!(payload.is_empty(), "trailing bytes in attestation");
+                self.attestations.push(attestation);
+                return Ok(());
+            }
+            0x02 => Op::Sha1,
+            0x08 => Op::Sha256,
+            0xf0 => Op::Append(read_varbytes(reader)?),
+            0xf1 => Op::Prepend(read_varbytes(reader)?),
+            0xf2 => Op::Reverse,
+            0xf3 => Op::Hexlify,
+            0x03 | 0x67 => bail!("unsupported timestamp operation {tag:#04x}"),
+            _ => bail!("unknown timestamp tag {tag:#04x}"),
+        };
+        self.ops.push((op, Self::read(reader, depth + 1)?));
+        Ok(())
+    }
+
+    fn write(&self, encoded: &mut Vec<u8>) {
+        let count = self.attestations.len() + self.ops.len();
+        let mut items = 0;
+        let mut separate = |encoded: &mut Vec<u8>| {
+            items += 1;
+            if items < count {
+                encoded.push(0xff);
+            }
+        };
+        for attestation in &self.attestations {
+            separate(encoded);
+            encoded.push(0x00);
+            let (tag, payload) = match attestation {
+                Attestation::Pending { uri } => {
+                    let mut payload = Vec::new();
+                    write_varbytes(&mut payload, uri.as_bytes());
+                    (PENDING_TAG, payload)
+                }
+                Attestation::Bitcoin { height } => {
+                    let mut payload = Vec::new();
+                    write_varuint(&mut payload, *height);
+                    (BITCOIN_TAG, payload)
+                }
+                Attestation::Unknown { tag, payload } => (*tag, payload.clone()),
+            };
+            encoded.extend_from_slice(&tag);
+            write_varbytes(encoded, &payload);
+        }
+        for (op, timestamp) in &self.ops {
+            separate(encoded);
+            encoded.push(op.tag());
+            if let Op::Append(arg) | Op::Prepend(arg) = op {
+                write_varbytes(encoded, arg);
+            }
+            timestamp.write(encoded);
+        }
+    }
+}
+
+fn read_byte(reader: &mut &[u8]) -> Result<u8> {
+    let (&byte, rest) = reader.split_first().context("truncated timestamp")?;
+    *reader = rest;
+    Ok(byte)
+}
+
+fn read_array<const N: usize>(reader: &mut &[u8]) -> Result<[u8; N]> {
+    let (bytes, rest) = reader.split_first_chunk().context("truncated timestamp")?;
+    *reader = rest;
+    Ok(*bytes)
+}
+
+/// Reads an unsigned LEB128 integer.
+fn read_varuint(reader: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_byte(reader)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("integer in timestamp is too large")
+}
+
+fn read_varbytes(reader: &mut &[u8]) -> Result<Vec<u8>> {
+    let len = read_varuint(reader)? as usize;
+    ensure!(
+        len <= MAX_PAYLOAD_LEN,
+        "field of {len} bytes in timestamp exceeds {MAX_PAYLOAD_LEN}"
+    );
+    ensure!(reader.len() >= len, "truncated timestamp");
+    let (bytes, rest) = reader.split_at(len);
+    *reader = rest;
+    Ok(bytes.to_vec())
+}
+
+fn write_varuint(encoded: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        encoded.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    encoded.push(value as u8);
+}
+
+fn write_varbytes(encoded: &mut Vec<u8>, bytes: &[u8]) {
+    write_varuint(encoded, bytes.len() as u64);
+    encoded.extend_from_slice(bytes);
+}
@@ -0,0 +1,111 @@
+//! How a journal is bound into the public input of its Groth16 proof.
+//!
+//! The BitVM verifier checks the proof against a single scalar derived from
+//! the journal with [`Blake3Prefix`]. Protocols that bind the journal
+//! differently, e.g. with another hash or a different truncation, implement
+//! [`OutputTransform`] and pass it to
+//! [`verify_receipt_with`](crate::verifier::verify_receipt_with), reusing the
+//! rest of the proving and verification pipeline.
+//!
+//! [`BindingHash`] selects between the BLAKE3 binding and [`Sha256Prefix`],
+//! the same binding with SHA-256, for integrators whose on-chain verifier only
+//! has cheap SHA-256 gadgets. The hash is fixed by the Groth16 circuit: the
+//! circuit this prover wraps receipts with binds with BLAKE3, so SHA-256 bound
+//! proofs come from a circuit built for it.
+
+use std::{fmt, str::FromStr};
+
+use risc0_zkp::core::digest::Digest;
+use sha2::{Digest as _, Sha256};
+
+use crate::verifier;
+
+/// Derives the public input of a proof from the journal it commits.
+pub trait OutputTransform: Send + Sync {
+    /// The big-endian public input for `journal` as committed by the guest
+    /// with `image_id`. It must be a scalar below the BN254 field modulus,
+    /// see [`verifier::public_input_scalar`].
+    fn public_input(&self, image_id: Digest, journal: &[u8]) -> Vec<u8>;
+}
+
+/// The transform of the BitVM circuit: `blake3(prefix || journal)` truncated
+/// to [`verifier::PUBLIC_INPUT_LEN`] bytes, where the prefix binds the image
+/// ID and the recursion control root.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3Prefix;
+
+impl OutputTransform for Blake3Prefix {
+    fn public_input(&self, image_id: Digest, journal: &[u8]) -> Vec<u8> {
+        verifier::expected_output_bytes(image_id, journal).to_vec()
+    }
+}
+
+/// `sha256(prefix || journal)` truncated to [`verifier::PUBLIC_INPUT_LEN`]
+/// bytes, with the same prefix as [`Blake3Prefix`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Prefix;
+
+impl OutputTransform for Sha256Prefix {
+    fn public_input(&self, image_id: Digest, journal: &[u8]) -> Vec<u8> {
+        BindingHash::Sha256.bind(
+            &verifier::calculate_succinct_output_prefix(image_id),
+            journal,
+        )
+    }
+}
+
+/// The hash binding the journal into the public input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BindingHash {
+    #[default]
+    Blake3,
+    Sha256,
+}
+
+impl FromStr for BindingHash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blake3" => Ok(Self::Blake3),
+            "sha256" => Ok(Self::Sha256),
+            _ => Err(format!(
+                "unknown binding hash {s:?}: expected `blake3` or `sha256`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BindingHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blake3 => write!(f, "blake3"),
+            Self::Sha256 => write!(f, "sha256"),
+        }
+    }
+}
+
+impl BindingHash {
+    /// `hash(prefix || journal)` truncated to [`verifier::PUBLIC_INPUT_LEN`]
+    /// bytes.
+    pub fn bind(&self, prefix: &[u8; 32], journal: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Blake3 => verifier_core::public_input(prefix, journal).to_vec(),
+            Self::Sha256 => {
+                let mut hasher = Sha256::new();
+                hasher.update(prefix);
+                hasher.update(journal);
+                hasher.finalize()[..verifier::PUBLIC_INPUT_LEN].to_vec()
+            }
+        }
+    }
+}
+
+impl OutputTransform for BindingHash {
+    fn public_input(&self, image_id: Digest, journal: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Blake3 => Blake3Prefix.public_input(image_id, journal),
+            Self::Sha256 => Sha256Prefix.public_input(image_id, journal),
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! The complete BLAKE3 tree of an input, block by block and parent by parent,
+//! exactly as the [`bao`](common::bao) guest modes hash it. A tree can be
+//! saved next to the input it describes, so authentication paths and slice
+//! proofs can be served later without rehashing the input, and two parties
+//! disagreeing about an input can compare trees to find the first block they
+//! differ on.
+
+use std::path::Path;
+
+use anyhow::{ensure, Context, Result};
+use blake3::hazmat::{self, ChainingValue, HasherExt};
+use common::bao::{self, MAX_BLOCK_LOG};
+use serde::{Deserialize, Serialize};
+
+use crate::{bao::context_key, storage};
+
+/// A node of a [`Tree`]: a block, or a parent of two subtrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Node {
+    /// Offset of the first input byte below the node.
+    pub start: u64,
+    /// Number of input bytes below the node.
+    pub len: u64,
+    /// The chaining value of the node, or the root hash for the root.
+    pub hash: ChainingValue,
+}
+
+/// One step of an authentication path, from a block towards the root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sibling {
+    pub hash: ChainingValue,
+    /// Whether the sibling is the left child of the parent.
+    pub left: bool,
+}
+
+/// The bincode-encoded tree of an input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tree {
+    pub block_log: u8,
+    pub context: Option<[u8; 32]>,
+    /// All nodes in pre-order: each parent is followed by its left subtree,
+    /// then its right subtree.
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    /// Hashes `data` into its tree, personalized with the derive-key `context`
+    /// if given.
+    pub fn new(data: &[u8], block_log: u8, context: Option<&str>) -> Result<Self> {
+        ensure!(
+            block_log <= MAX_BLOCK_LOG,
+            "block size log {block_log} exceeds {MAX_BLOCK_LOG}"
+        );
+        let mut tree = Self {
+            block_log,
+            context: context.map(context_key),
+            nodes: Vec::new(),
+        };
+        if data.len() as u64 <= tree.block_len() {
+            let mut hasher = bao::block_hasher(tree.context.as_ref(), 0);
+            hasher.update(data);
+            tree.nodes.push(Node {
+                start: 0,
+                len: data.len() as u64,
+                hash: hasher.finalize().into(),
+            });
+        } else {
+            tree.nodes.push(Node {
+                start: 0,
+                len: data.len() as u64,
+                hash: [0; 32],
+            });
+            let left = tree.push_subtree(data, 0, hazmat::left_subtree_len(data.len() as u64));
+            let right = tree.push_subtree(data, left.len, data.len() as u64 - left.len);
+            tree.nodes[0].hash =
+                hazmat::merge_subtrees_root(&left.hash, &right.hash, tree.mode()).into();
+        }
+        Ok(tree)
+    }
+
+    pub fn block_len(&self) -> u64 {
+        1024 << self.block_log
+    }
+
+    /// Length of the input.
+    pub fn len(&self) -> u64 {
+        self.nodes[0].len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.nodes[0].hash
+    }
+
+    pub fn block_count(&self) -> u64 {
+        self.len().div_ceil(self.block_len()).max(1)
+    }
+
+    /// The blocks, in input order.
+    pub fn blocks(&self) -> impl Iterator<Item = &Node> + '_ {
+        let block_len = self.block_len();
+        self.nodes.iter().filter(move |node| node.len <= block_len)
+    }
+
+    /// The authentication path of block `index`, from the block up to the
+    /// children of the root.
+    pub fn path(&self, index: u64) -> Result<Vec<Sibling>> {
+        ensure!(
+            index < self.block_count(),
+            "block {index} is past the last block {}",
+            self.block_count() - 1
+        );
+        let offset = index * self.block_len();
+        let mut path = Vec::new();
+        let mut at = 0;
+        while self.nodes[at].len > self.block_len() {
+            let (left, right) = self.children(at);
+            if offset < self.nodes[right].start {
+                path.push(Sibling {
+                    hash: self.nodes[right].hash,
+                    left: false,
+                });
+                at = left;
+            } else {
+                path.push(Sibling {
+                    hash: self.nodes[left].hash,
+                    left: true,
+                });
+                at = right;
+            }
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    /// Chaining values of the largest subtrees outside the byte range
+    /// `start..end`, in the order a left-to-right descent meets them, as
+    /// expected by [`bao::Slice::verify`].
+    pub fn slice_nodes(&self, start: u64, end: u64) -> Vec<ChainingValue> {
+        let mut nodes = Vec::new();
+        let mut pending = vec![0];
+        while let Some(at) = pending.pop() {
+            let node = &self.nodes[at];
+            if at != 0 && (node.start + node.len <= start || node.start >= end) {
+                nodes.push(node.hash);
+            } else if node.len > self.block_len() {
+                let (left, right) = self.children(at);
+                pending.extend([right, left]);
+            }
+        }
+        nodes
+    }
+
+    /// Index of the first block whose hash differs from `other`, or `None` if
+    /// the trees are equal. Only subtrees with differing hashes are visited.
+    pub fn first_difference(&self, other: &Tree) -> Result<Option<u64>> {
+        ensure!(
+            (self.len(), self.block_log, self.context)
+                == (other.len(), other.block_log, other.context),
+            "trees are over inputs of different lengths or with different settings"
+        );
+        if self.root() == other.root() {
+            return Ok(None);
+        }
+        let mut at = 0;
+        while self.nodes[at].len > self.block_len() {
+            let (left, right) = self.children(at);
+            at = if self.nodes[left].hash != other.nodes[left].hash {
+                left
+            } else {
+                right
+            };
+        }
+        Ok(Some(self.nodes[at].start / self.block_len()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        storage::write_atomic(path, &bincode::serialize(self)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let encoded = std::fs::read(path)
+            .with_context(|| format!("failed to read tree {}", path.display()))?;
+        let tree: Self = bincode::deserialize(&encoded)
+            .with_context(|| format!("invalid tree {}", path.display()))?;
+        tree.check_shape()
+            .with_context(|| format!("invalid tree {}", path.display()))?;
+        Ok(tree)
+    }
+
+    /// Checks that the nodes cover the input as [`Tree::new`] lays them out,
+    /// so that walking a loaded tree never leaves it. Hashes are not checked.
+    fn check_shape(&self) -> Result<()> {
+        ensure!(
+            self.block_log <= MAX_BLOCK_LOG,
+            "block size log {} exceeds {MAX_BLOCK_LOG}",
+            self.block_log
+        );
+        let root = self.nodes.first().context("tree has no nodes")?;
+        let mut next = 0;
+        let mut pending = vec![(0, root.len)];
+        while let Some((start, len)) = pending.pop() {
+            let node = self.nodes.get(next).context("tree is truncated")?;
+            ensure!(
+                (node.start, node.len) == (start, len),
+                "node {next} covers {} bytes at {} instead of {len} at {start}",
+                node.len,
+                node.start
+            );
+            next += 1;
+            if len > self.block_len() {
+                let left = hazmat::left_subtree_len(len);
+                pending.extend([(start + left, len - left), (start, left)]);
+            }
+        }
+        ensure!(
+            next == self.nodes.len(),
+            "tree has {} nodes past its last block",
+            self.nodes.len() - next
+        );
+        Ok(())
+    }
+
+    /// Appends the subtree of `len` bytes at `start` in pre-order and returns
+    /// its top node.
+    fn push_subtree(&mut self, data: &[u8], start: u64, len: u64) -> Node {
+        let at = self.nodes.len();
+        self.nodes.push(Node {
+            start,
+            len,
+            hash: [0; 32],
+        });
+        self.nodes[at].hash = if len <= self.block_len() {
+            let mut hasher = bao::block_hasher(self.context.as_ref(), start);
+            hasher.update(&data[start as usize..(start + len) as usize]);
+            hasher.finalize_non_root()
+        } else {
+            let left = self.push_subtree(data, start, hazmat::left_subtree_len(len));
+            let right = self.push_subtree(data, start + left.len, len - left.len);
+            hazmat::merge_subtrees_non_root(&left.hash, &right.hash, self.mode())
+        };
+        self.nodes[at]
+    }
+
+    /// Indices of the children of the parent at `at`. A subtree of `b` blocks
+    /// takes up `2b - 1` nodes.
+    fn children(&self, at: usize) -> (usize, usize) {
+        let left = at + 1;
+        let blocks = self.nodes[left].len.div_ceil(self.block_len()) as usize;
+        (left, left + 2 * blocks - 1)
+    }
+
+    fn mode(&self) -> hazmat::Mode<'_> {
+        bao::hash_mode(self.context.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(tree: &Tree) -> Result<Tree> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("tree");
+        tree.save(&path)?;
+        Tree::load(&path)
+    }
+
+    #[test]
+    fn load_checks_the_shape() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let tree = Tree::new(&data, 0, None).unwrap();
+        assert_eq!(round_trip(&tree).unwrap(), tree);
+        assert_eq!(
+            round_trip(&Tree::new(b"", 0, None).unwrap()).unwrap().len(),
+            0
+        );
+
+        // Same node count, but a left child claims the whole input.
+        let mut bad = tree.clone();
+        bad.nodes[1].len = bad.nodes[0].len;
+        assert!(round_trip(&bad).is_err());
+
+        let mut bad = tree.clone();
+        bad.nodes.swap(1, 2);
+        assert!(round_trip(&bad).is_err());
+
+        let mut bad = tree.clone();
+        bad.nodes.pop();
+        assert!(round_trip(&bad).is_err());
+
+        let mut bad = tree.clone();
+        bad.nodes.push(bad.nodes[1]);
+        assert!(round_trip(&bad).is_err());
+
+        let mut bad = tree;
+        bad.nodes[0].len = u64::MAX;
+        assert!(round_trip(&bad).is_err());
+    }
+}
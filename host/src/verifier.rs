@@ -0,0 +1,534 @@
+use std::fmt;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use risc0_binfmt::Digestible;
+use risc0_zkp::core::digest::Digest as Risc0Digest;
+use risc0_zkvm::{ExitCode, InnerReceipt, Receipt, ReceiptClaim};
+use serde::Serialize;
+pub use verifier_core::{
+    from_seal, get_ark_verifying_key, normalize_seal, prepared_verifying_key, to_seal,
+    PrefixInputs, ScalarPolicy, SealError, PUBLIC_INPUT_LEN, SEAL_LEN,
+};
+
+use crate::{
+    journal::Journal,
+    method_id::MethodId,
+    transform::{Blake3Prefix, OutputTransform},
+};
+
+/// Derives the 31-byte public input committed to by the BitVM Groth16 circuit:
+/// `blake3(prefix || journal)` truncated so that it fits in a BN254 scalar.
+/// For an empty journal this is the truncated digest of the prefix alone.
+pub fn expected_output_bytes(
+    method_id: impl Into<MethodId>,
+    journal: &[u8],
+) -> [u8; PUBLIC_INPUT_LEN] {
+    verifier_core::public_input(&calculate_succinct_output_prefix(method_id), journal)
+}
+
+/// Converts a big-endian public input of at most 32 bytes to a scalar,
+/// applying `policy` to values at or above r.
+pub fn public_input_scalar(bytes: &[u8], policy: ScalarPolicy) -> Result<ark_bn254::Fr> {
+    Ok(verifier_core::public_input_scalar(bytes, policy)?)
+}
+
+/// Largest journal [`check_journal`] accepts by default. Even a multi-input
+/// journal over tens of thousands of inputs fits comfortably.
+pub const DEFAULT_MAX_JOURNAL_LEN: usize = 4 * 1024 * 1024;
+
+/// Rejects journals longer than `max_len`, or that are not laid out as the
+/// guest commits them, before any time is spent hashing them.
+pub fn check_journal(journal: &[u8], max_len: usize) -> Result<()> {
+    ensure!(
+        journal.len() <= max_len,
+        "journal of {} bytes exceeds the {max_len} byte limit",
+        journal.len()
+    );
+    common::journal::parse(journal).context("malformed journal")?;
+    Ok(())
+}
+
+/// Verifies `receipt` against `method_id`, first with the risc0 verifier and then
+/// with the arkworks Groth16 verifier over the BLAKE3-derived public input.
+/// The journal layout is checked first; size limits are left to the caller,
+/// see [`check_journal`].
+pub fn verify_receipt(receipt: &Receipt, method_id: impl Into<Risc0Digest>) -> Result<()> {
+    verify_receipt_with(receipt, method_id, &Blake3Prefix)
+}
+
+/// Like [`verify_receipt`], with the Groth16 public input derived from the
+/// journal by `transform` instead.
+pub fn verify_receipt_with(
+    receipt: &Receipt,
+    method_id: impl Into<Risc0Digest>,
+    transform: &dyn OutputTransform,
+) -> Result<()> {
+    let method_id = method_id.into();
+    check_journal(&receipt.journal.bytes, usize::MAX)?;
+    receipt
+        .verify(method_id)
+        .context("risc0 receipt verification failed")?;
+
+    let res = ark_verify(receipt, method_id, transform)?;
+    ensure!(res, "proof verification failed for receipt");
+    Ok(())
+}
+
+/// Like [`verify_receipt`], but also checks the seal with risc0's own
+/// `risc0_groth16::Verifier` under the same verifying key and public input, and
+/// fails if the two verifiers disagree. This catches drift between the
+/// hand-written [`from_seal`] and risc0's seal encoding. Both verifiers are
+/// given the key from [`get_ark_verifying_key`], so a wrong key constant is
+/// not caught here.
+pub fn verify_receipt_cross_checked(
+    receipt: &Receipt,
+    method_id: impl Into<Risc0Digest>,
+) -> Result<()> {
+    let method_id = method_id.into();
+    check_journal(&receipt.journal.bytes, usize::MAX)?;
+    receipt
+        .verify(method_id)
+        .context("risc0 receipt verification failed")?;
+
+    let ark = ark_verify(receipt, method_id, &Blake3Prefix)?;
+    let risc0 = risc0_groth16_verify(receipt, method_id);
+    match (ark, &risc0) {
+        (true, Ok(())) => Ok(()),
+        (false, Err(_)) => bail!("proof verification failed for receipt"),
+        (ark, risc0) => bail!(
+            "Groth16 verifiers disagree: arkworks {}, risc0_groth16 {}",
+            if ark { "accepted" } else { "rejected" },
+            match risc0 {
+                Ok(()) => "accepted".to_string(),
+                Err(err) => format!("rejected ({err})"),
+            }
+        ),
+    }
+}
+
+/// Which receipts of a bundle are verified: the Groth16 receipt, the
+/// succinct STARK receipt it wraps, or both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VerifyMode {
+    #[default]
+    Groth16,
+    /// Only the STARK, without the Groth16 verifier or its trusted setup.
+    Stark,
+    Both,
+}
+
+impl std::str::FromStr for VerifyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "groth16" => Ok(Self::Groth16),
+            "stark" => Ok(Self::Stark),
+            "both" => Ok(Self::Both),
+            _ => Err(format!(
+                "unknown verify mode {s:?}: expected `groth16`, `stark` or `both`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for VerifyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Groth16 => write!(f, "groth16"),
+            Self::Stark => write!(f, "stark"),
+            Self::Both => write!(f, "both"),
+        }
+    }
+}
+
+impl VerifyMode {
+    pub fn groth16(&self) -> bool {
+        matches!(self, Self::Groth16 | Self::Both)
+    }
+
+    pub fn stark(&self) -> bool {
+        matches!(self, Self::Stark | Self::Both)
+    }
+}
+
+/// Verifies the succinct STARK receipt a bundle retains next to the Groth16
+/// `receipt` that wraps it, without the Groth16 trusted setup: the STARK must
+/// verify against `method_id` with risc0's own verifier and prove the same
+/// claim and journal as `receipt`.
+pub fn verify_stark(
+    stark: &Receipt,
+    receipt: &Receipt,
+    method_id: impl Into<Risc0Digest>,
+) -> Result<()> {
+    check_stark(stark, receipt)?;
+    stark
+        .verify(method_id.into())
+        .context("STARK receipt verification failed")
+}
+
+/// Like [`verify_stark`], against whichever of `image_ids` the STARK was
+/// produced for, which is returned.
+pub fn verify_stark_any(
+    stark: &Receipt,
+    receipt: &Receipt,
+    image_ids: &[Risc0Digest],
+) -> Result<Risc0Digest> {
+    check_stark(stark, receipt)?;
+    let image_id = claimed_image_id(stark)?;
+    ensure!(
+        image_ids.contains(&image_id),
+        "STARK receipt claims image ID {image_id}, not one of the {} trusted image IDs",
+        image_ids.len()
+    );
+    stark
+        .verify(image_id)
+        .context("STARK receipt verification failed")?;
+    Ok(image_id)
+}
+
+/// Checks that `stark` is a succinct receipt of the claim and journal of
+/// `receipt`.
+fn check_stark(stark: &Receipt, receipt: &Receipt) -> Result<()> {
+    ensure!(
+        matches!(stark.inner, InnerReceipt::Succinct(_)),
+        "retained STARK is not a succinct receipt"
+    );
+    ensure!(
+        stark.journal.bytes == receipt.journal.bytes,
+        "retained STARK commits to another journal"
+    );
+    ensure!(
+        stark.claim()?.digest::<risc0_zkvm::sha::Impl>()
+            == receipt.claim()?.digest::<risc0_zkvm::sha::Impl>(),
+        "retained STARK proves another claim"
+    );
+    Ok(())
+}
+
+/// Checks the Groth16 seal with arkworks, returning whether it verifies.
+fn ark_verify(
+    receipt: &Receipt,
+    method_id: Risc0Digest,
+    transform: &dyn OutputTransform,
+) -> Result<bool> {
+    let expected_output_bytes = transform.public_input(method_id, &receipt.journal.bytes);
+
+    let groth_proof = receipt.inner.groth16().context("Not a groth16 proof!!!")?;
+    verify_seal(
+        &groth_proof.seal,
+        &expected_output_bytes,
+        ScalarPolicy::Reject,
+    )
+}
+
+/// Checks a bare Groth16 seal against a big-endian public input with
+/// arkworks, returning whether it verifies.
+pub fn verify_seal(seal: &[u8], public_input: &[u8], policy: ScalarPolicy) -> Result<bool> {
+    Ok(verifier_core::verify_seal(seal, public_input, policy)?)
+}
+
+/// Like [`verify_seal`], under the verifying key `vk`.
+pub fn verify_seal_with_key(
+    seal: &[u8],
+    public_input: &[u8],
+    policy: ScalarPolicy,
+    vk: &ark_groth16::VerifyingKey<ark_bn254::Bn254>,
+) -> Result<bool> {
+    Ok(verifier_core::verify_seal_with_key(
+        seal,
+        public_input,
+        policy,
+        vk,
+    )?)
+}
+
+/// How the arkworks verifier behind [`verify_seal`] was compiled for this
+/// machine, so that operators can tell why one watchtower verifies slower
+/// than another without comparing build logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArithmeticBackend {
+    /// The BN254 field multiplication compiled in.
+    pub field_mul: &'static str,
+    /// Whether pairings are computed across threads.
+    pub parallel: bool,
+    /// Threads the pairings and multi-scalar multiplications of one proof
+    /// may use.
+    pub threads: usize,
+    /// Rebuilds that would verify faster on this CPU, detected at run time.
+    pub hints: Vec<&'static str>,
+}
+
+impl fmt::Display for ArithmeticBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.parallel {
+            write!(
+                f,
+                "{} field arithmetic, pairings across {} threads",
+                self.field_mul, self.threads
+            )
+        } else {
+            write!(
+                f,
+                "{} field arithmetic, single-threaded pairings",
+                self.field_mul
+            )
+        }
+    }
+}
+
+pub fn arithmetic_backend() -> ArithmeticBackend {
+    let parallel = cfg!(feature = "parallel");
+    let (field_mul, mut hints) = field_mul_backend();
+    if !parallel && std::thread::available_parallelism().is_ok_and(|n| n.get() > 1) {
+        hints.push("build with the `parallel` feature to spread pairings across cores");
+    }
+    ArithmeticBackend {
+        field_mul,
+        parallel,
+        threads: if parallel {
+            rayon::current_num_threads()
+        } else {
+            1
+        },
+        hints,
+    }
+}
+
+/// Sizes the thread pool arkworks spreads pairings and multi-scalar
+/// multiplications across, which batch verification also defaults to.
+/// Without `threads`, rayon uses one thread per CPU. Must be called before
+/// anything is verified.
+pub fn init_verify_threads(threads: Option<usize>) -> Result<()> {
+    let Some(threads) = threads else {
+        return Ok(());
+    };
+    ensure!(threads > 0, "--verify-threads must be at least 1");
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("verify-{index}"))
+        .build_global()
+        .context("failed to configure the verification thread pool")?;
+    if !cfg!(feature = "parallel") {
+        tracing::warn!(
+            "built without the `parallel` feature: batches use {threads} threads, \
+             but each proof's pairings run on one"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(target_arch = "x86_64")]
+fn field_mul_backend() -> (&'static str, Vec<&'static str>) {
+    if cfg!(all(
+        feature = "asm",
+        target_feature = "bmi2",
+        target_feature = "adx"
+    )) {
+        return ("x86-64 BMI2/ADX assembly", Vec::new());
+    }
+    let mut hints = Vec::new();
+    if std::arch::is_x86_feature_detected!("bmi2") && std::arch::is_x86_feature_detected!("adx") {
+        hints.push(
+            "this CPU has BMI2 and ADX: build with `--features asm` and \
+             `RUSTFLAGS=\"-C target-cpu=native\"` for assembly field multiplication",
+        );
+    }
+    ("portable", hints)
+}
+
+/// arkworks has no NEON kernels for BN254; its portable multiplication
+/// compiles to the 64-bit multiply-high instructions, which are the fast path
+/// on aarch64.
+#[cfg(target_arch = "aarch64")]
+fn field_mul_backend() -> (&'static str, Vec<&'static str>) {
+    ("portable (aarch64 UMULH)", Vec::new())
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn field_mul_backend() -> (&'static str, Vec<&'static str>) {
+    ("portable", Vec::new())
+}
+
+/// Checks the Groth16 seal with `risc0_groth16`, passing the verifying key in
+/// its snarkjs JSON form so that no arkworks types cross between the two.
+fn risc0_groth16_verify(receipt: &Receipt, method_id: Risc0Digest) -> Result<()> {
+    let groth_proof = receipt.inner.groth16().context("Not a groth16 proof!!!")?;
+    let seal = risc0_groth16::Seal::from_vec(&groth_proof.seal)?;
+    let public_input = risc0_groth16::fr_from_hex_string(&hex::encode(
+        Journal::of(receipt).public_input(method_id),
+    ))?;
+
+    let vk = get_ark_verifying_key();
+    let g1 = |p: ark_bn254::G1Affine| vec![p.x.to_string(), p.y.to_string()];
+    let g2 = |p: ark_bn254::G2Affine| {
+        vec![
+            vec![p.x.c0.to_string(), p.x.c1.to_string()],
+            vec![p.y.c0.to_string(), p.y.c1.to_string()],
+        ]
+    };
+    let vk_json: risc0_groth16::VerifyingKeyJson = serde_json::from_value(serde_json::json!({
+        "protocol": "groth16",
+        "curve": "bn128",
+        "nPublic": vk.gamma_abc_g1.len() - 1,
+        "vk_alpha_1": g1(vk.alpha_g1),
+        "vk_beta_2": g2(vk.beta_g2),
+        "vk_gamma_2": g2(vk.gamma_g2),
+        "vk_delta_2": g2(vk.delta_g2),
+        "vk_alphabeta_12": [],
+        "IC": vk.gamma_abc_g1.iter().map(|p| g1(*p)).collect::<Vec<_>>(),
+    }))?;
+
+    risc0_groth16::Verifier::new(&seal, &[public_input], &vk_json.verifying_key()?)?.verify()
+}
+
+/// Verifies `receipt` against whichever of `image_ids` it was produced for and
+/// returns that image ID, so verifiers can keep accepting the previous guest
+/// release while a new one is rolled out.
+pub fn verify_receipt_any(receipt: &Receipt, image_ids: &[Risc0Digest]) -> Result<Risc0Digest> {
+    verify_receipt_any_with(receipt, image_ids, &Blake3Prefix)
+}
+
+/// Like [`verify_receipt_any`], with the Groth16 public input derived from
+/// the journal by `transform` instead.
+pub fn verify_receipt_any_with(
+    receipt: &Receipt,
+    image_ids: &[Risc0Digest],
+    transform: &dyn OutputTransform,
+) -> Result<Risc0Digest> {
+    let image_id = claimed_image_id(receipt)?;
+    ensure!(
+        image_ids.contains(&image_id),
+        "receipt claims image ID {image_id}, not one of the {} trusted image IDs",
+        image_ids.len()
+    );
+    verify_receipt_with(receipt, image_id, transform)?;
+    Ok(image_id)
+}
+
+/// The image ID the claim of `receipt` names, which only verifying the
+/// receipt against it confirms. Picking the ID to verify against from the
+/// claim verifies the receipt once, rather than once per candidate.
+pub fn claimed_image_id(receipt: &Receipt) -> Result<Risc0Digest> {
+    let claim = receipt.claim()?;
+    let claim = claim
+        .as_value()
+        .map_err(|_| anyhow!("receipt claim is pruned"))?;
+    Ok(claim.pre.digest())
+}
+
+pub fn calculate_succinct_output_prefix(method_id: impl Into<MethodId>) -> [u8; 32] {
+    prefix_inputs(method_id.into(), default_control_root()).prefix()
+}
+
+/// Like [`calculate_succinct_output_prefix`], for receipts lifted under
+/// `control_root` instead of the control root of this build of risc0.
+pub fn succinct_output_prefix_with(
+    method_id: impl Into<MethodId>,
+    control_root: Risc0Digest,
+) -> [u8; 32] {
+    prefix_inputs(method_id.into(), control_root).prefix()
+}
+
+/// The digest of the claim of a session of `method_id` that committed
+/// `journal` and ended with `exit_code`, hashed as risc0 hashes a
+/// [`ReceiptClaim`]: the value a succinct receipt's control flow commits to,
+/// and that `receipt.claim()` of a receipt of such a session digests to. The
+/// post-state is that of a halted session, pc 0 with a zero memory root, with
+/// no input and no assumptions.
+pub fn claim_digest(
+    method_id: impl Into<Risc0Digest>,
+    journal: &[u8],
+    exit_code: ExitCode,
+) -> Risc0Digest {
+    let mut claim = ReceiptClaim::ok(method_id.into(), journal.to_vec());
+    claim.exit_code = exit_code;
+    claim.digest::<risc0_zkvm::sha::Impl>()
+}
+
+/// The succinct control root of this build of risc0.
+pub fn default_control_root() -> Risc0Digest {
+    risc0_zkvm::SuccinctReceiptVerifierParameters::default().control_root
+}
+
+/// The prefix inputs for `method_id` under `control_root`.
+fn prefix_inputs(method_id: MethodId, control_root: Risc0Digest) -> PrefixInputs {
+    let post_state = risc0_zkvm::SystemState {
+        pc: 0,
+        merkle_root: Risc0Digest::default(),
+    };
+    PrefixInputs {
+        control_root: control_root.into(),
+        pre_state: method_id.to_bytes(),
+        post_state: post_state.digest::<risc0_zkvm::sha::Impl>().into(),
+        control_id: risc0_circuit_recursion::control_id::BN254_IDENTITY_CONTROL_ID.into(),
+    }
+}
+
+/// One labelled intermediate value of the public input derivation.
+#[derive(Debug, Clone, Serialize)]
+pub struct DerivationStep {
+    pub label: &'static str,
+    pub value: String,
+}
+
+/// Longest journal prefix shown by [`explain_public_input`].
+const EXPLAINED_JOURNAL_LEN: usize = 64;
+
+/// Every intermediate value of [`expected_output_bytes`] for `method_id` and
+/// `journal`, from the succinct control root to the BN254 scalar, so the
+/// binding can be followed by hand.
+pub fn explain_public_input(method_id: impl Into<MethodId>, journal: &[u8]) -> Vec<DerivationStep> {
+    use ark_ff::PrimeField;
+
+    let inputs = prefix_inputs(method_id.into(), default_control_root());
+    let prefix = inputs.prefix();
+    let digest: [u8; 32] = blake3::Hasher::new()
+        .update(&prefix)
+        .update(journal)
+        .finalize()
+        .into();
+    let public_input = &digest[..PUBLIC_INPUT_LEN];
+    let shown = &journal[..journal.len().min(EXPLAINED_JOURNAL_LEN)];
+    let journal_hex = match journal.len() - shown.len() {
+        0 => hex::encode(shown),
+        rest => format!("{}... ({rest} more bytes)", hex::encode(shown)),
+    };
+    let scalar = ark_bn254::Fr::from_be_bytes_mod_order(public_input);
+
+    let step = |label, value: String| DerivationStep { label, value };
+    vec![
+        step("succinct control root", hex::encode(inputs.control_root)),
+        step(
+            "control root, bits of each byte reversed",
+            hex::encode(inputs.control_root_reversed()),
+        ),
+        step("pre-state digest (image ID)", hex::encode(inputs.pre_state)),
+        step(
+            "post-state digest (halted, pc 0, zero merkle root)",
+            hex::encode(inputs.post_state),
+        ),
+        step("BN254 identity control ID", hex::encode(inputs.control_id)),
+        step(
+            "prefix = sha256(reversed root || pre-state || post-state || control ID)",
+            hex::encode(prefix),
+        ),
+        step("journal length", journal.len().to_string()),
+        step("journal", journal_hex),
+        step("blake3(prefix || journal)", hex::encode(digest)),
+        step("public input = first 31 bytes", hex::encode(public_input)),
+        step("Fr scalar, read big-endian, in decimal", scalar.to_string()),
+    ]
+}
+
+/// Normalizes the seal of a Groth16 receipt in place with [`normalize_seal`],
+/// returning whether it changed.
+pub fn normalize_receipt_seal(receipt: &mut Receipt) -> Result<bool> {
+    let InnerReceipt::Groth16(groth_proof) = &mut receipt.inner else {
+        bail!("Not a groth16 proof!!!");
+    };
+    let normalized = normalize_seal(&groth_proof.seal)?;
+    let changed = groth_proof.seal != normalized;
+    groth_proof.seal = normalized.to_vec();
+    Ok(changed)
+}
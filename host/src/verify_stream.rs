@@ -0,0 +1,242 @@
+//! `verify-stream` mode: verifies many proof bundles across a thread pool and
+//! prints a verdict for each as a JSON line, for watchtowers that must keep up
+//! with the proofs of many operators.
+//!
+//! Bundles are read from the files of a directory, or from a stream on stdin
+//! in which each bundle is preceded by its length as a little-endian `u32`.
+//! Verdicts are printed as bundles finish verifying, which need not be the
+//! order they were read in; each names its bundle by file name or by its
+//! position in the stream.
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::{AuditLog, Check},
+    bundle::ProofBundle,
+    journal::Journal,
+    policy::{self, VerifierPolicy},
+    profile::Profile,
+    verifier,
+};
+
+/// Where bundles are read from.
+pub enum Source {
+    /// Every regular file of a directory whose name does not start with `.`.
+    Dir(PathBuf),
+    /// Length-prefixed bundles on stdin.
+    Stdin,
+}
+
+pub struct StreamOptions {
+    /// Parameters bundles are verified under, including the image IDs they
+    /// may verify against.
+    pub profile: Profile,
+    /// Size of the thread pool; defaults to the size of the global pool set
+    /// by [`verifier::init_verify_threads`], one thread per CPU unless set.
+    pub threads: Option<usize>,
+    /// Log every verdict is also recorded in.
+    pub audit: Option<Mutex<AuditLog>>,
+    /// Checked before each bundle is verified.
+    pub policy: VerifierPolicy,
+}
+
+/// The outcome of verifying one bundle.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Verdict {
+    /// File name of the bundle, or its zero-based position in the stream.
+    pub bundle: String,
+    /// BLAKE3 digest of the encoded bundle, if it could be read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle_blake3: Option<String>,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub journal_digest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Counts of the bundles verified by [`verify_stream`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Summary {
+    pub valid: usize,
+    pub invalid: usize,
+}
+
+/// Verifies every bundle from `source`, writing a verdict line for each to
+/// `out`. Fails only if bundles cannot be read or verdicts written; invalid
+/// bundles are reported in their verdicts and counted in the summary.
+pub fn verify_stream(
+    source: &Source,
+    options: &StreamOptions,
+    out: impl Write + Send,
+) -> Result<Summary> {
+    let pool = thread_pool(options)?;
+    let out = Mutex::new(out);
+    let valid = AtomicUsize::new(0);
+    let invalid = AtomicUsize::new(0);
+    let bundles: Box<dyn Iterator<Item = Result<(String, Vec<u8>)>> + Send> = match source {
+        Source::Dir(dir) => Box::new(dir_bundles(dir)?),
+        Source::Stdin => Box::new(stream_bundles(io::stdin(), max_bundle_len(options))),
+    };
+    pool.install(|| {
+        bundles.par_bridge().try_for_each(|bundle| -> Result<()> {
+            let (name, encoded) = bundle?;
+            let verdict = verify_one(name, &encoded, options)?;
+            let counter = if verdict.valid { &valid } else { &invalid };
+            counter.fetch_add(1, Ordering::Relaxed);
+            let mut out = out.lock().unwrap();
+            serde_json::to_writer(&mut *out, &verdict)?;
+            writeln!(out)?;
+            out.flush()?;
+            Ok(())
+        })
+    })?;
+    Ok(Summary {
+        valid: valid.into_inner(),
+        invalid: invalid.into_inner(),
+    })
+}
+
+/// The pool bundles are verified on, logging how it verifies.
+pub(crate) fn thread_pool(options: &StreamOptions) -> Result<rayon::ThreadPool> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads.unwrap_or_else(rayon::current_num_threads))
+        .build()?;
+    let backend = verifier::arithmetic_backend();
+    tracing::info!(
+        "verifying on {} threads with {backend}",
+        pool.current_num_threads()
+    );
+    for hint in &backend.hints {
+        tracing::warn!("{hint}");
+    }
+    Ok(pool)
+}
+
+/// Verifies one bundle, failing only if its verdict cannot be audited.
+pub(crate) fn verify_one(
+    bundle: String,
+    encoded: &[u8],
+    options: &StreamOptions,
+) -> Result<Verdict> {
+    let bundle_blake3 = Some(blake3::hash(encoded).to_hex().to_string());
+    let decoded = ProofBundle::decode(encoded);
+    let outcome = decoded
+        .as_ref()
+        .map_err(|err| anyhow!("{err:#}"))
+        .and_then(|decoded| {
+            options.policy.check(decoded, None, policy::unix_now())?;
+            let receipt = &decoded.receipt;
+            let image_id = options.profile.verify_receipt_any(receipt)?;
+            Ok((image_id, Journal::of(receipt).digest()))
+        });
+    if let Some(audit) = &options.audit {
+        audit.lock().unwrap().record(Check {
+            bundle: &bundle,
+            receipt: decoded.as_ref().ok().map(|decoded| &decoded.receipt),
+            image_id: outcome.as_ref().ok().map(|(image_id, _)| *image_id),
+            error: outcome.as_ref().err().map(|err| format!("{err:#}")),
+        })?;
+    }
+    Ok(match outcome {
+        Ok((image_id, journal_digest)) => Verdict {
+            bundle,
+            bundle_blake3,
+            valid: true,
+            image_id: Some(image_id.to_string()),
+            journal_digest: Some(journal_digest.to_string()),
+            error: None,
+        },
+        Err(err) => Verdict {
+            bundle,
+            bundle_blake3,
+            valid: false,
+            image_id: None,
+            journal_digest: None,
+            error: Some(format!("{err:#}")),
+        },
+    })
+}
+
+/// The bundle files of `dir`, in name order, read as they are verified.
+fn dir_bundles(dir: &Path) -> Result<impl Iterator<Item = Result<(String, Vec<u8>)>> + Send> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if entry.file_type()?.is_file() && !hidden {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths.into_iter().map(|path| {
+        let name = path
+            .file_name()
+            .expect("directory entries have names")
+            .to_string_lossy()
+            .into_owned();
+        let encoded =
+            fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        Ok((name, encoded))
+    }))
+}
+
+/// Room left in a bundle for the receipt around its journal.
+const MAX_BUNDLE_OVERHEAD: usize = 1024 * 1024;
+
+/// Longest stream frame accepted, so that a corrupt length prefix cannot make
+/// the reader allocate gigabytes.
+fn max_bundle_len(options: &StreamOptions) -> usize {
+    options
+        .profile
+        .max_journal_len
+        .saturating_add(MAX_BUNDLE_OVERHEAD)
+}
+
+/// Length-prefixed bundles read from `reader` until it ends.
+fn stream_bundles(
+    mut reader: impl Read + Send,
+    max_len: usize,
+) -> impl Iterator<Item = Result<(String, Vec<u8>)>> + Send {
+    let mut index = 0usize;
+    std::iter::from_fn(move || {
+        let mut len = [0; 4];
+        match reader.read(&mut len[..1]) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(err) => return Some(Err(err.into())),
+        }
+        let frame = reader
+            .read_exact(&mut len[1..])
+            .map_err(|err| anyhow!("truncated bundle {index} in stream: {err}"))
+            .and_then(|()| {
+                let len = u32::from_le_bytes(len) as usize;
+                ensure!(
+                    len <= max_len,
+                    "bundle {index} in stream is {len} bytes, over the {max_len} byte limit"
+                );
+                let mut encoded = vec![0; len];
+                reader
+                    .read_exact(&mut encoded)
+                    .map_err(|err| anyhow!("truncated bundle {index} in stream: {err}"))?;
+                Ok(encoded)
+            });
+        let name = index.to_string();
+        index += 1;
+        Some(frame.map(|encoded| (name, encoded)))
+    })
+}
@@ -0,0 +1,137 @@
+//! `watch` mode: proves every file that appears in an inbox directory and
+//! writes its proof bundle to a store, for operators who want proofs without
+//! writing any integration code.
+//!
+//! Each file is proven as a single unpadded input. Once proven it is moved to
+//! the processed directory; if proving fails it is moved to the failed
+//! directory instead, next to a `<name>.error` file holding the error, so it
+//! is not retried forever. Inputs should be moved into the inbox once fully
+//! written, since a file copied in place may be picked up half-written. Files
+//! whose names start with `.` are ignored, so they can be written there first.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use methods::METHOD_ID;
+use notify::{RecursiveMode, Watcher};
+use zeroize::Zeroizing;
+
+use crate::{
+    bundle::ProofBundle,
+    input::GuestInput,
+    journal::Journal,
+    prover::{self, ProverLimits},
+    storage::{Layout, Store},
+    verifier,
+};
+
+pub struct WatchOptions {
+    /// Directory to take inputs from.
+    pub inbox: PathBuf,
+    /// Where proven inputs are moved.
+    pub processed: PathBuf,
+    /// Where inputs that could not be proven are moved.
+    pub failed: PathBuf,
+    pub limits: ProverLimits,
+    /// Keys bundles are stored under.
+    pub layout: Layout,
+}
+
+impl WatchOptions {
+    /// Options moving inputs to `processed` and `failed` subdirectories of the
+    /// inbox.
+    pub fn new(inbox: impl Into<PathBuf>) -> Self {
+        let inbox = inbox.into();
+        Self {
+            processed: inbox.join("processed"),
+            failed: inbox.join("failed"),
+            inbox,
+            limits: ProverLimits::default(),
+            layout: Layout::default(),
+        }
+    }
+}
+
+/// Proves the files already in the inbox, then each new one as it appears,
+/// until the process is stopped.
+pub fn watch(options: &WatchOptions, store: &dyn Store) -> Result<()> {
+    for dir in [&options.processed, &options.failed] {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let (events, received) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(events)?;
+    watcher
+        .watch(&options.inbox, RecursiveMode::NonRecursive)
+        .with_context(|| format!("failed to watch {}", options.inbox.display()))?;
+    tracing::info!("watching {}", options.inbox.display());
+
+    loop {
+        prove_pending(options, store)?;
+        // Rescan on any change rather than tracking individual events; moving
+        // proven files out of the inbox triggers one more, harmless, scan.
+        match received.recv() {
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => tracing::warn!("watch error: {err}"),
+            Err(_) => bail!("directory watcher stopped"),
+        }
+        while received.try_recv().is_ok() {}
+    }
+}
+
+/// Proves every file currently in the inbox, in name order.
+pub fn prove_pending(options: &WatchOptions, store: &dyn Store) -> Result<()> {
+    let mut pending = Vec::new();
+    for entry in fs::read_dir(&options.inbox)
+        .with_context(|| format!("failed to read {}", options.inbox.display()))?
+    {
+        let entry = entry?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if entry.file_type()?.is_file() && !hidden {
+            pending.push(entry.path());
+        }
+    }
+    pending.sort();
+
+    for path in pending {
+        let name = path.file_name().expect("directory entries have names");
+        tracing::info!("proving {}", path.display());
+        match prove_file(&path, &options.limits, &options.layout, store) {
+            Ok(key) => {
+                println!("{} -> {key}", path.display());
+                fs::rename(&path, options.processed.join(name))?;
+            }
+            Err(err) => {
+                tracing::warn!("failed to prove {}: {err:#}", path.display());
+                let mut error_name = name.to_owned();
+                error_name.push(".error");
+                fs::write(options.failed.join(error_name), format!("{err:#}\n"))?;
+                fs::rename(&path, options.failed.join(name))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Proves one file and stores its bundle, returning the bundle's key.
+fn prove_file(
+    path: &Path,
+    limits: &ProverLimits,
+    layout: &Layout,
+    store: &dyn Store,
+) -> Result<String> {
+    let input = Zeroizing::new(GuestInput::Single {
+        data: fs::read(path)?,
+        padding: Default::default(),
+    });
+    let receipt = prover::prove(&input, limits)?;
+    ensure!(
+        Journal::of(&receipt).as_bytes() == input.expected_journal()?,
+        "journal does not commit to the BLAKE3 digest of the input"
+    );
+    verifier::verify_receipt(&receipt, METHOD_ID)?;
+    layout.put(store, &input, &ProofBundle::new(receipt))
+}
@@ -0,0 +1,96 @@
+//! The STARK-to-SNARK wrap under a memory cap. Wrapping a succinct receipt in
+//! Groth16 is the peak-memory stage of proving: risc0 runs its gnark prover
+//! in a Docker container with no limit, and on 32 GB machines the kernel
+//! OOM-kills it near the end.
+//!
+//! With a cap, the wrap is run here instead: the container is limited to the
+//! cap and allowed unlimited swap, so the prover's working set spills to
+//! disk when it reaches the cap instead of taking down the host. The witness
+//! and proof are exchanged through files in a fresh temporary directory, as
+//! risc0 does.
+
+use std::{fs, process::Command};
+
+use anyhow::{bail, ensure, Context, Result};
+use risc0_groth16::{ProofJson, Seal};
+use risc0_zkvm::{
+    sha::Digestible, ApiClient, AssetRequest, Groth16Receipt, Groth16ReceiptVerifierParameters,
+    ProverOpts, ReceiptClaim, SuccinctReceipt,
+};
+
+/// The gnark prover image the risc0-groth16 release this is built against
+/// wraps receipts with, used unless
+/// [`ProverLimits::wrap_image`](crate::prover::ProverLimits::wrap_image) names
+/// another. It must change with risc0, whose circuit the image proves.
+pub(crate) const DEFAULT_PROVER_IMAGE: &str = "risczero/risc0-groth16-prover:v2025-01-31.1";
+
+/// Exit status of a container killed for exceeding its memory limit.
+const OOM_KILLED: i32 = 137;
+
+/// Wraps `receipt` in Groth16 with the prover `image`, keeping it within
+/// `max_mem` bytes of memory.
+pub(crate) fn succinct_to_groth16(
+    client: &ApiClient,
+    receipt: SuccinctReceipt<ReceiptClaim>,
+    image: &str,
+    max_mem: u64,
+) -> Result<Groth16Receipt<ReceiptClaim>> {
+    ensure!(
+        cfg!(target_arch = "x86_64"),
+        "Groth16 wrapping is only supported on x86-64"
+    );
+    let claim = receipt.claim.clone();
+    let identity = client.identity_p254(
+        &ProverOpts::groth16(),
+        receipt.try_into()?,
+        AssetRequest::Inline,
+    )?;
+
+    // Removed when dropped; concurrent wraps each get their own.
+    let work_dir = tempfile::Builder::new()
+        .prefix("r0-bitvm-wrap-")
+        .tempdir()
+        .context("failed to create the Groth16 work directory")?;
+    let seal = wrap_in(work_dir.path(), &identity.get_seal_bytes(), image, max_mem)?;
+
+    Ok(Groth16Receipt::new(
+        seal.to_vec(),
+        claim,
+        Groth16ReceiptVerifierParameters::default().digest(),
+    ))
+}
+
+fn wrap_in(work_dir: &std::path::Path, seal: &[u8], image: &str, max_mem: u64) -> Result<Seal> {
+    fs::write(work_dir.join("seal.r0"), seal)?;
+    let mut witness = Vec::new();
+    risc0_groth16::to_json(seal, &mut witness)?;
+    fs::write(work_dir.join("input.json"), witness)?;
+
+    tracing::info!("wrapping in Groth16 within {max_mem} bytes of memory");
+    let output = Command::new("docker")
+        .arg("run")
+        .arg("--rm")
+        .arg(format!("--memory={max_mem}b"))
+        .arg("--memory-swap=-1")
+        .arg("-v")
+        .arg(format!("{}:/mnt", work_dir.display()))
+        .arg(image)
+        .output()
+        .context("failed to run docker; it is needed to wrap in Groth16")?;
+    match output.status.code() {
+        Some(0) => {}
+        Some(OOM_KILLED) => bail!(
+            "the Groth16 prover was killed at the {max_mem} byte memory cap; \
+             add swap for it to spill to, or raise --max-mem"
+        ),
+        code => bail!(
+            "the Groth16 prover failed with exit code {code:?}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+    }
+
+    let proof = fs::read_to_string(work_dir.join("proof.json"))
+        .context("the Groth16 prover wrote no proof")?;
+    let proof: ProofJson = serde_json::from_str(&proof)?;
+    proof.try_into()
+}
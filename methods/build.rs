@@ -1,3 +1,38 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use risc0_build::GuestOptionsBuilder;
+
 fn main() {
-    risc0_build::embed_methods();
+    // The feature is forwarded to the guest, changing its image ID.
+    let mut features = Vec::new();
+    if std::env::var_os("CARGO_FEATURE_SHA256_CHUNKS").is_some() {
+        features.push("sha256-chunks".to_string());
+    }
+    risc0_build::embed_methods_with_options(HashMap::from([(
+        "method",
+        GuestOptionsBuilder::default()
+            .features(features)
+            .build()
+            .expect("guest options are complete"),
+    )]));
+
+    // Recorded in proof bundles. The build script only reruns when the guest
+    // changes, so this is the time the guest ELF was built, unless
+    // `SOURCE_DATE_EPOCH` pins it so that reproducible builds of the host
+    // binary match too.
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let built_at = match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch
+            .trim()
+            .parse::<u64>()
+            .expect("SOURCE_DATE_EPOCH is not a Unix timestamp"),
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock is before the Unix epoch")
+            .as_secs(),
+    };
+    println!("cargo:rustc-env=GUEST_BUILT_AT={built_at}");
 }
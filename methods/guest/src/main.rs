@@ -1,13 +1,471 @@
+use std::{io::Read, sync::Mutex};
+
+use common::{
+    bao::{OutboardHasher, Slice, MAX_SLICE_NODES},
+    fields,
+    journal::{self, Entry, GuestVersion, InputStats, Output},
+    kv,
+    merkle::ExclusionProof,
+    padding::Padding,
+    rv32_blake3,
+    smt::{self, Defaults, SparseProof},
+    Mode, FRAME_HEADER_LEN, GUEST_VERSION_FLAG, INPUT_DIGEST_FLAG, INPUT_STATS_FLAG, MAX_FRAME_LEN,
+    NONCE_FLAG,
+};
 use risc0_zkvm::guest::env;
+use ruzstd::decoding::StreamingDecoder;
 
-fn main() {
-    // TODO: Implement your guest code here
+/// Hasher of everything read from stdin, set when the input digest is to be
+/// committed.
+static INPUT: Mutex<Option<rv32_blake3::Hasher>> = Mutex::new(None);
 
+/// Frames read so far, set when the input statistics are to be committed.
+static STATS: Mutex<Option<InputStats>> = Mutex::new(None);
+
+fn main() {
     // read the input
-    let input: u32 = env::read();
+    let mut word = [0u8; 4];
+    env::read_slice(&mut word);
+    let bind_input = word[0] & INPUT_DIGEST_FLAG != 0;
+    if bind_input {
+        let mut hasher = rv32_blake3::Hasher::new();
+        hasher.update(&word);
+        *INPUT.lock().unwrap() = Some(hasher);
+        word[0] &= !INPUT_DIGEST_FLAG;
+    }
+    let count_input = word[0] & INPUT_STATS_FLAG != 0;
+    if count_input {
+        *STATS.lock().unwrap() = Some(InputStats::default());
+        word[0] &= !INPUT_STATS_FLAG;
+    }
+    let commit_version = word[0] & GUEST_VERSION_FLAG != 0;
+    word[0] &= !GUEST_VERSION_FLAG;
+    let commit_nonce = word[0] & NONCE_FLAG != 0;
+    word[0] &= !NONCE_FLAG;
+    let frame_len = common::frame_len(word[1]).expect("unsupported frame size");
+    word[1] = 0;
+    let mode = Mode::from_u32(u32::from_le_bytes(word)).expect("unknown guest mode");
+    let nonce = commit_nonce.then(|| {
+        let mut nonce = [0u8; journal::NONCE_LEN];
+        read_slice(&mut nonce);
+        nonce
+    });
+
+    let mut frame = vec![0u8; frame_len];
+    let mut output = match mode {
+        Mode::Single => {
+            read_slice(&mut word);
+            let padding = match word {
+                [kind, param, 0, 0] => Padding::decode([kind, param]),
+                _ => None,
+            }
+            .expect("unknown padding");
+
+            let mut hasher = rv32_blake3::Hasher::new();
+            let mut len = 0u64;
+            read_frames(&mut frame, |data| {
+                hasher.update(data);
+                len += data.len() as u64;
+            });
+            padding
+                .apply(len, |data| {
+                    hasher.update(data);
+                })
+                .expect("input does not fit the padding");
+            Output::Single {
+                padding,
+                digest: hasher.finalize().as_bytes(),
+            }
+            .encode()
+        }
+        Mode::Multi => {
+            read_slice(&mut word);
+            let count = u32::from_le_bytes(word);
+
+            // hash each input on its own and as part of the concatenation of
+            // all inputs, recording where each one starts and ends
+            let mut combined = rv32_blake3::Hasher::new();
+            let mut offset = 0u64;
+            let entries: Vec<_> = (0..count)
+                .map(|_| {
+                    let mut hasher = rv32_blake3::Hasher::new();
+                    let mut len = 0u64;
+                    read_frames(&mut frame, |data| {
+                        hasher.update(data);
+                        combined.update(data);
+                        len += data.len() as u64;
+                    });
+                    let entry = Entry {
+                        offset,
+                        len,
+                        digest: hasher.finalize().into(),
+                    };
+                    offset += len;
+                    entry.to_bytes()
+                })
+                .collect();
+            Output::Multi {
+                combined: combined.finalize().as_bytes(),
+                entries: &entries,
+            }
+            .encode()
+        }
+        Mode::KeyValue => {
+            read_slice(&mut word);
+            let count = u32::from_le_bytes(word);
+
+            let mut hasher = rv32_blake3::Hasher::new();
+            hasher.update(&word);
+            let mut previous: Option<Vec<u8>> = None;
+            for _ in 0..count {
+                read_slice(&mut word);
+                let key_len = u32::from_le_bytes(word) as usize;
+                assert!(
+                    key_len <= kv::MAX_KEY_LEN,
+                    "key of {key_len} bytes exceeds the maximum of {}",
+                    kv::MAX_KEY_LEN
+                );
+                let mut key = vec![0u8; key_len];
+                read_slice(&mut key);
+                if let Some(previous) = &previous {
+                    assert!(key > *previous, "keys are not strictly increasing");
+                }
+                hasher.update(&kv::key_header(&key));
+                hasher.update(&key);
 
-    // TODO: do something with the input
+                let mut header = [0u8; 8];
+                read_slice(&mut header);
+                let value_len = u64::from_le_bytes(header);
+                hasher.update(&kv::value_header(value_len));
+                let mut len = 0u64;
+                read_frames(&mut frame, |data| {
+                    hasher.update(data);
+                    len += data.len() as u64;
+                });
+                assert_eq!(len, value_len, "value length does not match its header");
+                previous = Some(key);
+            }
+            Output::KeyValue {
+                count,
+                root: hasher.finalize().as_bytes(),
+            }
+            .encode()
+        }
+        Mode::Fields => {
+            read_slice(&mut word);
+            let count = u32::from_le_bytes(word);
+            assert!(
+                count as usize <= fields::MAX_FIELDS,
+                "{count} fields exceed the maximum of {}",
+                fields::MAX_FIELDS
+            );
+
+            let mut leaves = Vec::with_capacity(count as usize);
+            let mut previous: Option<Vec<u8>> = None;
+            for _ in 0..count {
+                read_slice(&mut word);
+                let name_len = u32::from_le_bytes(word) as usize;
+                assert!(
+                    name_len <= fields::MAX_NAME_LEN,
+                    "field name of {name_len} bytes exceeds the maximum of {}",
+                    fields::MAX_NAME_LEN
+                );
+                let mut name = vec![0u8; name_len];
+                read_slice(&mut name);
+                if let Some(previous) = &previous {
+                    assert!(name > *previous, "field names are not strictly increasing");
+                }
+
+                let mut header = [0u8; 8];
+                read_slice(&mut header);
+                let value_len = u64::from_le_bytes(header);
+                let mut hasher = rv32_blake3::Hasher::new();
+                let mut len = 0u64;
+                read_frames(&mut frame, |data| {
+                    hasher.update(data);
+                    len += data.len() as u64;
+                });
+                assert_eq!(len, value_len, "value length does not match its header");
+                leaves.push((
+                    fields::field_key(&name),
+                    <[u8; 32]>::from(hasher.finalize()),
+                ));
+                previous = Some(name);
+            }
+            leaves.sort_unstable();
+            let leaves: Vec<_> = leaves.iter().map(|(key, value)| (key, value)).collect();
+            Output::Fields {
+                count,
+                root: &smt::subtree_hash(&leaves, 0, &Defaults::new()),
+            }
+            .encode()
+        }
+        Mode::MerkleExclusion => {
+            let mut encoded = Vec::new();
+            read_frames(&mut frame, |data| {
+                assert!(
+                    encoded.len() + data.len() <= MAX_FRAME_LEN,
+                    "exclusion proof is too large"
+                );
+                encoded.extend_from_slice(data);
+            });
+            let exclusion = ExclusionProof::decode(&encoded)
+                .and_then(|proof| proof.verify())
+                .expect("invalid exclusion proof");
+            Output::MerkleExclusion {
+                root: &exclusion.root,
+                key: &exclusion.key,
+                lower: &exclusion.lower,
+                upper: &exclusion.upper,
+            }
+            .encode()
+        }
+        Mode::SparseMerkle => {
+            let mut encoded = Vec::new();
+            read_frames(&mut frame, |data| {
+                assert!(
+                    encoded.len() + data.len() <= smt::MAX_PROOF_LEN,
+                    "sparse Merkle proof is too large"
+                );
+                encoded.extend_from_slice(data);
+            });
+            let proof = SparseProof::decode(&encoded).expect("invalid sparse Merkle proof");
+            Output::SparseMerkle {
+                root: &proof.root(&Defaults::new()),
+                key: &proof.key,
+                value: &proof.value.unwrap_or_default(),
+            }
+            .encode()
+        }
+        Mode::Outboard => {
+            let (block_log, context) = read_tree_options();
+
+            let mut outboard = rv32_blake3::Hasher::new();
+            let mut hasher = OutboardHasher::new(block_log, context, |pair| {
+                outboard.update(pair);
+            })
+            .expect("block size too large");
+            read_frames(&mut frame, |data| hasher.update(data));
+            let (root, len) = hasher.finalize();
+            Output::Outboard {
+                block_log,
+                len,
+                context: &context.unwrap_or_default(),
+                root: &root,
+                outboard: outboard.finalize().as_bytes(),
+            }
+            .encode()
+        }
+        Mode::Slice => {
+            let (block_log, context) = read_tree_options();
+            let mut root = [0u8; 32];
+            read_slice(&mut root);
+            let mut range = [[0u8; 8]; 3];
+            read_slice(range.as_flattened_mut());
+            let [len, offset, length] = range.map(u64::from_le_bytes);
+            read_slice(&mut word);
+            let count = u32::from_le_bytes(word) as usize;
+            assert!(count <= MAX_SLICE_NODES, "too many chaining values");
+            let mut nodes = vec![[0u8; 32]; count];
+            read_slice(nodes.as_flattened_mut());
+
+            let slice = Slice {
+                root,
+                len,
+                offset,
+                length,
+                block_log,
+                context,
+            };
+            let mut digest = rv32_blake3::Hasher::new();
+            slice
+                .verify(&nodes, &mut FrameReader::new(&mut frame), |data| {
+                    digest.update(data);
+                })
+                .expect("invalid slice proof");
+            Output::Slice {
+                context: &context.unwrap_or_default(),
+                root: &root,
+                len,
+                offset,
+                length,
+                digest: digest.finalize().as_bytes(),
+            }
+            .encode()
+        }
+        #[cfg(feature = "sha256-chunks")]
+        Mode::Sha256Chunks => {
+            use common::sha256_chunks::ChunkHasher;
+            use risc0_zkvm::sha::{Impl, Sha256};
+
+            let mut hasher =
+                ChunkHasher::new(|chunk| Impl::hash_bytes(chunk).as_bytes().try_into().unwrap());
+            read_frames(&mut frame, |data| hasher.update(data));
+            let (len, digest) = hasher.finalize();
+            Output::Sha256Chunks {
+                len,
+                digest: &digest,
+            }
+            .encode()
+        }
+        #[cfg(not(feature = "sha256-chunks"))]
+        Mode::Sha256Chunks => panic!("guest built without the sha256-chunks feature"),
+        Mode::Zstd => {
+            let mut reader = FrameReader::new(&mut frame);
+            let mut decompressed = rv32_blake3::Hasher::new();
+            let mut buf = vec![0u8; frame_len];
+            // the input may hold several concatenated zstd frames
+            while reader.fill() {
+                let mut decoder =
+                    StreamingDecoder::new(&mut reader).expect("input is not valid zstd");
+                loop {
+                    let len = decoder.read(&mut buf).expect("input is not valid zstd");
+                    if len == 0 {
+                        break;
+                    }
+                    decompressed.update(&buf[..len]);
+                }
+            }
+            Output::Zstd {
+                compressed: reader.hasher.finalize().as_bytes(),
+                decompressed: decompressed.finalize().as_bytes(),
+            }
+            .encode()
+        }
+    };
+
+    if commit_version {
+        let version = GuestVersion::current(cfg!(feature = "sha256-chunks"));
+        journal::bind_version(&mut output, &version);
+    }
+    if let Some(nonce) = &nonce {
+        journal::bind_nonce(&mut output, nonce);
+    }
+    if count_input {
+        let stats = STATS.lock().unwrap().take().expect("input is counted");
+        journal::bind_stats(&mut output, &stats);
+    }
+    if bind_input {
+        let hasher = INPUT.lock().unwrap().take().expect("input is hashed");
+        journal::bind_input(&mut output, hasher.finalize().as_bytes());
+    }
 
     // write public output to the journal
-    env::commit(&input);
+    env::commit_slice(&output);
+}
+
+/// Reads exactly `buf.len()` bytes of input, hashing them if the input digest
+/// is to be committed.
+fn read_slice(buf: &mut [u8]) {
+    env::read_slice(buf);
+    if let Some(hasher) = INPUT.lock().unwrap().as_mut() {
+        hasher.update(buf);
+    }
+}
+
+/// Adds a frame of `len` bytes to the input statistics, if they are kept.
+fn count_frame(len: usize) {
+    if let Some(stats) = STATS.lock().unwrap().as_mut() {
+        stats.add_frame(len);
+    }
+}
+
+/// Reads the `[block_log, personalized, 0, 0]` word of the BLAKE3 tree modes,
+/// and the derive-key context key if personalized.
+fn read_tree_options() -> (u8, Option<[u8; 32]>) {
+    let mut word = [0u8; 4];
+    read_slice(&mut word);
+    match word {
+        [block_log, 0, 0, 0] => (block_log, None),
+        [block_log, 1, 0, 0] => {
+            let mut context = [0u8; 32];
+            read_slice(&mut context);
+            (block_log, Some(context))
+        }
+        _ => panic!("unknown tree options"),
+    }
+}
+
+/// Reads one terminated sequence of frames, passing each payload to `consume`.
+/// `frame` is used as the read buffer so that only one frame is ever held in
+/// memory.
+fn read_frames(frame: &mut [u8], mut consume: impl FnMut(&[u8])) {
+    loop {
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        read_slice(&mut header);
+        let len = u32::from_le_bytes(header) as usize;
+        if len == 0 {
+            break;
+        }
+        assert!(
+            len <= frame.len(),
+            "frame of {len} bytes exceeds the maximum of {}",
+            frame.len()
+        );
+        read_slice(&mut frame[..len]);
+        count_frame(len);
+        consume(&frame[..len]);
+    }
+}
+
+/// Reads a terminated sequence of frames as one byte stream, hashing the bytes
+/// as they are read.
+struct FrameReader<'a> {
+    frame: &'a mut [u8],
+    pos: usize,
+    end: usize,
+    done: bool,
+    hasher: rv32_blake3::Hasher,
+}
+
+impl<'a> FrameReader<'a> {
+    fn new(frame: &'a mut [u8]) -> Self {
+        Self {
+            frame,
+            pos: 0,
+            end: 0,
+            done: false,
+            hasher: rv32_blake3::Hasher::new(),
+        }
+    }
+
+    /// Reads the next frame if the current one is used up. Returns `false` once
+    /// the stream has ended.
+    fn fill(&mut self) -> bool {
+        while self.pos == self.end {
+            if self.done {
+                return false;
+            }
+            let mut header = [0u8; FRAME_HEADER_LEN];
+            read_slice(&mut header);
+            let len = u32::from_le_bytes(header) as usize;
+            if len == 0 {
+                self.done = true;
+                return false;
+            }
+            assert!(
+                len <= self.frame.len(),
+                "frame of {len} bytes exceeds the maximum of {}",
+                self.frame.len()
+            );
+            read_slice(&mut self.frame[..len]);
+            count_frame(len);
+            self.hasher.update(&self.frame[..len]);
+            self.pos = 0;
+            self.end = len;
+        }
+        true
+    }
+}
+
+impl Read for FrameReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if !self.fill() {
+            return Ok(0);
+        }
+        let len = buf.len().min(self.end - self.pos);
+        buf[..len].copy_from_slice(&self.frame[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
 }
@@ -0,0 +1,11 @@
+use risc0_zkvm::guest::env;
+
+/// Hashes one input with nothing around it, so that the journal can be
+/// compared with a BLAKE3 test vector as is.
+fn main() {
+    let mut word = [0u8; 4];
+    env::read_slice(&mut word);
+    let mut input = vec![0u8; u32::from_le_bytes(word) as usize];
+    env::read_slice(&mut input);
+    env::commit_slice(blake3::hash(&input).as_bytes());
+}
@@ -1 +1,7 @@
 include!(concat!(env!("OUT_DIR"), "/methods.rs"));
+
+/// Unix time, in seconds, at which the guest ELF was built.
+pub const GUEST_BUILT_AT: &str = env!("GUEST_BUILT_AT");
+
+/// Whether the guest was built with the SHA-256 chunk digest mode.
+pub const SHA256_CHUNKS: bool = cfg!(feature = "sha256-chunks");
@@ -0,0 +1,22 @@
+//! Prepares the verifying key of the circuit risc0 wraps receipts with for
+//! pairings, so that the crate embeds the result instead of computing a
+//! pairing every time it verifies.
+
+use std::{env, fs, path::PathBuf, str::FromStr};
+
+use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G2Affine};
+use ark_groth16::VerifyingKey;
+use ark_serialize::CanonicalSerialize;
+
+include!("src/vk.rs");
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/vk.rs");
+    let pvk = ark_groth16::prepare_verifying_key(&get_ark_verifying_key());
+    let mut encoded = Vec::new();
+    pvk.serialize_uncompressed(&mut encoded)
+        .expect("a prepared verifying key serializes");
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("cargo sets OUT_DIR"));
+    fs::write(out_dir.join("prepared_vk.bin"), encoded).expect("failed to write prepared_vk.bin");
+}
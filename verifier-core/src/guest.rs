@@ -0,0 +1,46 @@
+//! Verification from inside a risc0 guest, so that a higher-level zk program
+//! can check r0-bitvm-blake3 proofs recursively. The prefix inputs are taken
+//! from the risc0 release this crate is built against, as the host takes
+//! them, so the guest only needs the image ID, the journal and the seal.
+//!
+//! Verifying in arkworks costs the guest a pairing check in software, which
+//! runs to hundreds of millions of cycles, so verify once per proof and
+//! commit the result rather than re-verifying across segments.
+
+use risc0_zkvm::{
+    sha::{Digest, Digestible},
+    SuccinctReceiptVerifierParameters, SystemState,
+};
+
+use crate::{PrefixInputs, ScalarPolicy, VerifyError, PUBLIC_INPUT_LEN};
+
+/// The prefix inputs for `image_id`, as configured in risc0.
+pub fn prefix_inputs(image_id: impl Into<Digest>) -> PrefixInputs {
+    let post_state = SystemState {
+        pc: 0,
+        merkle_root: Digest::default(),
+    };
+    PrefixInputs {
+        control_root: SuccinctReceiptVerifierParameters::default()
+            .control_root
+            .into(),
+        pre_state: image_id.into().into(),
+        post_state: post_state.digest().into(),
+        control_id: risc0_circuit_recursion::control_id::BN254_IDENTITY_CONTROL_ID.into(),
+    }
+}
+
+/// The public input committed to by a proof of `journal` under `image_id`.
+pub fn public_input(image_id: impl Into<Digest>, journal: &[u8]) -> [u8; PUBLIC_INPUT_LEN] {
+    crate::public_input(&prefix_inputs(image_id).prefix(), journal)
+}
+
+/// Checks that `seal` proves `journal` was committed by the guest with
+/// `image_id`, returning whether it verifies.
+pub fn verify(
+    image_id: impl Into<Digest>,
+    journal: &[u8],
+    seal: &[u8],
+) -> Result<bool, VerifyError> {
+    crate::verify_seal(seal, &public_input(image_id, journal), ScalarPolicy::Reject)
+}
@@ -0,0 +1,449 @@
+//! The verification core: seal decoding, the succinct output prefix and
+//! the BN254 Groth16 check, with no dependency on `std` or risc0, so the same
+//! code can verify a BitVM BLAKE3 proof inside another guest, on an embedded
+//! device or in a smart-contract runtime that only provides an allocator.
+//!
+//! Values that risc0 derives at run time, such as the succinct control root,
+//! are taken as [`PrefixInputs`] rather than computed here; the host fills
+//! them in from risc0, and constrained verifiers can embed the prefix it
+//! prints. Inside a risc0 guest, the `guest-verifier` feature takes them from
+//! risc0 as the host does; see [`guest`].
+
+#![no_std]
+
+extern crate alloc;
+
+#[cfg(feature = "guest-verifier")]
+pub mod guest;
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::{fmt, str::FromStr};
+
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_serialize::CanonicalDeserialize;
+use sha2::{Digest, Sha256};
+
+/// Length of the public input: a BLAKE3 digest truncated to fit a BN254
+/// scalar.
+pub const PUBLIC_INPUT_LEN: usize = 31;
+
+// Every 31-byte value is below 2^248, and so below the 254-bit scalar field
+// modulus r: derived public inputs are always canonical scalars, and no
+// reduction ever happens for them.
+const _: () = assert!(8 * PUBLIC_INPUT_LEN < Fr::MODULUS_BIT_SIZE as usize);
+
+/// Size of a Groth16 seal: the G1 points `a` and `c` and the G2 point `b`, as
+/// big-endian field elements.
+pub const SEAL_LEN: usize = 256;
+
+/// The values hashed into the succinct output prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefixInputs {
+    /// Control root of the succinct verifier, as configured in risc0.
+    pub control_root: [u8; 32],
+    /// Digest of the initial state: the image ID in its canonical bytes.
+    pub pre_state: [u8; 32],
+    /// Digest of the expected post state for an execution that halted
+    /// successfully.
+    pub post_state: [u8; 32],
+    /// Control ID of the BN254 identity recursion program.
+    pub control_id: [u8; 32],
+}
+
+impl PrefixInputs {
+    /// The control root with the bits of each byte reversed, as the circuit
+    /// takes it.
+    pub fn control_root_reversed(&self) -> [u8; 32] {
+        self.control_root.map(u8::reverse_bits)
+    }
+
+    pub fn prefix(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.control_root_reversed());
+        hasher.update(self.pre_state);
+        hasher.update(self.post_state);
+        hasher.update(self.control_id);
+        hasher.finalize().into()
+    }
+}
+
+/// The 31-byte public input committed to by the circuit:
+/// `blake3(prefix || journal)` truncated so that it fits in a BN254 scalar.
+pub fn public_input(prefix: &[u8; 32], journal: &[u8]) -> [u8; PUBLIC_INPUT_LEN] {
+    let digest: [u8; 32] = blake3::Hasher::new()
+        .update(prefix)
+        .update(journal)
+        .finalize()
+        .into();
+    digest[..PUBLIC_INPUT_LEN].try_into().unwrap()
+}
+
+/// What to do with a public input given as a big-endian value at or above the
+/// scalar field modulus r.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScalarPolicy {
+    /// Reject it, as circuits that range-check their input would.
+    #[default]
+    Reject,
+    /// Reduce it modulo r, as `from_be_bytes_mod_order` does.
+    Reduce,
+}
+
+impl FromStr for ScalarPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "reduce" => Ok(Self::Reduce),
+            _ => Err(format!(
+                "unknown scalar policy {s:?}: expected `reject` or `reduce`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ScalarPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reject => write!(f, "reject"),
+            Self::Reduce => write!(f, "reduce"),
+        }
+    }
+}
+
+/// Why a public input could not be read as a scalar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicInputError {
+    /// The input is longer than 32 bytes.
+    Length { actual: usize },
+    /// The input is at or above r and the policy is [`ScalarPolicy::Reject`].
+    NotCanonical(Vec<u8>),
+}
+
+impl fmt::Display for PublicInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length { actual } => {
+                write!(f, "public input of {actual} bytes does not fit in 32")
+            }
+            Self::NotCanonical(bytes) => {
+                write!(f, "public input ")?;
+                for byte in bytes {
+                    write!(f, "{byte:02x}")?;
+                }
+                write!(f, " is not below the BN254 scalar field modulus")
+            }
+        }
+    }
+}
+
+impl core::error::Error for PublicInputError {}
+
+/// Converts a big-endian public input of at most 32 bytes to a scalar,
+/// applying `policy` to values at or above r.
+pub fn public_input_scalar(bytes: &[u8], policy: ScalarPolicy) -> Result<Fr, PublicInputError> {
+    if bytes.len() > 32 {
+        return Err(PublicInputError::Length {
+            actual: bytes.len(),
+        });
+    }
+    let scalar = Fr::from_be_bytes_mod_order(bytes);
+    let mut padded = [0; 32];
+    padded[32 - bytes.len()..].copy_from_slice(bytes);
+    if policy == ScalarPolicy::Reject && scalar.into_bigint().to_bytes_be() != padded {
+        return Err(PublicInputError::NotCanonical(bytes.to_vec()));
+    }
+    Ok(scalar)
+}
+
+/// Why a seal could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SealError {
+    /// The seal is not exactly [`SEAL_LEN`] bytes.
+    Length { actual: usize },
+    /// The field element at `offset` is not reduced modulo the field prime.
+    NonCanonical { offset: usize },
+    /// The named point is not on the curve or not in the prime-order subgroup.
+    InvalidPoint(&'static str),
+}
+
+impl fmt::Display for SealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length { actual } => {
+                write!(f, "seal is {actual} bytes, expected {SEAL_LEN}")
+            }
+            Self::NonCanonical { offset } => {
+                write!(f, "seal field element at byte {offset} is not canonical")
+            }
+            Self::InvalidPoint(point) => write!(f, "seal point `{point}` is not a valid point"),
+        }
+    }
+}
+
+impl core::error::Error for SealError {}
+
+/// Decodes a Groth16 seal, rejecting seals of the wrong length, non-canonical
+/// field elements and invalid points instead of panicking, so that seals
+/// received over the network are safe to pass in.
+pub fn from_seal(seal_bytes: &[u8]) -> Result<Proof<Bn254>, SealError> {
+    decode_seal(seal_bytes, true)
+}
+
+/// Rewrites a seal into its canonical encoding, reducing any field element
+/// written as a value at or above the field prime. Both encodings describe the
+/// same proof, but only the canonical one is accepted by [`from_seal`], so two
+/// byte-different seals cannot pass as different proofs in deduplication or
+/// on-chain scripts.
+pub fn normalize_seal(seal_bytes: &[u8]) -> Result<[u8; SEAL_LEN], SealError> {
+    decode_seal(seal_bytes, false).map(|proof| to_seal(&proof))
+}
+
+/// Encodes a proof in the layout read by [`from_seal`].
+pub fn to_seal(proof: &Proof<Bn254>) -> [u8; SEAL_LEN] {
+    let elements = [
+        proof.a.x,
+        proof.a.y,
+        proof.b.x.c1,
+        proof.b.x.c0,
+        proof.b.y.c1,
+        proof.b.y.c0,
+        proof.c.x,
+        proof.c.y,
+    ];
+    let mut seal = [0; SEAL_LEN];
+    for (chunk, element) in seal.as_chunks_mut::<32>().0.iter_mut().zip(elements) {
+        chunk.copy_from_slice(&element.into_bigint().to_bytes_be());
+    }
+    seal
+}
+
+fn decode_seal(seal_bytes: &[u8], strict: bool) -> Result<Proof<Bn254>, SealError> {
+    let seal_bytes: &[u8; SEAL_LEN] = seal_bytes.try_into().map_err(|_| SealError::Length {
+        actual: seal_bytes.len(),
+    })?;
+    let fq = |offset: usize| {
+        let bytes = &seal_bytes[offset..offset + 32];
+        let element = Fq::from_be_bytes_mod_order(bytes);
+        if strict && element.into_bigint().to_bytes_be() != bytes {
+            return Err(SealError::NonCanonical { offset });
+        }
+        Ok(element)
+    };
+
+    let a = G1Affine::new_unchecked(fq(0)?, fq(32)?);
+    let b = G2Affine::new_unchecked(Fq2::new(fq(96)?, fq(64)?), Fq2::new(fq(160)?, fq(128)?));
+    let c = G1Affine::new_unchecked(fq(192)?, fq(224)?);
+
+    if !(a.is_on_curve() && a.is_in_correct_subgroup_assuming_on_curve()) {
+        return Err(SealError::InvalidPoint("a"));
+    }
+    if !(b.is_on_curve() && b.is_in_correct_subgroup_assuming_on_curve()) {
+        return Err(SealError::InvalidPoint("b"));
+    }
+    if !(c.is_on_curve() && c.is_in_correct_subgroup_assuming_on_curve()) {
+        return Err(SealError::InvalidPoint("c"));
+    }
+
+    Ok(Proof { a, b, c })
+}
+
+/// Why [`verify_seal`] could not check a seal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    PublicInput(PublicInputError),
+    Seal(SealError),
+    /// The pairing check itself failed to run.
+    Groth16(String),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PublicInput(err) => err.fmt(f),
+            Self::Seal(err) => err.fmt(f),
+            Self::Groth16(err) => write!(f, "groth16 verification error: {err}"),
+        }
+    }
+}
+
+impl core::error::Error for VerifyError {}
+
+impl From<PublicInputError> for VerifyError {
+    fn from(err: PublicInputError) -> Self {
+        Self::PublicInput(err)
+    }
+}
+
+impl From<SealError> for VerifyError {
+    fn from(err: SealError) -> Self {
+        Self::Seal(err)
+    }
+}
+
+/// Checks a bare Groth16 seal against a big-endian public input, returning
+/// whether it verifies.
+pub fn verify_seal(
+    seal: &[u8],
+    public_input: &[u8],
+    policy: ScalarPolicy,
+) -> Result<bool, VerifyError> {
+    verify_seal_with_prepared_key(seal, public_input, policy, &prepared_verifying_key())
+}
+
+/// Like [`verify_seal`], under the verifying key `vk` instead of the one of
+/// the circuit risc0 wraps receipts with.
+pub fn verify_seal_with_key(
+    seal: &[u8],
+    public_input: &[u8],
+    policy: ScalarPolicy,
+    vk: &VerifyingKey<Bn254>,
+) -> Result<bool, VerifyError> {
+    let pvk = ark_groth16::prepare_verifying_key(vk);
+    verify_seal_with_prepared_key(seal, public_input, policy, &pvk)
+}
+
+/// Like [`verify_seal_with_key`], under a key already prepared for pairings,
+/// so that verifying many seals under one key prepares it once.
+pub fn verify_seal_with_prepared_key(
+    seal: &[u8],
+    public_input: &[u8],
+    policy: ScalarPolicy,
+    pvk: &PreparedVerifyingKey<Bn254>,
+) -> Result<bool, VerifyError> {
+    let public_input_scalar = public_input_scalar(public_input, policy)?;
+    let proof = from_seal(seal)?;
+
+    Groth16::<Bn254>::verify_proof(pvk, &proof, &[public_input_scalar])
+        .map_err(|err| VerifyError::Groth16(format!("{err:?}")))
+}
+
+include!("vk.rs");
+
+/// [`get_ark_verifying_key`] prepared for pairings by the build script: the
+/// pairing of `alpha` and `beta` and the line coefficients of `-gamma` and
+/// `-delta`, uncompressed.
+static PREPARED_VERIFYING_KEY: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/prepared_vk.bin"));
+
+/// The verifying key of the circuit risc0 wraps receipts with, prepared for
+/// pairings at build time, so that verifying spends no pairing on the key.
+pub fn prepared_verifying_key() -> PreparedVerifyingKey<Bn254> {
+    // Written by the build script from the same constants, so not checked
+    // again here.
+    PreparedVerifyingKey::deserialize_uncompressed_unchecked(PREPARED_VERIFYING_KEY)
+        .expect("the build script writes a prepared verifying key")
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::{AffineRepr, CurveGroup};
+
+    use super::*;
+
+    fn proof() -> Proof<Bn254> {
+        Proof {
+            a: G1Affine::generator(),
+            b: G2Affine::generator(),
+            c: (G1Affine::generator() * Fr::from(2u64)).into_affine(),
+        }
+    }
+
+    /// The big-endian bytes of `element` plus the field prime: the same
+    /// element, written as a value at or above the prime.
+    fn non_canonical(element: Fq) -> [u8; 32] {
+        let mut value = element.into_bigint();
+        assert!(!value.add_with_carry(&Fq::MODULUS));
+        value.to_bytes_be().try_into().unwrap()
+    }
+
+    #[test]
+    fn decodes_seals() {
+        assert_eq!(from_seal(&to_seal(&proof())), Ok(proof()));
+    }
+
+    #[test]
+    fn normalizes_seals() {
+        let canonical = to_seal(&proof());
+        assert_eq!(normalize_seal(&canonical), Ok(canonical));
+        assert_eq!(
+            Ok(to_seal(&from_seal(&canonical).unwrap())),
+            normalize_seal(&canonical)
+        );
+
+        let mut seal = canonical;
+        for offset in [0, 64, 160, 224] {
+            let element = Fq::from_be_bytes_mod_order(&seal[offset..offset + 32]);
+            seal[offset..offset + 32].copy_from_slice(&non_canonical(element));
+        }
+        let normalized = normalize_seal(&seal).unwrap();
+        assert_eq!(normalized, canonical);
+        // Normalizing again changes nothing.
+        assert_eq!(normalize_seal(&normalized), Ok(normalized));
+    }
+
+    #[test]
+    fn rejects_seals_of_the_wrong_length() {
+        let seal = to_seal(&proof());
+        assert_eq!(
+            from_seal(&seal[..SEAL_LEN - 1]),
+            Err(SealError::Length { actual: 255 })
+        );
+        assert_eq!(
+            from_seal(&[seal, seal].concat()),
+            Err(SealError::Length { actual: 512 })
+        );
+        let mut trailing = seal.to_vec();
+        trailing.push(0);
+        assert_eq!(from_seal(&trailing), Err(SealError::Length { actual: 257 }));
+        assert_eq!(
+            normalize_seal(&trailing),
+            Err(SealError::Length { actual: 257 })
+        );
+    }
+
+    #[test]
+    fn rejects_or_reduces_non_canonical_elements() {
+        let mut seal = to_seal(&proof());
+        seal[192..224].copy_from_slice(&non_canonical(proof().c.x));
+        assert_eq!(
+            from_seal(&seal),
+            Err(SealError::NonCanonical { offset: 192 })
+        );
+        assert_eq!(normalize_seal(&seal), Ok(to_seal(&proof())));
+
+        let modulus = Fr::MODULUS.to_bytes_be();
+        assert_eq!(
+            public_input_scalar(&modulus, ScalarPolicy::Reject),
+            Err(PublicInputError::NotCanonical(modulus.clone()))
+        );
+        assert_eq!(
+            public_input_scalar(&modulus, ScalarPolicy::Reduce),
+            Ok(Fr::from(0u64))
+        );
+    }
+
+    #[test]
+    fn rejects_points_off_the_curve() {
+        let mut seal = to_seal(&proof());
+        let y = proof().a.y + Fq::from(1u64);
+        seal[32..64].copy_from_slice(&y.into_bigint().to_bytes_be());
+        assert_eq!(from_seal(&seal), Err(SealError::InvalidPoint("a")));
+        assert_eq!(normalize_seal(&seal), Err(SealError::InvalidPoint("a")));
+    }
+
+    #[test]
+    fn rejects_points_outside_the_subgroup() {
+        // G2 has a cofactor, so points on the curve are almost never in the
+        // prime-order subgroup.
+        let b = (1u64..)
+            .find_map(|x| G2Affine::get_point_from_x_unchecked(Fq2::from(x), true))
+            .unwrap();
+        assert!(b.is_on_curve() && !b.is_in_correct_subgroup_assuming_on_curve());
+        let seal = to_seal(&Proof { b, ..proof() });
+        assert_eq!(from_seal(&seal), Err(SealError::InvalidPoint("b")));
+        assert_eq!(normalize_seal(&seal), Err(SealError::InvalidPoint("b")));
+    }
+}